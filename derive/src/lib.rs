@@ -0,0 +1,173 @@
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{Data, DeriveInput, Expr, ExprLit, Fields, Lit, Meta, parse_macro_input};
+
+#[proc_macro_derive(VertexLayout, attributes(location, normalized))]
+pub fn derive_vertex_layout(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match vertex_layout(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
+
+fn vertex_layout(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let name = &input.ident;
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new_spanned(
+            input,
+            "VertexLayout can only be derived for structs",
+        ));
+    };
+    let Fields::Named(fields) = &data.fields else {
+        return Err(syn::Error::new_spanned(
+            input,
+            "VertexLayout requires a struct with named fields",
+        ));
+    };
+
+    let mut attributes = Vec::new();
+    let mut locations: Vec<(u32, String)> = Vec::new();
+    for field in &fields.named {
+        let field_name = field.ident.as_ref().expect("named field");
+        let field_type = &field.ty;
+
+        let mut location = None;
+        let mut normalized = false;
+        for attribute in &field.attrs {
+            if attribute.path().is_ident("location") {
+                location = Some(parse_location(&attribute.meta)?);
+            } else if attribute.path().is_ident("normalized") {
+                normalized = true;
+            }
+        }
+
+        let Some(location) = location else {
+            return Err(syn::Error::new_spanned(
+                field,
+                format!(
+                    "field `{}` is missing a #[location = N] attribute",
+                    field_name
+                ),
+            ));
+        };
+
+        let field_label = field_name.to_string();
+        if let Some((_, other)) = locations.iter().find(|(used, _)| *used == location) {
+            return Err(syn::Error::new_spanned(
+                field,
+                format!(
+                    "field `{}` uses location {} which is already taken by `{}`",
+                    field_name, location, other
+                ),
+            ));
+        }
+        locations.push((location, field_label.clone()));
+        attributes.push(quote! {
+            ::app_core::support::vertex::VertexAttribute {
+                name: #field_label,
+                location: #location,
+                components: <#field_type as ::app_core::support::vertex::VertexFormat>::COMPONENTS,
+                gl_type: <#field_type as ::app_core::support::vertex::VertexFormat>::GL_TYPE,
+                normalized: #normalized,
+                offset: ::core::mem::offset_of!(#name, #field_name),
+            }
+        });
+    }
+
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::app_core::support::vertex::VertexLayout for #name #type_generics #where_clause {
+            fn attributes() -> &'static [::app_core::support::vertex::VertexAttribute] {
+                const ATTRIBUTES: &[::app_core::support::vertex::VertexAttribute] = &[#(#attributes),*];
+                ATTRIBUTES
+            }
+        }
+    })
+}
+
+fn parse_location(meta: &Meta) -> syn::Result<u32> {
+    let Meta::NameValue(name_value) = meta else {
+        return Err(syn::Error::new_spanned(meta, "expected #[location = N]"));
+    };
+    match &name_value.value {
+        Expr::Lit(ExprLit {
+            lit: Lit::Int(value),
+            ..
+        }) => value.base10_parse(),
+        other => Err(syn::Error::new_spanned(
+            other,
+            "expected an integer location",
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(input: DeriveInput) -> String {
+        vertex_layout(&input).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn rejects_duplicate_and_missing_locations() {
+        assert_eq!(
+            error(syn::parse_quote! {
+                struct Vertex {
+                    #[location = 0]
+                    a: [f32; 3],
+                    #[location = 0]
+                    b: [f32; 2],
+                }
+            }),
+            "field `b` uses location 0 which is already taken by `a`"
+        );
+        assert_eq!(
+            error(syn::parse_quote! {
+                struct Vertex {
+                    #[location = 0]
+                    a: [f32; 3],
+                    b: [f32; 2],
+                }
+            }),
+            "field `b` is missing a #[location = N] attribute"
+        );
+        assert_eq!(
+            error(syn::parse_quote! {
+                struct Vertex {
+                    #[location = "0"]
+                    a: [f32; 3],
+                }
+            }),
+            "expected an integer location"
+        );
+        assert_eq!(
+            error(syn::parse_quote! { struct Vertex([f32; 3]); }),
+            "VertexLayout requires a struct with named fields"
+        );
+    }
+
+    #[test]
+    fn emits_one_attribute_per_field() {
+        let tokens = vertex_layout(&syn::parse_quote! {
+            struct Vertex {
+                #[location = 1]
+                #[normalized]
+                color: [u8; 4],
+                #[location = 0]
+                position: [f32; 3],
+            }
+        })
+        .unwrap()
+        .to_string();
+        assert_eq!(tokens.matches("VertexAttribute {").count(), 2);
+        assert!(tokens.contains("location : 1u32"), "{}", tokens);
+        assert!(tokens.contains("normalized : true"), "{}", tokens);
+        assert!(
+            tokens.contains("offset_of ! (Vertex , position)"),
+            "{}",
+            tokens
+        );
+    }
+}
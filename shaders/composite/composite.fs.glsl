@@ -0,0 +1,10 @@
+#version 330 core
+
+uniform sampler2D scene_color;
+
+out vec4 frag_color;
+
+void main()
+{
+    frag_color = texelFetch(scene_color, ivec2(gl_FragCoord.xy), 0);
+}
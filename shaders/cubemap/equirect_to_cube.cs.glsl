@@ -0,0 +1,38 @@
+#version 430 core
+
+layout(local_size_x = 8, local_size_y = 8) in;
+
+uniform sampler2D equirectangular;
+layout(binding = 0, rgba16f) uniform writeonly image2D face_image;
+
+uniform int face;
+
+const float PI = 3.14159265359;
+
+// Direction through a texel of a cube face, following the face orientations in the OpenGL
+// specification.
+vec3 face_direction(int face, vec2 st) {
+    switch (face) {
+        case 0: return vec3(1.0, -st.y, -st.x);
+        case 1: return vec3(-1.0, -st.y, st.x);
+        case 2: return vec3(st.x, 1.0, st.y);
+        case 3: return vec3(st.x, -1.0, -st.y);
+        case 4: return vec3(st.x, -st.y, 1.0);
+        default: return vec3(-st.x, -st.y, -1.0);
+    }
+}
+
+void main() {
+    ivec2 texel = ivec2(gl_GlobalInvocationID.xy);
+    ivec2 size = imageSize(face_image);
+    if (texel.x >= size.x || texel.y >= size.y) {
+        return;
+    }
+    vec2 st = (vec2(texel) + 0.5) / vec2(size) * 2.0 - 1.0;
+    vec3 direction = normalize(face_direction(face, st));
+    vec2 uv = vec2(
+        atan(direction.z, direction.x) / (2.0 * PI) + 0.5,
+        acos(clamp(direction.y, -1.0, 1.0)) / PI
+    );
+    imageStore(face_image, texel, textureLod(equirectangular, uv, 0.0));
+}
@@ -0,0 +1,30 @@
+#version 450 core
+
+out vec4 color;
+
+in VS_OUT
+{
+    vec3 normal;
+    vec2 uv;
+    vec4 color;
+} fs_in;
+
+uniform vec4 base_color;
+uniform bool use_base_color_texture;
+uniform sampler2D base_color_texture;
+
+const vec3 light_direction = vec3(-0.4, 0.8, -0.45);
+
+void main(void)
+{
+    vec3 normal = fs_in.normal;
+    float diffuse = 1.0;
+    if (dot(normal, normal) > 0.0) {
+        diffuse = 0.35 + 0.65 * abs(dot(normalize(normal), normalize(light_direction)));
+    }
+    vec4 albedo = base_color * fs_in.color;
+    if (use_base_color_texture) {
+        albedo *= texture(base_color_texture, fs_in.uv);
+    }
+    color = vec4(albedo.rgb * diffuse, albedo.a);
+}
@@ -0,0 +1,25 @@
+#version 450 core
+
+layout(location = 0) in vec3 position;
+layout(location = 1) in vec3 normal;
+layout(location = 2) in vec4 tangent;
+layout(location = 3) in vec2 uv;
+layout(location = 4) in vec4 color;
+
+out VS_OUT
+{
+    vec3 normal;
+    vec2 uv;
+    vec4 color;
+} vs_out;
+
+uniform mat4 mvp;
+uniform mat4 model;
+
+void main(void)
+{
+    gl_Position = mvp * vec4(position, 1.0);
+    vs_out.normal = mat3(model) * normal;
+    vs_out.uv = uv;
+    vs_out.color = color;
+}
@@ -1,199 +1,1443 @@
+extern crate self as app_core;
+
 pub mod support {
     pub mod app;
+    pub mod camera;
+    pub mod camera_controller;
+    pub mod compressed_image;
+    pub mod console;
+    pub mod depth;
+    pub mod file_dialog;
+    #[cfg(test)]
+    pub mod headless;
+    pub mod image_data;
+    pub mod import;
+    pub mod inspector;
+    pub mod light;
+    pub mod log_console;
+    pub mod material;
+    pub mod mesh;
+    pub mod program_cache;
+    pub mod program_pipeline;
+    pub mod recent_files;
+    pub mod reflect;
+    pub mod resource;
+    pub mod scene_file;
+    pub mod scene_graph;
+    pub mod scene_tree;
     pub mod shader;
+    pub mod shader_error;
+    pub mod shader_validation;
+    pub mod texture;
+    pub mod transform;
+    pub mod transform_feedback;
+    pub mod undo;
+    pub mod vertex;
 }
 
-use anyhow::Result;
+use anyhow::{Context, Result, bail};
 use egui::MenuBar;
 use gl::types::*;
-use std::{mem, ptr};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use support::app::App;
+use support::camera::Camera;
+use support::camera_controller::{
+    CameraController, ControllerInput, ControllerMode, FlyController, OrbitController,
+    PanZoomController,
+};
+use support::console::{CommandConsole, ConsoleEvent};
+use support::depth::{DepthMode, FloatDepthTarget};
+use support::file_dialog::FileDialog;
+use support::image_data::{ImageData, SourceImage};
+use support::import::{CameraData, ImportedScene, ProjectionData, TextureData};
+use support::inspector::InspectorPanel;
+use support::light::{Light, LightKind};
+use support::log_console::LogConsole;
+use support::material::Material;
+use support::mesh::{BoundingBox, GpuMesh, Mesh, MeshVertex};
+use support::program_cache::ProgramCache;
+use support::recent_files::RecentFiles;
+use support::scene_file::{
+    ImportRecord, MeshSource, NodeRecord, Primitive, SCENE_FILE_EXTENSIONS, SCENE_FILE_VERSION,
+    SceneFile, SceneSettings, relative_path,
+};
+use support::scene_graph::{Node, NodeId, SceneGraph};
+use support::scene_tree::{NodeTemplate, SceneTreeAction, SceneTreePanel};
 use support::shader::ShaderProgram;
+use support::texture::{Texture2D, TextureOptions};
+use support::transform::Transform;
+use support::undo::{UndoCommand, UndoStack};
+use support::vertex::validate_layout;
+
+const PROGRAM_CACHE_DIRECTORY: &str = "opengl-example-program-cache";
+const CONSOLE_CONFIG: &str = "console.cfg";
+const RECENT_FILES: &str = "recent_files.txt";
+
+#[derive(Clone, Debug)]
+pub struct ImportedAsset {
+    pub scene: ImportedScene,
+    pub root: Option<NodeId>,
+    pub nodes: Vec<Option<NodeId>>,
+    pub animation: Option<usize>,
+    pub animation_time: f32,
+}
 
 pub struct Scene {
-    pub model: nalgebra_glm::Mat4,
-    pub projection: nalgebra_glm::Mat4,
-    pub vao: GLuint,
-    pub vbo: GLuint,
-    pub ibo: GLuint,
+    pub graph: SceneGraph,
+    pub turntable: Option<NodeId>,
+    pub camera: Camera,
+    pub active_camera: Option<NodeId>,
+    pub meshes: Vec<GpuMesh>,
+    pub mesh_sources: Vec<MeshSource>,
+    pub materials: Vec<Material>,
+    pub images: Vec<SourceImage>,
+    pub textures: Vec<TextureData>,
+    pub gpu_textures: Vec<Option<Texture2D>>,
+    pub imports: Vec<ImportedAsset>,
+    pub program_cache: ProgramCache,
     pub shader_program: ShaderProgram,
     pub mvp_location: GLint,
+    pub model_location: GLint,
+    pub base_color_location: GLint,
+    pub base_color_texture_location: GLint,
     pub aspect_ratio: f32,
-    pub projection_dirty: bool,
+    pub turntable_speed: f32,
+    pub wireframe: bool,
+    pub depth_mode: DepthMode,
+    pub clip_control: bool,
+    pub float_depth: bool,
+    pub depth_target: Option<FloatDepthTarget>,
+    pub viewport: (u32, u32),
 }
 
 impl Scene {
     pub fn new() -> Result<Self> {
-        let mut vao = 0;
-        let mut vbo = 0;
-        let mut ibo = 0;
+        let mesh = Primitive::Triangle.mesh().upload()?;
 
-        unsafe {
-            gl::GenVertexArrays(1, &mut vao);
-            gl::BindVertexArray(vao);
-
-            gl::GenBuffers(1, &mut vbo);
-            gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
-            gl::BufferData(
-                gl::ARRAY_BUFFER,
-                (VERTICES.len() * mem::size_of::<Vertex>()) as GLsizeiptr,
-                VERTICES.as_ptr() as *const gl::types::GLvoid,
-                gl::STATIC_DRAW,
-            );
-
-            gl::GenBuffers(1, &mut ibo);
-            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, ibo);
-            gl::BufferData(
-                gl::ELEMENT_ARRAY_BUFFER,
-                (INDICES.len() * mem::size_of::<u32>()) as GLsizeiptr,
-                INDICES.as_ptr() as *const gl::types::GLvoid,
-                gl::STATIC_DRAW,
-            );
+        let program_cache = ProgramCache::new(std::env::temp_dir().join(PROGRAM_CACHE_DIRECTORY));
+        let shader_program = Self::create_shader_program(&program_cache)?;
 
-            let stride = mem::size_of::<Vertex>() as GLsizei;
-            gl::VertexAttribPointer(0, 4, gl::FLOAT, gl::FALSE, stride, ptr::null());
-            gl::EnableVertexAttribArray(0);
-            gl::VertexAttribPointer(
-                1,
-                4,
-                gl::FLOAT,
-                gl::FALSE,
-                stride,
-                (4 * mem::size_of::<f32>()) as *const _,
-            );
-            gl::EnableVertexAttribArray(1);
-        }
+        let mvp_location = shader_program.uniform_location("mvp");
+        let model_location = shader_program.uniform_location("model");
+        let base_color_location = shader_program.uniform_location("base_color");
+        let base_color_texture_location = shader_program.uniform_location("use_base_color_texture");
 
-        let mut shader_program = ShaderProgram::new();
-        shader_program
-            .vertex_shader("shaders/triangle/triangle.vs.glsl")?
-            .fragment_shader("shaders/triangle/triangle.fs.glsl")?
-            .link()?;
+        let mut graph = SceneGraph::new();
+        let triangle = graph.add(Node::new("Triangle").with_mesh(0), None)?;
 
-        let mvp_location = shader_program.uniform_location("mvp");
+        let clip_control = support::depth::clip_control_supported();
+        if !clip_control {
+            log::warn!("glClipControl is unavailable, reversed-Z falls back to standard depth");
+        }
 
         Ok(Self {
-            model: nalgebra_glm::Mat4::identity(),
-            projection: nalgebra_glm::Mat4::identity(),
-            vao,
-            vbo,
-            ibo,
+            graph,
+            turntable: Some(triangle),
+            camera: Camera::default().with_position(nalgebra_glm::vec3(0.0, 0.0, 3.0)),
+            active_camera: None,
+            meshes: vec![mesh],
+            mesh_sources: vec![MeshSource::Primitive(Primitive::Triangle)],
+            materials: Vec::new(),
+            images: Vec::new(),
+            textures: Vec::new(),
+            gpu_textures: Vec::new(),
+            imports: Vec::new(),
+            program_cache,
             shader_program,
             mvp_location,
+            model_location,
+            base_color_location,
+            base_color_texture_location,
             aspect_ratio: 1.0,
-            projection_dirty: true,
+            turntable_speed: 30.0,
+            wireframe: false,
+            depth_mode: DepthMode::default(),
+            clip_control,
+            float_depth: true,
+            depth_target: None,
+            viewport: (0, 0),
         })
     }
 
-    pub fn update(&mut self, delta_time: f32) {
-        self.model = nalgebra_glm::rotate(
-            &self.model,
-            30_f32.to_radians() * delta_time,
-            &nalgebra_glm::Vec3::y(),
+    fn create_shader_program(program_cache: &ProgramCache) -> Result<ShaderProgram> {
+        let mut shader_program = ShaderProgram::new();
+        shader_program
+            .cache(program_cache)
+            .vertex_shader("shaders/mesh/mesh.vs.glsl")?
+            .fragment_shader("shaders/mesh/mesh.fs.glsl")?
+            .link()?;
+        validate_layout::<MeshVertex>(&shader_program)?;
+        Ok(shader_program)
+    }
+
+    pub fn reload_shaders(&mut self) -> Result<()> {
+        let shader_program = Self::create_shader_program(&self.program_cache)?;
+        self.mvp_location = shader_program.uniform_location("mvp");
+        self.model_location = shader_program.uniform_location("model");
+        self.base_color_location = shader_program.uniform_location("base_color");
+        self.base_color_texture_location =
+            shader_program.uniform_location("use_base_color_texture");
+        self.shader_program = shader_program;
+        Ok(())
+    }
+
+    pub fn import(&mut self, mut imported: ImportedScene) -> Result<()> {
+        let mesh_offset = self.import_resources(&mut imported)?;
+
+        let root = self.graph.add(Node::new(&imported.name), None)?;
+        let mut nodes = vec![Some(root); imported.nodes.len()];
+        let mut stack = imported
+            .roots
+            .iter()
+            .rev()
+            .map(|index| (*index, root))
+            .collect::<Vec<_>>();
+        while let Some((index, parent)) = stack.pop() {
+            let source = &imported.nodes[index];
+            let mut node = Node::new(&source.name).with_transform(source.transform);
+            node.mesh = source.meshes.first().map(|mesh| mesh_offset + mesh);
+            node.camera = source
+                .camera
+                .and_then(|camera| imported.cameras.get(camera).cloned());
+            node.light = source
+                .light
+                .and_then(|light| imported.lights.get(light).cloned());
+            let id = self.graph.add(node, Some(parent))?;
+            for mesh in source.meshes.iter().skip(1) {
+                let name = format!("{}.{}", source.name, mesh);
+                self.graph
+                    .add(Node::new(&name).with_mesh(mesh_offset + mesh), Some(id))?;
+            }
+            nodes[index] = Some(id);
+            stack.extend(source.children.iter().rev().map(|child| (*child, id)));
+        }
+
+        self.imports.push(ImportedAsset {
+            animation: (!imported.animations.is_empty()).then_some(0),
+            animation_time: 0.0,
+            scene: imported,
+            root: Some(root),
+            nodes,
+        });
+        Ok(())
+    }
+
+    fn import_resources(&mut self, imported: &mut ImportedScene) -> Result<usize> {
+        let mesh_offset = self.meshes.len();
+        let material_offset = self.materials.len();
+        let texture_offset = self.textures.len();
+        let image_offset = self.images.len();
+
+        let mut meshes = Vec::with_capacity(imported.meshes.len());
+        for mut mesh in std::mem::take(&mut imported.meshes) {
+            for submesh in &mut mesh.submeshes {
+                submesh.material = submesh.material.map(|material| material + material_offset);
+            }
+            meshes.push(mesh.upload()?);
+        }
+        let import = self.imports.len();
+        self.mesh_sources
+            .extend((0..meshes.len()).map(|mesh| MeshSource::Import { import, mesh }));
+        self.meshes.extend(meshes);
+
+        for mut material in std::mem::take(&mut imported.materials) {
+            for texture in [
+                &mut material.base_color_texture,
+                &mut material.metallic_roughness_texture,
+                &mut material.normal_texture,
+                &mut material.occlusion_texture,
+                &mut material.emissive_texture,
+            ]
+            .into_iter()
+            .flatten()
+            {
+                texture.texture += texture_offset;
+            }
+            self.materials.push(material);
+        }
+        self.textures.extend(
+            std::mem::take(&mut imported.textures)
+                .into_iter()
+                .map(|texture| TextureData {
+                    image: texture.image + image_offset,
+                    ..texture
+                }),
         );
+        self.images.extend(std::mem::take(&mut imported.images));
+
+        for index in texture_offset..self.textures.len() {
+            let texture = &self.textures[index];
+            let srgb = self.materials[material_offset..].iter().any(|material| {
+                material
+                    .color_textures()
+                    .any(|color| color.texture == index)
+            });
+            let options = TextureOptions {
+                srgb,
+                mipmaps: true,
+                sampler: texture.sampler,
+            };
+            let uploaded = self
+                .images
+                .get(texture.image)
+                .context("Texture references a missing image")
+                .and_then(|image| Texture2D::from_source(image, options));
+            match uploaded {
+                Ok(uploaded) => self.gpu_textures.push(Some(uploaded)),
+                Err(error) => {
+                    log::warn!("Failed to upload texture '{}': {:?}", texture.name, error);
+                    self.gpu_textures.push(None);
+                }
+            }
+        }
+        Ok(mesh_offset)
+    }
+
+    pub fn add_mesh(&mut self, mesh: &Mesh, source: MeshSource) -> Result<usize> {
+        self.meshes.push(mesh.upload()?);
+        self.mesh_sources.push(source);
+        Ok(self.meshes.len() - 1)
+    }
+
+    pub fn create_node(
+        &mut self,
+        template: NodeTemplate,
+        parent: Option<NodeId>,
+    ) -> Result<NodeId> {
+        let mut node = Node::new(template.name());
+        let primitive = match template {
+            NodeTemplate::Cube => Some(Primitive::Cube),
+            NodeTemplate::Sphere => Some(Primitive::Sphere),
+            NodeTemplate::Plane => Some(Primitive::Plane),
+            NodeTemplate::Cylinder => Some(Primitive::Cylinder),
+            NodeTemplate::Torus => Some(Primitive::Torus),
+            _ => None,
+        };
+        if let Some(primitive) = primitive {
+            node.mesh = Some(self.add_mesh(&primitive.mesh(), MeshSource::Primitive(primitive))?);
+        }
+        node.light = match template {
+            NodeTemplate::DirectionalLight => Some(LightKind::Directional),
+            NodeTemplate::PointLight => Some(LightKind::Point),
+            NodeTemplate::SpotLight => Some(LightKind::Spot {
+                inner_cone_angle: 0.0,
+                outer_cone_angle: std::f32::consts::FRAC_PI_4,
+            }),
+            _ => None,
+        }
+        .map(|kind| Light {
+            name: template.name().to_string(),
+            kind,
+            ..Default::default()
+        });
+        if template == NodeTemplate::Camera {
+            node.camera = Some(CameraData {
+                name: template.name().to_string(),
+                projection: ProjectionData::Perspective {
+                    yfov: 60_f32.to_radians(),
+                    aspect_ratio: None,
+                    znear: 0.1,
+                    zfar: None,
+                },
+            });
+        }
+        self.graph.add(node, parent)
+    }
+
+    pub fn duplicate_node(&mut self, id: NodeId) -> Result<NodeId> {
+        let Some(node) = self.graph.get(id) else {
+            bail!("Node {} does not exist", id);
+        };
+        let parent = node.parent();
+        let mut copy = node.clone();
+        copy.name = format!("{} Copy", node.name);
+        let duplicate = self.graph.add(copy, parent)?;
+
+        let mut stack = vec![(id, duplicate)];
+        while let Some((source, target)) = stack.pop() {
+            let children = self
+                .graph
+                .get(source)
+                .map(|node| node.children().to_vec())
+                .unwrap_or_default();
+            for child in children {
+                let Some(node) = self.graph.get(child).cloned() else {
+                    continue;
+                };
+                let copy = self.graph.add(node, Some(target))?;
+                stack.push((child, copy));
+            }
+        }
+        Ok(duplicate)
+    }
+
+    pub fn delete_node(&mut self, id: NodeId) -> bool {
+        let removed = self.graph.remove(id).is_some();
+        if self
+            .turntable
+            .is_some_and(|turntable| !self.graph.contains(turntable))
+        {
+            self.turntable = None;
+        }
+        if self
+            .active_camera
+            .is_some_and(|camera| !self.graph.contains(camera))
+        {
+            self.active_camera = None;
+        }
+        removed
+    }
+
+    pub fn reparent_nodes(
+        &mut self,
+        nodes: &[NodeId],
+        parent: Option<NodeId>,
+        position: usize,
+    ) -> Result<()> {
+        let nodes = top_level_nodes(&self.graph, nodes);
+        self.graph.move_nodes(&nodes, parent, position, true)
+    }
+
+    pub fn to_file(&self, directory: &Path) -> SceneFile {
+        let order = self.graph.depth_first();
+        let node_index = order
+            .iter()
+            .enumerate()
+            .map(|(index, id)| (*id, index))
+            .collect::<HashMap<_, _>>();
+
+        let mut mesh_index = HashMap::new();
+        let mut meshes = Vec::new();
+        let nodes = order
+            .iter()
+            .filter_map(|id| self.graph.get(*id))
+            .map(|node| NodeRecord {
+                name: node.name.clone(),
+                parent: node
+                    .parent()
+                    .and_then(|parent| node_index.get(&parent).copied()),
+                visible: node.visible,
+                transform: *node.transform(),
+                mesh: node
+                    .mesh
+                    .and_then(|mesh| Some((mesh, *self.mesh_sources.get(mesh)?)))
+                    .map(|(mesh, source)| {
+                        *mesh_index.entry(mesh).or_insert_with(|| {
+                            meshes.push(source);
+                            meshes.len() - 1
+                        })
+                    }),
+                camera: node.camera.clone(),
+                light: node.light.clone(),
+            })
+            .collect();
+
+        SceneFile {
+            version: SCENE_FILE_VERSION,
+            settings: SceneSettings {
+                turntable: self
+                    .turntable
+                    .and_then(|turntable| node_index.get(&turntable).copied()),
+                turntable_speed: self.turntable_speed,
+                wireframe: self.wireframe,
+                camera: self
+                    .active_camera
+                    .and_then(|camera| node_index.get(&camera).copied()),
+            },
+            imports: self
+                .imports
+                .iter()
+                .map(|asset| ImportRecord {
+                    path: relative_path(&asset.scene.source, directory),
+                    root: asset.root.and_then(|root| node_index.get(&root).copied()),
+                    nodes: asset
+                        .nodes
+                        .iter()
+                        .map(|node| node.and_then(|node| node_index.get(&node).copied()))
+                        .collect(),
+                })
+                .collect(),
+            meshes,
+            materials: self.materials.clone(),
+            nodes,
+        }
+    }
+
+    pub fn from_file(file: &SceneFile, directory: &Path) -> Result<Self> {
+        file.validate()?;
+        let mut scene = Self::new()?;
+        scene.graph.clear();
+        scene.turntable = None;
+        scene.meshes.clear();
+        scene.mesh_sources.clear();
+
+        let mut imported_scenes = Vec::with_capacity(file.imports.len());
+        let mut mesh_offsets = Vec::with_capacity(file.imports.len());
+        for record in &file.imports {
+            let mut imported = support::import::import_file(directory.join(&record.path))?;
+            mesh_offsets.push((scene.import_resources(&mut imported)?, scene.meshes.len()));
+            scene.imports.push(ImportedAsset {
+                scene: ImportedScene::default(),
+                root: None,
+                nodes: Vec::new(),
+                animation: None,
+                animation_time: 0.0,
+            });
+            imported_scenes.push(imported);
+        }
+
+        let meshes = file
+            .meshes
+            .iter()
+            .enumerate()
+            .map(|(index, source)| match *source {
+                MeshSource::Primitive(primitive) => scene.add_mesh(&primitive.mesh(), *source),
+                MeshSource::Import { import, mesh } => {
+                    let Some((start, end)) = mesh_offsets.get(import).copied() else {
+                        bail!(
+                            "Mesh {} refers to import {} but the scene has {} imports",
+                            index,
+                            import,
+                            file.imports.len()
+                        );
+                    };
+                    if start + mesh >= end {
+                        bail!(
+                            "'{}' no longer contains mesh {}",
+                            file.imports[import].path.display(),
+                            mesh
+                        );
+                    }
+                    Ok(start + mesh)
+                }
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        if file.materials.len() != scene.materials.len() {
+            log::warn!(
+                "Scene has {} materials but its imports provide {}, restoring the common ones",
+                file.materials.len(),
+                scene.materials.len()
+            );
+        }
+        for (target, material) in scene.materials.iter_mut().zip(&file.materials) {
+            *target = material.clone();
+        }
+
+        let mut nodes = Vec::with_capacity(file.nodes.len());
+        for (index, record) in file.nodes.iter().enumerate() {
+            let mut node = Node::new(&record.name).with_transform(record.transform);
+            node.visible = record.visible;
+            node.mesh = match record.mesh {
+                Some(mesh) => match meshes.get(mesh) {
+                    Some(mesh) => Some(*mesh),
+                    None => bail!(
+                        "Node {} refers to mesh {} but the scene has {} meshes",
+                        index,
+                        mesh,
+                        meshes.len()
+                    ),
+                },
+                None => None,
+            };
+            node.camera = record.camera.clone();
+            node.light = record.light.clone();
+            // Parents are written before their children, so only nodes read so far qualify.
+            let parent = match record.parent {
+                Some(parent) => match nodes.get(parent) {
+                    Some(parent) => Some(*parent),
+                    None => bail!(
+                        "Node {} has parent {} which does not come before it",
+                        index,
+                        parent
+                    ),
+                },
+                None => None,
+            };
+            nodes.push(scene.graph.add(node, parent)?);
+        }
+
+        let node = |index: Option<usize>| index.and_then(|index| nodes.get(index).copied());
+        for ((asset, record), imported) in scene
+            .imports
+            .iter_mut()
+            .zip(&file.imports)
+            .zip(imported_scenes)
+        {
+            asset.animation = (!imported.animations.is_empty()).then_some(0);
+            asset.scene = imported;
+            asset.root = node(record.root);
+            asset.nodes = record.nodes.iter().map(|index| node(*index)).collect();
+        }
+        scene.turntable = node(file.settings.turntable);
+        scene.active_camera = node(file.settings.camera);
+        scene.turntable_speed = file.settings.turntable_speed;
+        scene.wireframe = file.settings.wireframe;
+        scene.graph.update_world_transforms();
+        Ok(scene)
+    }
+
+    pub fn update(&mut self, delta_time: f32) {
+        if let Some(node) = self.turntable.and_then(|id| self.graph.get_mut(id)) {
+            let transform = node.transform_mut();
+            transform.rotation = nalgebra_glm::quat_rotate(
+                &transform.rotation,
+                self.turntable_speed.to_radians() * delta_time,
+                &nalgebra_glm::Vec3::y(),
+            );
+        }
+        self.update_depth_target();
+        for asset in &mut self.imports {
+            let Some(animation) = asset
+                .animation
+                .and_then(|animation| asset.scene.animations.get(animation))
+            else {
+                continue;
+            };
+            asset.animation_time += delta_time;
+            let duration = animation.duration();
+            if duration > 0.0 {
+                asset.animation_time %= duration;
+            }
+            for channel in &animation.channels {
+                if let Some(node) = asset
+                    .nodes
+                    .get(channel.node)
+                    .copied()
+                    .flatten()
+                    .and_then(|id| self.graph.get_mut(id))
+                {
+                    channel.apply(asset.animation_time, node.transform_mut());
+                }
+            }
+        }
+        self.graph.update_world_transforms();
+    }
+
+    /// Reversed-Z draws into a float depth buffer of its own, which is dropped again when
+    /// standard depth is selected. If it cannot be created, reversed-Z uses the window's
+    /// depth buffer instead.
+    fn update_depth_target(&mut self) {
+        let (width, height) = self.viewport;
+        if self.depth_mode.resolve(self.clip_control) != DepthMode::ReversedZ
+            || !self.float_depth
+            || width == 0
+            || height == 0
+        {
+            self.depth_target = None;
+            return;
+        }
+        if self.depth_target.is_none() {
+            log::info!(
+                "Reversed-Z renders into a 32-bit float depth buffer instead of the window's {}-bit one",
+                support::depth::depth_bits()
+            );
+        }
+        let result = match &mut self.depth_target {
+            Some(target) => target.resize(width, height),
+            None => FloatDepthTarget::new().and_then(|mut target| {
+                target.resize(width, height)?;
+                self.depth_target = Some(target);
+                Ok(())
+            }),
+        };
+        if let Err(error) = result {
+            log::error!("Failed to create the reversed-Z depth target: {:?}", error);
+            self.depth_target = None;
+            self.float_depth = false;
+        }
     }
 
     pub fn render(&self, _time: f32) {
+        let depth_mode = self.depth_mode.resolve(self.clip_control);
+        let offscreen = depth_mode == DepthMode::ReversedZ
+            && self
+                .depth_target
+                .as_ref()
+                .is_some_and(FloatDepthTarget::bind);
         unsafe {
             gl::Enable(gl::DEPTH_TEST);
-            gl::DepthFunc(gl::LESS);
-
+        }
+        depth_mode.apply(self.clip_control);
+        unsafe {
             gl::ClearColor(0.19, 0.24, 0.42, 1.0);
             gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
         }
 
-        let view = nalgebra_glm::look_at_lh(
-            &nalgebra_glm::vec3(0.0, 0.0, 3.0),
-            &nalgebra_glm::vec3(0.0, 0.0, 0.0),
-            &nalgebra_glm::Vec3::y(),
-        );
-        let mvp = self.projection * view * self.model;
+        let camera = self.view_camera();
 
         self.shader_program.activate();
+        if self.wireframe {
+            unsafe {
+                gl::PolygonMode(gl::FRONT_AND_BACK, gl::LINE);
+            }
+        }
+
+        for (_, node) in self.graph.iter() {
+            if !node.is_visible_in_hierarchy() {
+                continue;
+            }
+            let Some(mesh) = node.mesh.and_then(|mesh| self.meshes.get(mesh)) else {
+                continue;
+            };
+            let model = node.world_matrix();
+            let mvp = camera.view_projection() * model;
+
+            unsafe {
+                gl::UniformMatrix4fv(self.mvp_location, 1, gl::FALSE, mvp.as_ptr());
+                gl::UniformMatrix4fv(self.model_location, 1, gl::FALSE, model.as_ptr());
+            }
+
+            if mesh.submeshes.is_empty() {
+                self.apply_material(None);
+                mesh.draw();
+                continue;
+            }
+            for (index, submesh) in mesh.submeshes.iter().enumerate() {
+                self.apply_material(submesh.material);
+                mesh.draw_submesh(index);
+            }
+        }
 
+        if self.wireframe {
+            unsafe {
+                gl::PolygonMode(gl::FRONT_AND_BACK, gl::FILL);
+            }
+        }
+        // egui samples its textures on unit 0 without a sampler object.
         unsafe {
-            gl::UniformMatrix4fv(self.mvp_location, 1, gl::FALSE, mvp.as_ptr());
-
-            gl::BindVertexArray(self.vao);
-            gl::DrawElements(
-                gl::TRIANGLES,
-                INDICES.len() as _,
-                gl::UNSIGNED_INT,
-                ptr::null(),
-            );
+            gl::BindSampler(0, 0);
+        }
+        if offscreen && let Some(target) = &self.depth_target {
+            target.composite();
         }
     }
 
-    pub fn set_aspect_ratio(&mut self, width: u32, height: u32) {
-        let new_aspect_ratio = width as f32 / height.max(1) as f32;
-        if (new_aspect_ratio - self.aspect_ratio).abs() > f32::EPSILON {
-            self.aspect_ratio = new_aspect_ratio;
-            self.projection_dirty = true;
+    fn apply_material(&self, material: Option<usize>) {
+        let material = material.and_then(|material| self.materials.get(material));
+        let (color, point_size) = material
+            .map_or((nalgebra_glm::vec4(1.0, 1.0, 1.0, 1.0), 1.0), |material| {
+                (material.base_color, material.point_size)
+            });
+        let texture = material
+            .and_then(|material| material.base_color_texture)
+            .and_then(|texture| self.gpu_textures.get(texture.texture)?.as_ref());
+        if let Some(texture) = texture {
+            texture.bind(0);
+        }
+        unsafe {
+            gl::Uniform4fv(self.base_color_location, 1, color.as_ptr());
+            gl::Uniform1i(self.base_color_texture_location, texture.is_some() as GLint);
+            gl::PointSize(point_size.max(1.0));
         }
     }
 
-    pub fn update_projection(&mut self) {
-        if self.projection_dirty {
-            self.projection = nalgebra_glm::perspective_lh_zo(
-                self.aspect_ratio,
-                80_f32.to_radians(),
-                0.1,
-                1000.0,
-            );
-            self.projection_dirty = false;
+    pub fn set_aspect_ratio(&mut self, width: u32, height: u32) {
+        self.viewport = (width, height);
+        self.aspect_ratio = width as f32 / height.max(1) as f32;
+        self.camera.set_aspect_ratio(self.aspect_ratio);
+    }
+
+    /// World-space bounds of `nodes` and their descendants. Nodes without a mesh contribute
+    /// their position.
+    pub fn bounds(&self, nodes: &[NodeId]) -> BoundingBox {
+        let mut bounds = BoundingBox::empty();
+        for id in nodes.iter().flat_map(|id| self.graph.descendants(*id)) {
+            let Some(node) = self.graph.get(id) else {
+                continue;
+            };
+            let world = node.world_matrix();
+            match node.mesh.and_then(|mesh| self.meshes.get(mesh)) {
+                Some(mesh) if !mesh.bounds.is_empty() => {
+                    bounds = bounds.union(&mesh.bounds.transform(world));
+                }
+                _ => bounds.extend(&world.column(3).xyz()),
+            }
         }
+        bounds
+    }
+
+    /// The camera the scene is rendered from: the active camera node when there is one,
+    /// otherwise the free editor camera.
+    pub fn view_camera(&self) -> Camera {
+        let mut camera = self
+            .active_camera
+            .and_then(|id| self.graph.get(id))
+            .and_then(|node| {
+                let data = node.camera.as_ref()?;
+                Some(
+                    Camera::from_node(data, node.world_matrix(), self.aspect_ratio)
+                        .with_handedness(self.camera.handedness()),
+                )
+            })
+            .unwrap_or_else(|| self.camera.clone());
+        self.depth_mode
+            .resolve(self.clip_control)
+            .configure(&mut camera, self.clip_control);
+        camera
     }
 }
 
-impl Drop for Scene {
-    fn drop(&mut self) {
-        unsafe {
-            gl::DeleteBuffers(1, &self.ibo);
-            gl::DeleteBuffers(1, &self.vbo);
-            gl::DeleteVertexArrays(1, &self.vao);
+#[derive(Clone, Debug, PartialEq)]
+pub struct NodeState {
+    pub name: String,
+    pub visible: bool,
+    pub transform: Transform,
+    pub camera: Option<CameraData>,
+    pub light: Option<Light>,
+}
+
+impl NodeState {
+    pub fn capture(node: &Node) -> Self {
+        Self {
+            name: node.name.clone(),
+            visible: node.visible,
+            transform: *node.transform(),
+            camera: node.camera.clone(),
+            light: node.light.clone(),
         }
     }
+
+    pub fn apply(&self, node: &mut Node) {
+        node.name = self.name.clone();
+        node.visible = self.visible;
+        node.set_transform(self.transform);
+        node.camera = self.camera.clone();
+        node.light = self.light.clone();
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SceneSnapshot {
+    pub graph: SceneGraph,
+    pub turntable: Option<NodeId>,
+    pub active_camera: Option<NodeId>,
+    pub materials: Vec<Material>,
+    pub imports: Vec<ImportedAsset>,
+}
+
+impl SceneSnapshot {
+    pub fn capture(scene: &Scene) -> Box<Self> {
+        Box::new(Self {
+            graph: scene.graph.clone(),
+            turntable: scene.turntable,
+            active_camera: scene.active_camera,
+            materials: scene.materials.clone(),
+            imports: scene.imports.clone(),
+        })
+    }
+
+    pub fn restore(&self, scene: &mut Scene) {
+        scene.graph = self.graph.clone();
+        scene.turntable = self.turntable;
+        scene.active_camera = self.active_camera;
+        scene.materials.clone_from(&self.materials);
+        scene.imports.clone_from(&self.imports);
+        scene.graph.update_world_transforms();
+    }
 }
 
-#[repr(C)]
-#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
-struct Vertex {
-    position: [f32; 4],
-    color: [f32; 4],
+/// A scene together with its camera and settings, which Edit > Reset returns to the state the
+/// scene started from.
+#[derive(Clone, Debug)]
+pub struct SceneBaseline {
+    snapshot: Box<SceneSnapshot>,
+    camera: Camera,
+    turntable_speed: f32,
+    wireframe: bool,
+    depth_mode: DepthMode,
 }
 
-const VERTICES: [Vertex; 3] = [
-    Vertex {
-        position: [1.0, -1.0, 0.0, 1.0],
-        color: [1.0, 0.0, 0.0, 1.0],
+impl SceneBaseline {
+    fn capture(scene: &Scene) -> Self {
+        Self {
+            snapshot: SceneSnapshot::capture(scene),
+            camera: scene.camera.clone(),
+            turntable_speed: scene.turntable_speed,
+            wireframe: scene.wireframe,
+            depth_mode: scene.depth_mode,
+        }
+    }
+
+    fn restore(&self, scene: &mut Scene) {
+        self.snapshot.restore(scene);
+        let aspect_ratio = scene.aspect_ratio;
+        scene.camera = self.camera.clone();
+        scene.camera.set_aspect_ratio(aspect_ratio);
+        scene.turntable_speed = self.turntable_speed;
+        scene.wireframe = self.wireframe;
+        scene.depth_mode = self.depth_mode;
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum SceneCommand {
+    EditNode {
+        id: NodeId,
+        before: Box<NodeState>,
+        after: Box<NodeState>,
+    },
+    EditMaterial {
+        index: usize,
+        before: Box<Material>,
+        after: Box<Material>,
     },
-    Vertex {
-        position: [-1.0, -1.0, 0.0, 1.0],
-        color: [0.0, 1.0, 0.0, 1.0],
+    Structure {
+        label: String,
+        before: Box<SceneSnapshot>,
+        after: Box<SceneSnapshot>,
     },
-    Vertex {
-        position: [0.0, 1.0, 0.0, 1.0],
-        color: [0.0, 0.0, 1.0, 1.0],
+    Reset {
+        before: Box<SceneBaseline>,
+        after: Box<SceneBaseline>,
     },
-];
+}
+
+impl UndoCommand for SceneCommand {
+    type Target = Scene;
 
-const INDICES: [u32; 3] = [0, 1, 2];
+    fn label(&self) -> String {
+        match self {
+            SceneCommand::EditNode { before, after, .. } => {
+                if before.name != after.name {
+                    format!("Rename '{}' to '{}'", before.name, after.name)
+                } else if before.visible != after.visible {
+                    let verb = if after.visible { "Show" } else { "Hide" };
+                    format!("{} '{}'", verb, after.name)
+                } else if before.transform != after.transform {
+                    format!("Transform '{}'", after.name)
+                } else {
+                    format!("Edit '{}'", after.name)
+                }
+            }
+            SceneCommand::EditMaterial { after, .. } => format!("Edit material '{}'", after.name),
+            SceneCommand::Structure { label, .. } => label.clone(),
+            SceneCommand::Reset { .. } => "Reset scene".to_string(),
+        }
+    }
+
+    fn undo(&self, scene: &mut Scene) {
+        match self {
+            SceneCommand::EditNode { id, before, .. } => {
+                if let Some(node) = scene.graph.get_mut(*id) {
+                    before.apply(node);
+                }
+            }
+            SceneCommand::EditMaterial { index, before, .. } => {
+                if let Some(material) = scene.materials.get_mut(*index) {
+                    *material = (**before).clone();
+                }
+            }
+            SceneCommand::Structure { before, .. } => before.restore(scene),
+            SceneCommand::Reset { before, .. } => before.restore(scene),
+        }
+    }
+
+    fn redo(&self, scene: &mut Scene) {
+        match self {
+            SceneCommand::EditNode { id, after, .. } => {
+                if let Some(node) = scene.graph.get_mut(*id) {
+                    after.apply(node);
+                }
+            }
+            SceneCommand::EditMaterial { index, after, .. } => {
+                if let Some(material) = scene.materials.get_mut(*index) {
+                    *material = (**after).clone();
+                }
+            }
+            SceneCommand::Structure { after, .. } => after.restore(scene),
+            SceneCommand::Reset { after, .. } => after.restore(scene),
+        }
+    }
+
+    fn merge(&mut self, next: &Self) -> bool {
+        match (self, next) {
+            (
+                SceneCommand::EditNode { id, after, .. },
+                SceneCommand::EditNode {
+                    id: next_id,
+                    after: next_after,
+                    ..
+                },
+            ) if id == next_id => {
+                *after = next_after.clone();
+                true
+            }
+            (
+                SceneCommand::EditMaterial { index, after, .. },
+                SceneCommand::EditMaterial {
+                    index: next_index,
+                    after: next_after,
+                    ..
+                },
+            ) if index == next_index => {
+                *after = next_after.clone();
+                true
+            }
+            _ => false,
+        }
+    }
+}
 
 #[derive(Default)]
 pub struct TriangleApp {
     scene: Option<Scene>,
+    import_dialog: Option<FileDialog>,
+    scene_tree: SceneTreePanel,
+    inspector: InspectorPanel,
+    console: LogConsole,
+    commands: CommandConsole,
+    screenshot: Option<PathBuf>,
+    scene_path: Option<PathBuf>,
+    recent_files: RecentFiles,
+    load_dialog: Option<FileDialog>,
+    save_dialog: Option<FileDialog>,
+    history: UndoStack<SceneCommand>,
+    show_history: bool,
+    baseline: Option<SceneBaseline>,
+    confirm_clear: bool,
+    controller_mode: ControllerMode,
+    orbit: OrbitController,
+    fly: FlyController,
+    pan_zoom: PanZoomController,
+    controller_input: ControllerInput,
+}
+
+impl TriangleApp {
+    fn apply_scene_tree_action(&mut self, action: SceneTreeAction) -> Result<()> {
+        let Some(scene) = &mut self.scene else {
+            return Ok(());
+        };
+        let before = SceneSnapshot::capture(scene);
+        let label = match action {
+            SceneTreeAction::Create { template, parent } => {
+                let id = scene.create_node(template, parent)?;
+                self.scene_tree.select(id);
+                format!("Create {}", template.name())
+            }
+            SceneTreeAction::Duplicate(nodes) => {
+                let duplicates = top_level_nodes(&scene.graph, &nodes)
+                    .into_iter()
+                    .map(|node| scene.duplicate_node(node))
+                    .collect::<Result<Vec<_>>>()?;
+                self.scene_tree.set_selection(duplicates);
+                nodes_label("Duplicate", &scene.graph, &nodes)
+            }
+            SceneTreeAction::Delete(nodes) => {
+                let label = nodes_label("Delete", &scene.graph, &nodes);
+                for node in top_level_nodes(&scene.graph, &nodes) {
+                    scene.delete_node(node);
+                }
+                self.scene_tree.clear_selection();
+                label
+            }
+            SceneTreeAction::Rename(id, name) => {
+                return self.edit_node(id, |node| node.name = name);
+            }
+            SceneTreeAction::SetVisible(id, visible) => {
+                return self.edit_node(id, |node| node.visible = visible);
+            }
+            SceneTreeAction::Reparent {
+                nodes,
+                parent,
+                position,
+            } => {
+                scene.reparent_nodes(&nodes, parent, position)?;
+                nodes_label("Move", &scene.graph, &nodes)
+            }
+        };
+        let after = SceneSnapshot::capture(scene);
+        self.history.push(SceneCommand::Structure {
+            label,
+            before,
+            after,
+        });
+        Ok(())
+    }
+
+    fn edit_node(&mut self, id: NodeId, edit: impl FnOnce(&mut Node)) -> Result<()> {
+        let Some(node) = self
+            .scene
+            .as_mut()
+            .and_then(|scene| scene.graph.get_mut(id))
+        else {
+            return Ok(());
+        };
+        let before = Box::new(NodeState::capture(node));
+        edit(node);
+        let after = Box::new(NodeState::capture(node));
+        if before != after {
+            self.history
+                .push(SceneCommand::EditNode { id, before, after });
+        }
+        Ok(())
+    }
+
+    fn controller(&mut self) -> &mut dyn CameraController {
+        match self.controller_mode {
+            ControllerMode::Orbit => &mut self.orbit,
+            ControllerMode::Fly => &mut self.fly,
+            ControllerMode::PanZoom => &mut self.pan_zoom,
+        }
+    }
+
+    fn set_controller_mode(&mut self, mode: ControllerMode) {
+        let Some(mut camera) = self.scene.as_ref().map(|scene| scene.camera.clone()) else {
+            self.controller_mode = mode;
+            return;
+        };
+        self.controller().release(&mut camera);
+        self.controller_mode = mode;
+        self.controller().sync(&camera);
+        if let Some(scene) = &mut self.scene {
+            scene.camera = camera;
+        }
+    }
+
+    fn sync_controller(&mut self) {
+        if let Some(camera) = self.scene.as_ref().map(|scene| scene.camera.clone()) {
+            self.controller().sync(&camera);
+        }
+    }
+
+    /// Fits the selected nodes, or the whole scene when nothing is selected, into view.
+    fn frame_selection(&mut self) {
+        let Some(scene) = &self.scene else {
+            return;
+        };
+        let nodes = match self.scene_tree.selection() {
+            [] => scene.graph.roots().to_vec(),
+            selection => selection.to_vec(),
+        };
+        let bounds = scene.bounds(&nodes);
+        let camera = scene.camera.clone();
+        self.controller().focus(&camera, &bounds);
+    }
+
+    fn clear_scene(&mut self) {
+        let Some(scene) = &mut self.scene else {
+            return;
+        };
+        let before = SceneSnapshot::capture(scene);
+        scene.graph.clear();
+        scene.turntable = None;
+        self.scene_tree.clear_selection();
+        self.history.push(SceneCommand::Structure {
+            label: "Clear scene".to_string(),
+            before,
+            after: SceneSnapshot::capture(scene),
+        });
+    }
+
+    fn reset_scene(&mut self) -> Result<()> {
+        let (Some(scene), Some(baseline)) = (&mut self.scene, &self.baseline) else {
+            return Ok(());
+        };
+        let before = SceneBaseline::capture(scene);
+        baseline.restore(scene);
+        self.scene_tree.clear_selection();
+        self.history.push(SceneCommand::Reset {
+            before: Box::new(before),
+            after: Box::new(baseline.clone()),
+        });
+        self.sync_scene_settings()
+    }
+
+    fn depth_mode(&self) -> DepthMode {
+        match self.commands.get_bool("r.reversed_z") {
+            Some(false) => DepthMode::Standard,
+            _ => DepthMode::ReversedZ,
+        }
+    }
+
+    /// Pushes the scene's settings back into the cvars and the camera controller after they were
+    /// changed behind their back, as Reset and its undo do.
+    fn sync_scene_settings(&mut self) -> Result<()> {
+        if let Some(scene) = &self.scene {
+            self.commands
+                .sync_value("scene.turntable_speed", scene.turntable_speed.into())?;
+            self.commands
+                .sync_value("r.wireframe", scene.wireframe.into())?;
+            // The depth mode follows this cvar every frame, so it has to agree as well.
+            self.commands.sync_value(
+                "r.reversed_z",
+                (scene.depth_mode == DepthMode::ReversedZ).into(),
+            )?;
+        }
+        self.sync_controller();
+        Ok(())
+    }
+
+    fn confirm_clear(&mut self, ctx: &egui::Context) {
+        let modal = egui::Modal::new(egui::Id::new("confirm_clear")).show(ctx, |ui| {
+            ui.heading("Clear scene?");
+            ui.label("All nodes will be removed. This can be undone.");
+            ui.add_space(8.0);
+            ui.horizontal(|ui| {
+                if ui.button("Clear").clicked() {
+                    self.clear_scene();
+                    self.confirm_clear = false;
+                }
+                if ui.button("Cancel").clicked() {
+                    self.confirm_clear = false;
+                }
+            });
+        });
+        if modal.should_close() {
+            self.confirm_clear = false;
+        }
+    }
+
+    fn undo(&mut self) {
+        if let Some(scene) = &mut self.scene
+            && self.history.undo(scene)
+            && let Err(error) = self.sync_scene_settings()
+        {
+            log::error!("Failed to restore scene settings: {:?}", error);
+        }
+    }
+
+    fn redo(&mut self) {
+        if let Some(scene) = &mut self.scene
+            && self.history.redo(scene)
+            && let Err(error) = self.sync_scene_settings()
+        {
+            log::error!("Failed to restore scene settings: {:?}", error);
+        }
+    }
+
+    fn show_history(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_history;
+        let mut jump = None;
+        egui::Window::new("History")
+            .open(&mut open)
+            .default_width(220.0)
+            .show(ctx, |ui| {
+                let position = self.history.position();
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    let labels =
+                        std::iter::once("Initial state".to_string()).chain(self.history.labels());
+                    for (index, label) in labels.enumerate() {
+                        let text = if index > position {
+                            egui::RichText::new(label).weak()
+                        } else {
+                            egui::RichText::new(label)
+                        };
+                        if ui.selectable_label(index == position, text).clicked() {
+                            jump = Some(index);
+                        }
+                    }
+                });
+            });
+        self.show_history = open;
+        if let Some(position) = jump
+            && let Some(scene) = &mut self.scene
+        {
+            self.history.jump_to(position, scene);
+            if let Err(error) = self.sync_scene_settings() {
+                log::error!("Failed to restore scene settings: {:?}", error);
+            }
+        }
+    }
+
+    fn apply_console_event(&mut self, event: ConsoleEvent) -> Result<()> {
+        let ConsoleEvent::Command { name, args } = event else {
+            return Ok(());
+        };
+        match (name.as_str(), &args[..]) {
+            ("clear", []) => self.console.clear(),
+            ("screenshot", []) => {
+                let seconds = std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map_or(0, |duration| duration.as_secs());
+                self.screenshot = Some(PathBuf::from(format!("screenshot-{}.png", seconds)));
+            }
+            ("screenshot", [path]) => self.screenshot = Some(PathBuf::from(path)),
+            ("reload_shaders", []) => {
+                if let Some(scene) = &mut self.scene {
+                    scene.reload_shaders()?;
+                    log::info!(target: "console", "Shaders reloaded");
+                }
+            }
+            ("import", [path]) => self.import(Path::new(path)),
+            ("load", [path]) => self.load_scene(Path::new(path))?,
+            ("save", []) => match self.scene_path.clone() {
+                Some(path) => self.save_scene(&path)?,
+                None => bail!("The scene has not been saved yet, use 'save <path>'"),
+            },
+            ("save", [path]) => self.save_scene(Path::new(path))?,
+            (name, _) => {
+                let usage = self
+                    .commands
+                    .commands()
+                    .find(|command| command.name == name)
+                    .map_or(name, |command| command.usage.as_str());
+                bail!("Usage: {}", usage);
+            }
+        }
+        Ok(())
+    }
+
+    fn load_scene(&mut self, path: &Path) -> Result<()> {
+        let file = SceneFile::load(path)?;
+        let directory = path.parent().unwrap_or(Path::new("."));
+        let mut scene = Scene::from_file(&file, directory)
+            .with_context(|| format!("Failed to load scene '{}'", path.display()))?;
+        if let Some(current) = &self.scene {
+            scene.aspect_ratio = current.aspect_ratio;
+            scene.viewport = current.viewport;
+            scene.camera = current.camera.clone();
+        }
+        self.commands
+            .sync_value("scene.turntable_speed", scene.turntable_speed.into())?;
+        self.commands
+            .sync_value("r.wireframe", scene.wireframe.into())?;
+        scene.depth_mode = self.depth_mode();
+
+        self.baseline = Some(SceneBaseline::capture(&scene));
+        self.scene = Some(scene);
+        self.scene_tree.clear_selection();
+        self.history.clear();
+        self.scene_path = Some(path.to_path_buf());
+        self.recent_files.add(path);
+        log::info!("Loaded scene '{}'", path.display());
+        Ok(())
+    }
+
+    fn save_scene(&mut self, path: &Path) -> Result<()> {
+        let Some(scene) = &self.scene else {
+            bail!("There is no scene to save");
+        };
+        let directory = path.parent().unwrap_or(Path::new("."));
+        scene.to_file(directory).save(path)?;
+        self.scene_path = Some(path.to_path_buf());
+        self.recent_files.add(path);
+        log::info!("Saved scene '{}'", path.display());
+        Ok(())
+    }
+
+    fn open_scene_dialog(&mut self, save: bool) {
+        let directory = self
+            .scene_path
+            .as_ref()
+            .and_then(|path| path.parent())
+            .unwrap_or(Path::new("."))
+            .to_path_buf();
+        if save {
+            let file_name = self
+                .scene_path
+                .as_ref()
+                .and_then(|path| path.file_name())
+                .map_or("scene.json".to_string(), |name| {
+                    name.to_string_lossy().into_owned()
+                });
+            self.save_dialog = Some(
+                FileDialog::save("Save Scene", SCENE_FILE_EXTENSIONS)
+                    .directory(directory)
+                    .file_name(&file_name),
+            );
+        } else {
+            self.load_dialog =
+                Some(FileDialog::open("Load Scene", SCENE_FILE_EXTENSIONS).directory(directory));
+        }
+    }
+
+    fn save_or_prompt(&mut self) {
+        match self.scene_path.clone() {
+            Some(path) => {
+                if let Err(error) = self.save_scene(&path) {
+                    log::error!("{:?}", error);
+                }
+            }
+            None => self.open_scene_dialog(true),
+        }
+    }
+
+    fn import(&mut self, path: &Path) {
+        let Some(scene) = &mut self.scene else {
+            return;
+        };
+        let before = SceneSnapshot::capture(scene);
+        match support::import::import_file(path).and_then(|imported| scene.import(imported)) {
+            Ok(()) => {
+                let name = path.file_name().unwrap_or(path.as_os_str());
+                self.history.push(SceneCommand::Structure {
+                    label: format!("Import '{}'", name.to_string_lossy()),
+                    before,
+                    after: SceneSnapshot::capture(scene),
+                });
+                log::info!("Imported '{}'", path.display());
+            }
+            Err(error) => log::error!("Failed to import '{}': {:?}", path.display(), error),
+        }
+    }
 }
 
 impl App for TriangleApp {
     fn initialize(&mut self) -> Result<()> {
-        self.scene = Some(Scene::new()?);
+        self.commands = CommandConsole::new().with_config(CONSOLE_CONFIG);
+        self.commands
+            .register_command("clear", "clear", "Clear the log output")
+            .register_command(
+                "screenshot",
+                "screenshot [path]",
+                "Save the next rendered frame as a PNG",
+            )
+            .register_command(
+                "reload_shaders",
+                "reload_shaders",
+                "Recompile the scene shaders from disk",
+            )
+            .register_command("import", "import <path>", "Import a model file")
+            .register_command("load", "load <path>", "Load a scene file")
+            .register_command(
+                "save",
+                "save [path]",
+                "Save the scene, to its current file by default",
+            )
+            .register_cvar("r.vsync", true, "Wait for vertical blank when presenting")
+            .register_cvar("r.wireframe", false, "Draw scene meshes as wireframe")
+            .register_cvar(
+                "r.reversed_z",
+                true,
+                "Use a reversed, infinite-far depth buffer when the driver supports it",
+            )
+            .register_cvar(
+                "scene.turntable_speed",
+                30.0,
+                "Turntable rotation speed in degrees per second",
+            );
+
+        self.recent_files = RecentFiles::load(RECENT_FILES);
+
+        let mut scene = Scene::new()?;
+        scene.depth_mode = self.depth_mode();
+        self.baseline = Some(SceneBaseline::capture(&scene));
+        self.scene = Some(scene);
+        self.sync_controller();
         Ok(())
     }
 
     fn update(&mut self, delta_time: f32) -> Result<()> {
+        let depth_mode = self.depth_mode();
         if let Some(scene) = &mut self.scene {
+            scene.turntable_speed = self
+                .commands
+                .get_float("scene.turntable_speed")
+                .unwrap_or(30.0);
+            scene.wireframe = self.commands.get_bool("r.wireframe").unwrap_or(false);
+            scene.depth_mode = depth_mode;
+            let input = std::mem::take(&mut self.controller_input);
+            if scene.active_camera.is_none() {
+                let controller: &mut dyn CameraController = match self.controller_mode {
+                    ControllerMode::Orbit => &mut self.orbit,
+                    ControllerMode::Fly => &mut self.fly,
+                    ControllerMode::PanZoom => &mut self.pan_zoom,
+                };
+                controller.update(&mut scene.camera, &input, delta_time);
+            }
             scene.update(delta_time);
-            scene.update_projection();
         }
         Ok(())
     }
@@ -202,31 +1446,214 @@ impl App for TriangleApp {
         if let Some(scene) = &self.scene {
             scene.render(time);
         }
+
+        if let Some(path) = self.screenshot.take() {
+            let mut viewport = [0; 4];
+            unsafe {
+                gl::GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr());
+            }
+            let [x, y, width, height] = viewport;
+            ImageData::read_framebuffer(x, y, width as u32, height as u32)?.save(&path)?;
+            log::info!(target: "console", "Saved screenshot '{}'", path.display());
+        }
         Ok(())
     }
 
+    fn vsync(&self) -> Option<bool> {
+        self.commands.get_bool("r.vsync")
+    }
+
     fn render_ui(&mut self, ctx: &egui::Context) -> Result<()> {
+        let save_shortcut = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::S);
+        let load_shortcut = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::O);
+        if ctx.input_mut(|input| input.consume_shortcut(&save_shortcut)) {
+            self.save_or_prompt();
+        }
+        if ctx.input_mut(|input| input.consume_shortcut(&load_shortcut)) {
+            self.open_scene_dialog(false);
+        }
+
+        let undo_shortcut = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Z);
+        let redo_shortcut = egui::KeyboardShortcut::new(
+            egui::Modifiers::COMMAND | egui::Modifiers::SHIFT,
+            egui::Key::Z,
+        );
+        let redo_alt_shortcut = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Y);
+        // Text fields keep their own undo, and a drag stays one step until the pointer is released.
+        let editing = ctx.memory(|memory| memory.focused().is_some());
+        if !editing && !ctx.input(|input| input.pointer.any_down()) {
+            self.history.end_merge();
+        }
+        if !editing {
+            // The plain Ctrl+Z shortcut also matches with Shift held, so check redo first.
+            if ctx.input_mut(|input| {
+                input.consume_shortcut(&redo_shortcut) || input.consume_shortcut(&redo_alt_shortcut)
+            }) {
+                self.redo();
+            } else if ctx.input_mut(|input| input.consume_shortcut(&undo_shortcut)) {
+                self.undo();
+            }
+        }
+
+        let frame_shortcut = egui::KeyboardShortcut::new(egui::Modifiers::NONE, egui::Key::F);
+        let mut frame = !editing && ctx.input_mut(|input| input.consume_shortcut(&frame_shortcut));
+        let mut controller_mode = None;
+        let mut recent = None;
+        let mut reset = false;
         egui::TopBottomPanel::top("top").show(ctx, |ui| {
             ui.horizontal(|ui| {
                 MenuBar::new().ui(ui, |ui| {
                     ui.menu_button("File", |ui| {
-                        if ui.button("Load").clicked() {
+                        if ui
+                            .add(
+                                egui::Button::new("Load")
+                                    .shortcut_text(ui.ctx().format_shortcut(&load_shortcut)),
+                            )
+                            .clicked()
+                        {
+                            self.open_scene_dialog(false);
+                            ui.close();
+                        }
+                        ui.menu_button("Recent", |ui| {
+                            if self.recent_files.paths().is_empty() {
+                                ui.weak("No recent files");
+                                return;
+                            }
+                            for path in self.recent_files.paths() {
+                                if ui.button(path.display().to_string()).clicked() {
+                                    recent = Some(path.clone());
+                                    ui.close();
+                                }
+                            }
+                            ui.separator();
+                            if ui.button("Clear Recent").clicked() {
+                                self.recent_files.clear();
+                                ui.close();
+                            }
+                        });
+                        if ui
+                            .add(
+                                egui::Button::new("Save")
+                                    .shortcut_text(ui.ctx().format_shortcut(&save_shortcut)),
+                            )
+                            .clicked()
+                        {
+                            self.save_or_prompt();
                             ui.close();
                         }
-                        if ui.button("Save").clicked() {
+                        if ui.button("Save As").clicked() {
+                            self.open_scene_dialog(true);
                             ui.close();
                         }
                         ui.separator();
                         if ui.button("Import").clicked() {
+                            self.import_dialog = Some(FileDialog::open(
+                                "Import",
+                                support::import::SUPPORTED_EXTENSIONS,
+                            ));
                             ui.close();
                         }
                     });
 
                     ui.menu_button("Edit", |ui| {
+                        let undo_label = self.history.undo_label();
+                        let undo = egui::Button::new(match &undo_label {
+                            Some(label) => format!("Undo {}", label),
+                            None => "Undo".to_string(),
+                        })
+                        .shortcut_text(ui.ctx().format_shortcut(&undo_shortcut));
+                        if ui.add_enabled(undo_label.is_some(), undo).clicked() {
+                            self.undo();
+                            ui.close();
+                        }
+                        let redo_label = self.history.redo_label();
+                        let redo = egui::Button::new(match &redo_label {
+                            Some(label) => format!("Redo {}", label),
+                            None => "Redo".to_string(),
+                        })
+                        .shortcut_text(ui.ctx().format_shortcut(&redo_shortcut));
+                        if ui.add_enabled(redo_label.is_some(), redo).clicked() {
+                            self.redo();
+                            ui.close();
+                        }
+                        ui.checkbox(&mut self.show_history, "History");
+                        ui.separator();
                         if ui.button("Clear").clicked() {
+                            self.confirm_clear = true;
                             ui.close();
                         }
                         if ui.button("Reset").clicked() {
+                            reset = true;
+                            ui.close();
+                        }
+                    });
+
+                    ui.menu_button("View", |ui| {
+                        let Some(scene) = &mut self.scene else {
+                            ui.weak("No scene");
+                            return;
+                        };
+                        ui.label("Camera");
+                        ui.radio_value(&mut scene.active_camera, None, "Editor camera");
+                        for id in scene.graph.cameras() {
+                            if let Some(node) = scene.graph.get(id) {
+                                ui.radio_value(&mut scene.active_camera, Some(id), &node.name);
+                            }
+                        }
+                        ui.separator();
+                        ui.label("Controller");
+                        for mode in ControllerMode::ALL {
+                            if ui
+                                .radio(self.controller_mode == mode, mode.name())
+                                .clicked()
+                            {
+                                controller_mode = Some(mode);
+                            }
+                        }
+                        ui.horizontal(|ui| {
+                            ui.label("Smoothing");
+                            let mut smoothing = self.orbit.smoothing;
+                            if ui
+                                .add(egui::Slider::new(&mut smoothing, 0.0..=0.5).suffix(" s"))
+                                .changed()
+                            {
+                                self.orbit.smoothing = smoothing;
+                                self.fly.smoothing = smoothing;
+                                self.pan_zoom.smoothing = smoothing;
+                            }
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("Fly speed");
+                            ui.add(
+                                egui::DragValue::new(&mut self.fly.move_speed)
+                                    .range(0.01..=1000.0)
+                                    .speed(0.1),
+                            );
+                        });
+                        ui.separator();
+                        for asset in &mut scene.imports {
+                            if asset.scene.animations.is_empty() {
+                                continue;
+                            }
+                            ui.label(format!("{} animation", asset.scene.name));
+                            let before = asset.animation;
+                            ui.radio_value(&mut asset.animation, None, "None");
+                            for (index, animation) in asset.scene.animations.iter().enumerate() {
+                                ui.radio_value(&mut asset.animation, Some(index), &animation.name);
+                            }
+                            if asset.animation != before {
+                                asset.animation_time = 0.0;
+                            }
+                            ui.separator();
+                        }
+                        if ui
+                            .add(
+                                egui::Button::new("Frame Selection")
+                                    .shortcut_text(ui.ctx().format_shortcut(&frame_shortcut)),
+                            )
+                            .clicked()
+                        {
+                            frame = true;
                             ui.close();
                         }
                     });
@@ -246,17 +1673,147 @@ impl App for TriangleApp {
             });
         });
 
+        let mut scene_tree_actions = Vec::new();
         egui::SidePanel::left("left").show(ctx, |ui| {
             ui.heading("Scene Tree");
+            if let Some(scene) = &self.scene {
+                scene_tree_actions = self.scene_tree.show(ui, &scene.graph);
+            }
         });
+        for action in scene_tree_actions {
+            if let Err(error) = self.apply_scene_tree_action(action) {
+                log::error!("Scene tree action failed: {:?}", error);
+            }
+        }
 
         egui::SidePanel::right("right").show(ctx, |ui| {
             ui.heading("Inspector");
+            let Some(scene) = &mut self.scene else {
+                return;
+            };
+            let primary = self.scene_tree.primary_selection();
+            let before = primary.and_then(|id| scene.graph.get(id).map(NodeState::capture));
+            let materials = primary.map(|_| scene.materials.clone());
+            let changed = self.inspector.show(
+                ui,
+                &mut scene.graph,
+                self.scene_tree.selection(),
+                &mut scene.meshes,
+                &mut scene.materials,
+                &scene.gpu_textures,
+            );
+            if !changed {
+                return;
+            }
+            if let (Some(id), Some(before)) = (primary, before)
+                && let Some(node) = scene.graph.get(id)
+                && before != NodeState::capture(node)
+            {
+                self.history.push_merging(SceneCommand::EditNode {
+                    id,
+                    before: Box::new(before),
+                    after: Box::new(NodeState::capture(node)),
+                });
+            }
+            for (index, before) in materials.into_iter().flatten().enumerate() {
+                if let Some(after) = scene.materials.get(index)
+                    && *after != before
+                {
+                    self.history.push_merging(SceneCommand::EditMaterial {
+                        index,
+                        before: Box::new(before),
+                        after: Box::new(after.clone()),
+                    });
+                }
+            }
         });
+        egui::CentralPanel::default()
+            .frame(egui::Frame::NONE)
+            .show(ctx, |ui| {
+                let response = ui.interact(
+                    ui.max_rect(),
+                    egui::Id::new("viewport"),
+                    egui::Sense::click_and_drag(),
+                );
+                self.controller_input = ControllerInput::from_response(&response);
+            });
+        if let Some(mode) = controller_mode {
+            self.set_controller_mode(mode);
+        }
+        if frame {
+            self.frame_selection();
+        }
+        if reset && let Err(error) = self.reset_scene() {
+            log::error!("Failed to reset scene: {:?}", error);
+        }
+        if self.confirm_clear {
+            self.confirm_clear(ctx);
+        }
+        if self.show_history {
+            self.show_history(ctx);
+        }
 
-        egui::TopBottomPanel::bottom("Console").show(ctx, |ui| {
-            ui.heading("Console");
-        });
+        let mut console_events = Vec::new();
+        egui::TopBottomPanel::bottom("Console")
+            .resizable(true)
+            .default_height(160.0)
+            .show(ctx, |ui| {
+                ui.heading("Console");
+                ui.with_layout(egui::Layout::bottom_up(egui::Align::LEFT), |ui| {
+                    console_events = self.commands.show(ui);
+                    ui.with_layout(egui::Layout::top_down(egui::Align::LEFT), |ui| {
+                        self.console.show(ui);
+                    });
+                });
+            });
+        for event in console_events {
+            if let Err(error) = self.apply_console_event(event) {
+                log::error!(target: "console", "{}", error);
+            }
+        }
+
+        if let Some(path) = recent
+            && let Err(error) = self.load_scene(&path)
+        {
+            log::error!("{:?}", error);
+            if !path.exists() {
+                self.recent_files.remove(&path);
+            }
+        }
+
+        if let Some(dialog) = &mut self.load_dialog {
+            let selected = dialog.show(ctx);
+            if !dialog.is_open() {
+                self.load_dialog = None;
+            }
+            if let Some(path) = selected
+                && let Err(error) = self.load_scene(&path)
+            {
+                log::error!("{:?}", error);
+            }
+        }
+
+        if let Some(dialog) = &mut self.save_dialog {
+            let selected = dialog.show(ctx);
+            if !dialog.is_open() {
+                self.save_dialog = None;
+            }
+            if let Some(path) = selected
+                && let Err(error) = self.save_scene(&path)
+            {
+                log::error!("{:?}", error);
+            }
+        }
+
+        if let Some(dialog) = &mut self.import_dialog {
+            let selected = dialog.show(ctx);
+            if !dialog.is_open() {
+                self.import_dialog = None;
+            }
+            if let Some(path) = selected {
+                self.import(&path);
+            }
+        }
 
         Ok(())
     }
@@ -268,3 +1825,27 @@ impl App for TriangleApp {
         Ok(())
     }
 }
+
+fn top_level_nodes(graph: &SceneGraph, nodes: &[NodeId]) -> Vec<NodeId> {
+    nodes
+        .iter()
+        .copied()
+        .filter(|node| {
+            graph.contains(*node)
+                && !graph
+                    .ancestors(*node)
+                    .iter()
+                    .any(|ancestor| nodes.contains(ancestor))
+        })
+        .collect()
+}
+
+fn nodes_label(verb: &str, graph: &SceneGraph, nodes: &[NodeId]) -> String {
+    match nodes {
+        [node] => match graph.get(*node) {
+            Some(node) => format!("{} '{}'", verb, node.name),
+            None => verb.to_string(),
+        },
+        _ => format!("{} {} nodes", verb, nodes.len()),
+    }
+}
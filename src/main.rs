@@ -1,8 +1,36 @@
-use anyhow::Result;
+use anyhow::{Result, bail};
 use app_core::support::app::run_application;
+use app_core::support::shader_validation::validate_shader_directory;
+use std::path::Path;
 
 fn main() -> Result<()> {
-    let app = app_core::TriangleApp::default();
-    run_application(app)?;
+    let arguments = std::env::args().skip(1).collect::<Vec<_>>();
+    match arguments.first().map(String::as_str) {
+        Some("validate-shaders") => {
+            let directory = arguments.get(1).map_or("shaders", String::as_str);
+            validate_shaders(Path::new(directory))
+        }
+        Some(command) => bail!("Unknown command '{}', expected 'validate-shaders'", command),
+        None => {
+            let app = app_core::TriangleApp::default();
+            run_application(app)?;
+            Ok(())
+        }
+    }
+}
+
+fn validate_shaders(directory: &Path) -> Result<()> {
+    let diagnostics = validate_shader_directory(directory);
+    for diagnostic in &diagnostics {
+        eprintln!("{}", diagnostic);
+    }
+    if !diagnostics.is_empty() {
+        bail!(
+            "{} shader error(s) in '{}'",
+            diagnostics.len(),
+            directory.display()
+        );
+    }
+    println!("All shaders in '{}' are valid", directory.display());
     Ok(())
 }
@@ -1,9 +1,11 @@
+use crate::support::log_console;
+use crate::support::texture;
 use anyhow::Result;
 use glutin::config::ConfigTemplateBuilder;
 use glutin::context::{ContextAttributesBuilder, PossiblyCurrentContext, Version};
 use glutin::display::GetGlDisplay;
 use glutin::prelude::*;
-use glutin::surface::{Surface, WindowSurface};
+use glutin::surface::{Surface, SwapInterval, WindowSurface};
 use glutin_winit::DisplayBuilder;
 use raw_window_handle::HasWindowHandle;
 use std::num::NonZeroU32;
@@ -39,6 +41,9 @@ pub trait App {
     fn on_resize(&mut self, _width: u32, _height: u32) -> Result<()> {
         Ok(())
     }
+    fn vsync(&self) -> Option<bool> {
+        None
+    }
 }
 
 struct AppRunner {
@@ -51,6 +56,7 @@ struct AppRunner {
     app: Box<dyn App>,
     start_time: Instant,
     last_frame_time: Instant,
+    vsync: Option<bool>,
 }
 
 impl ApplicationHandler for AppRunner {
@@ -69,9 +75,13 @@ impl ApplicationHandler for AppRunner {
 
         let (window, gl_config) = display_builder
             .build(event_loop, template, |configs| {
+                // Reversed-Z draws into a float depth buffer of its own, so the window's depth
+                // serves standard depth: take the deepest one and then the most samples.
                 configs
                     .reduce(|accum, config| {
-                        if config.num_samples() > accum.num_samples() {
+                        if (config.depth_size(), config.num_samples())
+                            > (accum.depth_size(), accum.num_samples())
+                        {
                             config
                         } else {
                             accum
@@ -119,6 +129,13 @@ impl ApplicationHandler for AppRunner {
             gl_display.get_proc_address(symbol.as_c_str()).cast()
         });
 
+        crate::support::resource::register_context();
+
+        // Filter across cubemap face edges rather than within each face.
+        unsafe {
+            gl::Enable(gl::TEXTURE_CUBE_MAP_SEAMLESS);
+        }
+
         enable_gl_debug();
 
         let glow_context = unsafe {
@@ -239,6 +256,22 @@ impl ApplicationHandler for AppRunner {
                     eprintln!("Update error: {}", error);
                 }
 
+                if let Some(vsync) = self.app.vsync()
+                    && self.vsync != Some(vsync)
+                    && let (Some(gl_context), Some(gl_surface)) =
+                        (self.gl_context.as_ref(), self.gl_surface.as_ref())
+                {
+                    let interval = if vsync {
+                        SwapInterval::Wait(NonZeroU32::MIN)
+                    } else {
+                        SwapInterval::DontWait
+                    };
+                    if let Err(error) = gl_surface.set_swap_interval(gl_context, interval) {
+                        log::warn!("Failed to set swap interval: {}", error);
+                    }
+                    self.vsync = Some(vsync);
+                }
+
                 if let Err(error) = self.app.render(time) {
                     eprintln!("Render error: {}", error);
                 }
@@ -268,6 +301,16 @@ impl ApplicationHandler for AppRunner {
                     egui_glow.set_texture(id, &image_delta);
                 }
 
+                for name in texture::take_egui_textures() {
+                    let id = egui::TextureId::User(name as u64);
+                    let Some(native) = NonZeroU32::new(name).map(glow::NativeTexture) else {
+                        continue;
+                    };
+                    if egui_glow.texture(id) != Some(native) {
+                        egui_glow.replace_native_texture(id, native);
+                    }
+                }
+
                 unsafe {
                     gl::Disable(gl::SCISSOR_TEST);
                 }
@@ -277,6 +320,9 @@ impl ApplicationHandler for AppRunner {
                 for id in textures_delta.free {
                     egui_glow.free_texture(id);
                 }
+                for name in texture::take_released_egui_textures() {
+                    egui_glow.free_texture(egui::TextureId::User(name as u64));
+                }
 
                 if let (Some(gl_surface), Some(gl_context)) =
                     (self.gl_surface.as_ref(), self.gl_context.as_ref())
@@ -293,7 +339,7 @@ impl ApplicationHandler for AppRunner {
 }
 
 pub fn run_application(app: impl App + 'static) -> Result<()> {
-    env_logger::init();
+    log_console::init(log::LevelFilter::Debug)?;
 
     let event_loop = EventLoop::builder().build()?;
     event_loop.set_control_flow(winit::event_loop::ControlFlow::Poll);
@@ -310,6 +356,7 @@ pub fn run_application(app: impl App + 'static) -> Result<()> {
         app: Box::new(app),
         start_time: now,
         last_frame_time: now,
+        vsync: None,
     };
 
     event_loop.run_app(&mut app_runner)?;
@@ -384,19 +431,19 @@ extern "system" fn gl_debug_callback(
 
     match severity {
         gl::DEBUG_SEVERITY_HIGH => {
-            log::error!("[GL {}] {} ({}): {}", source_str, type_str, id, message);
+            log::error!(target: "gl", "[{}] {} ({}): {}", source_str, type_str, id, message);
         }
         gl::DEBUG_SEVERITY_MEDIUM => {
-            log::warn!("[GL {}] {} ({}): {}", source_str, type_str, id, message);
+            log::warn!(target: "gl", "[{}] {} ({}): {}", source_str, type_str, id, message);
         }
         gl::DEBUG_SEVERITY_LOW => {
-            log::info!("[GL {}] {} ({}): {}", source_str, type_str, id, message);
+            log::info!(target: "gl", "[{}] {} ({}): {}", source_str, type_str, id, message);
         }
         gl::DEBUG_SEVERITY_NOTIFICATION => {
-            log::debug!("[GL {}] {} ({}): {}", source_str, type_str, id, message);
+            log::debug!(target: "gl", "[{}] {} ({}): {}", source_str, type_str, id, message);
         }
         _ => {
-            log::trace!("[GL {}] {} ({}): {}", source_str, type_str, id, message);
+            log::trace!(target: "gl", "[{}] {} ({}): {}", source_str, type_str, id, message);
         }
     }
 }
@@ -0,0 +1,544 @@
+use crate::support::import::{CameraData, ProjectionData};
+use nalgebra_glm::{Mat4, Quat, Vec2, Vec3, Vec4};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Handedness {
+    Left,
+    Right,
+}
+
+/// The clip-space depth range the projection maps the near and far planes to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DepthRange {
+    ZeroToOne,
+    NegativeOneToOne,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Projection {
+    /// `far: None` places the far plane at infinity.
+    Perspective {
+        fov_y: f32,
+        near: f32,
+        far: Option<f32>,
+    },
+    Orthographic {
+        half_height: f32,
+        near: f32,
+        far: f32,
+    },
+}
+
+impl Default for Projection {
+    fn default() -> Self {
+        Projection::Perspective {
+            fov_y: 80_f32.to_radians(),
+            near: 0.1,
+            far: Some(1000.0),
+        }
+    }
+}
+
+impl From<ProjectionData> for Projection {
+    fn from(data: ProjectionData) -> Self {
+        match data {
+            ProjectionData::Perspective {
+                yfov, znear, zfar, ..
+            } => Projection::Perspective {
+                fov_y: yfov,
+                near: znear,
+                far: zfar,
+            },
+            ProjectionData::Orthographic {
+                ymag, znear, zfar, ..
+            } => Projection::Orthographic {
+                half_height: ymag,
+                near: znear,
+                far: zfar,
+            },
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Ray {
+    pub origin: Vec3,
+    pub direction: Vec3,
+}
+
+impl Ray {
+    pub fn at(&self, distance: f32) -> Vec3 {
+        self.origin + self.direction * distance
+    }
+}
+
+/// A viewpoint with cached view and projection matrices. The camera looks down its local -Z
+/// axis with +Y up, the same convention imported camera nodes use, whatever the handedness
+/// of the view space.
+#[derive(Clone, Debug)]
+pub struct Camera {
+    position: Vec3,
+    orientation: Quat,
+    projection: Projection,
+    aspect_ratio: f32,
+    handedness: Handedness,
+    depth_range: DepthRange,
+    reversed_z: bool,
+    view: Mat4,
+    projection_matrix: Mat4,
+    view_projection: Mat4,
+    inverse_view_projection: Mat4,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self::new(Projection::default())
+    }
+}
+
+impl Camera {
+    pub fn new(projection: Projection) -> Self {
+        let mut camera = Self {
+            position: Vec3::zeros(),
+            orientation: Quat::identity(),
+            projection,
+            aspect_ratio: 1.0,
+            handedness: Handedness::Left,
+            depth_range: DepthRange::ZeroToOne,
+            reversed_z: false,
+            view: Mat4::identity(),
+            projection_matrix: Mat4::identity(),
+            view_projection: Mat4::identity(),
+            inverse_view_projection: Mat4::identity(),
+        };
+        camera.update_view();
+        camera.update_projection();
+        camera
+    }
+
+    /// Builds a camera for a scene node from its camera data and world matrix.
+    pub fn from_node(data: &CameraData, world: &Mat4, aspect_ratio: f32) -> Self {
+        let mut camera = Self::new(data.projection.into());
+        camera.aspect_ratio = aspect_ratio;
+        camera.position = world.column(3).xyz();
+        let rotation = nalgebra_glm::mat4_to_mat3(world);
+        let forward = -rotation.column(2).normalize();
+        let up = rotation.column(1).normalize();
+        camera.orientation = look_rotation(&forward, &up);
+        camera.update_view();
+        camera.update_projection();
+        camera
+    }
+
+    pub fn with_position(mut self, position: Vec3) -> Self {
+        self.set_position(position);
+        self
+    }
+
+    pub fn with_handedness(mut self, handedness: Handedness) -> Self {
+        self.set_handedness(handedness);
+        self
+    }
+
+    pub fn with_depth_range(mut self, depth_range: DepthRange) -> Self {
+        self.set_depth_range(depth_range);
+        self
+    }
+
+    pub fn with_reversed_z(mut self, reversed_z: bool) -> Self {
+        self.set_reversed_z(reversed_z);
+        self
+    }
+
+    pub fn position(&self) -> Vec3 {
+        self.position
+    }
+
+    pub fn set_position(&mut self, position: Vec3) {
+        self.position = position;
+        self.update_view();
+    }
+
+    pub fn orientation(&self) -> Quat {
+        self.orientation
+    }
+
+    pub fn set_orientation(&mut self, orientation: Quat) {
+        self.orientation = nalgebra_glm::quat_normalize(&orientation);
+        self.update_view();
+    }
+
+    pub fn look_at(&mut self, target: &Vec3, up: &Vec3) {
+        let forward = target - self.position;
+        if forward.norm_squared() > f32::EPSILON {
+            self.set_orientation(look_rotation(&forward.normalize(), up));
+        }
+    }
+
+    pub fn forward(&self) -> Vec3 {
+        nalgebra_glm::quat_rotate_vec3(&self.orientation, &-Vec3::z())
+    }
+
+    /// The world direction that appears to the right on screen, which depends on handedness.
+    pub fn right(&self) -> Vec3 {
+        Vec3::new(self.view[(0, 0)], self.view[(0, 1)], self.view[(0, 2)])
+    }
+
+    pub fn up(&self) -> Vec3 {
+        nalgebra_glm::quat_rotate_vec3(&self.orientation, &Vec3::y())
+    }
+
+    pub fn projection(&self) -> &Projection {
+        &self.projection
+    }
+
+    pub fn set_projection(&mut self, projection: Projection) {
+        self.projection = projection;
+        self.update_projection();
+    }
+
+    pub fn aspect_ratio(&self) -> f32 {
+        self.aspect_ratio
+    }
+
+    pub fn set_aspect_ratio(&mut self, aspect_ratio: f32) {
+        if (aspect_ratio - self.aspect_ratio).abs() > f32::EPSILON {
+            self.aspect_ratio = aspect_ratio;
+            self.update_projection();
+        }
+    }
+
+    pub fn handedness(&self) -> Handedness {
+        self.handedness
+    }
+
+    pub fn set_handedness(&mut self, handedness: Handedness) {
+        self.handedness = handedness;
+        self.update_view();
+        self.update_projection();
+    }
+
+    pub fn depth_range(&self) -> DepthRange {
+        self.depth_range
+    }
+
+    pub fn set_depth_range(&mut self, depth_range: DepthRange) {
+        self.depth_range = depth_range;
+        self.update_projection();
+    }
+
+    pub fn reversed_z(&self) -> bool {
+        self.reversed_z
+    }
+
+    /// Maps the near plane to the far end of the depth range, which spreads float depth
+    /// precision evenly over distance. The depth test must be flipped to match.
+    pub fn set_reversed_z(&mut self, reversed_z: bool) {
+        self.reversed_z = reversed_z;
+        self.update_projection();
+    }
+
+    pub fn view(&self) -> &Mat4 {
+        &self.view
+    }
+
+    pub fn projection_matrix(&self) -> &Mat4 {
+        &self.projection_matrix
+    }
+
+    pub fn view_projection(&self) -> &Mat4 {
+        &self.view_projection
+    }
+
+    /// Casts a ray through a point given in pixels from the top-left corner of the viewport.
+    pub fn screen_to_ray(&self, screen: &Vec2, viewport: &Vec2) -> Ray {
+        let x = 2.0 * screen.x / viewport.x.max(1.0) - 1.0;
+        let y = 1.0 - 2.0 * screen.y / viewport.y.max(1.0);
+        let (near, far) = self.ndc_depth();
+        let start = self.unproject(&Vec3::new(x, y, near));
+        // The far plane may be at infinity, so aim through a point halfway there in clip space.
+        let end = self.unproject(&Vec3::new(x, y, (near + far) * 0.5));
+        Ray {
+            origin: start,
+            direction: (end - start).normalize(),
+        }
+    }
+
+    /// Projects a world position to pixels from the top-left corner of the viewport. The z
+    /// component holds the normalized device depth. Returns `None` for points behind the camera.
+    pub fn world_to_screen(&self, point: &Vec3, viewport: &Vec2) -> Option<Vec3> {
+        let clip = self.view_projection * Vec4::new(point.x, point.y, point.z, 1.0);
+        if clip.w <= f32::EPSILON {
+            return None;
+        }
+        let ndc = clip.xyz() / clip.w;
+        Some(Vec3::new(
+            (ndc.x + 1.0) * 0.5 * viewport.x,
+            (1.0 - ndc.y) * 0.5 * viewport.y,
+            ndc.z,
+        ))
+    }
+
+    fn unproject(&self, ndc: &Vec3) -> Vec3 {
+        let point = self.inverse_view_projection * Vec4::new(ndc.x, ndc.y, ndc.z, 1.0);
+        point.xyz() / point.w
+    }
+
+    fn ndc_depth(&self) -> (f32, f32) {
+        let (near, far) = match self.depth_range {
+            DepthRange::ZeroToOne => (0.0, 1.0),
+            DepthRange::NegativeOneToOne => (-1.0, 1.0),
+        };
+        if self.reversed_z {
+            (far, near)
+        } else {
+            (near, far)
+        }
+    }
+
+    fn forward_sign(&self) -> f32 {
+        match self.handedness {
+            Handedness::Left => 1.0,
+            Handedness::Right => -1.0,
+        }
+    }
+
+    fn update_view(&mut self) {
+        let target = self.position + self.forward();
+        self.view = match self.handedness {
+            Handedness::Left => nalgebra_glm::look_at_lh(&self.position, &target, &self.up()),
+            Handedness::Right => nalgebra_glm::look_at_rh(&self.position, &target, &self.up()),
+        };
+        self.update_view_projection();
+    }
+
+    fn update_projection(&mut self) {
+        let sign = self.forward_sign();
+        let (near_depth, far_depth) = self.ndc_depth();
+        self.projection_matrix = match self.projection {
+            Projection::Perspective { fov_y, near, far } => {
+                let focal = 1.0 / (fov_y * 0.5).tan();
+                // Depth is a + b / distance, solved so near and far land on the range ends.
+                let (a, b) = match far {
+                    Some(far) => {
+                        let b = (near_depth - far_depth) * near * far / (far - near);
+                        (far_depth - b / far, b)
+                    }
+                    None => (far_depth, (near_depth - far_depth) * near),
+                };
+                Mat4::new(
+                    focal / self.aspect_ratio,
+                    0.0,
+                    0.0,
+                    0.0,
+                    0.0,
+                    focal,
+                    0.0,
+                    0.0,
+                    0.0,
+                    0.0,
+                    a * sign,
+                    b,
+                    0.0,
+                    0.0,
+                    sign,
+                    0.0,
+                )
+            }
+            Projection::Orthographic {
+                half_height,
+                near,
+                far,
+            } => {
+                let scale = (far_depth - near_depth) / (far - near);
+                Mat4::new(
+                    1.0 / (half_height * self.aspect_ratio),
+                    0.0,
+                    0.0,
+                    0.0,
+                    0.0,
+                    1.0 / half_height,
+                    0.0,
+                    0.0,
+                    0.0,
+                    0.0,
+                    scale * sign,
+                    near_depth - near * scale,
+                    0.0,
+                    0.0,
+                    0.0,
+                    1.0,
+                )
+            }
+        };
+        self.update_view_projection();
+    }
+
+    fn update_view_projection(&mut self) {
+        self.view_projection = self.projection_matrix * self.view;
+        self.inverse_view_projection = self
+            .view_projection
+            .try_inverse()
+            .unwrap_or_else(Mat4::identity);
+    }
+}
+
+/// The rotation that turns the camera's -Z axis towards `forward` with +Y as close to `up`
+/// as possible.
+fn look_rotation(forward: &Vec3, up: &Vec3) -> Quat {
+    let back = -forward;
+    let mut right = up.cross(&back);
+    if right.norm_squared() < f32::EPSILON {
+        right = Vec3::y().cross(&back);
+        if right.norm_squared() < f32::EPSILON {
+            right = Vec3::x();
+        }
+    }
+    let right = right.normalize();
+    let up = back.cross(&right);
+    let rotation = nalgebra_glm::Mat3::from_columns(&[right, up, back]);
+    nalgebra_glm::to_quat(&nalgebra_glm::mat3_to_mat4(&rotation))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VIEWPORT: Vec2 = Vec2::new(800.0, 600.0);
+
+    fn depth_at(camera: &Camera, distance: f32) -> f32 {
+        let point = camera.position() + camera.forward() * distance;
+        camera.world_to_screen(&point, &VIEWPORT).unwrap().z
+    }
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 1e-4,
+            "expected {expected}, got {actual}"
+        );
+    }
+
+    fn cameras(projection: Projection) -> Vec<Camera> {
+        let mut cameras = Vec::new();
+        for handedness in [Handedness::Left, Handedness::Right] {
+            for depth_range in [DepthRange::ZeroToOne, DepthRange::NegativeOneToOne] {
+                for reversed_z in [false, true] {
+                    cameras.push(
+                        Camera::new(projection)
+                            .with_position(Vec3::new(1.0, 2.0, 3.0))
+                            .with_handedness(handedness)
+                            .with_depth_range(depth_range)
+                            .with_reversed_z(reversed_z),
+                    );
+                }
+            }
+        }
+        cameras
+    }
+
+    #[test]
+    fn perspective_maps_near_and_far_to_the_depth_range() {
+        let projection = Projection::Perspective {
+            fov_y: 60_f32.to_radians(),
+            near: 0.5,
+            far: Some(100.0),
+        };
+        for camera in cameras(projection) {
+            let (near, far) = camera.ndc_depth();
+            assert_close(depth_at(&camera, 0.5), near);
+            assert_close(depth_at(&camera, 100.0), far);
+        }
+    }
+
+    #[test]
+    fn infinite_perspective_approaches_the_far_depth() {
+        let projection = Projection::Perspective {
+            fov_y: 60_f32.to_radians(),
+            near: 0.5,
+            far: None,
+        };
+        for camera in cameras(projection) {
+            let (near, far) = camera.ndc_depth();
+            assert_close(depth_at(&camera, 0.5), near);
+            let distant = depth_at(&camera, 1.0e6);
+            assert_close(distant, far);
+            // Depth must still move towards the far end between the two.
+            let middle = depth_at(&camera, 10.0);
+            assert!((middle - near).abs() < (far - near).abs());
+            assert!((middle - near).signum() == (far - near).signum());
+        }
+    }
+
+    #[test]
+    fn orthographic_maps_near_and_far_to_the_depth_range() {
+        let projection = Projection::Orthographic {
+            half_height: 5.0,
+            near: 1.0,
+            far: 50.0,
+        };
+        for camera in cameras(projection) {
+            let (near, far) = camera.ndc_depth();
+            assert_close(depth_at(&camera, 1.0), near);
+            assert_close(depth_at(&camera, 50.0), far);
+        }
+    }
+
+    #[test]
+    fn looks_down_negative_z() {
+        for handedness in [Handedness::Left, Handedness::Right] {
+            let camera = Camera::default().with_handedness(handedness);
+            let centre = camera
+                .world_to_screen(&Vec3::new(0.0, 0.0, -10.0), &VIEWPORT)
+                .unwrap();
+            assert_close(centre.x, 400.0);
+            assert_close(centre.y, 300.0);
+            let right = camera
+                .world_to_screen(&(Vec3::new(0.0, 0.0, -10.0) + camera.right()), &VIEWPORT)
+                .unwrap();
+            assert!(right.x > 400.0);
+            assert_close(right.y, 300.0);
+            assert!(
+                camera
+                    .world_to_screen(&Vec3::new(0.0, 0.0, 10.0), &VIEWPORT)
+                    .is_none()
+            );
+        }
+    }
+
+    #[test]
+    fn screen_to_ray_round_trips_through_world_to_screen() {
+        let projections = [
+            Projection::default(),
+            Projection::Perspective {
+                fov_y: 45_f32.to_radians(),
+                near: 0.1,
+                far: None,
+            },
+            Projection::Orthographic {
+                half_height: 4.0,
+                near: 0.1,
+                far: 100.0,
+            },
+        ];
+        for projection in projections {
+            for mut camera in cameras(projection) {
+                camera.set_aspect_ratio(VIEWPORT.x / VIEWPORT.y);
+                camera.look_at(&Vec3::new(-2.0, 0.5, -4.0), &Vec3::y());
+                for screen in [
+                    Vec2::new(400.0, 300.0),
+                    Vec2::new(10.0, 20.0),
+                    Vec2::new(790.0, 450.0),
+                ] {
+                    let ray = camera.screen_to_ray(&screen, &VIEWPORT);
+                    assert_close(ray.direction.norm(), 1.0);
+                    let point = ray.at(7.5);
+                    let projected = camera.world_to_screen(&point, &VIEWPORT).unwrap();
+                    assert!(
+                        (projected.xy() - screen).norm() < 1e-2,
+                        "{screen:?} came back as {projected:?}"
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,484 @@
+use crate::support::camera::{Camera, Projection};
+use crate::support::mesh::BoundingBox;
+use nalgebra_glm::{Vec2, Vec3};
+use std::f32::consts::FRAC_PI_2;
+
+const MAX_PITCH: f32 = FRAC_PI_2 - 0.01;
+const FRAME_MARGIN: f32 = 1.1;
+
+/// Pointer and keyboard state for one frame, in points relative to the full viewport.
+#[derive(Clone, Debug, Default)]
+pub struct ControllerInput {
+    pub viewport: Vec2,
+    pub cursor: Option<Vec2>,
+    pub primary_drag: Vec2,
+    pub secondary_drag: Vec2,
+    pub middle_drag: Vec2,
+    /// Scroll distance in points, positive when scrolling up.
+    pub scroll: f32,
+    /// Keyboard movement: x to the right, y up, z forward.
+    pub movement: Vec3,
+    pub fast: bool,
+}
+
+impl ControllerInput {
+    /// Collects input for a viewport widget. Drags and scrolling only count while the pointer
+    /// is over it, and keys are ignored while a text field has focus.
+    pub fn from_response(response: &egui::Response) -> Self {
+        let ctx = &response.ctx;
+        let viewport = ctx.content_rect();
+        let drag = |button| {
+            if response.dragged_by(button) {
+                let delta = response.drag_delta();
+                Vec2::new(delta.x, delta.y)
+            } else {
+                Vec2::zeros()
+            }
+        };
+        let mut input = Self {
+            viewport: Vec2::new(viewport.width(), viewport.height()),
+            cursor: response
+                .hover_pos()
+                .map(|position| Vec2::new(position.x, position.y)),
+            primary_drag: drag(egui::PointerButton::Primary),
+            secondary_drag: drag(egui::PointerButton::Secondary),
+            middle_drag: drag(egui::PointerButton::Middle),
+            ..Self::default()
+        };
+        if response.hovered() {
+            input.scroll = ctx.input(|input| input.smooth_scroll_delta.y);
+        }
+        if ctx.memory(|memory| memory.focused().is_none()) {
+            ctx.input(|state| {
+                let axis = |positive, negative| {
+                    state.key_down(positive) as i32 as f32 - state.key_down(negative) as i32 as f32
+                };
+                input.movement = Vec3::new(
+                    axis(egui::Key::D, egui::Key::A),
+                    axis(egui::Key::E, egui::Key::Q),
+                    axis(egui::Key::W, egui::Key::S),
+                );
+                input.fast = state.modifiers.shift;
+            });
+        }
+        input
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ControllerMode {
+    #[default]
+    Orbit,
+    Fly,
+    PanZoom,
+}
+
+impl ControllerMode {
+    pub const ALL: [ControllerMode; 3] = [
+        ControllerMode::Orbit,
+        ControllerMode::Fly,
+        ControllerMode::PanZoom,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ControllerMode::Orbit => "Orbit",
+            ControllerMode::Fly => "Fly",
+            ControllerMode::PanZoom => "2D Pan/Zoom",
+        }
+    }
+}
+
+pub trait CameraController {
+    /// Adopts the camera's current pose so switching controllers does not make it jump.
+    fn sync(&mut self, camera: &Camera);
+    fn update(&mut self, camera: &mut Camera, input: &ControllerInput, delta_time: f32);
+    /// Moves the camera so `bounds` fills the view.
+    fn focus(&mut self, camera: &Camera, bounds: &BoundingBox);
+    /// Called when another controller takes over the camera.
+    fn release(&mut self, _camera: &mut Camera) {}
+}
+
+/// Orbits around a target point: primary drag rotates, middle or secondary drag pans and
+/// scrolling zooms.
+#[derive(Clone, Debug)]
+pub struct OrbitController {
+    pub rotate_speed: f32,
+    pub pan_speed: f32,
+    pub zoom_speed: f32,
+    pub smoothing: f32,
+    pub min_distance: f32,
+    goal: OrbitState,
+    current: OrbitState,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct OrbitState {
+    target: Vec3,
+    distance: f32,
+    yaw: f32,
+    pitch: f32,
+}
+
+impl Default for OrbitController {
+    fn default() -> Self {
+        let state = OrbitState {
+            target: Vec3::zeros(),
+            distance: 3.0,
+            yaw: 0.0,
+            pitch: 0.0,
+        };
+        Self {
+            rotate_speed: 0.01,
+            pan_speed: 1.0,
+            zoom_speed: 0.002,
+            smoothing: 0.08,
+            min_distance: 0.01,
+            goal: state,
+            current: state,
+        }
+    }
+}
+
+impl OrbitController {
+    pub fn target(&self) -> Vec3 {
+        self.goal.target
+    }
+
+    pub fn set_target(&mut self, target: Vec3) {
+        self.goal.target = target;
+    }
+}
+
+impl CameraController for OrbitController {
+    fn sync(&mut self, camera: &Camera) {
+        let distance = (camera.position() - self.goal.target).norm();
+        let distance = if distance > self.min_distance {
+            distance
+        } else {
+            self.goal.distance
+        };
+        let (yaw, pitch) = yaw_pitch(&-camera.forward());
+        self.goal = OrbitState {
+            target: camera.position() + camera.forward() * distance,
+            distance,
+            yaw,
+            pitch,
+        };
+        self.current = self.goal;
+    }
+
+    fn update(&mut self, camera: &mut Camera, input: &ControllerInput, delta_time: f32) {
+        let sign = yaw_sign(camera, self.current.yaw);
+        self.goal.yaw -= input.primary_drag.x * self.rotate_speed * sign;
+        self.goal.pitch = (self.goal.pitch + input.primary_drag.y * self.rotate_speed)
+            .clamp(-MAX_PITCH, MAX_PITCH);
+
+        let pan = input.middle_drag + input.secondary_drag;
+        let scale = world_per_point(camera, self.current.distance, input.viewport) * self.pan_speed;
+        self.goal.target += (camera.up() * pan.y - camera.right() * pan.x) * scale;
+
+        self.goal.distance =
+            (self.goal.distance * (-input.scroll * self.zoom_speed).exp()).max(self.min_distance);
+
+        let t = smoothing_factor(self.smoothing, delta_time);
+        self.current = OrbitState {
+            target: self.current.target.lerp(&self.goal.target, t),
+            distance: lerp(self.current.distance, self.goal.distance, t),
+            yaw: lerp(self.current.yaw, self.goal.yaw, t),
+            pitch: lerp(self.current.pitch, self.goal.pitch, t),
+        };
+        let OrbitState {
+            target,
+            distance,
+            yaw,
+            pitch,
+        } = self.current;
+        camera.set_position(target + direction(yaw, pitch) * distance);
+        camera.look_at(&target, &Vec3::y());
+    }
+
+    fn focus(&mut self, camera: &Camera, bounds: &BoundingBox) {
+        if bounds.is_empty() {
+            return;
+        }
+        self.goal.target = bounds.center();
+        self.goal.distance = fit_distance(camera, bounds.radius()).max(self.min_distance);
+    }
+}
+
+/// A free-flying camera: secondary drag looks around, WASD moves, Q/E move down and up and
+/// Shift moves faster. Scrolling changes the movement speed.
+#[derive(Clone, Debug)]
+pub struct FlyController {
+    pub move_speed: f32,
+    pub fast_multiplier: f32,
+    pub look_speed: f32,
+    pub smoothing: f32,
+    goal: FlyState,
+    current: FlyState,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct FlyState {
+    position: Vec3,
+    yaw: f32,
+    pitch: f32,
+}
+
+impl Default for FlyController {
+    fn default() -> Self {
+        let state = FlyState {
+            position: Vec3::new(0.0, 0.0, 3.0),
+            yaw: 0.0,
+            pitch: 0.0,
+        };
+        Self {
+            move_speed: 5.0,
+            fast_multiplier: 4.0,
+            look_speed: 0.005,
+            smoothing: 0.05,
+            goal: state,
+            current: state,
+        }
+    }
+}
+
+impl CameraController for FlyController {
+    fn sync(&mut self, camera: &Camera) {
+        let (yaw, pitch) = yaw_pitch(&-camera.forward());
+        self.goal = FlyState {
+            position: camera.position(),
+            yaw,
+            pitch,
+        };
+        self.current = self.goal;
+    }
+
+    fn update(&mut self, camera: &mut Camera, input: &ControllerInput, delta_time: f32) {
+        let sign = yaw_sign(camera, self.current.yaw);
+        self.goal.yaw -= input.secondary_drag.x * self.look_speed * sign;
+        self.goal.pitch = (self.goal.pitch + input.secondary_drag.y * self.look_speed)
+            .clamp(-MAX_PITCH, MAX_PITCH);
+
+        self.move_speed = (self.move_speed * (input.scroll * 0.002).exp()).clamp(0.01, 1000.0);
+        let speed = if input.fast {
+            self.move_speed * self.fast_multiplier
+        } else {
+            self.move_speed
+        };
+        let movement = camera.right() * input.movement.x
+            + Vec3::y() * input.movement.y
+            + camera.forward() * input.movement.z;
+        if movement.norm_squared() > 0.0 {
+            self.goal.position += movement.normalize() * speed * delta_time;
+        }
+
+        let t = smoothing_factor(self.smoothing, delta_time);
+        self.current = FlyState {
+            position: self.current.position.lerp(&self.goal.position, t),
+            yaw: lerp(self.current.yaw, self.goal.yaw, t),
+            pitch: lerp(self.current.pitch, self.goal.pitch, t),
+        };
+        let FlyState {
+            position,
+            yaw,
+            pitch,
+        } = self.current;
+        camera.set_position(position);
+        camera.look_at(&(position - direction(yaw, pitch)), &Vec3::y());
+    }
+
+    fn focus(&mut self, camera: &Camera, bounds: &BoundingBox) {
+        if bounds.is_empty() {
+            return;
+        }
+        let forward = -direction(self.goal.yaw, self.goal.pitch);
+        self.goal.position = bounds.center() - forward * fit_distance(camera, bounds.radius());
+    }
+}
+
+/// A 2D view with an orthographic projection: primary or middle drag pans and scrolling
+/// zooms towards the cursor.
+#[derive(Clone, Debug)]
+pub struct PanZoomController {
+    pub zoom_speed: f32,
+    pub smoothing: f32,
+    pub min_half_height: f32,
+    goal: PanZoomState,
+    current: PanZoomState,
+    near: f32,
+    far: f32,
+    previous: Option<Projection>,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct PanZoomState {
+    center: Vec3,
+    half_height: f32,
+}
+
+impl Default for PanZoomController {
+    fn default() -> Self {
+        let state = PanZoomState {
+            center: Vec3::new(0.0, 0.0, 3.0),
+            half_height: 2.0,
+        };
+        Self {
+            zoom_speed: 0.002,
+            smoothing: 0.05,
+            min_half_height: 0.001,
+            goal: state,
+            current: state,
+            near: 0.1,
+            far: 1000.0,
+            previous: None,
+        }
+    }
+}
+
+impl CameraController for PanZoomController {
+    fn sync(&mut self, camera: &Camera) {
+        let half_height = match *camera.projection() {
+            Projection::Orthographic {
+                half_height,
+                near,
+                far,
+            } => {
+                self.near = near;
+                self.far = far;
+                half_height
+            }
+            Projection::Perspective { near, far, .. } => {
+                self.previous = Some(*camera.projection());
+                self.near = near;
+                self.far = far.unwrap_or(self.far);
+                // Match the perspective view's scale at the world origin.
+                world_per_point(camera, camera.position().norm(), Vec2::new(2.0, 2.0))
+            }
+        };
+        self.goal = PanZoomState {
+            center: camera.position(),
+            half_height: half_height.max(self.min_half_height),
+        };
+        self.current = self.goal;
+    }
+
+    fn update(&mut self, camera: &mut Camera, input: &ControllerInput, delta_time: f32) {
+        let scale = 2.0 * self.current.half_height / input.viewport.y.max(1.0);
+        let pan = input.primary_drag + input.middle_drag;
+        self.goal.center += (camera.up() * pan.y - camera.right() * pan.x) * scale;
+
+        if input.scroll != 0.0 {
+            let factor = (-input.scroll * self.zoom_speed).exp();
+            let half_height = (self.goal.half_height * factor).max(self.min_half_height);
+            if let Some(cursor) = input.cursor {
+                // Keep the point under the cursor fixed while zooming.
+                let offset = cursor - input.viewport * 0.5;
+                let scale = 2.0 * self.goal.half_height / input.viewport.y.max(1.0);
+                let anchor = camera.right() * offset.x * scale - camera.up() * offset.y * scale;
+                self.goal.center += anchor * (1.0 - half_height / self.goal.half_height);
+            }
+            self.goal.half_height = half_height;
+        }
+
+        let t = smoothing_factor(self.smoothing, delta_time);
+        self.current = PanZoomState {
+            center: self.current.center.lerp(&self.goal.center, t),
+            half_height: lerp(self.current.half_height, self.goal.half_height, t),
+        };
+        camera.set_position(self.current.center);
+        camera.set_projection(Projection::Orthographic {
+            half_height: self.current.half_height,
+            near: self.near,
+            far: self.far,
+        });
+    }
+
+    fn focus(&mut self, camera: &Camera, bounds: &BoundingBox) {
+        if bounds.is_empty() {
+            return;
+        }
+        let center = bounds.center();
+        let forward = camera.forward();
+        // Slide along the view direction so the bounds sit in front of the near plane.
+        self.goal.center = center - forward * (bounds.radius() + self.near.max(0.0) + 1.0);
+        self.goal.half_height = (bounds.radius() * FRAME_MARGIN / camera.aspect_ratio().min(1.0))
+            .max(self.min_half_height);
+    }
+
+    fn release(&mut self, camera: &mut Camera) {
+        if let Some(projection) = self.previous.take() {
+            camera.set_projection(projection);
+        }
+    }
+}
+
+fn lerp(from: f32, to: f32, t: f32) -> f32 {
+    from + (to - from) * t
+}
+
+/// Frame-rate independent blend towards a goal that covers about 63% of the gap every
+/// `smoothing` seconds. The controllers' `smoothing` fields feed this, and zero snaps straight
+/// to the goal.
+fn smoothing_factor(smoothing: f32, delta_time: f32) -> f32 {
+    if smoothing <= 0.0 {
+        1.0
+    } else {
+        1.0 - (-delta_time / smoothing).exp()
+    }
+}
+
+/// Unit vector from the orbit target towards the camera.
+fn direction(yaw: f32, pitch: f32) -> Vec3 {
+    Vec3::new(
+        pitch.cos() * yaw.sin(),
+        pitch.sin(),
+        pitch.cos() * yaw.cos(),
+    )
+}
+
+fn yaw_pitch(direction: &Vec3) -> (f32, f32) {
+    let direction = direction.normalize();
+    (
+        direction.x.atan2(direction.z),
+        direction
+            .y
+            .clamp(-1.0, 1.0)
+            .asin()
+            .clamp(-MAX_PITCH, MAX_PITCH),
+    )
+}
+
+/// Whether increasing yaw moves the camera to the right on screen, so horizontal drags feel
+/// the same in left- and right-handed views.
+fn yaw_sign(camera: &Camera, yaw: f32) -> f32 {
+    let tangent = Vec3::new(yaw.cos(), 0.0, -yaw.sin());
+    if camera.right().dot(&tangent) >= 0.0 {
+        1.0
+    } else {
+        -1.0
+    }
+}
+
+/// World units covered by one point of pointer movement at `distance` from the camera.
+fn world_per_point(camera: &Camera, distance: f32, viewport: Vec2) -> f32 {
+    let height = match *camera.projection() {
+        Projection::Perspective { fov_y, .. } => 2.0 * distance * (fov_y * 0.5).tan(),
+        Projection::Orthographic { half_height, .. } => 2.0 * half_height,
+    };
+    height / viewport.y.max(1.0)
+}
+
+/// Distance at which a sphere of `radius` fits the narrower field of view.
+fn fit_distance(camera: &Camera, radius: f32) -> f32 {
+    let radius = radius.max(0.01) * FRAME_MARGIN;
+    match *camera.projection() {
+        Projection::Perspective { fov_y, .. } => {
+            let half_y = fov_y * 0.5;
+            let half_x = (half_y.tan() * camera.aspect_ratio()).atan();
+            radius / half_y.min(half_x).sin()
+        }
+        Projection::Orthographic { .. } => radius * 2.0,
+    }
+}
@@ -0,0 +1,1069 @@
+use crate::support::resource::{Texture, gl_version, has_extension};
+use crate::support::texture::has_texture_storage;
+use anyhow::{Context, Result, anyhow, bail};
+use gl::types::*;
+use std::borrow::Cow;
+use std::io::Read;
+use std::path::Path;
+
+const KTX2_MAGIC: [u8; 12] = [
+    0xAB, b'K', b'T', b'X', b' ', b'2', b'0', 0xBB, b'\r', b'\n', 0x1A, b'\n',
+];
+const DDS_MAGIC: [u8; 4] = *b"DDS ";
+
+// S3TC and ASTC are extensions on desktop OpenGL, so the core bindings lack their enums.
+const COMPRESSED_RGB_S3TC_DXT1: GLenum = 0x83F0;
+const COMPRESSED_RGBA_S3TC_DXT1: GLenum = 0x83F1;
+const COMPRESSED_RGBA_S3TC_DXT3: GLenum = 0x83F2;
+const COMPRESSED_RGBA_S3TC_DXT5: GLenum = 0x83F3;
+const COMPRESSED_SRGB_S3TC_DXT1: GLenum = 0x8C4C;
+const COMPRESSED_SRGB_ALPHA_S3TC_DXT1: GLenum = 0x8C4D;
+const COMPRESSED_SRGB_ALPHA_S3TC_DXT3: GLenum = 0x8C4E;
+const COMPRESSED_SRGB_ALPHA_S3TC_DXT5: GLenum = 0x8C4F;
+const COMPRESSED_RGBA_ASTC_4X4: GLenum = 0x93B0;
+const COMPRESSED_SRGB8_ALPHA8_ASTC_4X4: GLenum = 0x93D0;
+
+/// ASTC block footprints in the order both the GL and Vulkan format enums list them.
+const ASTC_BLOCKS: [(u8, u8); 14] = [
+    (4, 4),
+    (5, 4),
+    (5, 5),
+    (6, 5),
+    (6, 6),
+    (8, 5),
+    (8, 6),
+    (8, 8),
+    (10, 5),
+    (10, 6),
+    (10, 8),
+    (10, 10),
+    (12, 10),
+    (12, 12),
+];
+
+/// Pixel layouts a compressed container can hold, named after the block format rather than
+/// any one API's enum.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextureFormat {
+    /// BC1 without alpha.
+    Bc1,
+    /// BC1 with one bit alpha.
+    Bc1Alpha,
+    Bc2,
+    Bc3,
+    Bc4,
+    Bc4Signed,
+    Bc5,
+    Bc5Signed,
+    Bc6hUfloat,
+    Bc6hSfloat,
+    Bc7,
+    Etc2Rgb,
+    Etc2RgbA1,
+    Etc2Rgba,
+    EacR11,
+    EacR11Signed,
+    EacRg11,
+    EacRg11Signed,
+    Astc {
+        width: u8,
+        height: u8,
+    },
+    Rgba8,
+    Bgra8,
+    Rgba16F,
+    Rgba32F,
+}
+
+impl TextureFormat {
+    pub fn is_compressed(&self) -> bool {
+        !matches!(
+            self,
+            TextureFormat::Rgba8
+                | TextureFormat::Bgra8
+                | TextureFormat::Rgba16F
+                | TextureFormat::Rgba32F
+        )
+    }
+
+    /// Texels covered by one block; 1x1 for uncompressed formats.
+    pub fn block_size(&self) -> (u32, u32) {
+        match self {
+            TextureFormat::Astc { width, height } => (*width as u32, *height as u32),
+            format if format.is_compressed() => (4, 4),
+            _ => (1, 1),
+        }
+    }
+
+    pub fn block_bytes(&self) -> usize {
+        match self {
+            TextureFormat::Bc1
+            | TextureFormat::Bc1Alpha
+            | TextureFormat::Bc4
+            | TextureFormat::Bc4Signed
+            | TextureFormat::Etc2Rgb
+            | TextureFormat::Etc2RgbA1
+            | TextureFormat::EacR11
+            | TextureFormat::EacR11Signed
+            | TextureFormat::Rgba16F => 8,
+            TextureFormat::Rgba8 | TextureFormat::Bgra8 => 4,
+            _ => 16,
+        }
+    }
+
+    /// Bytes one image of the given size takes up.
+    pub fn image_size(&self, width: u32, height: u32) -> usize {
+        let (block_width, block_height) = self.block_size();
+        width.div_ceil(block_width) as usize
+            * height.div_ceil(block_height) as usize
+            * self.block_bytes()
+    }
+
+    pub fn has_srgb(&self) -> bool {
+        matches!(
+            self,
+            TextureFormat::Bc1
+                | TextureFormat::Bc1Alpha
+                | TextureFormat::Bc2
+                | TextureFormat::Bc3
+                | TextureFormat::Bc7
+                | TextureFormat::Etc2Rgb
+                | TextureFormat::Etc2RgbA1
+                | TextureFormat::Etc2Rgba
+                | TextureFormat::Astc { .. }
+                | TextureFormat::Rgba8
+                | TextureFormat::Bgra8
+        )
+    }
+
+    /// The internal format to allocate, using the sRGB variant when asked for and available.
+    pub fn gl_internal_format(&self, srgb: bool) -> GLenum {
+        let srgb = srgb && self.has_srgb();
+        let pick = |linear, encoded| if srgb { encoded } else { linear };
+        match self {
+            TextureFormat::Bc1 => pick(COMPRESSED_RGB_S3TC_DXT1, COMPRESSED_SRGB_S3TC_DXT1),
+            TextureFormat::Bc1Alpha => {
+                pick(COMPRESSED_RGBA_S3TC_DXT1, COMPRESSED_SRGB_ALPHA_S3TC_DXT1)
+            }
+            TextureFormat::Bc2 => pick(COMPRESSED_RGBA_S3TC_DXT3, COMPRESSED_SRGB_ALPHA_S3TC_DXT3),
+            TextureFormat::Bc3 => pick(COMPRESSED_RGBA_S3TC_DXT5, COMPRESSED_SRGB_ALPHA_S3TC_DXT5),
+            TextureFormat::Bc4 => gl::COMPRESSED_RED_RGTC1,
+            TextureFormat::Bc4Signed => gl::COMPRESSED_SIGNED_RED_RGTC1,
+            TextureFormat::Bc5 => gl::COMPRESSED_RG_RGTC2,
+            TextureFormat::Bc5Signed => gl::COMPRESSED_SIGNED_RG_RGTC2,
+            TextureFormat::Bc6hUfloat => gl::COMPRESSED_RGB_BPTC_UNSIGNED_FLOAT,
+            TextureFormat::Bc6hSfloat => gl::COMPRESSED_RGB_BPTC_SIGNED_FLOAT,
+            TextureFormat::Bc7 => pick(
+                gl::COMPRESSED_RGBA_BPTC_UNORM,
+                gl::COMPRESSED_SRGB_ALPHA_BPTC_UNORM,
+            ),
+            TextureFormat::Etc2Rgb => pick(gl::COMPRESSED_RGB8_ETC2, gl::COMPRESSED_SRGB8_ETC2),
+            TextureFormat::Etc2RgbA1 => pick(
+                gl::COMPRESSED_RGB8_PUNCHTHROUGH_ALPHA1_ETC2,
+                gl::COMPRESSED_SRGB8_PUNCHTHROUGH_ALPHA1_ETC2,
+            ),
+            TextureFormat::Etc2Rgba => pick(
+                gl::COMPRESSED_RGBA8_ETC2_EAC,
+                gl::COMPRESSED_SRGB8_ALPHA8_ETC2_EAC,
+            ),
+            TextureFormat::EacR11 => gl::COMPRESSED_R11_EAC,
+            TextureFormat::EacR11Signed => gl::COMPRESSED_SIGNED_R11_EAC,
+            TextureFormat::EacRg11 => gl::COMPRESSED_RG11_EAC,
+            TextureFormat::EacRg11Signed => gl::COMPRESSED_SIGNED_RG11_EAC,
+            TextureFormat::Astc { width, height } => {
+                let index = ASTC_BLOCKS
+                    .iter()
+                    .position(|block| *block == (*width, *height))
+                    .unwrap_or(0) as GLenum;
+                pick(COMPRESSED_RGBA_ASTC_4X4, COMPRESSED_SRGB8_ALPHA8_ASTC_4X4) + index
+            }
+            TextureFormat::Rgba8 | TextureFormat::Bgra8 => pick(gl::RGBA8, gl::SRGB8_ALPHA8),
+            TextureFormat::Rgba16F => gl::RGBA16F,
+            TextureFormat::Rgba32F => gl::RGBA32F,
+        }
+    }
+
+    /// Client format and type for uncompressed uploads.
+    fn gl_pixel_format(&self) -> Option<(GLenum, GLenum)> {
+        match self {
+            TextureFormat::Rgba8 => Some((gl::RGBA, gl::UNSIGNED_BYTE)),
+            TextureFormat::Bgra8 => Some((gl::BGRA, gl::UNSIGNED_BYTE)),
+            TextureFormat::Rgba16F => Some((gl::RGBA, gl::HALF_FLOAT)),
+            TextureFormat::Rgba32F => Some((gl::RGBA, gl::FLOAT)),
+            _ => None,
+        }
+    }
+
+    fn from_vk_format(format: ktx2::Format) -> Option<(TextureFormat, bool)> {
+        use ktx2::Format;
+
+        let format = match format {
+            Format::BC1_RGB_UNORM_BLOCK => (TextureFormat::Bc1, false),
+            Format::BC1_RGB_SRGB_BLOCK => (TextureFormat::Bc1, true),
+            Format::BC1_RGBA_UNORM_BLOCK => (TextureFormat::Bc1Alpha, false),
+            Format::BC1_RGBA_SRGB_BLOCK => (TextureFormat::Bc1Alpha, true),
+            Format::BC2_UNORM_BLOCK => (TextureFormat::Bc2, false),
+            Format::BC2_SRGB_BLOCK => (TextureFormat::Bc2, true),
+            Format::BC3_UNORM_BLOCK => (TextureFormat::Bc3, false),
+            Format::BC3_SRGB_BLOCK => (TextureFormat::Bc3, true),
+            Format::BC4_UNORM_BLOCK => (TextureFormat::Bc4, false),
+            Format::BC4_SNORM_BLOCK => (TextureFormat::Bc4Signed, false),
+            Format::BC5_UNORM_BLOCK => (TextureFormat::Bc5, false),
+            Format::BC5_SNORM_BLOCK => (TextureFormat::Bc5Signed, false),
+            Format::BC6H_UFLOAT_BLOCK => (TextureFormat::Bc6hUfloat, false),
+            Format::BC6H_SFLOAT_BLOCK => (TextureFormat::Bc6hSfloat, false),
+            Format::BC7_UNORM_BLOCK => (TextureFormat::Bc7, false),
+            Format::BC7_SRGB_BLOCK => (TextureFormat::Bc7, true),
+            Format::ETC2_R8G8B8_UNORM_BLOCK => (TextureFormat::Etc2Rgb, false),
+            Format::ETC2_R8G8B8_SRGB_BLOCK => (TextureFormat::Etc2Rgb, true),
+            Format::ETC2_R8G8B8A1_UNORM_BLOCK => (TextureFormat::Etc2RgbA1, false),
+            Format::ETC2_R8G8B8A1_SRGB_BLOCK => (TextureFormat::Etc2RgbA1, true),
+            Format::ETC2_R8G8B8A8_UNORM_BLOCK => (TextureFormat::Etc2Rgba, false),
+            Format::ETC2_R8G8B8A8_SRGB_BLOCK => (TextureFormat::Etc2Rgba, true),
+            Format::EAC_R11_UNORM_BLOCK => (TextureFormat::EacR11, false),
+            Format::EAC_R11_SNORM_BLOCK => (TextureFormat::EacR11Signed, false),
+            Format::EAC_R11G11_UNORM_BLOCK => (TextureFormat::EacRg11, false),
+            Format::EAC_R11G11_SNORM_BLOCK => (TextureFormat::EacRg11Signed, false),
+            Format::R8G8B8A8_UNORM => (TextureFormat::Rgba8, false),
+            Format::R8G8B8A8_SRGB => (TextureFormat::Rgba8, true),
+            Format::B8G8R8A8_UNORM => (TextureFormat::Bgra8, false),
+            Format::B8G8R8A8_SRGB => (TextureFormat::Bgra8, true),
+            Format::R16G16B16A16_SFLOAT => (TextureFormat::Rgba16F, false),
+            Format::R32G32B32A32_SFLOAT => (TextureFormat::Rgba32F, false),
+            format => {
+                // The LDR ASTC formats come in UNORM/SRGB pairs starting at 4x4 UNORM.
+                let offset = format
+                    .value()
+                    .checked_sub(Format::ASTC_4x4_UNORM_BLOCK.value())?;
+                let (width, height) = *ASTC_BLOCKS.get(offset as usize / 2)?;
+                (TextureFormat::Astc { width, height }, offset % 2 == 1)
+            }
+        };
+        Some(format)
+    }
+
+    fn from_dxgi_format(format: ddsfile::DxgiFormat) -> Option<(TextureFormat, bool)> {
+        use ddsfile::DxgiFormat;
+
+        let format = match format {
+            DxgiFormat::BC1_Typeless | DxgiFormat::BC1_UNorm => (TextureFormat::Bc1Alpha, false),
+            DxgiFormat::BC1_UNorm_sRGB => (TextureFormat::Bc1Alpha, true),
+            DxgiFormat::BC2_Typeless | DxgiFormat::BC2_UNorm => (TextureFormat::Bc2, false),
+            DxgiFormat::BC2_UNorm_sRGB => (TextureFormat::Bc2, true),
+            DxgiFormat::BC3_Typeless | DxgiFormat::BC3_UNorm => (TextureFormat::Bc3, false),
+            DxgiFormat::BC3_UNorm_sRGB => (TextureFormat::Bc3, true),
+            DxgiFormat::BC4_Typeless | DxgiFormat::BC4_UNorm => (TextureFormat::Bc4, false),
+            DxgiFormat::BC4_SNorm => (TextureFormat::Bc4Signed, false),
+            DxgiFormat::BC5_Typeless | DxgiFormat::BC5_UNorm => (TextureFormat::Bc5, false),
+            DxgiFormat::BC5_SNorm => (TextureFormat::Bc5Signed, false),
+            DxgiFormat::BC6H_Typeless | DxgiFormat::BC6H_UF16 => (TextureFormat::Bc6hUfloat, false),
+            DxgiFormat::BC6H_SF16 => (TextureFormat::Bc6hSfloat, false),
+            DxgiFormat::BC7_Typeless | DxgiFormat::BC7_UNorm => (TextureFormat::Bc7, false),
+            DxgiFormat::BC7_UNorm_sRGB => (TextureFormat::Bc7, true),
+            DxgiFormat::R8G8B8A8_Typeless | DxgiFormat::R8G8B8A8_UNorm => {
+                (TextureFormat::Rgba8, false)
+            }
+            DxgiFormat::R8G8B8A8_UNorm_sRGB => (TextureFormat::Rgba8, true),
+            DxgiFormat::B8G8R8A8_Typeless | DxgiFormat::B8G8R8A8_UNorm => {
+                (TextureFormat::Bgra8, false)
+            }
+            DxgiFormat::B8G8R8A8_UNorm_sRGB => (TextureFormat::Bgra8, true),
+            DxgiFormat::R16G16B16A16_Float => (TextureFormat::Rgba16F, false),
+            DxgiFormat::R32G32B32A32_Float => (TextureFormat::Rgba32F, false),
+            _ => return None,
+        };
+        Some(format)
+    }
+
+    fn from_d3d_format(format: ddsfile::D3DFormat) -> Option<TextureFormat> {
+        use ddsfile::D3DFormat;
+
+        match format {
+            D3DFormat::DXT1 => Some(TextureFormat::Bc1Alpha),
+            // Premultiplied alpha variants share the block layout.
+            D3DFormat::DXT2 | D3DFormat::DXT3 => Some(TextureFormat::Bc2),
+            D3DFormat::DXT4 | D3DFormat::DXT5 => Some(TextureFormat::Bc3),
+            // D3D9 names list channels from the most significant bit.
+            D3DFormat::A8R8G8B8 => Some(TextureFormat::Bgra8),
+            D3DFormat::A8B8G8R8 => Some(TextureFormat::Rgba8),
+            D3DFormat::A16B16G16R16F => Some(TextureFormat::Rgba16F),
+            D3DFormat::A32B32G32R32F => Some(TextureFormat::Rgba32F),
+            _ => None,
+        }
+    }
+}
+
+/// The block compression families the current context can sample from.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FormatSupport {
+    pub s3tc: bool,
+    pub rgtc: bool,
+    pub bptc: bool,
+    pub etc2: bool,
+    pub astc: bool,
+}
+
+impl FormatSupport {
+    /// Queries the current context's version and extensions.
+    pub fn current() -> Self {
+        let version = gl_version();
+        Self {
+            s3tc: has_extension("GL_EXT_texture_compression_s3tc"),
+            rgtc: version >= (3, 0) || has_extension("GL_ARB_texture_compression_rgtc"),
+            bptc: version >= (4, 2) || has_extension("GL_ARB_texture_compression_bptc"),
+            etc2: version >= (4, 3) || has_extension("GL_ARB_ES3_compatibility"),
+            astc: has_extension("GL_KHR_texture_compression_astc_ldr"),
+        }
+    }
+
+    pub fn supports(&self, format: TextureFormat) -> bool {
+        match format {
+            TextureFormat::Bc1
+            | TextureFormat::Bc1Alpha
+            | TextureFormat::Bc2
+            | TextureFormat::Bc3 => self.s3tc,
+            TextureFormat::Bc4
+            | TextureFormat::Bc4Signed
+            | TextureFormat::Bc5
+            | TextureFormat::Bc5Signed => self.rgtc,
+            TextureFormat::Bc6hUfloat | TextureFormat::Bc6hSfloat | TextureFormat::Bc7 => self.bptc,
+            TextureFormat::Etc2Rgb
+            | TextureFormat::Etc2RgbA1
+            | TextureFormat::Etc2Rgba
+            | TextureFormat::EacR11
+            | TextureFormat::EacR11Signed
+            | TextureFormat::EacRg11
+            | TextureFormat::EacRg11Signed => self.etc2,
+            TextureFormat::Astc { .. } => self.astc,
+            TextureFormat::Rgba8
+            | TextureFormat::Bgra8
+            | TextureFormat::Rgba16F
+            | TextureFormat::Rgba32F => true,
+        }
+    }
+
+    /// The format Basis Universal data is transcoded to. BC7 comes first because desktop
+    /// drivers that expose ETC2, and some that expose ASTC, decompress it in software.
+    fn basis_target(&self, has_alpha: bool) -> (basisu::TargetFormat, TextureFormat) {
+        use basisu::TargetFormat;
+
+        if self.bptc {
+            (TargetFormat::Bc7Rgba, TextureFormat::Bc7)
+        } else if self.astc {
+            (
+                TargetFormat::Astc4x4Rgba,
+                TextureFormat::Astc {
+                    width: 4,
+                    height: 4,
+                },
+            )
+        } else if self.s3tc && has_alpha {
+            (TargetFormat::Bc3Rgba, TextureFormat::Bc3)
+        } else if self.s3tc {
+            (TargetFormat::Bc1Rgb, TextureFormat::Bc1)
+        } else if self.etc2 && has_alpha {
+            (TargetFormat::Etc2Rgba, TextureFormat::Etc2Rgba)
+        } else if self.etc2 {
+            // ETC1 is a subset of ETC2.
+            (TargetFormat::Etc1Rgb, TextureFormat::Etc2Rgb)
+        } else {
+            (TargetFormat::Rgba32, TextureFormat::Rgba8)
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Payload {
+    /// Mip levels from largest to smallest, each holding every layer and face in turn.
+    Levels {
+        format: TextureFormat,
+        levels: Vec<Vec<u8>>,
+    },
+    /// An ETC1S or UASTC KTX2 file, transcoded once the target context is known.
+    Basis {
+        bytes: Vec<u8>,
+        levels: u32,
+        has_alpha: bool,
+    },
+}
+
+/// A texture read from a KTX2 or DDS container, keeping its block compressed data and mip
+/// chain as stored.
+#[derive(Clone, Debug)]
+pub struct CompressedImage {
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+    /// Array layers, 1 unless `array` is set.
+    pub layers: u32,
+    /// 6 for cubemaps, 1 otherwise.
+    pub faces: u32,
+    pub array: bool,
+    /// Whether the container marks the data as sRGB encoded, or `None` if it does not say.
+    pub srgb: Option<bool>,
+    payload: Payload,
+}
+
+impl CompressedImage {
+    /// Whether `bytes` start like a KTX2 or DDS file.
+    pub fn is_container(bytes: &[u8]) -> bool {
+        bytes.starts_with(&KTX2_MAGIC) || bytes.starts_with(&DDS_MAGIC)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path)
+            .with_context(|| format!("Failed to read texture '{}'", path.display()))?;
+        Self::from_memory(&path.display().to_string(), &bytes)
+    }
+
+    pub fn from_memory(name: &str, bytes: &[u8]) -> Result<Self> {
+        let image = if bytes.starts_with(&KTX2_MAGIC) {
+            Self::from_ktx2(name, bytes)
+        } else if bytes.starts_with(&DDS_MAGIC) {
+            Self::from_dds(name, bytes)
+        } else {
+            bail!("'{}' is neither a KTX2 nor a DDS file", name);
+        };
+        image.with_context(|| format!("Failed to decode texture '{}'", name))
+    }
+
+    pub fn level_count(&self) -> u32 {
+        match &self.payload {
+            Payload::Levels { levels, .. } => levels.len() as u32,
+            Payload::Basis { levels, .. } => *levels,
+        }
+    }
+
+    /// The stored format, or `None` for Basis Universal data that has yet to be transcoded.
+    pub fn format(&self) -> Option<TextureFormat> {
+        match &self.payload {
+            Payload::Levels { format, .. } => Some(*format),
+            Payload::Basis { .. } => None,
+        }
+    }
+
+    pub fn is_cubemap(&self) -> bool {
+        self.faces == 6
+    }
+
+    /// The texture target matching the container's layers and faces.
+    pub fn gl_target(&self) -> GLenum {
+        match (self.is_cubemap(), self.array) {
+            (false, false) => gl::TEXTURE_2D,
+            (false, true) => gl::TEXTURE_2D_ARRAY,
+            (true, false) => gl::TEXTURE_CUBE_MAP,
+            (true, true) => gl::TEXTURE_CUBE_MAP_ARRAY,
+        }
+    }
+
+    pub fn level_size(&self, level: u32) -> (u32, u32) {
+        ((self.width >> level).max(1), (self.height >> level).max(1))
+    }
+
+    /// The mip levels in a format `support` can sample, transcoding Basis Universal data.
+    pub fn levels(&self, support: &FormatSupport) -> Result<(TextureFormat, Cow<'_, [Vec<u8>]>)> {
+        let (bytes, levels, has_alpha) = match &self.payload {
+            Payload::Levels { format, levels } => {
+                return Ok((*format, Cow::Borrowed(levels)));
+            }
+            Payload::Basis {
+                bytes,
+                levels,
+                has_alpha,
+            } => (bytes, *levels, *has_alpha),
+        };
+        let transcoder = basisu::Transcoder::new(bytes)
+            .map_err(|error| anyhow!("{:?}", error))
+            .with_context(|| format!("Failed to open Basis Universal data in '{}'", self.name))?;
+        let (target, format) = support.basis_target(has_alpha);
+        let mut output = Vec::with_capacity(levels as usize);
+        for level in 0..levels {
+            let mut data = Vec::new();
+            for layer in 0..self.layers {
+                for face in 0..self.faces {
+                    let image = transcoder
+                        .transcode_image(level, layer, face, target, basisu::DecodeFlags::NONE)
+                        .map_err(|error| anyhow!("{:?}", error))
+                        .with_context(|| {
+                            format!(
+                                "Failed to transcode level {} of '{}' to {:?}",
+                                level, self.name, target
+                            )
+                        })?;
+                    data.extend_from_slice(&image);
+                }
+            }
+            output.push(data);
+        }
+        Ok((format, Cow::Owned(output)))
+    }
+
+    /// Creates a texture with immutable storage for every stored mip level. Basis Universal
+    /// data is transcoded to the best format the current context supports.
+    pub fn upload(&self, srgb: bool) -> Result<(Texture, TextureFormat)> {
+        let support = FormatSupport::current();
+        let (format, levels) = self.levels(&support)?;
+        if !support.supports(format) {
+            bail!(
+                "'{}' is stored as {:?}, which this OpenGL context cannot sample",
+                self.name,
+                format
+            );
+        }
+        if !has_texture_storage() {
+            bail!(
+                "'{}' needs immutable texture storage, which needs OpenGL 4.2 or ARB_texture_storage",
+                self.name
+            );
+        }
+        let mut max_size = 0;
+        unsafe {
+            gl::GetIntegerv(gl::MAX_TEXTURE_SIZE, &mut max_size);
+        }
+        if self.width.max(self.height) > max_size as u32 {
+            bail!(
+                "'{}' is {}x{} but textures are limited to {}",
+                self.name,
+                self.width,
+                self.height,
+                max_size
+            );
+        }
+
+        let target = self.gl_target();
+        let internal_format = format.gl_internal_format(srgb);
+        let slices = (self.layers * self.faces) as GLsizei;
+        let texture = Texture::new(target);
+        texture.bind();
+        unsafe {
+            if target == gl::TEXTURE_2D || target == gl::TEXTURE_CUBE_MAP {
+                gl::TexStorage2D(
+                    target,
+                    levels.len() as GLsizei,
+                    internal_format,
+                    self.width as GLsizei,
+                    self.height as GLsizei,
+                );
+            } else {
+                gl::TexStorage3D(
+                    target,
+                    levels.len() as GLsizei,
+                    internal_format,
+                    self.width as GLsizei,
+                    self.height as GLsizei,
+                    slices,
+                );
+            }
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+        }
+        for (level, data) in levels.iter().enumerate() {
+            let (width, height) = self.level_size(level as u32);
+            let image_size = format.image_size(width, height);
+            if data.len() != image_size * slices as usize {
+                bail!(
+                    "Level {} of '{}' has {} bytes but needs {}",
+                    level,
+                    self.name,
+                    data.len(),
+                    image_size * slices as usize
+                );
+            }
+            match target {
+                gl::TEXTURE_2D => upload_image(
+                    target,
+                    level,
+                    (width, height, 1),
+                    format,
+                    internal_format,
+                    data,
+                ),
+                gl::TEXTURE_CUBE_MAP => {
+                    for (face, data) in data.chunks_exact(image_size).enumerate() {
+                        upload_image(
+                            gl::TEXTURE_CUBE_MAP_POSITIVE_X + face as GLenum,
+                            level,
+                            (width, height, 1),
+                            format,
+                            internal_format,
+                            data,
+                        );
+                    }
+                }
+                _ => upload_image(
+                    target,
+                    level,
+                    (width, height, slices as u32),
+                    format,
+                    internal_format,
+                    data,
+                ),
+            }
+        }
+        unsafe {
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+        }
+        texture.parameter_i(gl::TEXTURE_MAX_LEVEL, levels.len() as GLint - 1);
+        Ok((texture, format))
+    }
+
+    fn from_ktx2(name: &str, bytes: &[u8]) -> Result<Self> {
+        let reader = ktx2::Reader::new(bytes)?;
+        let header = reader.header();
+        if header.pixel_depth > 1 {
+            bail!("3D textures are not supported");
+        }
+        if header.pixel_height == 0 {
+            bail!("1D textures are not supported");
+        }
+        let srgb = reader.dfd_blocks().next().and_then(|block| {
+            let block = ktx2::DfdBlockBasic::parse(block.data).ok()?;
+            Some(block.header.transfer_function == Some(ktx2::TransferFunction::SRGB))
+        });
+        let layers = header.layer_count.max(1);
+        let faces = header.face_count;
+        let mut image = Self {
+            name: name.to_string(),
+            width: header.pixel_width,
+            height: header.pixel_height,
+            layers,
+            faces,
+            array: header.layer_count > 0,
+            srgb,
+            payload: Payload::Levels {
+                format: TextureFormat::Rgba8,
+                levels: Vec::new(),
+            },
+        };
+
+        let Some(vk_format) = header.format else {
+            // An undefined format means the levels hold Basis Universal data.
+            let transcoder =
+                basisu::Transcoder::new(bytes).map_err(|error| anyhow!("{:?}", error))?;
+            image.payload = Payload::Basis {
+                bytes: bytes.to_vec(),
+                levels: transcoder.level_count(),
+                has_alpha: transcoder.has_alpha(),
+            };
+            return Ok(image);
+        };
+        let (format, format_srgb) = TextureFormat::from_vk_format(vk_format)
+            .with_context(|| format!("Unsupported format {:?}", vk_format))?;
+        image.srgb = Some(format_srgb);
+
+        let mut levels = Vec::with_capacity(header.level_count.max(1) as usize);
+        for (index, level) in reader.levels().enumerate() {
+            let data = match header.supercompression_scheme {
+                None => level.data.to_vec(),
+                Some(ktx2::SupercompressionScheme::Zstandard) => {
+                    let mut data = Vec::with_capacity(level.uncompressed_byte_length as usize);
+                    ruzstd::StreamingDecoder::new(level.data)
+                        .map_err(|error| anyhow!("{}", error))?
+                        .read_to_end(&mut data)?;
+                    data
+                }
+                Some(ktx2::SupercompressionScheme::ZLIB) => {
+                    miniz_oxide::inflate::decompress_to_vec_zlib(level.data)
+                        .map_err(|error| anyhow!("{}", error))?
+                }
+                Some(scheme) => bail!("Unsupported supercompression {:?}", scheme),
+            };
+            let (width, height) = image.level_size(index as u32);
+            let expected = format.image_size(width, height) * (layers * faces) as usize;
+            if data.len() != expected {
+                bail!(
+                    "Level {} has {} bytes but needs {}",
+                    index,
+                    data.len(),
+                    expected
+                );
+            }
+            levels.push(data);
+        }
+        image.payload = Payload::Levels { format, levels };
+        Ok(image)
+    }
+
+    fn from_dds(name: &str, bytes: &[u8]) -> Result<Self> {
+        let dds = ddsfile::Dds::read(bytes)?;
+        if dds.get_depth() > 1 || dds.header.caps2.contains(ddsfile::Caps2::VOLUME) {
+            bail!("3D textures are not supported");
+        }
+        // Legacy headers cannot say whether their colors are sRGB encoded.
+        let (format, srgb) = if let Some(header) = &dds.header10 {
+            let (format, srgb) = TextureFormat::from_dxgi_format(header.dxgi_format)
+                .with_context(|| format!("Unsupported format {:?}", header.dxgi_format))?;
+            (format, Some(srgb))
+        } else {
+            let format = dds
+                .get_d3d_format()
+                .and_then(TextureFormat::from_d3d_format)
+                .or_else(|| {
+                    let format = dds.get_dxgi_format()?;
+                    Some(TextureFormat::from_dxgi_format(format)?.0)
+                })
+                .with_context(|| format!("Unsupported pixel format {:?}", dds.header.spf))?;
+            (format, None)
+        };
+
+        let (faces, layers, array) = match &dds.header10 {
+            Some(header) => {
+                let cubemap = header.misc_flag.contains(ddsfile::MiscFlag::TEXTURECUBE);
+                let layers = header.array_size.max(1);
+                (if cubemap { 6 } else { 1 }, layers, layers > 1)
+            }
+            None if dds.header.caps2.contains(ddsfile::Caps2::CUBEMAP) => {
+                if !dds.header.caps2.contains(ddsfile::Caps2::CUBEMAP_ALLFACES) {
+                    bail!("Cubemaps with missing faces are not supported");
+                }
+                (6, 1, false)
+            }
+            None => (1, 1, false),
+        };
+        let image = Self {
+            name: name.to_string(),
+            width: dds.get_width(),
+            height: dds.get_height(),
+            layers,
+            faces,
+            array,
+            srgb,
+            payload: Payload::Levels {
+                format,
+                levels: Vec::new(),
+            },
+        };
+
+        // DDS stores every mip level of a face before moving on to the next face, while GL
+        // uploads a level at a time.
+        let level_count = dds.get_num_mipmap_levels().max(1);
+        let sizes: Vec<_> = (0..level_count)
+            .map(|level| {
+                let (width, height) = image.level_size(level);
+                format.image_size(width, height)
+            })
+            .collect();
+        let slice_size: usize = sizes.iter().sum();
+        let slices = (layers * faces) as usize;
+        if dds.data.len() < slice_size * slices {
+            bail!(
+                "Has {} bytes of data but needs {}",
+                dds.data.len(),
+                slice_size * slices
+            );
+        }
+        let mut levels: Vec<_> = sizes
+            .iter()
+            .map(|size| Vec::with_capacity(size * slices))
+            .collect();
+        for slice in dds.data.chunks_exact(slice_size).take(slices) {
+            let mut offset = 0;
+            for (level, size) in sizes.iter().enumerate() {
+                levels[level].extend_from_slice(&slice[offset..offset + size]);
+                offset += size;
+            }
+        }
+        Ok(Self {
+            payload: Payload::Levels { format, levels },
+            ..image
+        })
+    }
+}
+
+fn upload_image(
+    target: GLenum,
+    level: usize,
+    (width, height, depth): (u32, u32, u32),
+    format: TextureFormat,
+    internal_format: GLenum,
+    data: &[u8],
+) {
+    let three_d = target == gl::TEXTURE_2D_ARRAY || target == gl::TEXTURE_CUBE_MAP_ARRAY;
+    unsafe {
+        match (format.gl_pixel_format(), three_d) {
+            (None, false) => gl::CompressedTexSubImage2D(
+                target,
+                level as GLint,
+                0,
+                0,
+                width as GLsizei,
+                height as GLsizei,
+                internal_format,
+                data.len() as GLsizei,
+                data.as_ptr().cast(),
+            ),
+            (None, true) => gl::CompressedTexSubImage3D(
+                target,
+                level as GLint,
+                0,
+                0,
+                0,
+                width as GLsizei,
+                height as GLsizei,
+                depth as GLsizei,
+                internal_format,
+                data.len() as GLsizei,
+                data.as_ptr().cast(),
+            ),
+            (Some((pixel_format, data_type)), false) => gl::TexSubImage2D(
+                target,
+                level as GLint,
+                0,
+                0,
+                width as GLsizei,
+                height as GLsizei,
+                pixel_format,
+                data_type,
+                data.as_ptr().cast(),
+            ),
+            (Some((pixel_format, data_type)), true) => gl::TexSubImage3D(
+                target,
+                level as GLint,
+                0,
+                0,
+                0,
+                width as GLsizei,
+                height as GLsizei,
+                depth as GLsizei,
+                pixel_format,
+                data_type,
+                data.as_ptr().cast(),
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(values: &[u32]) -> Vec<u8> {
+        values
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .collect()
+    }
+
+    /// A DDS file with a FourCC pixel format, optionally followed by a DX10 header of
+    /// `[dxgi_format, resource_dimension, misc_flag, array_size, misc_flags2]`.
+    fn dds(
+        (width, height): (u32, u32),
+        levels: u32,
+        caps2: u32,
+        fourcc: &[u8; 4],
+        dx10: Option<[u32; 5]>,
+        data: &[u8],
+    ) -> Vec<u8> {
+        // CAPS | HEIGHT | WIDTH | PIXELFORMAT | MIPMAPCOUNT
+        let flags = 0x1 | 0x2 | 0x4 | 0x1000 | 0x20000;
+        let mut bytes = DDS_MAGIC.to_vec();
+        bytes.extend(words(&[124, flags, height, width, 0, 0, levels]));
+        bytes.extend(words(&[0; 11]));
+        bytes.extend(words(&[
+            32,
+            0x4,
+            u32::from_le_bytes(*fourcc),
+            0,
+            0,
+            0,
+            0,
+            0,
+        ]));
+        bytes.extend(words(&[0x1000 | 0x400000 | 0x8, caps2, 0, 0, 0]));
+        if let Some(header) = dx10 {
+            bytes.extend(words(&header));
+        }
+        bytes.extend_from_slice(data);
+        bytes
+    }
+
+    /// A KTX2 file with an empty data format descriptor and the given levels, largest first.
+    fn ktx2(
+        vk_format: u32,
+        (width, height): (u32, u32),
+        (layers, faces): (u32, u32),
+        levels: &[&[u8]],
+    ) -> Vec<u8> {
+        let index_end = 80 + 24 * levels.len() as u32;
+        let mut bytes = KTX2_MAGIC.to_vec();
+        bytes.extend(words(&[vk_format, 1, width, height, 0, layers, faces]));
+        bytes.extend(words(&[
+            levels.len() as u32,
+            0,
+            index_end,
+            4,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+        ]));
+        let mut offset = index_end as u64 + 4;
+        for level in levels {
+            let length = level.len() as u64;
+            for value in [offset, length, length] {
+                bytes.extend(value.to_le_bytes());
+            }
+            offset += length;
+        }
+        bytes.extend(words(&[4]));
+        for level in levels {
+            bytes.extend_from_slice(level);
+        }
+        bytes
+    }
+
+    fn stored_levels(image: &CompressedImage) -> (TextureFormat, Vec<Vec<u8>>) {
+        let (format, levels) = image.levels(&FormatSupport::default()).unwrap();
+        (format, levels.into_owned())
+    }
+
+    #[test]
+    fn dds_levels_are_gathered_across_faces() {
+        // An 8x8 DXT1 cubemap stores 32, 8, 8 and 8 bytes for its four levels, face by face.
+        let sizes = [32, 8, 8, 8];
+        let data: Vec<u8> = (0..6u8)
+            .flat_map(|face| {
+                sizes
+                    .iter()
+                    .enumerate()
+                    .flat_map(move |(level, size)| vec![face * 16 + level as u8; *size])
+            })
+            .collect();
+        let cubemap = 0x200 | 0xFC00;
+        let bytes = dds((8, 8), 4, cubemap, b"DXT1", None, &data);
+
+        let image = CompressedImage::from_memory("sky.dds", &bytes).unwrap();
+        assert_eq!((image.width, image.height), (8, 8));
+        assert_eq!((image.faces, image.layers, image.array), (6, 1, false));
+        assert_eq!(image.srgb, None);
+        assert_eq!(image.gl_target(), gl::TEXTURE_CUBE_MAP);
+        let (format, levels) = stored_levels(&image);
+        assert_eq!(format, TextureFormat::Bc1Alpha);
+        assert_eq!(levels.len(), 4);
+        for (level, data) in levels.iter().enumerate() {
+            let expected: Vec<u8> = (0..6u8)
+                .flat_map(|face| vec![face * 16 + level as u8; sizes[level]])
+                .collect();
+            assert_eq!(data, &expected, "level {}", level);
+        }
+    }
+
+    #[test]
+    fn dds_dx10_header_gives_arrays_and_color_space() {
+        // Three 2x2 R8G8B8A8_UNORM_SRGB layers with two levels: 16 and 4 bytes each.
+        let data: Vec<u8> = (0..3u8)
+            .flat_map(|layer| [vec![layer; 16], vec![layer + 10; 4]].concat())
+            .collect();
+        let bytes = dds((2, 2), 2, 0, b"DX10", Some([29, 3, 0, 3, 0]), &data);
+
+        let image = CompressedImage::from_memory("layers.dds", &bytes).unwrap();
+        assert_eq!((image.layers, image.faces, image.array), (3, 1, true));
+        assert_eq!(image.srgb, Some(true));
+        assert_eq!(image.gl_target(), gl::TEXTURE_2D_ARRAY);
+        let (format, levels) = stored_levels(&image);
+        assert_eq!(format, TextureFormat::Rgba8);
+        assert_eq!(levels[0], [vec![0; 16], vec![1; 16], vec![2; 16]].concat());
+        assert_eq!(levels[1], [vec![10; 4], vec![11; 4], vec![12; 4]].concat());
+    }
+
+    #[test]
+    fn rejects_truncated_and_unknown_dds_files() {
+        let bytes = dds((8, 8), 1, 0, b"DXT5", None, &[0; 63]);
+        let error = CompressedImage::from_memory("short.dds", &bytes).unwrap_err();
+        assert!(format!("{:#}", error).contains("needs 64"), "{:#}", error);
+
+        let bytes = dds((4, 4), 1, 0, b"ABCD", None, &[0; 16]);
+        let error = CompressedImage::from_memory("odd.dds", &bytes).unwrap_err();
+        assert!(
+            format!("{:#}", error).contains("Unsupported"),
+            "{:#}",
+            error
+        );
+
+        // Cut off inside the header.
+        assert!(CompressedImage::from_memory("cut.dds", &bytes[..40]).is_err());
+        assert!(CompressedImage::from_memory("text.dds", b"not a texture").is_err());
+    }
+
+    #[test]
+    fn reads_ktx2_levels() {
+        // VK_FORMAT_R8G8B8A8_SRGB
+        let bytes = ktx2(43, (2, 2), (0, 1), &[&[1; 16], &[2; 4]]);
+        let image = CompressedImage::from_memory("albedo.ktx2", &bytes).unwrap();
+        assert_eq!((image.width, image.height), (2, 2));
+        assert_eq!((image.layers, image.faces, image.array), (1, 1, false));
+        assert_eq!(image.srgb, Some(true));
+        assert_eq!(image.level_count(), 2);
+        let (format, levels) = stored_levels(&image);
+        assert_eq!(format, TextureFormat::Rgba8);
+        assert_eq!(levels, [vec![1; 16], vec![2; 4]]);
+
+        // VK_FORMAT_BC7_UNORM_BLOCK, as a cubemap array of two.
+        let bytes = ktx2(145, (4, 4), (2, 6), &[&[7; 16 * 12]]);
+        let image = CompressedImage::from_memory("probes.ktx2", &bytes).unwrap();
+        assert_eq!(image.format(), Some(TextureFormat::Bc7));
+        assert_eq!(image.srgb, Some(false));
+        assert_eq!(image.gl_target(), gl::TEXTURE_CUBE_MAP_ARRAY);
+    }
+
+    #[test]
+    fn rejects_ktx2_levels_of_the_wrong_size() {
+        let bytes = ktx2(43, (2, 2), (0, 1), &[&[1; 16], &[2; 3]]);
+        let error = CompressedImage::from_memory("albedo.ktx2", &bytes).unwrap_err();
+        assert!(
+            format!("{:#}", error).contains("Level 1 has 3 bytes but needs 4"),
+            "{:#}",
+            error
+        );
+
+        let bytes = ktx2(43, (2, 2), (0, 1), &[&[1; 16]]);
+        assert!(CompressedImage::from_memory("cut.ktx2", &bytes[..bytes.len() - 8]).is_err());
+    }
+
+    #[test]
+    fn basis_targets_follow_support() {
+        let support = |bptc, astc, s3tc, etc2| FormatSupport {
+            s3tc,
+            rgtc: true,
+            bptc,
+            etc2,
+            astc,
+        };
+        let target = |support: FormatSupport, alpha| support.basis_target(alpha).1;
+        let astc = TextureFormat::Astc {
+            width: 4,
+            height: 4,
+        };
+
+        assert_eq!(
+            target(support(true, true, true, true), false),
+            TextureFormat::Bc7
+        );
+        assert_eq!(target(support(false, true, true, true), true), astc);
+        assert_eq!(
+            target(support(false, false, true, true), true),
+            TextureFormat::Bc3
+        );
+        assert_eq!(
+            target(support(false, false, true, true), false),
+            TextureFormat::Bc1
+        );
+        assert_eq!(
+            target(support(false, false, false, true), true),
+            TextureFormat::Etc2Rgba
+        );
+        assert_eq!(
+            target(support(false, false, false, true), false),
+            TextureFormat::Etc2Rgb
+        );
+        assert_eq!(target(FormatSupport::default(), true), TextureFormat::Rgba8);
+        for alpha in [false, true] {
+            for support in [support(true, true, true, true), FormatSupport::default()] {
+                let (_, format) = support.basis_target(alpha);
+                assert!(support.supports(format), "{:?}", format);
+            }
+        }
+    }
+}
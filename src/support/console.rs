@@ -0,0 +1,823 @@
+use anyhow::{Context, Result, bail};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const MAX_HISTORY: usize = 200;
+const MAX_SUGGESTIONS: usize = 8;
+const BUILTIN_COMMANDS: [(&str, &str, &str); 6] = [
+    (
+        "help",
+        "help [name]",
+        "List commands or describe a command or variable",
+    ),
+    ("set", "set <variable> <value>", "Set a console variable"),
+    ("get", "get <variable>", "Print a console variable"),
+    (
+        "reset",
+        "reset <variable>",
+        "Reset a console variable to its default",
+    ),
+    ("cvars", "cvars [filter]", "List console variables"),
+    ("history", "history", "Print the command history"),
+];
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum CvarValue {
+    Bool(bool),
+    Int(i64),
+    Float(f32),
+    String(String),
+}
+
+impl CvarValue {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            CvarValue::Bool(_) => "bool",
+            CvarValue::Int(_) => "int",
+            CvarValue::Float(_) => "float",
+            CvarValue::String(_) => "string",
+        }
+    }
+
+    pub fn parse_as(&self, text: &str) -> Result<CvarValue> {
+        Ok(match self {
+            CvarValue::Bool(_) => CvarValue::Bool(match text.to_lowercase().as_str() {
+                "1" | "true" | "on" | "yes" => true,
+                "0" | "false" | "off" | "no" => false,
+                _ => bail!("Expected a bool (0/1, true/false, on/off), got '{}'", text),
+            }),
+            CvarValue::Int(_) => CvarValue::Int(
+                text.parse()
+                    .with_context(|| format!("Expected an int, got '{}'", text))?,
+            ),
+            CvarValue::Float(_) => CvarValue::Float(
+                text.parse()
+                    .with_context(|| format!("Expected a float, got '{}'", text))?,
+            ),
+            CvarValue::String(_) => CvarValue::String(text.to_string()),
+        })
+    }
+}
+
+impl fmt::Display for CvarValue {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CvarValue::Bool(value) => write!(formatter, "{}", u8::from(*value)),
+            CvarValue::Int(value) => write!(formatter, "{}", value),
+            CvarValue::Float(value) => write!(formatter, "{}", value),
+            CvarValue::String(value) => write!(formatter, "\"{}\"", escape(value)),
+        }
+    }
+}
+
+impl From<bool> for CvarValue {
+    fn from(value: bool) -> Self {
+        CvarValue::Bool(value)
+    }
+}
+
+impl From<i64> for CvarValue {
+    fn from(value: i64) -> Self {
+        CvarValue::Int(value)
+    }
+}
+
+impl From<f32> for CvarValue {
+    fn from(value: f32) -> Self {
+        CvarValue::Float(value)
+    }
+}
+
+impl From<&str> for CvarValue {
+    fn from(value: &str) -> Self {
+        CvarValue::String(value.to_string())
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Cvar {
+    pub name: String,
+    pub value: CvarValue,
+    pub default: CvarValue,
+    pub help: String,
+    /// The value written to the config, which `sync_value` leaves alone.
+    pub saved: CvarValue,
+}
+
+#[derive(Clone, Debug)]
+pub struct CommandInfo {
+    pub name: String,
+    pub usage: String,
+    pub help: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ConsoleEvent {
+    Command { name: String, args: Vec<String> },
+    CvarChanged { name: String, value: CvarValue },
+}
+
+#[derive(Debug, Default)]
+pub struct CommandConsole {
+    commands: BTreeMap<String, CommandInfo>,
+    cvars: BTreeMap<String, Cvar>,
+    pending: HashMap<String, String>,
+    events: Vec<ConsoleEvent>,
+    history: Vec<String>,
+    history_cursor: Option<usize>,
+    input: String,
+    config_path: Option<PathBuf>,
+}
+
+impl CommandConsole {
+    pub fn new() -> Self {
+        let mut console = Self::default();
+        for (name, usage, help) in BUILTIN_COMMANDS {
+            console.register_command(name, usage, help);
+        }
+        console
+    }
+
+    /// Loads cvar values and history from `path`, and saves them back there whenever they change.
+    /// Values for cvars that are registered later are applied on registration.
+    pub fn with_config(mut self, path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        if let Err(error) = self.load_config(&path) {
+            log::warn!("{:?}", error);
+        }
+        self.config_path = Some(path);
+        self
+    }
+
+    pub fn register_command(&mut self, name: &str, usage: &str, help: &str) -> &mut Self {
+        self.commands.insert(
+            name.to_string(),
+            CommandInfo {
+                name: name.to_string(),
+                usage: usage.to_string(),
+                help: help.to_string(),
+            },
+        );
+        self
+    }
+
+    pub fn register_cvar(
+        &mut self,
+        name: &str,
+        default: impl Into<CvarValue>,
+        help: &str,
+    ) -> &mut Self {
+        let default = default.into();
+        let mut value = default.clone();
+        if let Some(text) = self.pending.remove(name) {
+            match default.parse_as(&text) {
+                Ok(parsed) => value = parsed,
+                Err(error) => log::warn!("Ignoring saved value for '{}': {}", name, error),
+            }
+        }
+        self.cvars.insert(
+            name.to_string(),
+            Cvar {
+                name: name.to_string(),
+                saved: value.clone(),
+                value,
+                default,
+                help: help.to_string(),
+            },
+        );
+        self
+    }
+
+    pub fn commands(&self) -> impl Iterator<Item = &CommandInfo> {
+        self.commands.values()
+    }
+
+    pub fn cvars(&self) -> impl Iterator<Item = &Cvar> {
+        self.cvars.values()
+    }
+
+    pub fn cvar(&self, name: &str) -> Option<&Cvar> {
+        self.cvars.get(name)
+    }
+
+    pub fn get_bool(&self, name: &str) -> Option<bool> {
+        match self.cvar(name)?.value {
+            CvarValue::Bool(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn get_int(&self, name: &str) -> Option<i64> {
+        match self.cvar(name)?.value {
+            CvarValue::Int(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn get_float(&self, name: &str) -> Option<f32> {
+        match self.cvar(name)?.value {
+            CvarValue::Float(value) => Some(value),
+            CvarValue::Int(value) => Some(value as f32),
+            _ => None,
+        }
+    }
+
+    pub fn get_string(&self, name: &str) -> Option<&str> {
+        match &self.cvar(name)?.value {
+            CvarValue::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn set(&mut self, name: &str, text: &str) -> Result<()> {
+        let Some(cvar) = self.cvars.get(name) else {
+            bail!("Unknown variable '{}'", name);
+        };
+        let value = cvar.value.parse_as(text)?;
+        self.set_value(name, value)
+    }
+
+    pub fn set_value(&mut self, name: &str, value: CvarValue) -> Result<()> {
+        self.assign(name, value, true)
+    }
+
+    /// Sets a cvar to mirror state loaded from elsewhere, such as a scene file, without
+    /// saving it to the config.
+    pub fn sync_value(&mut self, name: &str, value: CvarValue) -> Result<()> {
+        self.assign(name, value, false)
+    }
+
+    fn assign(&mut self, name: &str, value: CvarValue, persist: bool) -> Result<()> {
+        let Some(cvar) = self.cvars.get_mut(name) else {
+            bail!("Unknown variable '{}'", name);
+        };
+        if cvar.value.type_name() != value.type_name() {
+            bail!(
+                "Variable '{}' is a {}, not a {}",
+                name,
+                cvar.value.type_name(),
+                value.type_name()
+            );
+        }
+        let save = persist && cvar.saved != value;
+        if save {
+            cvar.saved = value.clone();
+        }
+        if cvar.value != value {
+            cvar.value = value.clone();
+            self.events.push(ConsoleEvent::CvarChanged {
+                name: name.to_string(),
+                value,
+            });
+        }
+        if save {
+            self.save_config();
+        }
+        Ok(())
+    }
+
+    pub fn take_events(&mut self) -> Vec<ConsoleEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    pub fn history(&self) -> &[String] {
+        &self.history
+    }
+
+    pub fn execute(&mut self, line: &str) -> Result<()> {
+        let tokens = tokenize(line)?;
+        let Some((name, args)) = tokens.split_first() else {
+            return Ok(());
+        };
+        match (name.as_str(), args) {
+            ("help", []) => {
+                for command in self.commands.values() {
+                    log::info!(target: "console", "{:<32} {}", command.usage, command.help);
+                }
+                log::info!(
+                    target: "console",
+                    "Type a variable name to print it, or '<variable> <value>' to set it"
+                );
+            }
+            ("help", [topic]) => {
+                if let Some(command) = self.commands.get(topic) {
+                    log::info!(target: "console", "{}: {}", command.usage, command.help);
+                } else if let Some(cvar) = self.cvars.get(topic) {
+                    log::info!(target: "console", "{}", describe(cvar));
+                    log::info!(target: "console", "{}", cvar.help);
+                } else {
+                    bail!("No command or variable named '{}'", topic);
+                }
+            }
+            ("set", [variable, value]) => self.set(variable, value)?,
+            ("get", [variable]) => {
+                let Some(cvar) = self.cvars.get(variable) else {
+                    bail!("Unknown variable '{}'", variable);
+                };
+                log::info!(target: "console", "{}", describe(cvar));
+            }
+            ("reset", [variable]) => {
+                let Some(cvar) = self.cvars.get(variable) else {
+                    bail!("Unknown variable '{}'", variable);
+                };
+                self.set_value(variable, cvar.default.clone())?;
+            }
+            ("cvars", filter) => {
+                let filter = filter.first().map(|filter| filter.to_lowercase());
+                for cvar in self.cvars.values().filter(|cvar| {
+                    filter
+                        .as_ref()
+                        .is_none_or(|filter| cvar.name.to_lowercase().contains(filter))
+                }) {
+                    log::info!(target: "console", "{}", describe(cvar));
+                }
+            }
+            ("history", []) => {
+                for (index, line) in self.history.iter().enumerate() {
+                    log::info!(target: "console", "{:>4} {}", index + 1, line);
+                }
+            }
+            (builtin, _) if BUILTIN_COMMANDS.iter().any(|(name, ..)| *name == builtin) => {
+                bail!("Usage: {}", self.commands[builtin].usage);
+            }
+            (command, args) if self.commands.contains_key(command) => {
+                self.events.push(ConsoleEvent::Command {
+                    name: command.to_string(),
+                    args: args.to_vec(),
+                });
+            }
+            (variable, []) if self.cvars.contains_key(variable) => {
+                log::info!(target: "console", "{}", describe(&self.cvars[variable]));
+            }
+            (variable, [value]) if self.cvars.contains_key(variable) => {
+                self.set(variable, value)?;
+            }
+            (name, _) => bail!("Unknown command '{}', type 'help' for a list", name),
+        }
+        Ok(())
+    }
+
+    pub fn complete(&self, line: &str) -> Vec<String> {
+        let tokens = line.split_whitespace().collect::<Vec<_>>();
+        let completing_new_token = line.is_empty() || line.ends_with(char::is_whitespace);
+        let (prefix, position) = match (tokens.len(), completing_new_token) {
+            (0, _) => ("", 0),
+            (count, true) => ("", count),
+            (count, false) => (tokens[count - 1], count - 1),
+        };
+
+        let candidates = match position {
+            0 => self
+                .commands
+                .keys()
+                .chain(self.cvars.keys())
+                .collect::<Vec<_>>(),
+            1 if matches!(tokens[0], "set" | "get" | "reset") => self.cvars.keys().collect(),
+            1 if tokens[0] == "help" => self.commands.keys().chain(self.cvars.keys()).collect(),
+            _ => Vec::new(),
+        };
+        let mut candidates = candidates
+            .into_iter()
+            .filter(|candidate| candidate.starts_with(prefix))
+            .cloned()
+            .collect::<Vec<_>>();
+        candidates.sort();
+        candidates.dedup();
+        candidates
+    }
+
+    fn complete_input(&mut self) {
+        let candidates = self.complete(&self.input);
+        let Some(first) = candidates.first() else {
+            return;
+        };
+        let common = candidates.iter().fold(first.as_str(), |common, candidate| {
+            let length = common
+                .char_indices()
+                .zip(candidate.chars())
+                .find(|((_, a), b)| a != b)
+                .map_or(common.len().min(candidate.len()), |((index, _), _)| index);
+            &common[..length]
+        });
+
+        let start = if self.input.ends_with(char::is_whitespace) {
+            self.input.len()
+        } else {
+            self.input
+                .rfind(char::is_whitespace)
+                .map_or(0, |index| index + 1)
+        };
+        let mut input = format!("{}{}", &self.input[..start], common);
+        if candidates.len() == 1 {
+            input.push(' ');
+        } else {
+            log::info!(target: "console", "{}", candidates.join("  "));
+        }
+        self.input = input;
+    }
+
+    fn submit(&mut self) {
+        let line = self.input.trim().to_string();
+        self.input.clear();
+        self.history_cursor = None;
+        if line.is_empty() {
+            return;
+        }
+
+        log::info!(target: "console", "> {}", line);
+        if self.history.last() != Some(&line) {
+            self.history.push(line.clone());
+            if self.history.len() > MAX_HISTORY {
+                self.history.remove(0);
+            }
+            self.save_config();
+        }
+        if let Err(error) = self.execute(&line) {
+            log::error!(target: "console", "{}", error);
+        }
+    }
+
+    fn recall(&mut self, older: bool) {
+        if self.history.is_empty() {
+            return;
+        }
+        let cursor = match (self.history_cursor, older) {
+            (None, true) => Some(self.history.len() - 1),
+            (None, false) => None,
+            (Some(cursor), true) => Some(cursor.saturating_sub(1)),
+            (Some(cursor), false) if cursor + 1 < self.history.len() => Some(cursor + 1),
+            (Some(_), false) => None,
+        };
+        self.history_cursor = cursor;
+        self.input = cursor.map_or_else(String::new, |cursor| self.history[cursor].clone());
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui) -> Vec<ConsoleEvent> {
+        let id = ui.make_persistent_id("command_console_input");
+        let focused = ui.memory(|memory| memory.has_focus(id));
+        let mut edited = false;
+        if focused {
+            let (tab, up, down) = ui.input_mut(|input| {
+                (
+                    input.consume_key(egui::Modifiers::NONE, egui::Key::Tab),
+                    input.consume_key(egui::Modifiers::NONE, egui::Key::ArrowUp),
+                    input.consume_key(egui::Modifiers::NONE, egui::Key::ArrowDown),
+                )
+            });
+            if tab {
+                self.complete_input();
+            }
+            if up || down {
+                self.recall(up);
+            }
+            edited = tab || up || down;
+        }
+
+        let mut suggestion = None;
+        ui.horizontal(|ui| {
+            ui.label(">");
+            let response = ui.add(
+                egui::TextEdit::singleline(&mut self.input)
+                    .id(id)
+                    .lock_focus(true)
+                    .hint_text("Type a command, Tab to complete, 'help' for a list")
+                    .desired_width(f32::INFINITY)
+                    .font(egui::TextStyle::Monospace),
+            );
+            if response.lost_focus() && ui.input(|input| input.key_pressed(egui::Key::Enter)) {
+                self.submit();
+                response.request_focus();
+            }
+        });
+
+        if edited && let Some(mut state) = egui::TextEdit::load_state(ui.ctx(), id) {
+            let end = egui::text::CCursor::new(self.input.chars().count());
+            state
+                .cursor
+                .set_char_range(Some(egui::text::CCursorRange::one(end)));
+            state.store(ui.ctx(), id);
+        }
+
+        if focused && !self.input.trim().is_empty() {
+            let candidates = self.complete(&self.input);
+            if !candidates.is_empty() {
+                ui.horizontal_wrapped(|ui| {
+                    for candidate in candidates.iter().take(MAX_SUGGESTIONS) {
+                        let hover = self
+                            .commands
+                            .get(candidate)
+                            .map(|command| format!("{}\n{}", command.usage, command.help))
+                            .or_else(|| {
+                                self.cvars
+                                    .get(candidate)
+                                    .map(|cvar| format!("{}\n{}", describe(cvar), cvar.help))
+                            })
+                            .unwrap_or_default();
+                        if ui.small_button(candidate).on_hover_text(hover).clicked() {
+                            suggestion = Some(candidate.clone());
+                        }
+                    }
+                    if candidates.len() > MAX_SUGGESTIONS {
+                        ui.weak(format!("+{} more", candidates.len() - MAX_SUGGESTIONS));
+                    }
+                });
+            }
+        }
+        if let Some(suggestion) = suggestion {
+            let start = self
+                .input
+                .rfind(char::is_whitespace)
+                .map_or(0, |index| index + 1);
+            self.input = format!("{}{} ", &self.input[..start], suggestion);
+            ui.memory_mut(|memory| memory.request_focus(id));
+        }
+
+        self.take_events()
+    }
+
+    fn load_config(&mut self, path: &Path) -> Result<()> {
+        if !path.exists() {
+            return Ok(());
+        }
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read console config '{}'", path.display()))?;
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if let Some(entry) = line.strip_prefix("history ") {
+                self.history.push(entry.to_string());
+                continue;
+            }
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let tokens = tokenize(line)
+                .with_context(|| format!("{}:{}: invalid line", path.display(), index + 1))?;
+            let [command, name, value] = &tokens[..] else {
+                bail!(
+                    "{}:{}: expected 'set <variable> <value>'",
+                    path.display(),
+                    index + 1
+                );
+            };
+            if command != "set" {
+                bail!(
+                    "{}:{}: unknown directive '{}'",
+                    path.display(),
+                    index + 1,
+                    command
+                );
+            }
+            if self.cvars.contains_key(name) {
+                self.set(name, value)?;
+            } else {
+                self.pending.insert(name.clone(), value.clone());
+            }
+        }
+        self.history.truncate(MAX_HISTORY);
+        self.events.clear();
+        Ok(())
+    }
+
+    fn save_config(&self) {
+        let Some(path) = &self.config_path else {
+            return;
+        };
+        let mut text = String::from("# Console variables and history, written by the console\n");
+        for cvar in self
+            .cvars
+            .values()
+            .filter(|cvar| cvar.saved != cvar.default)
+        {
+            text.push_str(&format!("set {} {}\n", cvar.name, cvar.saved));
+        }
+        for (name, value) in &self.pending {
+            text.push_str(&format!("set {} {}\n", name, quote(value)));
+        }
+        for line in &self.history {
+            text.push_str(&format!("history {}\n", line));
+        }
+        if let Err(error) = fs::write(path, text) {
+            log::warn!(
+                "Failed to save console config '{}': {}",
+                path.display(),
+                error
+            );
+        }
+    }
+}
+
+fn describe(cvar: &Cvar) -> String {
+    format!(
+        "{} = {} ({}, default {})",
+        cvar.name,
+        cvar.value,
+        cvar.value.type_name(),
+        cvar.default
+    )
+}
+
+fn quote(value: &str) -> String {
+    if value.is_empty() || value.contains(|c: char| c.is_whitespace() || c == '"' || c == '\\') {
+        format!("\"{}\"", escape(value))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Escapes quotes and backslashes for a quoted token, the inverse of `tokenize`.
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Splits a line on whitespace, keeping quoted text together. Inside quotes `\"` and `\\`
+/// stand for a quote and a backslash; other backslashes are kept, so Windows paths work.
+fn tokenize(line: &str) -> Result<Vec<String>> {
+    let mut tokens = Vec::new();
+    let mut current = None::<String>;
+    let mut quoted = false;
+    let mut characters = line.chars().peekable();
+    while let Some(character) = characters.next() {
+        match character {
+            '\\' if quoted && matches!(characters.peek(), Some('"' | '\\')) => {
+                current
+                    .get_or_insert_with(String::new)
+                    .extend(characters.next());
+            }
+            '"' => {
+                quoted = !quoted;
+                current.get_or_insert_with(String::new);
+            }
+            character if character.is_whitespace() && !quoted => {
+                tokens.extend(current.take());
+            }
+            character => current.get_or_insert_with(String::new).push(character),
+        }
+    }
+    if quoted {
+        bail!("Unterminated quote");
+    }
+    tokens.extend(current);
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn console() -> CommandConsole {
+        let mut console = CommandConsole::new();
+        console
+            .register_command("load", "load <path>", "Load a scene file")
+            .register_cvar("r.vsync", true, "")
+            .register_cvar("r.wireframe", false, "")
+            .register_cvar("scene.turntable_speed", 30.0, "")
+            .register_cvar("ui.title", "app", "");
+        console
+    }
+
+    #[test]
+    fn tokenizes_quotes_and_escapes() {
+        let tokens = |line: &str| tokenize(line).unwrap();
+        assert_eq!(tokens("  set  r.vsync 0 "), ["set", "r.vsync", "0"]);
+        assert_eq!(tokens(r#"load "my scene.json""#), ["load", "my scene.json"]);
+        assert_eq!(tokens(r#"set ui.title """#), ["set", "ui.title", ""]);
+        assert_eq!(tokens(r#""say \"hi\"" \\"#), [r#"say "hi""#, r"\\"]);
+        assert_eq!(tokens(r#""a\\b" "C:\models""#), [r"a\b", r"C:\models"]);
+        assert!(tokens("").is_empty());
+        assert!(tokenize(r#"load "unterminated"#).is_err());
+        assert!(tokenize(r#"load "escaped quote\""#).is_err());
+    }
+
+    #[test]
+    fn formatted_strings_tokenize_back() {
+        for text in [
+            "plain",
+            "two words",
+            "",
+            r#"quote " inside"#,
+            r"back\slash",
+            r#"\""#,
+        ] {
+            let formatted = CvarValue::from(text).to_string();
+            assert_eq!(tokenize(&formatted).unwrap(), [text], "{}", formatted);
+            assert_eq!(tokenize(&quote(text)).unwrap(), [text]);
+        }
+    }
+
+    #[test]
+    fn parses_and_formats_cvar_values() {
+        let bool = CvarValue::Bool(false);
+        assert_eq!(bool.parse_as("on").unwrap(), CvarValue::Bool(true));
+        assert_eq!(bool.parse_as("0").unwrap(), CvarValue::Bool(false));
+        assert!(bool.parse_as("maybe").is_err());
+        assert_eq!(
+            CvarValue::Int(0).parse_as("-3").unwrap(),
+            CvarValue::Int(-3)
+        );
+        assert!(CvarValue::Int(0).parse_as("1.5").is_err());
+        assert_eq!(
+            CvarValue::Float(0.0).parse_as("2.5").unwrap(),
+            CvarValue::Float(2.5)
+        );
+        assert_eq!(CvarValue::Bool(true).to_string(), "1");
+        assert_eq!(CvarValue::Float(0.5).to_string(), "0.5");
+        assert_eq!(CvarValue::from("a b").to_string(), "\"a b\"");
+
+        let mut console = console();
+        console.execute("r.vsync off").unwrap();
+        console.execute("set scene.turntable_speed 12.5").unwrap();
+        assert_eq!(console.get_bool("r.vsync"), Some(false));
+        assert_eq!(console.get_float("scene.turntable_speed"), Some(12.5));
+        assert!(console.execute("set r.vsync sometimes").is_err());
+        assert!(console.set_value("r.vsync", CvarValue::Int(1)).is_err());
+        assert!(console.execute("set r.missing 1").is_err());
+        console.execute("reset r.vsync").unwrap();
+        assert_eq!(console.get_bool("r.vsync"), Some(true));
+
+        console.take_events();
+        console.execute(r#"load "a scene.json""#).unwrap();
+        assert_eq!(
+            console.take_events(),
+            [ConsoleEvent::Command {
+                name: "load".to_string(),
+                args: vec!["a scene.json".to_string()],
+            }]
+        );
+    }
+
+    #[test]
+    fn completes_commands_and_variables() {
+        let console = console();
+        assert_eq!(console.complete("r."), ["r.vsync", "r.wireframe"]);
+        assert_eq!(console.complete("lo"), ["load"]);
+        assert_eq!(console.complete("set sc"), ["scene.turntable_speed"]);
+        assert_eq!(console.complete("help l"), ["load"]);
+        assert!(console.complete("load sc").is_empty());
+        assert!(console.complete("r.vsync ").is_empty());
+        assert!(console.complete("").contains(&"help".to_string()));
+
+        let mut console = console;
+        console.input = "set r.w".to_string();
+        console.complete_input();
+        assert_eq!(console.input, "set r.wireframe ");
+        console.input = "r.".to_string();
+        console.complete_input();
+        assert_eq!(console.input, "r.");
+    }
+
+    #[test]
+    fn history_skips_repeats_and_recalls_in_order() {
+        let mut console = console();
+        for line in ["r.vsync 0", "r.vsync 0", "  get r.vsync  ", "", "unknown"] {
+            console.input = line.to_string();
+            console.submit();
+        }
+        assert_eq!(console.history(), ["r.vsync 0", "get r.vsync", "unknown"]);
+
+        console.recall(true);
+        assert_eq!(console.input, "unknown");
+        console.recall(true);
+        console.recall(true);
+        console.recall(true);
+        assert_eq!(console.input, "r.vsync 0");
+        console.recall(false);
+        assert_eq!(console.input, "get r.vsync");
+        console.recall(false);
+        console.recall(false);
+        assert_eq!(console.input, "");
+    }
+
+    #[test]
+    fn config_keeps_synced_values_out() {
+        let path = std::env::temp_dir().join(format!("console_test_{}.cfg", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let mut console = console().with_config(&path);
+        console.execute("set r.vsync 0").unwrap();
+        console.execute(r#"set ui.title "say \"hi\"""#).unwrap();
+        console
+            .sync_value("scene.turntable_speed", CvarValue::Float(90.0))
+            .unwrap();
+        console
+            .sync_value("r.wireframe", CvarValue::Bool(true))
+            .unwrap();
+        assert_eq!(console.get_bool("r.wireframe"), Some(true));
+        // Saving again, as any later change does, must still leave the synced values out.
+        console.input = "get r.vsync".to_string();
+        console.submit();
+
+        let text = fs::read_to_string(&path).unwrap();
+        assert!(!text.contains("turntable_speed"), "{}", text);
+        assert!(!text.contains("wireframe"), "{}", text);
+
+        let mut reloaded = CommandConsole::new().with_config(&path);
+        reloaded
+            .register_cvar("r.vsync", true, "")
+            .register_cvar("ui.title", "app", "");
+        fs::remove_file(&path).unwrap();
+        assert_eq!(reloaded.get_bool("r.vsync"), Some(false));
+        assert_eq!(reloaded.get_string("ui.title"), Some(r#"say "hi""#));
+        assert_eq!(reloaded.history(), ["get r.vsync"]);
+    }
+}
@@ -0,0 +1,248 @@
+use crate::support::camera::{Camera, DepthRange, Projection};
+use crate::support::resource::{
+    Framebuffer, Renderbuffer, Texture, VertexArray, gl_version, has_extension,
+};
+use crate::support::shader::{ShaderKind, ShaderProgram};
+use anyhow::Result;
+use gl::types::*;
+
+const COMPOSITE_VERTEX_SHADER: &str = "shaders/composite/composite.vs.glsl";
+const COMPOSITE_FRAGMENT_SHADER: &str = "shaders/composite/composite.fs.glsl";
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DepthMode {
+    /// Near maps to 0 and far to 1 with a `LESS` depth test.
+    #[default]
+    Standard,
+    /// Near maps to 1 and an infinitely distant far plane to 0 with a `GREATER` depth test.
+    /// Combined with a zero-to-one clip range this keeps depth precision roughly constant
+    /// over distance instead of concentrating it next to the near plane.
+    ReversedZ,
+}
+
+impl DepthMode {
+    /// Falls back to `Standard` when reversed-Z was requested but the context cannot change
+    /// the clip depth range, since a -1..1 range throws away the precision it would gain.
+    pub fn resolve(self, clip_control: bool) -> DepthMode {
+        match self {
+            DepthMode::ReversedZ if !clip_control => DepthMode::Standard,
+            mode => mode,
+        }
+    }
+
+    /// Adapts a camera's projection to the depth convention this mode renders with.
+    pub fn configure(self, camera: &mut Camera, clip_control: bool) {
+        let depth_range = if clip_control {
+            DepthRange::ZeroToOne
+        } else {
+            DepthRange::NegativeOneToOne
+        };
+        camera.set_depth_range(depth_range);
+        camera.set_reversed_z(self == DepthMode::ReversedZ);
+        if self == DepthMode::ReversedZ
+            && let Projection::Perspective {
+                fov_y,
+                near,
+                far: Some(_),
+            } = *camera.projection()
+        {
+            camera.set_projection(Projection::Perspective {
+                fov_y,
+                near,
+                far: None,
+            });
+        }
+    }
+
+    /// Sets the clip range, depth test and clear value for this mode.
+    pub fn apply(self, clip_control: bool) {
+        unsafe {
+            if clip_control {
+                gl::ClipControl(gl::LOWER_LEFT, gl::ZERO_TO_ONE);
+            }
+            match self {
+                DepthMode::Standard => {
+                    gl::DepthFunc(gl::LESS);
+                    gl::ClearDepth(1.0);
+                }
+                DepthMode::ReversedZ => {
+                    gl::DepthFunc(gl::GREATER);
+                    gl::ClearDepth(0.0);
+                }
+            }
+        }
+    }
+}
+
+/// Whether `glClipControl` is usable: core since OpenGL 4.5, otherwise through
+/// `ARB_clip_control`.
+pub fn clip_control_supported() -> bool {
+    if !gl::ClipControl::is_loaded() {
+        return false;
+    }
+    gl_version() >= (4, 5) || has_extension("GL_ARB_clip_control")
+}
+
+/// Bits in the depth buffer of the default framebuffer, which must be bound for drawing.
+pub fn depth_bits() -> GLint {
+    let mut bits = 0;
+    unsafe {
+        gl::GetFramebufferAttachmentParameteriv(
+            gl::DRAW_FRAMEBUFFER,
+            gl::DEPTH,
+            gl::FRAMEBUFFER_ATTACHMENT_DEPTH_SIZE,
+            &mut bits,
+        );
+    }
+    bits
+}
+
+/// Offscreen colour and `DEPTH_COMPONENT32F` buffers for reversed-Z. Window-system framebuffers
+/// only offer fixed-point depth, which gives back most of the precision reversed-Z gains, so
+/// the scene is drawn here and then composited into the window.
+pub struct FloatDepthTarget {
+    program: ShaderProgram,
+    vertex_array: VertexArray,
+    samples: u32,
+    buffers: Option<TargetBuffers>,
+}
+
+struct TargetBuffers {
+    width: u32,
+    height: u32,
+    framebuffer: Framebuffer,
+    _color: Renderbuffer,
+    _depth: Renderbuffer,
+    resolve: Framebuffer,
+    resolved: Texture,
+}
+
+impl FloatDepthTarget {
+    /// Must be called with the default framebuffer bound, whose sample count it matches.
+    pub fn new() -> Result<Self> {
+        let mut program = ShaderProgram::new();
+        program
+            .attach_source(
+                ShaderKind::Vertex,
+                COMPOSITE_VERTEX_SHADER,
+                include_str!("../../shaders/composite/composite.vs.glsl"),
+            )
+            .attach_source(
+                ShaderKind::Fragment,
+                COMPOSITE_FRAGMENT_SHADER,
+                include_str!("../../shaders/composite/composite.fs.glsl"),
+            )
+            .link()?;
+        let mut samples = 0;
+        unsafe {
+            gl::GetIntegerv(gl::SAMPLES, &mut samples);
+        }
+        Ok(Self {
+            program,
+            vertex_array: VertexArray::new(),
+            samples: samples.max(0) as u32,
+            buffers: None,
+        })
+    }
+
+    /// Reallocates the buffers when the window size changed.
+    pub fn resize(&mut self, width: u32, height: u32) -> Result<()> {
+        if self
+            .buffers
+            .as_ref()
+            .is_some_and(|buffers| (buffers.width, buffers.height) == (width, height))
+        {
+            return Ok(());
+        }
+        self.buffers = None;
+
+        let color = Renderbuffer::new(gl::RGBA8, width, height, self.samples);
+        let depth = Renderbuffer::new(gl::DEPTH_COMPONENT32F, width, height, self.samples);
+        let framebuffer = Framebuffer::new();
+        framebuffer.attach_renderbuffer(gl::COLOR_ATTACHMENT0, &color);
+        framebuffer.attach_renderbuffer(gl::DEPTH_ATTACHMENT, &depth);
+        framebuffer.check_status()?;
+
+        let resolved = Texture::new(gl::TEXTURE_2D);
+        resolved.bind();
+        unsafe {
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                gl::RGBA8 as GLint,
+                width as GLsizei,
+                height as GLsizei,
+                0,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                std::ptr::null(),
+            );
+        }
+        resolved.parameter_i(gl::TEXTURE_MIN_FILTER, gl::NEAREST as GLint);
+        resolved.parameter_i(gl::TEXTURE_MAG_FILTER, gl::NEAREST as GLint);
+        let resolve = Framebuffer::new();
+        resolve.attach_texture(gl::COLOR_ATTACHMENT0, &resolved, 0);
+        resolve.check_status()?;
+        resolve.unbind();
+
+        self.buffers = Some(TargetBuffers {
+            width,
+            height,
+            framebuffer,
+            _color: color,
+            _depth: depth,
+            resolve,
+            resolved,
+        });
+        Ok(())
+    }
+
+    /// Redirects drawing into the target. Returns false before the first `resize`, in which
+    /// case drawing stays on the current framebuffer.
+    pub fn bind(&self) -> bool {
+        let Some(buffers) = &self.buffers else {
+            return false;
+        };
+        buffers.framebuffer.bind();
+        unsafe {
+            gl::Viewport(0, 0, buffers.width as GLsizei, buffers.height as GLsizei);
+        }
+        true
+    }
+
+    /// Resolves the samples and draws the colour into the default framebuffer, leaving it
+    /// bound. The window's own depth buffer is left untouched.
+    pub fn composite(&self) {
+        let Some(buffers) = &self.buffers else {
+            return;
+        };
+        let (width, height) = (buffers.width as GLint, buffers.height as GLint);
+        unsafe {
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, buffers.framebuffer.id());
+            gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, buffers.resolve.id());
+            gl::BlitFramebuffer(
+                0,
+                0,
+                width,
+                height,
+                0,
+                0,
+                width,
+                height,
+                gl::COLOR_BUFFER_BIT,
+                gl::NEAREST,
+            );
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+            gl::Disable(gl::DEPTH_TEST);
+        }
+        self.program.activate();
+        buffers.resolved.bind_unit(0);
+        self.vertex_array.bind();
+        unsafe {
+            gl::Uniform1i(self.program.uniform_location("scene_color"), 0);
+            gl::DrawArrays(gl::TRIANGLES, 0, 3);
+            gl::Enable(gl::DEPTH_TEST);
+        }
+        self.vertex_array.unbind();
+    }
+}
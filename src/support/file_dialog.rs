@@ -0,0 +1,207 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FileDialogMode {
+    Open,
+    Save,
+}
+
+#[derive(Debug)]
+struct Entry {
+    path: PathBuf,
+    name: String,
+    is_directory: bool,
+}
+
+#[derive(Debug)]
+pub struct FileDialog {
+    title: String,
+    mode: FileDialogMode,
+    extensions: Vec<String>,
+    directory: PathBuf,
+    file_name: String,
+    entries: Vec<Entry>,
+    error: Option<String>,
+    open: bool,
+}
+
+impl FileDialog {
+    pub fn open(title: &str, extensions: &[&str]) -> Self {
+        Self::new(title, FileDialogMode::Open, extensions)
+    }
+
+    pub fn save(title: &str, extensions: &[&str]) -> Self {
+        Self::new(title, FileDialogMode::Save, extensions)
+    }
+
+    fn new(title: &str, mode: FileDialogMode, extensions: &[&str]) -> Self {
+        let mut dialog = Self {
+            title: title.to_string(),
+            mode,
+            extensions: extensions
+                .iter()
+                .map(|extension| extension.to_ascii_lowercase())
+                .collect(),
+            directory: std::env::current_dir().unwrap_or_default(),
+            file_name: String::new(),
+            entries: Vec::new(),
+            error: None,
+            open: true,
+        };
+        dialog.refresh();
+        dialog
+    }
+
+    pub fn directory(mut self, directory: impl AsRef<Path>) -> Self {
+        if directory.as_ref().is_dir() {
+            self.directory = directory.as_ref().to_path_buf();
+            self.refresh();
+        }
+        self
+    }
+
+    pub fn file_name(mut self, file_name: &str) -> Self {
+        self.file_name = file_name.to_string();
+        self
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context) -> Option<PathBuf> {
+        let mut selected = None;
+        let mut navigate = None;
+        let mut open = self.open;
+
+        egui::Window::new(&self.title)
+            .open(&mut open)
+            .collapsible(false)
+            .default_size([480.0, 360.0])
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if ui.button("Up").clicked() {
+                        navigate = self.directory.parent().map(Path::to_path_buf);
+                    }
+                    ui.label(self.directory.display().to_string());
+                });
+                ui.separator();
+
+                egui::ScrollArea::vertical()
+                    .max_height(240.0)
+                    .auto_shrink([false, false])
+                    .show(ui, |ui| {
+                        for entry in &self.entries {
+                            let label = if entry.is_directory {
+                                format!("[{}]", entry.name)
+                            } else {
+                                entry.name.clone()
+                            };
+                            let response = ui.selectable_label(self.file_name == entry.name, label);
+                            if response.clicked() && !entry.is_directory {
+                                self.file_name = entry.name.clone();
+                            }
+                            if response.double_clicked()
+                                || (response.clicked() && entry.is_directory)
+                            {
+                                if entry.is_directory {
+                                    navigate = Some(entry.path.clone());
+                                } else {
+                                    selected = Some(entry.path.clone());
+                                }
+                            }
+                        }
+                    });
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.label("File name:");
+                    ui.text_edit_singleline(&mut self.file_name);
+                });
+                if let Some(error) = &self.error {
+                    ui.colored_label(egui::Color32::LIGHT_RED, error);
+                }
+                ui.horizontal(|ui| {
+                    let action = match self.mode {
+                        FileDialogMode::Open => "Open",
+                        FileDialogMode::Save => "Save",
+                    };
+                    if ui
+                        .add_enabled(!self.file_name.is_empty(), egui::Button::new(action))
+                        .clicked()
+                    {
+                        selected = Some(self.directory.join(&self.file_name));
+                    }
+                    if ui.button("Cancel").clicked() {
+                        self.open = false;
+                    }
+                });
+            });
+
+        if let Some(directory) = navigate {
+            self.directory = directory;
+            self.refresh();
+        }
+
+        let selected = selected.and_then(|path| self.accept(path));
+        self.open &= open && selected.is_none();
+        selected
+    }
+
+    fn accept(&mut self, path: PathBuf) -> Option<PathBuf> {
+        match self.mode {
+            FileDialogMode::Open if !path.is_file() => {
+                self.error = Some(format!("'{}' does not exist", path.display()));
+                None
+            }
+            FileDialogMode::Save if path.extension().is_none() => {
+                Some(match self.extensions.first() {
+                    Some(extension) => path.with_extension(extension),
+                    None => path,
+                })
+            }
+            _ => Some(path),
+        }
+    }
+
+    fn refresh(&mut self) {
+        self.entries.clear();
+        self.error = None;
+        let read_dir = match fs::read_dir(&self.directory) {
+            Ok(read_dir) => read_dir,
+            Err(error) => {
+                self.error = Some(format!(
+                    "Failed to read '{}': {}",
+                    self.directory.display(),
+                    error
+                ));
+                return;
+            }
+        };
+
+        for entry in read_dir.flatten() {
+            let path = entry.path();
+            let is_directory = path.is_dir();
+            let matches_filter = self.extensions.is_empty()
+                || path
+                    .extension()
+                    .and_then(|extension| extension.to_str())
+                    .is_some_and(|extension| {
+                        self.extensions.contains(&extension.to_ascii_lowercase())
+                    });
+            if is_directory || matches_filter {
+                self.entries.push(Entry {
+                    name: entry.file_name().to_string_lossy().into_owned(),
+                    path,
+                    is_directory,
+                });
+            }
+        }
+        self.entries.sort_by(|a, b| {
+            b.is_directory
+                .cmp(&a.is_directory)
+                .then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase()))
+        });
+    }
+}
@@ -0,0 +1,36 @@
+use crate::support::resource::register_context;
+use glutin::api::egl::{context::PossiblyCurrentContext, device::Device, display::Display};
+use glutin::config::{ConfigSurfaceTypes, ConfigTemplateBuilder};
+use glutin::context::{ContextApi, ContextAttributesBuilder, GlProfile, Version};
+use glutin::prelude::*;
+
+/// A surfaceless OpenGL 4.5 context on the first EGL device, which is Mesa's llvmpipe
+/// software renderer on machines without a GPU.
+pub fn headless_context() -> (Display, PossiblyCurrentContext) {
+    let device = Device::query_devices()
+        .expect("EGL devices")
+        .next()
+        .expect("an EGL device");
+    let display = unsafe { Display::with_device(&device, None) }.expect("EGL display");
+    let template = ConfigTemplateBuilder::new()
+        .with_surface_type(ConfigSurfaceTypes::empty())
+        .build();
+    let config = unsafe { display.find_configs(template) }
+        .expect("EGL configs")
+        .next()
+        .expect("a surfaceless EGL config");
+    let attributes = ContextAttributesBuilder::new()
+        .with_context_api(ContextApi::OpenGl(Some(Version::new(4, 5))))
+        .with_profile(GlProfile::Core)
+        .build(None);
+    let context = unsafe { display.create_context(&config, &attributes) }
+        .expect("OpenGL 4.5 context")
+        .make_current_surfaceless()
+        .expect("current context");
+    gl::load_with(|symbol| {
+        let symbol = std::ffi::CString::new(symbol).unwrap();
+        display.get_proc_address(&symbol).cast()
+    });
+    register_context();
+    (display, context)
+}
@@ -0,0 +1,355 @@
+use crate::support::compressed_image::CompressedImage;
+use anyhow::{Context, Result, bail};
+use gl::types::*;
+use std::path::Path;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImageFormat {
+    R8,
+    Rg8,
+    Rgb8,
+    Rgba8,
+    R16,
+    Rg16,
+    Rgb16,
+    Rgba16,
+    Rgb32F,
+    Rgba32F,
+}
+
+impl ImageFormat {
+    pub fn channels(&self) -> usize {
+        match self {
+            ImageFormat::R8 | ImageFormat::R16 => 1,
+            ImageFormat::Rg8 | ImageFormat::Rg16 => 2,
+            ImageFormat::Rgb8 | ImageFormat::Rgb16 | ImageFormat::Rgb32F => 3,
+            ImageFormat::Rgba8 | ImageFormat::Rgba16 | ImageFormat::Rgba32F => 4,
+        }
+    }
+
+    pub fn bytes_per_channel(&self) -> usize {
+        match self {
+            ImageFormat::R8 | ImageFormat::Rg8 | ImageFormat::Rgb8 | ImageFormat::Rgba8 => 1,
+            ImageFormat::R16 | ImageFormat::Rg16 | ImageFormat::Rgb16 | ImageFormat::Rgba16 => 2,
+            ImageFormat::Rgb32F | ImageFormat::Rgba32F => 4,
+        }
+    }
+
+    pub fn bytes_per_pixel(&self) -> usize {
+        self.channels() * self.bytes_per_channel()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ImageData {
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+    pub format: ImageFormat,
+    pub pixels: Vec<u8>,
+}
+
+impl ImageData {
+    pub fn new(
+        name: &str,
+        width: u32,
+        height: u32,
+        format: ImageFormat,
+        pixels: Vec<u8>,
+    ) -> Result<Self> {
+        let expected = width as usize * height as usize * format.bytes_per_pixel();
+        if pixels.len() != expected {
+            bail!(
+                "Image '{}' has {} bytes but {}x{} {:?} needs {}",
+                name,
+                pixels.len(),
+                width,
+                height,
+                format,
+                expected
+            );
+        }
+        Ok(Self {
+            name: name.to_string(),
+            width,
+            height,
+            format,
+            pixels,
+        })
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let image = image::open(path)
+            .with_context(|| format!("Failed to load image '{}'", path.display()))?;
+        Self::from_dynamic(&path.display().to_string(), image)
+    }
+
+    pub fn from_memory(name: &str, bytes: &[u8]) -> Result<Self> {
+        let image = image::load_from_memory(bytes)
+            .with_context(|| format!("Failed to decode image '{}'", name))?;
+        Self::from_dynamic(name, image)
+    }
+
+    pub fn read_framebuffer(x: i32, y: i32, width: u32, height: u32) -> Result<Self> {
+        let row = width as usize * 4;
+        let mut pixels = vec![0_u8; row * height as usize];
+        unsafe {
+            gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+            gl::ReadPixels(
+                x,
+                y,
+                width as GLsizei,
+                height as GLsizei,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                pixels.as_mut_ptr().cast(),
+            );
+        }
+        let pixels = pixels
+            .chunks_exact(row.max(1))
+            .rev()
+            .flatten()
+            .copied()
+            .collect();
+        Self::new("framebuffer", width, height, ImageFormat::Rgba8, pixels)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        image::save_buffer(
+            path,
+            &self.to_rgba8(),
+            self.width,
+            self.height,
+            image::ExtendedColorType::Rgba8,
+        )
+        .with_context(|| format!("Failed to save image '{}'", path.display()))
+    }
+
+    fn from_dynamic(name: &str, image: image::DynamicImage) -> Result<Self> {
+        use image::DynamicImage;
+
+        let (width, height) = (image.width(), image.height());
+        let words = |data: &[u16]| data.iter().flat_map(|value| value.to_le_bytes()).collect();
+        let floats = |data: &[f32]| data.iter().flat_map(|value| value.to_le_bytes()).collect();
+        let (format, pixels) = match image {
+            DynamicImage::ImageLuma8(image) => (ImageFormat::R8, image.into_raw()),
+            DynamicImage::ImageLumaA8(image) => (ImageFormat::Rg8, image.into_raw()),
+            DynamicImage::ImageRgb8(image) => (ImageFormat::Rgb8, image.into_raw()),
+            DynamicImage::ImageRgba8(image) => (ImageFormat::Rgba8, image.into_raw()),
+            DynamicImage::ImageLuma16(image) => (ImageFormat::R16, words(&image)),
+            DynamicImage::ImageLumaA16(image) => (ImageFormat::Rg16, words(&image)),
+            DynamicImage::ImageRgb16(image) => (ImageFormat::Rgb16, words(&image)),
+            DynamicImage::ImageRgba16(image) => (ImageFormat::Rgba16, words(&image)),
+            DynamicImage::ImageRgb32F(image) => (ImageFormat::Rgb32F, floats(&image)),
+            DynamicImage::ImageRgba32F(image) => (ImageFormat::Rgba32F, floats(&image)),
+            image => (ImageFormat::Rgba8, image.into_rgba8().into_raw()),
+        };
+        Self::new(name, width, height, format, pixels)
+    }
+
+    pub fn to_rgba8(&self) -> Vec<u8> {
+        let channels = self.format.channels();
+        let bytes_per_channel = self.format.bytes_per_channel();
+        let channel = |pixel: &[u8], index: usize| -> u8 {
+            let bytes = &pixel[index * bytes_per_channel..(index + 1) * bytes_per_channel];
+            match self.format {
+                ImageFormat::Rgb32F | ImageFormat::Rgba32F => {
+                    let value = f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+                    (value.clamp(0.0, 1.0) * 255.0).round() as u8
+                }
+                _ if bytes_per_channel == 2 => bytes[1],
+                _ => bytes[0],
+            }
+        };
+
+        let mut output = Vec::with_capacity(self.width as usize * self.height as usize * 4);
+        for pixel in self.pixels.chunks_exact(self.format.bytes_per_pixel()) {
+            let rgba = match channels {
+                1 => {
+                    let value = channel(pixel, 0);
+                    [value, value, value, 255]
+                }
+                2 => [channel(pixel, 0), channel(pixel, 1), 0, 255],
+                3 => [channel(pixel, 0), channel(pixel, 1), channel(pixel, 2), 255],
+                _ => [
+                    channel(pixel, 0),
+                    channel(pixel, 1),
+                    channel(pixel, 2),
+                    channel(pixel, 3),
+                ],
+            };
+            output.extend_from_slice(&rgba);
+        }
+        output
+    }
+}
+
+/// An image as it was read from disk: decoded pixels, or block compressed data from a KTX2 or
+/// DDS container that stays compressed on the GPU.
+#[derive(Clone, Debug)]
+pub enum SourceImage {
+    Decoded(ImageData),
+    Compressed(CompressedImage),
+}
+
+impl SourceImage {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let extension = path
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase());
+        match extension.as_deref() {
+            Some("ktx2" | "dds") => Ok(SourceImage::Compressed(CompressedImage::load(path)?)),
+            _ => Ok(SourceImage::Decoded(ImageData::load(path)?)),
+        }
+    }
+
+    pub fn from_memory(name: &str, bytes: &[u8]) -> Result<Self> {
+        if CompressedImage::is_container(bytes) {
+            Ok(SourceImage::Compressed(CompressedImage::from_memory(
+                name, bytes,
+            )?))
+        } else {
+            Ok(SourceImage::Decoded(ImageData::from_memory(name, bytes)?))
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            SourceImage::Decoded(image) => &image.name,
+            SourceImage::Compressed(image) => &image.name,
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        match self {
+            SourceImage::Decoded(image) => image.width,
+            SourceImage::Compressed(image) => image.width,
+        }
+    }
+
+    pub fn height(&self) -> u32 {
+        match self {
+            SourceImage::Decoded(image) => image.height,
+            SourceImage::Compressed(image) => image.height,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Filter {
+    Nearest,
+    #[default]
+    Linear,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Wrap {
+    #[default]
+    Repeat,
+    MirroredRepeat,
+    ClampToEdge,
+}
+
+impl Wrap {
+    pub fn gl_value(&self) -> GLenum {
+        match self {
+            Wrap::Repeat => gl::REPEAT,
+            Wrap::MirroredRepeat => gl::MIRRORED_REPEAT,
+            Wrap::ClampToEdge => gl::CLAMP_TO_EDGE,
+        }
+    }
+}
+
+/// The comparison a depth texture sampler makes against the reference value, for shadow
+/// lookups.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CompareFunction {
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+}
+
+impl CompareFunction {
+    pub fn gl_value(&self) -> GLenum {
+        match self {
+            CompareFunction::Less => gl::LESS,
+            CompareFunction::LessEqual => gl::LEQUAL,
+            CompareFunction::Greater => gl::GREATER,
+            CompareFunction::GreaterEqual => gl::GEQUAL,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SamplerSettings {
+    pub mag_filter: Filter,
+    pub min_filter: Filter,
+    pub mipmap_filter: Option<Filter>,
+    pub wrap_s: Wrap,
+    pub wrap_t: Wrap,
+    /// Wrapping along the third coordinate of 3D textures and cubemap directions.
+    pub wrap_r: Wrap,
+    /// Maximum anisotropic filtering ratio; 1 disables it.
+    pub anisotropy: f32,
+    /// Turns a depth texture into a shadow sampler that returns the comparison result.
+    pub compare: Option<CompareFunction>,
+}
+
+impl Default for SamplerSettings {
+    fn default() -> Self {
+        Self {
+            mag_filter: Filter::Linear,
+            min_filter: Filter::Linear,
+            mipmap_filter: Some(Filter::Linear),
+            wrap_s: Wrap::Repeat,
+            wrap_t: Wrap::Repeat,
+            wrap_r: Wrap::Repeat,
+            anisotropy: 1.0,
+            compare: None,
+        }
+    }
+}
+
+impl SamplerSettings {
+    /// Clamps every coordinate, as skyboxes, lookup tables and volumes usually want.
+    pub fn clamped() -> Self {
+        Self {
+            wrap_s: Wrap::ClampToEdge,
+            wrap_t: Wrap::ClampToEdge,
+            wrap_r: Wrap::ClampToEdge,
+            ..Self::default()
+        }
+    }
+
+    /// A hardware filtered depth comparison for `sampler2DShadow` style lookups, such as the
+    /// cascades of a shadow map array.
+    pub fn shadow(compare: CompareFunction) -> Self {
+        Self {
+            mipmap_filter: None,
+            compare: Some(compare),
+            ..Self::clamped()
+        }
+    }
+
+    pub fn gl_mag_filter(&self) -> GLenum {
+        match self.mag_filter {
+            Filter::Nearest => gl::NEAREST,
+            Filter::Linear => gl::LINEAR,
+        }
+    }
+
+    pub fn gl_min_filter(&self) -> GLenum {
+        match (self.min_filter, self.mipmap_filter) {
+            (Filter::Nearest, None) => gl::NEAREST,
+            (Filter::Linear, None) => gl::LINEAR,
+            (Filter::Nearest, Some(Filter::Nearest)) => gl::NEAREST_MIPMAP_NEAREST,
+            (Filter::Nearest, Some(Filter::Linear)) => gl::NEAREST_MIPMAP_LINEAR,
+            (Filter::Linear, Some(Filter::Nearest)) => gl::LINEAR_MIPMAP_NEAREST,
+            (Filter::Linear, Some(Filter::Linear)) => gl::LINEAR_MIPMAP_LINEAR,
+        }
+    }
+}
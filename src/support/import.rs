@@ -0,0 +1,283 @@
+pub mod gltf;
+pub mod obj;
+pub mod ply;
+pub mod stl;
+
+use crate::support::image_data::{SamplerSettings, SourceImage};
+use crate::support::light::Light;
+use crate::support::material::Material;
+use crate::support::mesh::Mesh;
+use crate::support::transform::Transform;
+use anyhow::{Result, bail};
+use nalgebra_glm::{Mat4, Quat, Vec3};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+pub const SUPPORTED_EXTENSIONS: &[&str] = &["gltf", "glb", "obj", "stl", "ply"];
+
+#[derive(Clone, Debug)]
+pub struct TextureData {
+    pub name: String,
+    pub image: usize,
+    pub sampler: SamplerSettings,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ProjectionData {
+    Perspective {
+        yfov: f32,
+        aspect_ratio: Option<f32>,
+        znear: f32,
+        zfar: Option<f32>,
+    },
+    Orthographic {
+        xmag: f32,
+        ymag: f32,
+        znear: f32,
+        zfar: f32,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CameraData {
+    pub name: String,
+    pub projection: ProjectionData,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ImportedNode {
+    pub name: String,
+    pub transform: Transform,
+    pub children: Vec<usize>,
+    pub meshes: Vec<usize>,
+    pub camera: Option<usize>,
+    pub light: Option<usize>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Interpolation {
+    Linear,
+    Step,
+    CubicSpline,
+}
+
+#[derive(Clone, Debug)]
+pub enum AnimationValues {
+    Translations(Vec<Vec3>),
+    Rotations(Vec<Quat>),
+    Scales(Vec<Vec3>),
+}
+
+#[derive(Clone, Debug)]
+pub struct AnimationChannel {
+    pub node: usize,
+    pub interpolation: Interpolation,
+    pub times: Vec<f32>,
+    pub values: AnimationValues,
+}
+
+impl AnimationChannel {
+    pub fn apply(&self, time: f32, transform: &mut Transform) {
+        let lerp = |a, b, t| a + (b - a) * t;
+        match &self.values {
+            AnimationValues::Translations(values) => {
+                if let Some(value) = self.sample(values, time, lerp) {
+                    transform.translation = value;
+                }
+            }
+            AnimationValues::Rotations(values) => {
+                if let Some(value) =
+                    self.sample(values, time, |a, b, t| nalgebra_glm::quat_slerp(&a, &b, t))
+                {
+                    transform.rotation = nalgebra_glm::quat_normalize(&value);
+                }
+            }
+            AnimationValues::Scales(values) => {
+                if let Some(value) = self.sample(values, time, lerp) {
+                    transform.scale = value;
+                }
+            }
+        }
+    }
+
+    fn sample<T>(&self, values: &[T], time: f32, lerp: impl Fn(T, T, f32) -> T) -> Option<T>
+    where
+        T: Copy + std::ops::Add<Output = T> + std::ops::Mul<f32, Output = T>,
+    {
+        let next = self.times.partition_point(|key| *key <= time);
+        let (previous, next) = match next {
+            0 => (0, 0),
+            next if next == self.times.len() => (next - 1, next - 1),
+            next => (next - 1, next),
+        };
+        let (start, end) = (*self.times.get(previous)?, *self.times.get(next)?);
+        let duration = end - start;
+        let t = if duration > 0.0 {
+            (time - start) / duration
+        } else {
+            0.0
+        };
+        match self.interpolation {
+            Interpolation::Step => values.get(previous).copied(),
+            Interpolation::Linear => Some(lerp(*values.get(previous)?, *values.get(next)?, t)),
+            // Each key stores an in-tangent, the value and an out-tangent.
+            Interpolation::CubicSpline => {
+                let start = *values.get(previous * 3 + 1)?;
+                if previous == next {
+                    return Some(start);
+                }
+                let out_tangent = *values.get(previous * 3 + 2)?;
+                let in_tangent = *values.get(next * 3)?;
+                let end = *values.get(next * 3 + 1)?;
+                let (t2, t3) = (t * t, t * t * t);
+                Some(
+                    start * (2.0 * t3 - 3.0 * t2 + 1.0)
+                        + out_tangent * (duration * (t3 - 2.0 * t2 + t))
+                        + end * (-2.0 * t3 + 3.0 * t2)
+                        + in_tangent * (duration * (t3 - t2)),
+                )
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Animation {
+    pub name: String,
+    pub channels: Vec<AnimationChannel>,
+}
+
+impl Animation {
+    pub fn duration(&self) -> f32 {
+        self.channels
+            .iter()
+            .filter_map(|channel| channel.times.last().copied())
+            .fold(0.0, f32::max)
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ImportedScene {
+    pub name: String,
+    pub source: PathBuf,
+    pub meshes: Vec<Mesh>,
+    pub materials: Vec<Material>,
+    pub images: Vec<SourceImage>,
+    pub textures: Vec<TextureData>,
+    pub cameras: Vec<CameraData>,
+    pub lights: Vec<Light>,
+    pub nodes: Vec<ImportedNode>,
+    pub roots: Vec<usize>,
+    pub animations: Vec<Animation>,
+}
+
+impl ImportedScene {
+    pub fn world_transforms(&self) -> Vec<Mat4> {
+        let mut world = vec![Mat4::identity(); self.nodes.len()];
+        let mut stack = self
+            .roots
+            .iter()
+            .map(|root| (*root, Mat4::identity()))
+            .collect::<Vec<_>>();
+        while let Some((index, parent)) = stack.pop() {
+            let node = &self.nodes[index];
+            world[index] = parent * node.transform.matrix();
+            stack.extend(node.children.iter().map(|child| (*child, world[index])));
+        }
+        world
+    }
+}
+
+pub fn is_supported(path: &Path) -> bool {
+    extension(path).is_some_and(|extension| SUPPORTED_EXTENSIONS.contains(&extension.as_str()))
+}
+
+pub fn import_file(path: impl AsRef<Path>) -> Result<ImportedScene> {
+    let path = path.as_ref();
+    match extension(path).as_deref() {
+        Some("gltf" | "glb") => gltf::import(path),
+        Some("obj") => obj::import(path),
+        Some("stl") => stl::import(path),
+        Some("ply") => ply::import(path),
+        _ => bail!("Unsupported import format: '{}'", path.display()),
+    }
+}
+
+fn extension(path: &Path) -> Option<String> {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_ascii_lowercase)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn channel(
+        interpolation: Interpolation,
+        times: Vec<f32>,
+        values: Vec<Vec3>,
+    ) -> AnimationChannel {
+        AnimationChannel {
+            node: 0,
+            interpolation,
+            times,
+            values: AnimationValues::Translations(values),
+        }
+    }
+
+    fn translation_at(channel: &AnimationChannel, time: f32) -> Vec3 {
+        let mut transform = Transform::default();
+        channel.apply(time, &mut transform);
+        transform.translation
+    }
+
+    #[test]
+    fn interpolates_linear_keys_and_clamps_outside_them() {
+        let channel = channel(
+            Interpolation::Linear,
+            vec![1.0, 3.0],
+            vec![Vec3::zeros(), Vec3::new(2.0, 4.0, 0.0)],
+        );
+        assert_eq!(translation_at(&channel, 0.0), Vec3::zeros());
+        assert_eq!(translation_at(&channel, 2.0), Vec3::new(1.0, 2.0, 0.0));
+        assert_eq!(translation_at(&channel, 5.0), Vec3::new(2.0, 4.0, 0.0));
+    }
+
+    #[test]
+    fn holds_step_keys_until_the_next_one() {
+        let channel = channel(
+            Interpolation::Step,
+            vec![0.0, 1.0],
+            vec![Vec3::x(), Vec3::y()],
+        );
+        assert_eq!(translation_at(&channel, 0.99), Vec3::x());
+        assert_eq!(translation_at(&channel, 1.0), Vec3::y());
+    }
+
+    #[test]
+    fn cubic_spline_passes_through_its_keys() {
+        // In-tangent, value and out-tangent per key, with flat tangents.
+        let channel = channel(
+            Interpolation::CubicSpline,
+            vec![0.0, 2.0],
+            vec![
+                Vec3::zeros(),
+                Vec3::x(),
+                Vec3::zeros(),
+                Vec3::zeros(),
+                Vec3::z(),
+                Vec3::zeros(),
+            ],
+        );
+        assert_eq!(translation_at(&channel, 0.0), Vec3::x());
+        assert_eq!(translation_at(&channel, 2.0), Vec3::z());
+        assert!((translation_at(&channel, 1.0) - Vec3::new(0.5, 0.0, 0.5)).norm() < 1e-6);
+    }
+
+    #[test]
+    fn ignores_channels_with_missing_values() {
+        let channel = channel(Interpolation::Linear, vec![0.0, 1.0], vec![Vec3::x()]);
+        assert_eq!(translation_at(&channel, 0.5), Vec3::zeros());
+    }
+}
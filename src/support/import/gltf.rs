@@ -0,0 +1,537 @@
+use crate::support::image_data::{Filter, SamplerSettings, SourceImage, Wrap};
+use crate::support::import::{
+    Animation, AnimationChannel, AnimationValues, CameraData, ImportedNode, ImportedScene,
+    Interpolation, ProjectionData, TextureData,
+};
+use crate::support::light::{Light, LightKind};
+use crate::support::material::{AlphaMode, Material, TextureRef};
+use crate::support::mesh::{Indices, Mesh, PrimitiveTopology, Submesh};
+use crate::support::transform::Transform;
+use anyhow::{Context, Result};
+use nalgebra_glm::{Quat, Vec2, Vec3, Vec4};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::path::Path;
+
+pub fn import(path: &Path) -> Result<ImportedScene> {
+    let error = || format!("Failed to import glTF file '{}'", path.display());
+    let ::gltf::Gltf { document, blob } = ::gltf::Gltf::open(path).with_context(error)?;
+    let base = path.parent();
+    let buffers = ::gltf::import_buffers(&document, base, blob).with_context(error)?;
+    let buffer_data = |buffer: ::gltf::Buffer| buffers.get(buffer.index()).map(|data| &data.0[..]);
+
+    let mut scene = ImportedScene {
+        name: path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        source: path.to_path_buf(),
+        ..Default::default()
+    };
+
+    // Images are decoded on first use so that the fallbacks of compressed textures are skipped.
+    let mut images = HashMap::new();
+    for texture in document.textures() {
+        let source = texture_source(&texture)
+            .and_then(|source| document.images().nth(source))
+            .with_context(|| format!("Texture {} has no usable image", texture.index()))?;
+        let image = match images.get(&source.index()) {
+            Some(image) => *image,
+            None => {
+                scene.images.push(load_image(&source, base, &buffers)?);
+                images.insert(source.index(), scene.images.len() - 1);
+                scene.images.len() - 1
+            }
+        };
+        scene.textures.push(TextureData {
+            name: texture
+                .name()
+                .map(str::to_string)
+                .unwrap_or_else(|| format!("texture_{}", texture.index())),
+            image,
+            sampler: sampler_settings(&texture.sampler()),
+        });
+    }
+
+    scene.materials = document
+        .materials()
+        .map(|material| convert_material(&material))
+        .collect();
+
+    let mut mesh_parts = Vec::new();
+    for mesh in document.meshes() {
+        let mut parts = Vec::new();
+        for imported in convert_mesh(&mesh, &buffer_data)? {
+            parts.push(scene.meshes.len());
+            scene.meshes.push(imported);
+        }
+        mesh_parts.push(parts);
+    }
+
+    scene.cameras = document
+        .cameras()
+        .map(|camera| CameraData {
+            name: camera
+                .name()
+                .map(str::to_string)
+                .unwrap_or_else(|| format!("camera_{}", camera.index())),
+            projection: match camera.projection() {
+                ::gltf::camera::Projection::Perspective(perspective) => {
+                    ProjectionData::Perspective {
+                        yfov: perspective.yfov(),
+                        aspect_ratio: perspective.aspect_ratio(),
+                        znear: perspective.znear(),
+                        zfar: perspective.zfar(),
+                    }
+                }
+                ::gltf::camera::Projection::Orthographic(orthographic) => {
+                    ProjectionData::Orthographic {
+                        xmag: orthographic.xmag(),
+                        ymag: orthographic.ymag(),
+                        znear: orthographic.znear(),
+                        zfar: orthographic.zfar(),
+                    }
+                }
+            },
+        })
+        .collect();
+
+    if let Some(lights) = document.lights() {
+        scene.lights = lights
+            .map(|light| Light {
+                name: light
+                    .name()
+                    .map(str::to_string)
+                    .unwrap_or_else(|| format!("light_{}", light.index())),
+                kind: match light.kind() {
+                    ::gltf::khr_lights_punctual::Kind::Directional => LightKind::Directional,
+                    ::gltf::khr_lights_punctual::Kind::Point => LightKind::Point,
+                    ::gltf::khr_lights_punctual::Kind::Spot {
+                        inner_cone_angle,
+                        outer_cone_angle,
+                    } => LightKind::Spot {
+                        inner_cone_angle,
+                        outer_cone_angle,
+                    },
+                },
+                color: Vec3::from(light.color()),
+                intensity: light.intensity(),
+                range: light.range(),
+            })
+            .collect();
+    }
+
+    scene.nodes = document
+        .nodes()
+        .map(|node| {
+            let (translation, rotation, scale) = node.transform().decomposed();
+            ImportedNode {
+                name: node
+                    .name()
+                    .map(str::to_string)
+                    .unwrap_or_else(|| format!("node_{}", node.index())),
+                transform: Transform {
+                    translation: Vec3::from(translation),
+                    rotation: quaternion(rotation),
+                    scale: Vec3::from(scale),
+                },
+                children: node.children().map(|child| child.index()).collect(),
+                meshes: node
+                    .mesh()
+                    .map(|mesh| mesh_parts[mesh.index()].clone())
+                    .unwrap_or_default(),
+                camera: node.camera().map(|camera| camera.index()),
+                light: node.light().map(|light| light.index()),
+            }
+        })
+        .collect();
+
+    scene.roots = match document
+        .default_scene()
+        .or_else(|| document.scenes().next())
+    {
+        Some(root_scene) => root_scene.nodes().map(|node| node.index()).collect(),
+        None => {
+            let mut is_child = vec![false; scene.nodes.len()];
+            for node in &scene.nodes {
+                for child in &node.children {
+                    is_child[*child] = true;
+                }
+            }
+            (0..scene.nodes.len())
+                .filter(|index| !is_child[*index])
+                .collect()
+        }
+    };
+
+    if document.skins().len() > 0 {
+        log::warn!(
+            "'{}' has {} skins, skinning is not supported so skinned meshes are drawn unskinned",
+            path.display(),
+            document.skins().len()
+        );
+    }
+
+    for animation in document.animations() {
+        let mut channels = Vec::new();
+        for channel in animation.channels() {
+            let reader = channel.reader(buffer_data);
+            let (Some(inputs), Some(outputs)) = (reader.read_inputs(), reader.read_outputs())
+            else {
+                continue;
+            };
+            let values = match outputs {
+                ::gltf::animation::util::ReadOutputs::Translations(values) => {
+                    AnimationValues::Translations(values.map(Vec3::from).collect())
+                }
+                ::gltf::animation::util::ReadOutputs::Rotations(values) => {
+                    AnimationValues::Rotations(values.into_f32().map(quaternion).collect())
+                }
+                ::gltf::animation::util::ReadOutputs::Scales(values) => {
+                    AnimationValues::Scales(values.map(Vec3::from).collect())
+                }
+                ::gltf::animation::util::ReadOutputs::MorphTargetWeights(_) => {
+                    log::warn!(
+                        "Skipping a morph target channel of animation {} in '{}', morph targets are not supported",
+                        animation.index(),
+                        path.display()
+                    );
+                    continue;
+                }
+            };
+            channels.push(AnimationChannel {
+                node: channel.target().node().index(),
+                interpolation: match channel.sampler().interpolation() {
+                    ::gltf::animation::Interpolation::Linear => Interpolation::Linear,
+                    ::gltf::animation::Interpolation::Step => Interpolation::Step,
+                    ::gltf::animation::Interpolation::CubicSpline => Interpolation::CubicSpline,
+                },
+                times: inputs.collect(),
+                values,
+            });
+        }
+        scene.animations.push(Animation {
+            name: animation
+                .name()
+                .map(str::to_string)
+                .unwrap_or_else(|| format!("animation_{}", animation.index())),
+            channels,
+        });
+    }
+
+    log::info!(
+        "Imported '{}': {} meshes, {} materials, {} textures, {} nodes, {} animations",
+        path.display(),
+        scene.meshes.len(),
+        scene.materials.len(),
+        scene.textures.len(),
+        scene.nodes.len(),
+        scene.animations.len()
+    );
+
+    Ok(scene)
+}
+
+fn convert_mesh<'a>(
+    mesh: &::gltf::Mesh,
+    buffer_data: &impl Fn(::gltf::Buffer<'_>) -> Option<&'a [u8]>,
+) -> Result<Vec<Mesh>> {
+    let name = mesh
+        .name()
+        .map(str::to_string)
+        .unwrap_or_else(|| format!("mesh_{}", mesh.index()));
+
+    let mut parts: Vec<(PrimitiveTopology, Vec<Mesh>)> = Vec::new();
+    for primitive in mesh.primitives() {
+        let reader = primitive.reader(buffer_data);
+        let Some(positions) = reader.read_positions() else {
+            continue;
+        };
+
+        let mut part = Mesh::new(&name, PrimitiveTopology::Triangles);
+        part.positions = positions.map(Vec3::from).collect();
+        if let Some(normals) = reader.read_normals() {
+            part.normals = normals.map(Vec3::from).collect();
+        }
+        if let Some(tangents) = reader.read_tangents() {
+            part.tangents = tangents.map(Vec4::from).collect();
+        }
+        if let Some(uvs) = reader.read_tex_coords(0) {
+            part.uvs = uvs.into_f32().map(Vec2::from).collect();
+        }
+        if let Some(colors) = reader.read_colors(0) {
+            part.colors = colors.into_rgba_f32().map(Vec4::from).collect();
+        }
+        if let Some(joints) = reader.read_joints(0) {
+            part.joints = joints.into_u16().collect();
+        }
+        if let Some(weights) = reader.read_weights(0) {
+            part.weights = weights.into_f32().map(Vec4::from).collect();
+        }
+        let indices = reader
+            .read_indices()
+            .map(|indices| indices.into_u32().collect::<Vec<_>>());
+
+        let (topology, elements) = list_elements(primitive.mode(), indices, part.vertex_count());
+        part.topology = topology;
+        part.indices = Some(Indices::U32(elements));
+        part.submeshes = vec![Submesh {
+            name: format!("{}_{}", name, primitive.index()),
+            index_offset: 0,
+            index_count: part.element_count(),
+            material: primitive.material().index(),
+        }];
+        part.validate().with_context(|| {
+            format!("Invalid primitive {} in mesh '{}'", primitive.index(), name)
+        })?;
+
+        if topology == PrimitiveTopology::Triangles {
+            if part.normals.is_empty() {
+                part.compute_flat_normals();
+            }
+            if part.tangents.is_empty() && !part.uvs.is_empty() {
+                part.compute_tangents();
+            }
+        }
+
+        match parts.iter_mut().find(|(existing, _)| *existing == topology) {
+            Some((_, meshes)) => meshes.push(part),
+            None => parts.push((topology, vec![part])),
+        }
+    }
+
+    Ok(parts
+        .into_iter()
+        .map(|(topology, meshes)| {
+            let mut merged = merge_parts(&name, topology, meshes);
+            merged.compute_bounds();
+            merged
+        })
+        .collect())
+}
+
+fn list_elements(
+    mode: ::gltf::mesh::Mode,
+    indices: Option<Vec<u32>>,
+    vertex_count: usize,
+) -> (PrimitiveTopology, Vec<u32>) {
+    use ::gltf::mesh::Mode;
+
+    let elements = indices.unwrap_or_else(|| (0..vertex_count as u32).collect());
+    match mode {
+        Mode::Points => (PrimitiveTopology::Points, elements),
+        Mode::Lines => (PrimitiveTopology::Lines, elements),
+        Mode::LineStrip | Mode::LineLoop => {
+            let mut lines = elements
+                .windows(2)
+                .flat_map(|pair| [pair[0], pair[1]])
+                .collect::<Vec<_>>();
+            if mode == Mode::LineLoop
+                && let (Some(first), Some(last)) = (elements.first(), elements.last())
+                && elements.len() > 2
+            {
+                lines.extend([*last, *first]);
+            }
+            (PrimitiveTopology::Lines, lines)
+        }
+        Mode::Triangles => (PrimitiveTopology::Triangles, elements),
+        Mode::TriangleStrip | Mode::TriangleFan => {
+            let mut strip = Mesh::new(
+                "",
+                if mode == Mode::TriangleStrip {
+                    PrimitiveTopology::TriangleStrip
+                } else {
+                    PrimitiveTopology::TriangleFan
+                },
+            );
+            strip.positions = vec![Vec3::zeros(); vertex_count];
+            strip.indices = Some(Indices::U32(elements));
+            let triangles = strip.triangles().into_iter().flatten().collect();
+            (PrimitiveTopology::Triangles, triangles)
+        }
+    }
+}
+
+fn merge_parts(name: &str, topology: PrimitiveTopology, parts: Vec<Mesh>) -> Mesh {
+    let has_normals = parts.iter().any(|part| !part.normals.is_empty());
+    let has_tangents = parts.iter().any(|part| !part.tangents.is_empty());
+    let has_uvs = parts.iter().any(|part| !part.uvs.is_empty());
+    let has_colors = parts.iter().any(|part| !part.colors.is_empty());
+    let has_joints = parts.iter().any(|part| !part.joints.is_empty());
+    let has_weights = parts.iter().any(|part| !part.weights.is_empty());
+
+    let mut merged = Mesh::new(name, topology);
+    let mut indices = Vec::new();
+    for part in parts {
+        let base = merged.vertex_count() as u32;
+        let count = part.vertex_count();
+        merged.positions.extend(part.positions);
+        merged
+            .normals
+            .extend(fill(count, part.normals, has_normals, Vec3::y()));
+        merged.tangents.extend(fill(
+            count,
+            part.tangents,
+            has_tangents,
+            Vec4::new(1.0, 0.0, 0.0, 1.0),
+        ));
+        merged
+            .uvs
+            .extend(fill(count, part.uvs, has_uvs, Vec2::zeros()));
+        merged.colors.extend(fill(
+            count,
+            part.colors,
+            has_colors,
+            Vec4::new(1.0, 1.0, 1.0, 1.0),
+        ));
+        merged
+            .joints
+            .extend(fill(count, part.joints, has_joints, [0; 4]));
+        merged
+            .weights
+            .extend(fill(count, part.weights, has_weights, Vec4::zeros()));
+
+        let offset = indices.len();
+        let part_indices = part
+            .indices
+            .map(|indices| indices.to_u32())
+            .unwrap_or_else(|| (0..count as u32).collect());
+        indices.extend(part_indices.iter().map(|index| base + index));
+        merged
+            .submeshes
+            .extend(part.submeshes.into_iter().map(|submesh| Submesh {
+                index_offset: offset + submesh.index_offset,
+                ..submesh
+            }));
+    }
+    merged.indices = Some(Indices::compact(indices));
+    merged
+}
+
+fn fill<T: Clone>(count: usize, values: Vec<T>, enabled: bool, default: T) -> Vec<T> {
+    if !enabled {
+        Vec::new()
+    } else if values.is_empty() {
+        vec![default; count]
+    } else {
+        values
+    }
+}
+
+fn convert_material(material: &::gltf::Material) -> Material {
+    let texture_ref = |info: Option<::gltf::texture::Info>| {
+        info.map(|info| TextureRef {
+            texture: info.texture().index(),
+            uv_set: info.tex_coord(),
+        })
+    };
+    let pbr = material.pbr_metallic_roughness();
+
+    Material {
+        name: material
+            .name()
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("material_{}", material.index().unwrap_or_default())),
+        base_color: Vec4::from(pbr.base_color_factor()),
+        base_color_texture: texture_ref(pbr.base_color_texture()),
+        metallic: pbr.metallic_factor(),
+        roughness: pbr.roughness_factor(),
+        metallic_roughness_texture: texture_ref(pbr.metallic_roughness_texture()),
+        normal_texture: material.normal_texture().map(|info| TextureRef {
+            texture: info.texture().index(),
+            uv_set: info.tex_coord(),
+        }),
+        normal_scale: material.normal_texture().map_or(1.0, |info| info.scale()),
+        occlusion_texture: material.occlusion_texture().map(|info| TextureRef {
+            texture: info.texture().index(),
+            uv_set: info.tex_coord(),
+        }),
+        occlusion_strength: material
+            .occlusion_texture()
+            .map_or(1.0, |info| info.strength()),
+        emissive: Vec3::from(material.emissive_factor()),
+        emissive_texture: texture_ref(material.emissive_texture()),
+        alpha_mode: match material.alpha_mode() {
+            ::gltf::material::AlphaMode::Opaque => AlphaMode::Opaque,
+            ::gltf::material::AlphaMode::Mask => {
+                AlphaMode::Mask(material.alpha_cutoff().unwrap_or(0.5))
+            }
+            ::gltf::material::AlphaMode::Blend => AlphaMode::Blend,
+        },
+        double_sided: material.double_sided(),
+        unlit: material.unlit(),
+        ..Default::default()
+    }
+}
+
+fn sampler_settings(sampler: &::gltf::texture::Sampler) -> SamplerSettings {
+    use ::gltf::texture::{MagFilter, MinFilter, WrappingMode};
+
+    let wrap = |mode: WrappingMode| match mode {
+        WrappingMode::Repeat => Wrap::Repeat,
+        WrappingMode::MirroredRepeat => Wrap::MirroredRepeat,
+        WrappingMode::ClampToEdge => Wrap::ClampToEdge,
+    };
+    let (min_filter, mipmap_filter) = match sampler.min_filter() {
+        Some(MinFilter::Nearest) => (Filter::Nearest, None),
+        Some(MinFilter::Linear) => (Filter::Linear, None),
+        Some(MinFilter::NearestMipmapNearest) => (Filter::Nearest, Some(Filter::Nearest)),
+        Some(MinFilter::NearestMipmapLinear) => (Filter::Nearest, Some(Filter::Linear)),
+        Some(MinFilter::LinearMipmapNearest) => (Filter::Linear, Some(Filter::Nearest)),
+        Some(MinFilter::LinearMipmapLinear) | None => (Filter::Linear, Some(Filter::Linear)),
+    };
+
+    SamplerSettings {
+        mag_filter: match sampler.mag_filter() {
+            Some(MagFilter::Nearest) => Filter::Nearest,
+            Some(MagFilter::Linear) | None => Filter::Linear,
+        },
+        min_filter,
+        mipmap_filter,
+        wrap_s: wrap(sampler.wrap_s()),
+        wrap_t: wrap(sampler.wrap_t()),
+        ..SamplerSettings::default()
+    }
+}
+
+/// The image a texture samples, preferring the KTX2 image of `KHR_texture_basisu` and the DDS
+/// image of `MSFT_texture_dds` over the core fallback.
+fn texture_source(texture: &::gltf::Texture) -> Option<usize> {
+    ["KHR_texture_basisu", "MSFT_texture_dds"]
+        .into_iter()
+        .find_map(|extension| texture.extension_value(extension)?.get("source")?.as_u64())
+        .map(|source| source as usize)
+        .or_else(|| texture.source().map(|image| image.index()))
+}
+
+fn load_image(
+    image: &::gltf::Image,
+    base: Option<&Path>,
+    buffers: &[::gltf::buffer::Data],
+) -> Result<SourceImage> {
+    let name = image
+        .name()
+        .map(str::to_string)
+        .unwrap_or_else(|| format!("image_{}", image.index()));
+    let bytes = match image.source() {
+        ::gltf::image::Source::View { view, .. } => {
+            let range = view.offset()..view.offset() + view.length();
+            let bytes = buffers
+                .get(view.buffer().index())
+                .and_then(|buffer| buffer.0.get(range))
+                .with_context(|| format!("Image '{}' is outside its buffer", name))?;
+            Cow::Borrowed(bytes)
+        }
+        // Buffers resolve data URIs and relative paths the same way images need to.
+        ::gltf::image::Source::Uri { uri, .. } => Cow::Owned(
+            ::gltf::buffer::Data::from_source(::gltf::buffer::Source::Uri(uri), base)
+                .with_context(|| format!("Failed to read image '{}'", uri))?
+                .0,
+        ),
+    };
+    SourceImage::from_memory(&name, &bytes)
+}
+
+fn quaternion([x, y, z, w]: [f32; 4]) -> Quat {
+    Quat::new(w, x, y, z)
+}
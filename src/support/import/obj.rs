@@ -0,0 +1,573 @@
+use crate::support::image_data::{SamplerSettings, SourceImage};
+use crate::support::import::{ImportedNode, ImportedScene, TextureData};
+use crate::support::material::{AlphaMode, Material, TextureRef};
+use crate::support::mesh::{Indices, Mesh, PrimitiveTopology, Submesh, triangulate_polygon};
+use anyhow::{Context, Result, bail};
+use nalgebra_glm::{Vec2, Vec3, Vec4};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NormalGeneration {
+    #[default]
+    SmoothingGroups,
+    Smooth,
+    Flat,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ObjOptions {
+    pub normals: NormalGeneration,
+}
+
+pub fn import(path: &Path) -> Result<ImportedScene> {
+    import_with_options(path, &ObjOptions::default())
+}
+
+pub fn import_with_options(path: &Path, options: &ObjOptions) -> Result<ImportedScene> {
+    let source = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read OBJ file '{}'", path.display()))?;
+    let directory = path.parent().unwrap_or(Path::new("."));
+    let mut scene = parse(&source, directory, options)
+        .with_context(|| format!("Failed to parse OBJ file '{}'", path.display()))?;
+    scene.name = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    scene.source = path.to_path_buf();
+
+    log::info!(
+        "Imported '{}': {} meshes, {} materials, {} textures",
+        path.display(),
+        scene.meshes.len(),
+        scene.materials.len(),
+        scene.textures.len()
+    );
+
+    Ok(scene)
+}
+
+pub fn parse(source: &str, directory: &Path, options: &ObjOptions) -> Result<ImportedScene> {
+    let mut scene = ImportedScene::default();
+    let mut parser = Parser::default();
+
+    for (index, line) in source.lines().enumerate() {
+        let line_number = index + 1;
+        let line = line.split('#').next().unwrap_or_default().trim();
+        let Some((keyword, rest)) = line
+            .split_once(char::is_whitespace)
+            .map(|(keyword, rest)| (keyword, rest.trim()))
+            .or_else(|| (!line.is_empty()).then_some((line, "")))
+        else {
+            continue;
+        };
+
+        let result = match keyword {
+            "v" => parser.parse_position(rest),
+            "vt" => floats(rest).map(|values| {
+                parser.uvs.push(Vec2::new(
+                    values.first().copied().unwrap_or_default(),
+                    values.get(1).copied().unwrap_or_default(),
+                ));
+            }),
+            "vn" => floats(rest).and_then(|values| match values[..] {
+                [x, y, z, ..] => {
+                    parser.normals.push(Vec3::new(x, y, z));
+                    Ok(())
+                }
+                _ => bail!("Expected 3 components for 'vn'"),
+            }),
+            "f" => parser.parse_face(rest),
+            "o" | "g" => {
+                parser.begin_group(rest);
+                Ok(())
+            }
+            "s" => {
+                parser.smoothing = match rest {
+                    "off" | "0" | "" => 0,
+                    group => group.parse().unwrap_or(1),
+                };
+                Ok(())
+            }
+            "usemtl" => {
+                parser.material = Some(material_index(&mut scene, rest));
+                Ok(())
+            }
+            "mtllib" => {
+                for file_name in rest.split_whitespace() {
+                    if let Err(error) =
+                        load_material_library(&mut scene, &directory.join(file_name))
+                    {
+                        log::warn!("{:?}", error);
+                    }
+                }
+                Ok(())
+            }
+            "l" | "p" | "curv" | "curv2" | "surf" => {
+                parser.unsupported += 1;
+                Ok(())
+            }
+            _ => Ok(()),
+        };
+        result.with_context(|| format!("Line {}: '{}'", line_number, line))?;
+    }
+
+    if parser.unsupported > 0 {
+        log::warn!(
+            "Skipped {} unsupported OBJ statements (lines, points or curves)",
+            parser.unsupported
+        );
+    }
+
+    for group in &parser.groups {
+        if group.faces.is_empty() {
+            continue;
+        }
+        let mesh = parser.build_mesh(group, options.normals);
+        scene.nodes.push(ImportedNode {
+            name: group.name.clone(),
+            meshes: vec![scene.meshes.len()],
+            ..Default::default()
+        });
+        scene.meshes.push(mesh);
+    }
+    scene.roots = (0..scene.nodes.len()).collect();
+
+    Ok(scene)
+}
+
+#[derive(Clone, Copy, Debug)]
+struct FaceVertex {
+    position: usize,
+    uv: Option<usize>,
+    normal: Option<usize>,
+}
+
+#[derive(Debug)]
+struct Face {
+    vertices: Vec<FaceVertex>,
+    material: Option<usize>,
+    smoothing: u32,
+}
+
+#[derive(Debug, Default)]
+struct Group {
+    name: String,
+    faces: Vec<Face>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum NormalKey {
+    Explicit(usize),
+    Smooth(u32),
+    Face(usize),
+}
+
+#[derive(Debug, Default)]
+struct Parser {
+    positions: Vec<Vec3>,
+    colors: Vec<Option<Vec4>>,
+    uvs: Vec<Vec2>,
+    normals: Vec<Vec3>,
+    groups: Vec<Group>,
+    material: Option<usize>,
+    smoothing: u32,
+    unsupported: usize,
+}
+
+impl Parser {
+    fn parse_position(&mut self, rest: &str) -> Result<()> {
+        let values = floats(rest)?;
+        let (position, color) = match values[..] {
+            [x, y, z, r, g, b, ..] => (Vec3::new(x, y, z), Some(Vec4::new(r, g, b, 1.0))),
+            [x, y, z, w] if w != 0.0 && w != 1.0 => (Vec3::new(x, y, z) / w, None),
+            [x, y, z, ..] => (Vec3::new(x, y, z), None),
+            _ => bail!("Expected at least 3 components for 'v'"),
+        };
+        self.positions.push(position);
+        self.colors.push(color);
+        Ok(())
+    }
+
+    fn parse_face(&mut self, rest: &str) -> Result<()> {
+        let vertices = rest
+            .split_whitespace()
+            .map(|vertex| {
+                let mut parts = vertex.split('/');
+                let position = parts.next().unwrap_or_default();
+                let uv = parts.next().filter(|part| !part.is_empty());
+                let normal = parts.next().filter(|part| !part.is_empty());
+                Ok(FaceVertex {
+                    position: resolve_index(position, self.positions.len(), "position")?,
+                    uv: uv
+                        .map(|uv| resolve_index(uv, self.uvs.len(), "texture coordinate"))
+                        .transpose()?,
+                    normal: normal
+                        .map(|normal| resolve_index(normal, self.normals.len(), "normal"))
+                        .transpose()?,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        if vertices.len() < 3 {
+            bail!("Face has {} vertices, expected at least 3", vertices.len());
+        }
+
+        if self.groups.is_empty() {
+            self.begin_group("default");
+        }
+        let face = Face {
+            vertices,
+            material: self.material,
+            smoothing: self.smoothing,
+        };
+        if let Some(group) = self.groups.last_mut() {
+            group.faces.push(face);
+        }
+        Ok(())
+    }
+
+    fn begin_group(&mut self, name: &str) {
+        let name = if name.is_empty() { "default" } else { name };
+        match self.groups.last_mut() {
+            Some(group) if group.faces.is_empty() => group.name = name.to_string(),
+            _ => self.groups.push(Group {
+                name: name.to_string(),
+                faces: Vec::new(),
+            }),
+        }
+    }
+
+    fn build_mesh(&self, group: &Group, normals: NormalGeneration) -> Mesh {
+        let has_uvs = group
+            .faces
+            .iter()
+            .flat_map(|face| &face.vertices)
+            .any(|vertex| vertex.uv.is_some());
+        let has_colors = self.colors.iter().any(Option::is_some);
+
+        let mut mesh = Mesh::new(&group.name, PrimitiveTopology::Triangles);
+        let mut generated = Vec::new();
+        let mut vertex_map = HashMap::new();
+        let mut batches: Vec<(Option<usize>, Vec<u32>)> = Vec::new();
+
+        for (face_index, face) in group.faces.iter().enumerate() {
+            let corners = face
+                .vertices
+                .iter()
+                .map(|vertex| {
+                    let normal_key = match (vertex.normal, normals) {
+                        (Some(normal), _) => NormalKey::Explicit(normal),
+                        (None, NormalGeneration::Smooth) => NormalKey::Smooth(0),
+                        (None, NormalGeneration::SmoothingGroups) if face.smoothing != 0 => {
+                            NormalKey::Smooth(face.smoothing)
+                        }
+                        (None, _) => NormalKey::Face(face_index),
+                    };
+                    let key = (vertex.position, vertex.uv, normal_key);
+                    *vertex_map.entry(key).or_insert_with(|| {
+                        let index = mesh.positions.len() as u32;
+                        mesh.positions.push(self.positions[vertex.position]);
+                        mesh.normals.push(
+                            vertex
+                                .normal
+                                .map_or_else(Vec3::zeros, |normal| self.normals[normal]),
+                        );
+                        generated.push(vertex.normal.is_none());
+                        if has_uvs {
+                            mesh.uvs
+                                .push(vertex.uv.map_or_else(Vec2::zeros, |uv| self.uvs[uv]));
+                        }
+                        if has_colors {
+                            mesh.colors.push(
+                                self.colors[vertex.position]
+                                    .unwrap_or_else(|| Vec4::new(1.0, 1.0, 1.0, 1.0)),
+                            );
+                        }
+                        index
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            let points = face
+                .vertices
+                .iter()
+                .map(|vertex| self.positions[vertex.position])
+                .collect::<Vec<_>>();
+            let indices = match batches
+                .iter_mut()
+                .find(|(material, _)| *material == face.material)
+            {
+                Some((_, indices)) => indices,
+                None => {
+                    batches.push((face.material, Vec::new()));
+                    &mut batches.last_mut().expect("batch was just pushed").1
+                }
+            };
+            for [a, b, c] in triangulate_polygon(&points) {
+                let triangle = [corners[a], corners[b], corners[c]];
+                indices.extend(triangle);
+
+                let [a, b, c] = triangle.map(|index| index as usize);
+                let face_normal = (mesh.positions[b] - mesh.positions[a])
+                    .cross(&(mesh.positions[c] - mesh.positions[a]));
+                for corner in [a, b, c] {
+                    if generated[corner] {
+                        mesh.normals[corner] += face_normal;
+                    }
+                }
+            }
+        }
+
+        for (normal, generated) in mesh.normals.iter_mut().zip(generated) {
+            if generated {
+                *normal = normal.try_normalize(f32::EPSILON).unwrap_or_else(Vec3::y);
+            }
+        }
+
+        let mut indices = Vec::new();
+        for (material, batch) in batches {
+            mesh.submeshes.push(Submesh {
+                name: format!("{}_{}", group.name, mesh.submeshes.len()),
+                index_offset: indices.len(),
+                index_count: batch.len(),
+                material,
+            });
+            indices.extend(batch);
+        }
+        mesh.indices = Some(Indices::compact(indices));
+        if has_uvs {
+            mesh.compute_tangents();
+        }
+        mesh.compute_bounds();
+        mesh
+    }
+}
+
+fn floats(text: &str) -> Result<Vec<f32>> {
+    text.split_whitespace()
+        .map(|value| {
+            value
+                .parse::<f32>()
+                .with_context(|| format!("Invalid number '{}'", value))
+        })
+        .collect()
+}
+
+fn resolve_index(text: &str, len: usize, kind: &str) -> Result<usize> {
+    let index = text
+        .parse::<i64>()
+        .with_context(|| format!("Invalid {} index '{}'", kind, text))?;
+    let resolved = match index {
+        0 => bail!("Invalid {} index 0, OBJ indices start at 1", kind),
+        index if index > 0 => index - 1,
+        index => len as i64 + index,
+    };
+    if resolved < 0 || resolved as usize >= len {
+        bail!(
+            "The {} index {} is out of range for {} elements",
+            kind,
+            index,
+            len
+        );
+    }
+    Ok(resolved as usize)
+}
+
+fn material_index(scene: &mut ImportedScene, name: &str) -> usize {
+    if let Some(index) = scene
+        .materials
+        .iter()
+        .position(|material| material.name == name)
+    {
+        return index;
+    }
+    log::warn!(
+        "OBJ material '{}' is not defined in any material library",
+        name
+    );
+    scene.materials.push(obj_material(name));
+    scene.materials.len() - 1
+}
+
+fn obj_material(name: &str) -> Material {
+    Material {
+        metallic: 0.0,
+        ..Material::new(name)
+    }
+}
+
+pub fn load_material_library(scene: &mut ImportedScene, path: &Path) -> Result<()> {
+    let source = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read material library '{}'", path.display()))?;
+    let directory = path.parent().unwrap_or(Path::new("."));
+    let mut texture_cache = HashMap::new();
+    let mut current: Option<usize> = None;
+
+    for line in source.lines() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        let Some((keyword, rest)) = line.split_once(char::is_whitespace) else {
+            continue;
+        };
+        let rest = rest.trim();
+
+        if keyword == "newmtl" {
+            current = Some(
+                match scene
+                    .materials
+                    .iter()
+                    .position(|material| material.name == rest)
+                {
+                    Some(index) => index,
+                    None => {
+                        scene.materials.push(obj_material(rest));
+                        scene.materials.len() - 1
+                    }
+                },
+            );
+            continue;
+        }
+        let Some(index) = current else {
+            continue;
+        };
+
+        let values = floats(rest).unwrap_or_default();
+        let color = match values[..] {
+            [r, g, b, ..] => Some(Vec3::new(r, g, b)),
+            [value] => Some(Vec3::new(value, value, value)),
+            _ => None,
+        };
+        let texture = if keyword.starts_with("map_") || keyword == "bump" || keyword == "norm" {
+            texture_file(rest).and_then(|file_name| {
+                load_texture(scene, &mut texture_cache, &directory.join(file_name))
+            })
+        } else {
+            None
+        };
+
+        let material = &mut scene.materials[index];
+        match keyword {
+            "Kd" => {
+                if let Some(color) = color {
+                    material.base_color =
+                        Vec4::new(color.x, color.y, color.z, material.base_color.w);
+                }
+            }
+            "d" => {
+                if let Some(alpha) = values.first() {
+                    material.base_color.w = *alpha;
+                }
+            }
+            "Tr" => {
+                if let Some(transparency) = values.first() {
+                    material.base_color.w = 1.0 - transparency;
+                }
+            }
+            "Ns" => {
+                if let Some(exponent) = values.first() {
+                    material.roughness = (2.0 / (exponent.max(0.0) + 2.0)).sqrt();
+                }
+            }
+            "Pr" => material.roughness = values.first().copied().unwrap_or(material.roughness),
+            "Pm" => material.metallic = values.first().copied().unwrap_or(material.metallic),
+            "Ke" => material.emissive = color.unwrap_or(material.emissive),
+            "map_Kd" => material.base_color_texture = texture,
+            "map_Ke" => material.emissive_texture = texture,
+            "map_Bump" | "map_bump" | "bump" | "norm" => material.normal_texture = texture,
+            _ => {}
+        }
+        if material.base_color.w < 1.0 {
+            material.alpha_mode = AlphaMode::Blend;
+        }
+    }
+
+    Ok(())
+}
+
+fn texture_file(arguments: &str) -> Option<String> {
+    let tokens = arguments.split_whitespace().collect::<Vec<_>>();
+    let mut index = 0;
+    while let Some(token) = tokens.get(index) {
+        let argument_count = match *token {
+            "-blendu" | "-blendv" | "-bm" | "-boost" | "-cc" | "-clamp" | "-imfchan"
+            | "-texres" | "-type" => 1,
+            "-mm" => 2,
+            // The v and w components are optional.
+            "-o" | "-s" | "-t" => tokens
+                .iter()
+                .skip(index + 1)
+                .take(3)
+                .take_while(|token| token.parse::<f32>().is_ok())
+                .count(),
+            _ => break,
+        };
+        index += 1 + argument_count;
+    }
+    let file_name = tokens.get(index..)?.join(" ");
+    (!file_name.is_empty()).then_some(file_name)
+}
+
+fn load_texture(
+    scene: &mut ImportedScene,
+    cache: &mut HashMap<std::path::PathBuf, usize>,
+    path: &Path,
+) -> Option<TextureRef> {
+    if let Some(texture) = cache.get(path) {
+        return Some(TextureRef {
+            texture: *texture,
+            uv_set: 0,
+        });
+    }
+    let image = match SourceImage::load(path) {
+        Ok(image) => image,
+        Err(error) => {
+            log::warn!("{:?}", error);
+            return None;
+        }
+    };
+    scene.images.push(image);
+    scene.textures.push(TextureData {
+        name: path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        image: scene.images.len() - 1,
+        sampler: SamplerSettings::default(),
+    });
+    let texture = scene.textures.len() - 1;
+    cache.insert(path.to_path_buf(), texture);
+    Some(TextureRef { texture, uv_set: 0 })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skips_texture_options() {
+        assert_eq!(
+            texture_file("-blendu on -mm 0 1 -o 1 2 3 wood.png").as_deref(),
+            Some("wood.png")
+        );
+        assert_eq!(
+            texture_file("-clamp on my texture.png").as_deref(),
+            Some("my texture.png")
+        );
+    }
+
+    #[test]
+    fn reads_one_to_three_numbers_after_offset_scale_and_turbulence() {
+        assert_eq!(texture_file("-s 2 wood.png").as_deref(), Some("wood.png"));
+        assert_eq!(
+            texture_file("-o 0.5 0.25 -t 1 wood.png").as_deref(),
+            Some("wood.png")
+        );
+        assert_eq!(texture_file("-s 1 1 1 2.png").as_deref(), Some("2.png"));
+    }
+
+    #[test]
+    fn rejects_missing_file_names() {
+        assert_eq!(texture_file("-s 1 1"), None);
+        assert_eq!(texture_file(""), None);
+    }
+}
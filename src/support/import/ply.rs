@@ -0,0 +1,565 @@
+use crate::support::import::{ImportedNode, ImportedScene};
+use crate::support::material::Material;
+use crate::support::mesh::{Indices, Mesh, PrimitiveTopology, Submesh, triangulate_polygon};
+use anyhow::{Context, Result, bail};
+use nalgebra_glm::{Vec2, Vec3, Vec4};
+use std::fs;
+use std::path::Path;
+
+#[derive(Clone, Debug)]
+pub struct PlyOptions {
+    pub point_size: f32,
+}
+
+impl Default for PlyOptions {
+    fn default() -> Self {
+        Self { point_size: 3.0 }
+    }
+}
+
+pub fn import(path: &Path) -> Result<ImportedScene> {
+    import_with_options(path, &PlyOptions::default())
+}
+
+pub fn import_with_options(path: &Path, options: &PlyOptions) -> Result<ImportedScene> {
+    let bytes =
+        fs::read(path).with_context(|| format!("Failed to read PLY file '{}'", path.display()))?;
+    let name = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let mesh = parse(&name, &bytes)
+        .with_context(|| format!("Failed to parse PLY file '{}'", path.display()))?;
+
+    log::info!(
+        "Imported '{}': {} vertices, {} triangles",
+        path.display(),
+        mesh.vertex_count(),
+        mesh.triangle_count()
+    );
+
+    let mut scene = ImportedScene {
+        name: name.clone(),
+        source: path.to_path_buf(),
+        nodes: vec![ImportedNode {
+            name,
+            meshes: vec![0],
+            ..Default::default()
+        }],
+        roots: vec![0],
+        ..Default::default()
+    };
+    if mesh.topology == PrimitiveTopology::Points {
+        scene.materials.push(Material {
+            point_size: options.point_size,
+            metallic: 0.0,
+            ..Material::new("point_cloud")
+        });
+    }
+    scene.meshes.push(mesh);
+    Ok(scene)
+}
+
+pub fn parse(name: &str, bytes: &[u8]) -> Result<Mesh> {
+    let (header, body) = parse_header(bytes)?;
+    let mut reader = match header.encoding {
+        Encoding::Ascii => ValueReader::Ascii(
+            std::str::from_utf8(body)
+                .context("ASCII PLY body is not valid UTF-8")?
+                .split_ascii_whitespace(),
+        ),
+        Encoding::BinaryLittleEndian | Encoding::BinaryBigEndian => ValueReader::Binary {
+            data: body,
+            offset: 0,
+            big_endian: header.encoding == Encoding::BinaryBigEndian,
+        },
+    };
+
+    let mut mesh = Mesh::new(name, PrimitiveTopology::Triangles);
+    let mut polygons: Vec<Vec<u32>> = Vec::new();
+    for element in &header.elements {
+        match element.name.as_str() {
+            "vertex" => read_vertices(&mut reader, element, &mut mesh)?,
+            "face" => {
+                let index_property = element
+                    .properties
+                    .iter()
+                    .position(|property| {
+                        property.name == "vertex_indices" || property.name == "vertex_index"
+                    })
+                    .context("Face element has no 'vertex_indices' property")?;
+                for face in 0..element.count {
+                    for (index, property) in element.properties.iter().enumerate() {
+                        let values = reader.read_property(property)?;
+                        if index == index_property {
+                            polygons.push(face_indices(face, values)?);
+                        }
+                    }
+                }
+            }
+            _ => {
+                for _ in 0..element.count {
+                    for property in &element.properties {
+                        reader.read_property(property)?;
+                    }
+                }
+            }
+        }
+    }
+
+    if polygons.is_empty() {
+        mesh.topology = PrimitiveTopology::Points;
+        mesh.submeshes = vec![Submesh {
+            name: name.to_string(),
+            index_offset: 0,
+            index_count: mesh.vertex_count(),
+            material: Some(0),
+        }];
+        mesh.compute_bounds();
+        return Ok(mesh);
+    }
+
+    let mut indices = Vec::new();
+    for polygon in polygons {
+        if let Some(index) = polygon
+            .iter()
+            .find(|index| **index as usize >= mesh.vertex_count())
+        {
+            bail!(
+                "Face index {} is out of range for {} vertices",
+                index,
+                mesh.vertex_count()
+            );
+        }
+        let points = polygon
+            .iter()
+            .map(|index| mesh.positions[*index as usize])
+            .collect::<Vec<_>>();
+        for triangle in triangulate_polygon(&points) {
+            indices.extend(triangle.map(|corner| polygon[corner]));
+        }
+    }
+    mesh.indices = Some(Indices::compact(indices));
+    if mesh.normals.is_empty() {
+        mesh.compute_smooth_normals();
+    }
+    if !mesh.uvs.is_empty() {
+        mesh.compute_tangents();
+    }
+    mesh.compute_bounds();
+    Ok(mesh)
+}
+
+fn face_indices(face: usize, values: Vec<f64>) -> Result<Vec<u32>> {
+    values
+        .into_iter()
+        .map(|value| {
+            if value < 0.0 || value.fract() != 0.0 || value > u32::MAX as f64 {
+                bail!("Face {} has invalid vertex index {}", face, value);
+            }
+            Ok(value as u32)
+        })
+        .collect()
+}
+
+fn read_vertices(reader: &mut ValueReader, element: &Element, mesh: &mut Mesh) -> Result<()> {
+    let find = |names: &[&str]| {
+        element
+            .properties
+            .iter()
+            .position(|property| names.contains(&property.name.as_str()))
+    };
+    let position = [find(&["x"]), find(&["y"]), find(&["z"])];
+    let normal = [find(&["nx"]), find(&["ny"]), find(&["nz"])];
+    let color = [
+        find(&["red", "r", "diffuse_red"]),
+        find(&["green", "g", "diffuse_green"]),
+        find(&["blue", "b", "diffuse_blue"]),
+    ];
+    let alpha = find(&["alpha", "a", "diffuse_alpha"]);
+    let uv = [
+        find(&["u", "s", "texture_u", "texture_s"]),
+        find(&["v", "t", "texture_v", "texture_t"]),
+    ];
+    if position.iter().any(Option::is_none) {
+        bail!("Vertex element is missing x, y or z");
+    }
+    let has_normals = normal.iter().all(Option::is_some);
+    let has_colors = color.iter().all(Option::is_some);
+    let has_uvs = uv.iter().all(Option::is_some);
+
+    let mut values = vec![0.0; element.properties.len()];
+    for _ in 0..element.count {
+        for (index, property) in element.properties.iter().enumerate() {
+            values[index] = match &property.kind {
+                PropertyKind::Scalar(ty) => {
+                    let value = reader.read(*ty)?;
+                    if color.contains(&Some(index)) || alpha == Some(index) {
+                        ty.normalize(value)
+                    } else {
+                        value
+                    }
+                }
+                PropertyKind::List { .. } => {
+                    reader.read_property(property)?;
+                    0.0
+                }
+            };
+        }
+        let get = |slot: Option<usize>| slot.map_or(0.0, |slot| values[slot] as f32);
+
+        mesh.positions.push(Vec3::new(
+            get(position[0]),
+            get(position[1]),
+            get(position[2]),
+        ));
+        if has_normals {
+            mesh.normals
+                .push(Vec3::new(get(normal[0]), get(normal[1]), get(normal[2])));
+        }
+        if has_colors {
+            mesh.colors.push(Vec4::new(
+                get(color[0]),
+                get(color[1]),
+                get(color[2]),
+                alpha.map_or(1.0, |alpha| values[alpha] as f32),
+            ));
+        }
+        if has_uvs {
+            mesh.uvs.push(Vec2::new(get(uv[0]), get(uv[1])));
+        }
+    }
+    Ok(())
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Encoding {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ScalarType {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl ScalarType {
+    fn parse(name: &str) -> Result<Self> {
+        Ok(match name {
+            "char" | "int8" => ScalarType::I8,
+            "uchar" | "uint8" => ScalarType::U8,
+            "short" | "int16" => ScalarType::I16,
+            "ushort" | "uint16" => ScalarType::U16,
+            "int" | "int32" => ScalarType::I32,
+            "uint" | "uint32" => ScalarType::U32,
+            "float" | "float32" => ScalarType::F32,
+            "double" | "float64" => ScalarType::F64,
+            other => bail!("Unknown PLY property type '{}'", other),
+        })
+    }
+
+    fn size(&self) -> usize {
+        match self {
+            ScalarType::I8 | ScalarType::U8 => 1,
+            ScalarType::I16 | ScalarType::U16 => 2,
+            ScalarType::I32 | ScalarType::U32 | ScalarType::F32 => 4,
+            ScalarType::F64 => 8,
+        }
+    }
+
+    fn normalize(&self, value: f64) -> f64 {
+        match self {
+            ScalarType::I8 => value / i8::MAX as f64,
+            ScalarType::U8 => value / u8::MAX as f64,
+            ScalarType::I16 => value / i16::MAX as f64,
+            ScalarType::U16 => value / u16::MAX as f64,
+            ScalarType::I32 => value / i32::MAX as f64,
+            ScalarType::U32 => value / u32::MAX as f64,
+            ScalarType::F32 | ScalarType::F64 => value,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum PropertyKind {
+    Scalar(ScalarType),
+    List { count: ScalarType, item: ScalarType },
+}
+
+#[derive(Clone, Debug)]
+struct Property {
+    name: String,
+    kind: PropertyKind,
+}
+
+#[derive(Clone, Debug)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+#[derive(Debug)]
+struct Header {
+    encoding: Encoding,
+    elements: Vec<Element>,
+}
+
+fn parse_header(bytes: &[u8]) -> Result<(Header, &[u8])> {
+    if !bytes.starts_with(b"ply") {
+        bail!("Missing 'ply' magic number");
+    }
+    // Binary bodies can contain any bytes, so the header ends at the first line that is
+    // exactly 'end_header' rather than wherever the text first appears.
+    let mut line_start = 0;
+    let (end, body_start) = loop {
+        let line_end = bytes[line_start..]
+            .iter()
+            .position(|byte| *byte == b'\n')
+            .map(|newline| line_start + newline);
+        let line = &bytes[line_start..line_end.unwrap_or(bytes.len())];
+        if line.trim_ascii() == b"end_header" {
+            break (
+                line_start,
+                line_end.map_or(bytes.len(), |newline| newline + 1),
+            );
+        }
+        match line_end {
+            Some(newline) => line_start = newline + 1,
+            None => bail!("Missing 'end_header'"),
+        }
+    };
+    let text = std::str::from_utf8(&bytes[..end]).context("PLY header is not valid UTF-8")?;
+
+    let mut encoding = None;
+    let mut elements: Vec<Element> = Vec::new();
+    for line in text.lines().skip(1) {
+        let tokens = line.split_whitespace().collect::<Vec<_>>();
+        match tokens[..] {
+            ["format", format, _version] => {
+                encoding = Some(match format {
+                    "ascii" => Encoding::Ascii,
+                    "binary_little_endian" => Encoding::BinaryLittleEndian,
+                    "binary_big_endian" => Encoding::BinaryBigEndian,
+                    other => bail!("Unknown PLY format '{}'", other),
+                });
+            }
+            ["element", name, count] => elements.push(Element {
+                name: name.to_string(),
+                count: count
+                    .parse()
+                    .with_context(|| format!("Invalid element count '{}'", count))?,
+                properties: Vec::new(),
+            }),
+            ["property", "list", count, item, name] => elements
+                .last_mut()
+                .context("Property declared before any element")?
+                .properties
+                .push(Property {
+                    name: name.to_string(),
+                    kind: PropertyKind::List {
+                        count: ScalarType::parse(count)?,
+                        item: ScalarType::parse(item)?,
+                    },
+                }),
+            ["property", ty, name] => elements
+                .last_mut()
+                .context("Property declared before any element")?
+                .properties
+                .push(Property {
+                    name: name.to_string(),
+                    kind: PropertyKind::Scalar(ScalarType::parse(ty)?),
+                }),
+            _ => {}
+        }
+    }
+
+    Ok((
+        Header {
+            encoding: encoding.context("Missing 'format' line")?,
+            elements,
+        },
+        &bytes[body_start..],
+    ))
+}
+
+enum ValueReader<'a> {
+    Ascii(std::str::SplitAsciiWhitespace<'a>),
+    Binary {
+        data: &'a [u8],
+        offset: usize,
+        big_endian: bool,
+    },
+}
+
+impl ValueReader<'_> {
+    fn read(&mut self, ty: ScalarType) -> Result<f64> {
+        match self {
+            ValueReader::Ascii(tokens) => {
+                let token = tokens.next().context("Unexpected end of PLY data")?;
+                token
+                    .parse::<f64>()
+                    .with_context(|| format!("Invalid PLY value '{}'", token))
+            }
+            ValueReader::Binary {
+                data,
+                offset,
+                big_endian,
+            } => {
+                let bytes = data
+                    .get(*offset..*offset + ty.size())
+                    .context("Unexpected end of PLY data")?;
+                *offset += ty.size();
+                let mut buffer = [0u8; 8];
+                buffer[..bytes.len()].copy_from_slice(bytes);
+                if *big_endian {
+                    buffer[..bytes.len()].reverse();
+                }
+                Ok(match ty {
+                    ScalarType::I8 => buffer[0] as i8 as f64,
+                    ScalarType::U8 => buffer[0] as f64,
+                    ScalarType::I16 => i16::from_le_bytes([buffer[0], buffer[1]]) as f64,
+                    ScalarType::U16 => u16::from_le_bytes([buffer[0], buffer[1]]) as f64,
+                    ScalarType::I32 => {
+                        i32::from_le_bytes([buffer[0], buffer[1], buffer[2], buffer[3]]) as f64
+                    }
+                    ScalarType::U32 => {
+                        u32::from_le_bytes([buffer[0], buffer[1], buffer[2], buffer[3]]) as f64
+                    }
+                    ScalarType::F32 => {
+                        f32::from_le_bytes([buffer[0], buffer[1], buffer[2], buffer[3]]) as f64
+                    }
+                    ScalarType::F64 => f64::from_le_bytes(buffer),
+                })
+            }
+        }
+    }
+
+    fn read_property(&mut self, property: &Property) -> Result<Vec<f64>> {
+        match property.kind {
+            PropertyKind::Scalar(ty) => Ok(vec![self.read(ty)?]),
+            PropertyKind::List { count, item } => {
+                let count = self.read(count)? as usize;
+                (0..count).map(|_| self.read(item)).collect()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ASCII_QUAD: &str = "ply
+format ascii 1.0
+comment the end_header line comes after the elements
+element vertex 4
+property float x
+property float y
+property float z
+property uchar red
+property uchar green
+property uchar blue
+element face 1
+property list uchar int vertex_indices
+end_header
+0 0 0 255 0 0
+1 0 0 0 255 0
+1 1 0 0 0 255
+0 1 0 255 255 255
+4 0 1 2 3
+";
+
+    fn binary_triangle(big_endian: bool) -> Vec<u8> {
+        let format = if big_endian { "big" } else { "little" };
+        let mut bytes = format!(
+            "ply\nformat binary_{}_endian 1.0\nelement vertex 3\nproperty float x\n\
+             property float y\nproperty float z\nelement face 1\n\
+             property list uchar uint vertex_indices\nend_header\n",
+            format
+        )
+        .into_bytes();
+        let order = |mut value: [u8; 4]| {
+            if big_endian {
+                value.reverse();
+            }
+            value
+        };
+        for position in [[0.0f32, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]] {
+            for value in position {
+                bytes.extend(order(value.to_le_bytes()));
+            }
+        }
+        bytes.push(3);
+        for index in [0u32, 1, 2] {
+            bytes.extend(order(index.to_le_bytes()));
+        }
+        bytes
+    }
+
+    #[test]
+    fn reads_ascii_faces_and_colors() {
+        let mesh = parse("quad", ASCII_QUAD.as_bytes()).unwrap();
+        assert_eq!(mesh.topology, PrimitiveTopology::Triangles);
+        assert_eq!(mesh.vertex_count(), 4);
+        assert_eq!(mesh.triangle_count(), 2);
+        assert_eq!(mesh.colors[1], Vec4::new(0.0, 1.0, 0.0, 1.0));
+        assert_eq!(mesh.normals.len(), 4);
+        assert!(mesh.normals.iter().all(|normal| normal.z > 0.99));
+    }
+
+    #[test]
+    fn reads_binary_in_both_byte_orders() {
+        for big_endian in [false, true] {
+            let mesh = parse("triangle", &binary_triangle(big_endian)).unwrap();
+            assert_eq!(mesh.positions[1], Vec3::new(1.0, 0.0, 0.0));
+            assert_eq!(mesh.indices.as_ref().unwrap().to_u32(), [0, 1, 2]);
+        }
+    }
+
+    #[test]
+    fn vertices_without_faces_are_points() {
+        let text = "ply\nformat ascii 1.0\nelement vertex 2\nproperty float x\n\
+                    property float y\nproperty float z\nend_header\n0 0 0\n1 2 3\n";
+        let mesh = parse("cloud", text.as_bytes()).unwrap();
+        assert_eq!(mesh.topology, PrimitiveTopology::Points);
+        assert_eq!(mesh.submeshes[0].index_count, 2);
+    }
+
+    #[test]
+    fn rejects_invalid_face_indices() {
+        for face in ["4 0 1 2 -1", "4 0 1 2 1.5", "4 0 1 2 4"] {
+            let text = ASCII_QUAD.replace("4 0 1 2 3", face);
+            let error = parse("quad", text.as_bytes()).unwrap_err();
+            assert!(
+                format!("{}", error).contains("index"),
+                "{}: {}",
+                face,
+                error
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_truncated_files() {
+        let bytes = binary_triangle(false);
+        let error = parse("triangle", &bytes[..bytes.len() - 2]).unwrap_err();
+        assert!(format!("{}", error).contains("Unexpected end"), "{}", error);
+
+        let text = ASCII_QUAD.trim_end().trim_end_matches("4 0 1 2 3");
+        assert!(parse("quad", text.as_bytes()).is_err());
+
+        let header_only = &ASCII_QUAD[..ASCII_QUAD.find("end_header\n").unwrap()];
+        let error = parse("quad", header_only.as_bytes()).unwrap_err();
+        assert!(format!("{}", error).contains("end_header"), "{}", error);
+        assert!(parse("quad", b"solid cube").is_err());
+    }
+}
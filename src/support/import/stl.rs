@@ -0,0 +1,216 @@
+use crate::support::import::{ImportedNode, ImportedScene};
+use crate::support::mesh::{Mesh, PrimitiveTopology};
+use anyhow::{Context, Result, bail};
+use nalgebra_glm::Vec3;
+use std::fs;
+use std::path::Path;
+
+const HEADER_SIZE: usize = 80;
+const TRIANGLE_SIZE: usize = 50;
+
+pub fn import(path: &Path) -> Result<ImportedScene> {
+    let bytes =
+        fs::read(path).with_context(|| format!("Failed to read STL file '{}'", path.display()))?;
+    let name = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let mesh = parse(&name, &bytes)
+        .with_context(|| format!("Failed to parse STL file '{}'", path.display()))?;
+
+    log::info!(
+        "Imported '{}': {} triangles",
+        path.display(),
+        mesh.triangle_count()
+    );
+
+    Ok(ImportedScene {
+        name: name.clone(),
+        source: path.to_path_buf(),
+        meshes: vec![mesh],
+        nodes: vec![ImportedNode {
+            name,
+            meshes: vec![0],
+            ..Default::default()
+        }],
+        roots: vec![0],
+        ..Default::default()
+    })
+}
+
+pub fn parse(name: &str, bytes: &[u8]) -> Result<Mesh> {
+    let triangles = if is_binary(bytes) {
+        parse_binary(bytes)?
+    } else {
+        let text = std::str::from_utf8(bytes).context("ASCII STL is not valid UTF-8")?;
+        parse_ascii(text)?
+    };
+
+    let mut mesh = Mesh::new(name, PrimitiveTopology::Triangles);
+    for (file_normal, corners) in triangles {
+        let normal = (corners[1] - corners[0])
+            .cross(&(corners[2] - corners[0]))
+            .try_normalize(f32::EPSILON)
+            .or_else(|| file_normal.try_normalize(f32::EPSILON))
+            .unwrap_or_else(Vec3::y);
+        for corner in corners {
+            mesh.positions.push(corner);
+            mesh.normals.push(normal);
+        }
+    }
+    mesh.compute_bounds();
+    Ok(mesh)
+}
+
+fn is_binary(bytes: &[u8]) -> bool {
+    if bytes.len() < HEADER_SIZE + 4 {
+        return false;
+    }
+    let count = u32::from_le_bytes([bytes[80], bytes[81], bytes[82], bytes[83]]) as usize;
+    let expected = count
+        .checked_mul(TRIANGLE_SIZE)
+        .and_then(|size| size.checked_add(HEADER_SIZE + 4));
+    expected == Some(bytes.len()) || !bytes.trim_ascii_start().starts_with(b"solid")
+}
+
+fn parse_binary(bytes: &[u8]) -> Result<Vec<(Vec3, [Vec3; 3])>> {
+    if bytes.len() < HEADER_SIZE + 4 {
+        bail!("Binary STL is truncated: {} bytes", bytes.len());
+    }
+    let count = u32::from_le_bytes([bytes[80], bytes[81], bytes[82], bytes[83]]) as usize;
+    let data = &bytes[HEADER_SIZE + 4..];
+    if data.len() < count * TRIANGLE_SIZE {
+        bail!(
+            "Binary STL declares {} triangles but only contains {} bytes of triangle data",
+            count,
+            data.len()
+        );
+    }
+
+    let vector = |chunk: &[u8]| {
+        let value =
+            |index: usize| f32::from_le_bytes(chunk[index * 4..index * 4 + 4].try_into().unwrap());
+        Vec3::new(value(0), value(1), value(2))
+    };
+    Ok(data
+        .chunks_exact(TRIANGLE_SIZE)
+        .take(count)
+        .map(|triangle| {
+            (
+                vector(&triangle[0..12]),
+                [
+                    vector(&triangle[12..24]),
+                    vector(&triangle[24..36]),
+                    vector(&triangle[36..48]),
+                ],
+            )
+        })
+        .collect())
+}
+
+fn parse_ascii(text: &str) -> Result<Vec<(Vec3, [Vec3; 3])>> {
+    let vector = |tokens: &[&str], line: usize| -> Result<Vec3> {
+        let values = tokens
+            .iter()
+            .map(|token| token.parse::<f32>())
+            .collect::<Result<Vec<_>, _>>()
+            .with_context(|| format!("Line {}: invalid number", line))?;
+        match values[..] {
+            [x, y, z] => Ok(Vec3::new(x, y, z)),
+            _ => bail!("Line {}: expected 3 components", line),
+        }
+    };
+
+    let mut triangles = Vec::new();
+    let mut normal = Vec3::zeros();
+    let mut corners = Vec::with_capacity(3);
+    for (index, line) in text.lines().enumerate() {
+        let tokens = line.split_whitespace().collect::<Vec<_>>();
+        match tokens[..] {
+            ["facet", "normal", ..] => {
+                normal = vector(&tokens[2..], index + 1)?;
+                corners.clear();
+            }
+            ["vertex", ..] => corners.push(vector(&tokens[1..], index + 1)?),
+            ["endfacet"] => {
+                if corners.len() != 3 {
+                    bail!(
+                        "Line {}: facet has {} vertices, expected 3",
+                        index + 1,
+                        corners.len()
+                    );
+                }
+                triangles.push((normal, [corners[0], corners[1], corners[2]]));
+                corners.clear();
+            }
+            _ => {}
+        }
+    }
+    Ok(triangles)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ASCII_TRIANGLE: &str = "solid triangle
+  facet normal 0 0 1
+    outer loop
+      vertex 0 0 0
+      vertex 1 0 0
+      vertex 0 1 0
+    endloop
+  endfacet
+endsolid triangle
+";
+
+    fn binary(header: &[u8], triangles: &[[f32; 12]]) -> Vec<u8> {
+        let mut bytes = header.to_vec();
+        bytes.resize(HEADER_SIZE, 0);
+        bytes.extend((triangles.len() as u32).to_le_bytes());
+        for triangle in triangles {
+            for value in triangle {
+                bytes.extend(value.to_le_bytes());
+            }
+            bytes.extend([0, 0]);
+        }
+        bytes
+    }
+
+    const CLOCKWISE: [f32; 12] = [0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 1.0, 0.0, 0.0];
+
+    #[test]
+    fn reads_ascii_facets() {
+        let mesh = parse("triangle", ASCII_TRIANGLE.as_bytes()).unwrap();
+        assert_eq!(mesh.positions[1], Vec3::new(1.0, 0.0, 0.0));
+        assert_eq!(mesh.normals, [Vec3::z(); 3]);
+    }
+
+    #[test]
+    fn reads_binary_even_with_a_solid_header() {
+        // Exporters often start binary headers with "solid" too; the size decides.
+        let bytes = binary(b"solid exported", &[CLOCKWISE, CLOCKWISE]);
+        let mesh = parse("triangles", &bytes).unwrap();
+        assert_eq!(mesh.triangle_count(), 2);
+        assert_eq!(mesh.positions[1], Vec3::new(0.0, 1.0, 0.0));
+        // Normals follow the winding rather than the stored normal.
+        assert_eq!(mesh.normals[0], -Vec3::z());
+    }
+
+    #[test]
+    fn rejects_truncated_files() {
+        let bytes = binary(b"binary", &[CLOCKWISE, CLOCKWISE]);
+        let error = parse("triangles", &bytes[..bytes.len() - 10]).unwrap_err();
+        assert!(format!("{}", error).contains("declares 2"), "{}", error);
+
+        let text = ASCII_TRIANGLE.replace("      vertex 0 1 0\n", "");
+        let error = parse("triangle", text.as_bytes()).unwrap_err();
+        assert!(
+            format!("{}", error).contains("facet has 2 vertices"),
+            "{}",
+            error
+        );
+        let text = ASCII_TRIANGLE.replace("vertex 1 0 0", "vertex 1 0");
+        assert!(parse("triangle", text.as_bytes()).is_err());
+    }
+}
@@ -0,0 +1,338 @@
+use crate::support::material::Material;
+use crate::support::mesh::GpuMesh;
+use crate::support::reflect::{Reflect, Visitor};
+use crate::support::scene_graph::{NodeId, SceneGraph};
+use crate::support::texture::Texture2D;
+use crate::support::transform::Transform;
+use nalgebra_glm::{Quat, Vec3, Vec4};
+use std::hash::Hash;
+use std::ops::RangeInclusive;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RotationMode {
+    #[default]
+    Euler,
+    Quaternion,
+}
+
+#[derive(Debug, Default)]
+pub struct InspectorPanel {
+    rotation_mode: RotationMode,
+    euler: Option<(Quat, Vec3)>,
+}
+
+impl InspectorPanel {
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        graph: &mut SceneGraph,
+        selection: &[NodeId],
+        meshes: &mut [GpuMesh],
+        materials: &mut [Material],
+        textures: &[Option<Texture2D>],
+    ) -> bool {
+        let Some(id) = selection.last().copied() else {
+            ui.label("No node selected");
+            return false;
+        };
+        let Some(node) = graph.get_mut(id) else {
+            ui.label("No node selected");
+            return false;
+        };
+        if selection.len() > 1 {
+            ui.label(format!(
+                "{} nodes selected, editing '{}'",
+                selection.len(),
+                node.name
+            ));
+        }
+
+        let mut changed = false;
+        egui::ScrollArea::vertical()
+            .auto_shrink([false, false])
+            .show(ui, |ui| {
+                changed |= self.section(ui, "Node", "Node", |visitor| {
+                    visitor.label("Id", &id.to_string());
+                    visitor.string("Name", &mut node.name);
+                    visitor.bool("Visible", &mut node.visible);
+                });
+
+                let mut transform = *node.transform();
+                if self.section(ui, "Transform", "Transform", |visitor| {
+                    transform.reflect(visitor)
+                }) {
+                    node.set_transform(transform);
+                    changed = true;
+                }
+                ui.horizontal(|ui| {
+                    ui.label("Rotation as");
+                    ui.selectable_value(&mut self.rotation_mode, RotationMode::Euler, "Euler");
+                    ui.selectable_value(
+                        &mut self.rotation_mode,
+                        RotationMode::Quaternion,
+                        "Quaternion",
+                    );
+                });
+
+                for component in node.components_mut() {
+                    changed |= self.section(
+                        ui,
+                        component.type_name(),
+                        component.type_name(),
+                        |visitor| component.reflect(visitor),
+                    );
+                }
+
+                let Some(mesh) = node.mesh.and_then(|mesh| meshes.get_mut(mesh)) else {
+                    return;
+                };
+                self.section(ui, "Mesh", "Mesh", |visitor| mesh.reflect(visitor));
+                let mut shown = Vec::new();
+                for material in mesh.submeshes.iter().filter_map(|submesh| submesh.material) {
+                    if shown.contains(&material) {
+                        continue;
+                    }
+                    shown.push(material);
+                    let id = ("Material", material);
+                    let Some(material) = materials.get_mut(material) else {
+                        continue;
+                    };
+                    // Keyed by index so renaming the material keeps the section and its focus.
+                    let title = format!("Material '{}'", material.name);
+                    changed |= self.section(ui, &title, id, |visitor| material.reflect(visitor));
+                    texture_thumbnails(ui, material, textures);
+                }
+            });
+        changed
+    }
+
+    fn section(
+        &mut self,
+        ui: &mut egui::Ui,
+        title: &str,
+        id: impl Hash,
+        contents: impl FnOnce(&mut dyn Visitor),
+    ) -> bool {
+        let mut changed = false;
+        let id = egui::Id::new(id);
+        egui::CollapsingHeader::new(title)
+            .id_salt(id)
+            .default_open(true)
+            .show(ui, |ui| {
+                egui::Grid::new(id.with("grid"))
+                    .num_columns(2)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        let mut visitor = UiVisitor {
+                            ui,
+                            changed: false,
+                            rotation_mode: self.rotation_mode,
+                            euler: &mut self.euler,
+                        };
+                        contents(&mut visitor);
+                        changed = visitor.changed;
+                    });
+            });
+        changed
+    }
+}
+
+struct UiVisitor<'a> {
+    ui: &'a mut egui::Ui,
+    changed: bool,
+    rotation_mode: RotationMode,
+    euler: &'a mut Option<(Quat, Vec3)>,
+}
+
+impl UiVisitor<'_> {
+    fn row(&mut self, name: &str, add_contents: impl FnOnce(&mut egui::Ui) -> bool) {
+        self.ui.label(name);
+        let changed = self
+            .ui
+            .horizontal(|ui| ui.push_id(name, |ui| add_contents(ui)).inner)
+            .inner;
+        self.ui.end_row();
+        self.changed |= changed;
+    }
+}
+
+fn drag(value: &mut f32, speed: f32) -> egui::DragValue<'_> {
+    egui::DragValue::new(value).speed(speed).max_decimals(4)
+}
+
+fn range_speed(range: &RangeInclusive<f32>) -> f32 {
+    ((range.end() - range.start()) / 1000.0).clamp(0.001, 0.1)
+}
+
+impl Visitor for UiVisitor<'_> {
+    fn label(&mut self, name: &str, value: &str) {
+        self.row(name, |ui| {
+            ui.label(value);
+            false
+        });
+    }
+
+    fn bool(&mut self, name: &str, value: &mut bool) {
+        self.row(name, |ui| ui.checkbox(value, "").changed());
+    }
+
+    fn f32(&mut self, name: &str, value: &mut f32, range: RangeInclusive<f32>) {
+        let speed = range_speed(&range);
+        self.row(name, |ui| ui.add(drag(value, speed).range(range)).changed());
+    }
+
+    fn angle(&mut self, name: &str, radians: &mut f32, range: RangeInclusive<f32>) {
+        let mut degrees = radians.to_degrees();
+        let range = range.start().to_degrees()..=range.end().to_degrees();
+        self.row(name, |ui| {
+            let changed = ui
+                .add(drag(&mut degrees, 0.5).range(range).suffix("°"))
+                .changed();
+            if changed {
+                *radians = degrees.to_radians();
+            }
+            changed
+        });
+    }
+
+    fn optional_f32(
+        &mut self,
+        name: &str,
+        value: &mut Option<f32>,
+        default: f32,
+        range: RangeInclusive<f32>,
+    ) {
+        let speed = range_speed(&range);
+        self.row(name, |ui| {
+            let mut infinite = value.is_none();
+            let mut changed = ui.checkbox(&mut infinite, "Infinite").changed();
+            if changed {
+                *value = if infinite { None } else { Some(default) };
+            }
+            if let Some(value) = value {
+                changed |= ui.add(drag(value, speed).range(range)).changed();
+            }
+            changed
+        });
+    }
+
+    fn string(&mut self, name: &str, value: &mut String) {
+        self.row(name, |ui| ui.text_edit_singleline(value).changed());
+    }
+
+    fn vec3(&mut self, name: &str, value: &mut Vec3) {
+        self.row(name, |ui| {
+            let mut changed = false;
+            for (component, prefix) in value.iter_mut().zip(["X ", "Y ", "Z "]) {
+                changed |= ui.add(drag(component, 0.01).prefix(prefix)).changed();
+            }
+            changed
+        });
+    }
+
+    fn rotation(&mut self, name: &str, value: &mut Quat) {
+        match self.rotation_mode {
+            RotationMode::Euler => {
+                // Re-deriving the angles every frame would snap them to a canonical range
+                // while dragging, so keep the user's angles until the rotation changes elsewhere.
+                let mut degrees = match *self.euler {
+                    Some((rotation, degrees)) if rotation == *value => degrees,
+                    _ => Transform {
+                        rotation: *value,
+                        ..Transform::IDENTITY
+                    }
+                    .euler_degrees(),
+                };
+                self.row(name, |ui| {
+                    let mut changed = false;
+                    for (component, prefix) in degrees.iter_mut().zip(["X ", "Y ", "Z "]) {
+                        changed |= ui
+                            .add(drag(component, 0.5).prefix(prefix).suffix("°"))
+                            .changed();
+                    }
+                    if changed {
+                        let mut transform = Transform::IDENTITY;
+                        transform.set_euler_degrees(&degrees);
+                        *value = transform.rotation;
+                    }
+                    changed
+                });
+                *self.euler = Some((*value, degrees));
+            }
+            RotationMode::Quaternion => self.row(name, |ui| {
+                let mut changed = false;
+                for (component, prefix) in value.coords.iter_mut().zip(["X ", "Y ", "Z ", "W "]) {
+                    changed |= ui
+                        .add(drag(component, 0.01).prefix(prefix).range(-1.0..=1.0))
+                        .changed();
+                }
+                if changed {
+                    *value = nalgebra_glm::quat_normalize(value);
+                }
+                changed
+            }),
+        }
+    }
+
+    fn color3(&mut self, name: &str, value: &mut Vec3) {
+        self.row(name, |ui| {
+            let mut color = [value.x, value.y, value.z];
+            let changed = ui.color_edit_button_rgb(&mut color).changed();
+            if changed {
+                *value = Vec3::from(color);
+            }
+            changed
+        });
+    }
+
+    fn color4(&mut self, name: &str, value: &mut Vec4) {
+        self.row(name, |ui| {
+            let mut color = [value.x, value.y, value.z, value.w];
+            let changed = ui.color_edit_button_rgba_unmultiplied(&mut color).changed();
+            if changed {
+                *value = Vec4::from(color);
+            }
+            changed
+        });
+    }
+
+    fn choice(&mut self, name: &str, selected: &mut usize, options: &[&str]) {
+        self.row(name, |ui| {
+            let previous = *selected;
+            egui::ComboBox::from_id_salt(name)
+                .selected_text(options.get(*selected).copied().unwrap_or_default())
+                .show_ui(ui, |ui| {
+                    for (index, option) in options.iter().enumerate() {
+                        ui.selectable_value(selected, index, *option);
+                    }
+                });
+            *selected != previous
+        });
+    }
+}
+
+const THUMBNAIL_SIZE: f32 = 64.0;
+
+fn texture_thumbnails(ui: &mut egui::Ui, material: &Material, textures: &[Option<Texture2D>]) {
+    let textures: Vec<_> = material
+        .textures()
+        .into_iter()
+        .filter_map(|(slot, texture)| Some((slot, textures.get(texture.texture)?.as_ref()?)))
+        .collect();
+    if textures.is_empty() {
+        return;
+    }
+    ui.horizontal_wrapped(|ui| {
+        for (slot, texture) in textures {
+            let size = egui::vec2(THUMBNAIL_SIZE, THUMBNAIL_SIZE);
+            ui.image((texture.egui_id(), size)).on_hover_text(format!(
+                "{}: '{}' ({}x{}, {} levels)",
+                slot,
+                texture.name(),
+                texture.width(),
+                texture.height(),
+                texture.levels()
+            ));
+        }
+    });
+}
@@ -0,0 +1,44 @@
+use nalgebra_glm::Vec3;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum LightKind {
+    Directional,
+    Point,
+    Spot {
+        inner_cone_angle: f32,
+        outer_cone_angle: f32,
+    },
+}
+
+impl LightKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            LightKind::Directional => "Directional",
+            LightKind::Point => "Point",
+            LightKind::Spot { .. } => "Spot",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Light {
+    pub name: String,
+    pub kind: LightKind,
+    pub color: Vec3,
+    pub intensity: f32,
+    pub range: Option<f32>,
+}
+
+impl Default for Light {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            kind: LightKind::Directional,
+            color: Vec3::new(1.0, 1.0, 1.0),
+            intensity: 1.0,
+            range: None,
+        }
+    }
+}
@@ -0,0 +1,273 @@
+use anyhow::{Context, Result};
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use std::collections::VecDeque;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+const CAPACITY: usize = 10_000;
+
+static BUFFER: OnceLock<LogBuffer> = OnceLock::new();
+
+#[derive(Clone, Debug)]
+pub struct LogRecord {
+    pub sequence: u64,
+    pub level: Level,
+    pub target: String,
+    pub message: String,
+    pub time: Duration,
+}
+
+impl LogRecord {
+    pub fn format(&self) -> String {
+        format!(
+            "[{:>9.3}s {:<5} {}] {}",
+            self.time.as_secs_f64(),
+            self.level,
+            self.target,
+            self.message
+        )
+    }
+}
+
+#[derive(Debug)]
+pub struct LogBuffer {
+    start: Instant,
+    capacity: usize,
+    state: Mutex<BufferState>,
+}
+
+#[derive(Debug, Default)]
+struct BufferState {
+    records: VecDeque<LogRecord>,
+    next_sequence: u64,
+}
+
+impl LogBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            start: Instant::now(),
+            capacity,
+            state: Mutex::new(BufferState::default()),
+        }
+    }
+
+    pub fn push(&self, level: Level, target: &str, message: String) {
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        let sequence = state.next_sequence;
+        state.next_sequence += 1;
+        if state.records.len() == self.capacity {
+            state.records.pop_front();
+        }
+        state.records.push_back(LogRecord {
+            sequence,
+            level,
+            target: target.to_string(),
+            message,
+            time: self.start.elapsed(),
+        });
+    }
+
+    pub fn records_since(&self, sequence: u64) -> Vec<LogRecord> {
+        let Ok(state) = self.state.lock() else {
+            return Vec::new();
+        };
+        let first = state
+            .records
+            .partition_point(|record| record.sequence < sequence);
+        state.records.range(first..).cloned().collect()
+    }
+
+    pub fn clear(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            state.records.clear();
+        }
+    }
+}
+
+struct ConsoleLogger {
+    stderr: env_logger::Logger,
+    buffer: &'static LogBuffer,
+    level: LevelFilter,
+}
+
+impl Log for ConsoleLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level || self.stderr.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if self.stderr.matches(record) {
+            self.stderr.log(record);
+        }
+        if record.level() <= self.level {
+            self.buffer
+                .push(record.level(), record.target(), record.args().to_string());
+        }
+    }
+
+    fn flush(&self) {
+        self.stderr.flush();
+    }
+}
+
+/// Installs a logger that writes to stderr as configured by `RUST_LOG` and also keeps
+/// the most recent records up to `level` for the in-app console.
+pub fn init(level: LevelFilter) -> Result<&'static LogBuffer> {
+    let buffer = BUFFER.get_or_init(|| LogBuffer::new(CAPACITY));
+    let stderr = env_logger::Builder::from_default_env().build();
+    log::set_max_level(stderr.filter().max(level));
+    log::set_boxed_logger(Box::new(ConsoleLogger {
+        stderr,
+        buffer,
+        level,
+    }))
+    .context("Failed to install logger")?;
+    Ok(buffer)
+}
+
+pub fn buffer() -> Option<&'static LogBuffer> {
+    BUFFER.get()
+}
+
+#[derive(Debug)]
+pub struct LogConsole {
+    records: VecDeque<LogRecord>,
+    next_sequence: u64,
+    level: LevelFilter,
+    target_filter: String,
+    search: String,
+    auto_scroll: bool,
+}
+
+impl Default for LogConsole {
+    fn default() -> Self {
+        Self {
+            records: VecDeque::new(),
+            next_sequence: 0,
+            level: LevelFilter::Info,
+            target_filter: String::new(),
+            search: String::new(),
+            auto_scroll: true,
+        }
+    }
+}
+
+impl LogConsole {
+    pub fn clear(&mut self) {
+        self.records.clear();
+        if let Some(buffer) = buffer() {
+            buffer.clear();
+        }
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui) {
+        self.poll();
+
+        let header = ui.horizontal(|ui| {
+            egui::ComboBox::from_id_salt("log_level")
+                .selected_text(self.level.as_str())
+                .show_ui(ui, |ui| {
+                    for level in LevelFilter::iter().skip(1) {
+                        ui.selectable_value(&mut self.level, level, level.as_str());
+                    }
+                });
+            ui.label("Target:");
+            ui.add(egui::TextEdit::singleline(&mut self.target_filter).desired_width(120.0));
+            ui.label("Search:");
+            ui.add(egui::TextEdit::singleline(&mut self.search).desired_width(160.0));
+            ui.checkbox(&mut self.auto_scroll, "Auto-scroll");
+
+            let visible = self.filtered();
+            if ui.button("Copy").clicked() {
+                let text = visible
+                    .iter()
+                    .map(|record| record.format())
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                ui.ctx().copy_text(text);
+            }
+            let clear = ui.button("Clear").clicked();
+            ui.label(format!("{} / {}", visible.len(), self.records.len()));
+            clear
+        });
+        if header.inner {
+            self.clear();
+        }
+        ui.separator();
+
+        let lines = self
+            .filtered()
+            .into_iter()
+            .flat_map(|record| {
+                record
+                    .message
+                    .lines()
+                    .enumerate()
+                    .map(move |(index, line)| (record, index == 0, line))
+            })
+            .collect::<Vec<_>>();
+        let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+        egui::ScrollArea::both()
+            .auto_shrink([false, false])
+            .stick_to_bottom(self.auto_scroll)
+            .show_rows(ui, row_height, lines.len(), |ui, rows| {
+                for (record, first, line) in &lines[rows] {
+                    ui.horizontal(|ui| {
+                        let (time, level, target) = if *first {
+                            (
+                                format!("{:>9.3}", record.time.as_secs_f64()),
+                                format!("{:<5}", record.level),
+                                record.target.as_str(),
+                            )
+                        } else {
+                            (" ".repeat(9), " ".repeat(5), "")
+                        };
+                        ui.label(egui::RichText::new(time).monospace().weak());
+                        ui.label(
+                            egui::RichText::new(level)
+                                .monospace()
+                                .color(level_color(record.level)),
+                        );
+                        ui.label(egui::RichText::new(target).monospace().weak());
+                        ui.label(egui::RichText::new(*line).monospace());
+                    });
+                }
+            });
+    }
+
+    fn poll(&mut self) {
+        let Some(buffer) = buffer() else {
+            return;
+        };
+        for record in buffer.records_since(self.next_sequence) {
+            self.next_sequence = record.sequence + 1;
+            if self.records.len() == CAPACITY {
+                self.records.pop_front();
+            }
+            self.records.push_back(record);
+        }
+    }
+
+    fn filtered(&self) -> Vec<&LogRecord> {
+        let target = self.target_filter.trim().to_lowercase();
+        let search = self.search.trim().to_lowercase();
+        self.records
+            .iter()
+            .filter(|record| record.level <= self.level)
+            .filter(|record| target.is_empty() || record.target.to_lowercase().contains(&target))
+            .filter(|record| search.is_empty() || record.message.to_lowercase().contains(&search))
+            .collect()
+    }
+}
+
+fn level_color(level: Level) -> egui::Color32 {
+    match level {
+        Level::Error => egui::Color32::from_rgb(240, 80, 80),
+        Level::Warn => egui::Color32::from_rgb(230, 180, 60),
+        Level::Info => egui::Color32::from_rgb(120, 200, 120),
+        Level::Debug => egui::Color32::from_rgb(110, 160, 230),
+        Level::Trace => egui::Color32::GRAY,
+    }
+}
@@ -0,0 +1,95 @@
+use nalgebra_glm::{Vec3, Vec4};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum AlphaMode {
+    #[default]
+    Opaque,
+    Mask(f32),
+    Blend,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TextureRef {
+    pub texture: usize,
+    pub uv_set: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Material {
+    pub name: String,
+    pub base_color: Vec4,
+    pub base_color_texture: Option<TextureRef>,
+    pub metallic: f32,
+    pub roughness: f32,
+    pub metallic_roughness_texture: Option<TextureRef>,
+    pub normal_texture: Option<TextureRef>,
+    pub normal_scale: f32,
+    pub occlusion_texture: Option<TextureRef>,
+    pub occlusion_strength: f32,
+    pub emissive: Vec3,
+    pub emissive_texture: Option<TextureRef>,
+    pub alpha_mode: AlphaMode,
+    pub double_sided: bool,
+    pub unlit: bool,
+    pub point_size: f32,
+}
+
+impl Material {
+    /// The texture slots that are in use, with their display names.
+    pub fn textures(&self) -> Vec<(&'static str, TextureRef)> {
+        [
+            ("Base Color Texture", self.base_color_texture),
+            (
+                "Metallic Roughness Texture",
+                self.metallic_roughness_texture,
+            ),
+            ("Normal Texture", self.normal_texture),
+            ("Occlusion Texture", self.occlusion_texture),
+            ("Emissive Texture", self.emissive_texture),
+        ]
+        .into_iter()
+        .filter_map(|(name, texture)| Some((name, texture?)))
+        .collect()
+    }
+
+    /// Textures holding colors, which are stored in sRGB.
+    pub fn color_textures(&self) -> impl Iterator<Item = TextureRef> {
+        [self.base_color_texture, self.emissive_texture]
+            .into_iter()
+            .flatten()
+    }
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            base_color: Vec4::new(1.0, 1.0, 1.0, 1.0),
+            base_color_texture: None,
+            metallic: 1.0,
+            roughness: 1.0,
+            metallic_roughness_texture: None,
+            normal_texture: None,
+            normal_scale: 1.0,
+            occlusion_texture: None,
+            occlusion_strength: 1.0,
+            emissive: Vec3::zeros(),
+            emissive_texture: None,
+            alpha_mode: AlphaMode::Opaque,
+            double_sided: false,
+            unlit: false,
+            point_size: 1.0,
+        }
+    }
+}
+
+impl Material {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            ..Default::default()
+        }
+    }
+}
@@ -0,0 +1,869 @@
+use crate::support::resource::{Buffer, BufferTarget, BufferUsage, VertexArray};
+use crate::support::vertex::VertexLayout;
+use anyhow::{Result, bail};
+use gl::types::*;
+use nalgebra_glm::{Mat4, Vec2, Vec3, Vec4};
+use std::f32::consts::{PI, TAU};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PrimitiveTopology {
+    Points,
+    Lines,
+    LineStrip,
+    LineLoop,
+    #[default]
+    Triangles,
+    TriangleStrip,
+    TriangleFan,
+}
+
+impl PrimitiveTopology {
+    pub fn gl_mode(&self) -> GLenum {
+        match self {
+            PrimitiveTopology::Points => gl::POINTS,
+            PrimitiveTopology::Lines => gl::LINES,
+            PrimitiveTopology::LineStrip => gl::LINE_STRIP,
+            PrimitiveTopology::LineLoop => gl::LINE_LOOP,
+            PrimitiveTopology::Triangles => gl::TRIANGLES,
+            PrimitiveTopology::TriangleStrip => gl::TRIANGLE_STRIP,
+            PrimitiveTopology::TriangleFan => gl::TRIANGLE_FAN,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Indices {
+    U16(Vec<u16>),
+    U32(Vec<u32>),
+}
+
+impl Indices {
+    pub fn len(&self) -> usize {
+        match self {
+            Indices::U16(indices) => indices.len(),
+            Indices::U32(indices) => indices.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, index: usize) -> u32 {
+        match self {
+            Indices::U16(indices) => indices[index] as u32,
+            Indices::U32(indices) => indices[index],
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = u32> + '_ {
+        (0..self.len()).map(|index| self.get(index))
+    }
+
+    pub fn to_u32(&self) -> Vec<u32> {
+        self.iter().collect()
+    }
+
+    pub fn gl_type(&self) -> GLenum {
+        match self {
+            Indices::U16(_) => gl::UNSIGNED_SHORT,
+            Indices::U32(_) => gl::UNSIGNED_INT,
+        }
+    }
+
+    pub fn compact(indices: Vec<u32>) -> Self {
+        if indices.iter().all(|index| *index <= u16::MAX as u32) {
+            Indices::U16(indices.into_iter().map(|index| index as u16).collect())
+        } else {
+            Indices::U32(indices)
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BoundingBox {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Default for BoundingBox {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+impl BoundingBox {
+    pub fn empty() -> Self {
+        Self {
+            min: Vec3::repeat(f32::INFINITY),
+            max: Vec3::repeat(f32::NEG_INFINITY),
+        }
+    }
+
+    pub fn from_points<'a>(points: impl IntoIterator<Item = &'a Vec3>) -> Self {
+        let mut bounds = Self::empty();
+        for point in points {
+            bounds.extend(point);
+        }
+        bounds
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min.x > self.max.x || self.min.y > self.max.y || self.min.z > self.max.z
+    }
+
+    pub fn extend(&mut self, point: &Vec3) {
+        self.min = nalgebra_glm::min2(&self.min, point);
+        self.max = nalgebra_glm::max2(&self.max, point);
+    }
+
+    pub fn union(&self, other: &BoundingBox) -> BoundingBox {
+        BoundingBox {
+            min: nalgebra_glm::min2(&self.min, &other.min),
+            max: nalgebra_glm::max2(&self.max, &other.max),
+        }
+    }
+
+    pub fn center(&self) -> Vec3 {
+        (self.min + self.max) * 0.5
+    }
+
+    pub fn extents(&self) -> Vec3 {
+        self.max - self.min
+    }
+
+    pub fn radius(&self) -> f32 {
+        self.extents().norm() * 0.5
+    }
+
+    pub fn corners(&self) -> [Vec3; 8] {
+        let (min, max) = (self.min, self.max);
+        [
+            Vec3::new(min.x, min.y, min.z),
+            Vec3::new(max.x, min.y, min.z),
+            Vec3::new(min.x, max.y, min.z),
+            Vec3::new(max.x, max.y, min.z),
+            Vec3::new(min.x, min.y, max.z),
+            Vec3::new(max.x, min.y, max.z),
+            Vec3::new(min.x, max.y, max.z),
+            Vec3::new(max.x, max.y, max.z),
+        ]
+    }
+
+    pub fn transform(&self, matrix: &Mat4) -> BoundingBox {
+        if self.is_empty() {
+            return *self;
+        }
+        let corners = self
+            .corners()
+            .map(|corner| nalgebra_glm::vec4_to_vec3(&(matrix * corner.push(1.0))));
+        BoundingBox::from_points(corners.iter())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Submesh {
+    pub name: String,
+    pub index_offset: usize,
+    pub index_count: usize,
+    pub material: Option<usize>,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable, VertexLayout)]
+pub struct MeshVertex {
+    #[location = 0]
+    pub position: [f32; 3],
+    #[location = 1]
+    pub normal: [f32; 3],
+    #[location = 2]
+    pub tangent: [f32; 4],
+    #[location = 3]
+    pub uv: [f32; 2],
+    #[location = 4]
+    pub color: [f32; 4],
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Mesh {
+    pub name: String,
+    pub positions: Vec<Vec3>,
+    pub normals: Vec<Vec3>,
+    pub tangents: Vec<Vec4>,
+    pub uvs: Vec<Vec2>,
+    pub colors: Vec<Vec4>,
+    pub joints: Vec<[u16; 4]>,
+    pub weights: Vec<Vec4>,
+    pub indices: Option<Indices>,
+    pub topology: PrimitiveTopology,
+    pub submeshes: Vec<Submesh>,
+    pub bounds: BoundingBox,
+}
+
+impl Mesh {
+    pub fn new(name: &str, topology: PrimitiveTopology) -> Self {
+        Self {
+            name: name.to_string(),
+            topology,
+            ..Default::default()
+        }
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn element_count(&self) -> usize {
+        self.indices
+            .as_ref()
+            .map_or(self.vertex_count(), Indices::len)
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        match self.topology {
+            PrimitiveTopology::Triangles => self.element_count() / 3,
+            PrimitiveTopology::TriangleStrip | PrimitiveTopology::TriangleFan => {
+                self.element_count().saturating_sub(2)
+            }
+            _ => 0,
+        }
+    }
+
+    pub fn compute_bounds(&mut self) {
+        self.bounds = BoundingBox::from_points(self.positions.iter());
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        let vertex_count = self.vertex_count();
+        for (name, len) in [
+            ("normals", self.normals.len()),
+            ("tangents", self.tangents.len()),
+            ("uvs", self.uvs.len()),
+            ("colors", self.colors.len()),
+            ("joints", self.joints.len()),
+            ("weights", self.weights.len()),
+        ] {
+            if len != 0 && len != vertex_count {
+                bail!(
+                    "Mesh '{}' has {} {} but {} positions",
+                    self.name,
+                    len,
+                    name,
+                    vertex_count
+                );
+            }
+        }
+        if let Some(indices) = &self.indices
+            && let Some(index) = indices.iter().find(|index| *index as usize >= vertex_count)
+        {
+            bail!(
+                "Mesh '{}' index {} is out of range for {} vertices",
+                self.name,
+                index,
+                vertex_count
+            );
+        }
+        for submesh in &self.submeshes {
+            if submesh.index_offset + submesh.index_count > self.element_count() {
+                bail!(
+                    "Mesh '{}' submesh '{}' exceeds the element count",
+                    self.name,
+                    submesh.name
+                );
+            }
+        }
+        Ok(())
+    }
+
+    pub fn triangles(&self) -> Vec<[u32; 3]> {
+        let elements = match &self.indices {
+            Some(indices) => indices.to_u32(),
+            None => (0..self.vertex_count() as u32).collect(),
+        };
+        match self.topology {
+            PrimitiveTopology::Triangles => elements
+                .chunks_exact(3)
+                .map(|triangle| [triangle[0], triangle[1], triangle[2]])
+                .collect(),
+            PrimitiveTopology::TriangleStrip => (2..elements.len())
+                .map(|index| {
+                    if index % 2 == 0 {
+                        [elements[index - 2], elements[index - 1], elements[index]]
+                    } else {
+                        [elements[index - 1], elements[index - 2], elements[index]]
+                    }
+                })
+                .collect(),
+            PrimitiveTopology::TriangleFan => (2..elements.len())
+                .map(|index| [elements[0], elements[index - 1], elements[index]])
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    pub fn compute_smooth_normals(&mut self) {
+        let mut normals = vec![Vec3::zeros(); self.vertex_count()];
+        for [a, b, c] in self.triangles() {
+            let (a, b, c) = (a as usize, b as usize, c as usize);
+            let face_normal = (self.positions[b] - self.positions[a])
+                .cross(&(self.positions[c] - self.positions[a]));
+            normals[a] += face_normal;
+            normals[b] += face_normal;
+            normals[c] += face_normal;
+        }
+        self.normals = normals
+            .into_iter()
+            .map(|normal| normal.try_normalize(f32::EPSILON).unwrap_or_else(Vec3::y))
+            .collect();
+    }
+
+    pub fn compute_flat_normals(&mut self) {
+        let triangles = self.triangles();
+        if triangles.is_empty() {
+            return;
+        }
+
+        let mut mesh = Mesh::new(&self.name, PrimitiveTopology::Triangles);
+        for [a, b, c] in triangles {
+            let corners = [a as usize, b as usize, c as usize];
+            let face_normal = (self.positions[corners[1]] - self.positions[corners[0]])
+                .cross(&(self.positions[corners[2]] - self.positions[corners[0]]))
+                .try_normalize(f32::EPSILON)
+                .unwrap_or_else(Vec3::y);
+            for corner in corners {
+                mesh.positions.push(self.positions[corner]);
+                mesh.normals.push(face_normal);
+                if !self.uvs.is_empty() {
+                    mesh.uvs.push(self.uvs[corner]);
+                }
+                if !self.colors.is_empty() {
+                    mesh.colors.push(self.colors[corner]);
+                }
+                if !self.tangents.is_empty() {
+                    mesh.tangents.push(self.tangents[corner]);
+                }
+                if !self.joints.is_empty() {
+                    mesh.joints.push(self.joints[corner]);
+                }
+                if !self.weights.is_empty() {
+                    mesh.weights.push(self.weights[corner]);
+                }
+            }
+        }
+
+        mesh.submeshes = self.submeshes.clone();
+        mesh.bounds = self.bounds;
+        *self = mesh;
+    }
+
+    pub fn compute_tangents(&mut self) {
+        if self.uvs.len() != self.vertex_count() || self.normals.len() != self.vertex_count() {
+            return;
+        }
+
+        let mut tangents = vec![Vec3::zeros(); self.vertex_count()];
+        let mut bitangents = vec![Vec3::zeros(); self.vertex_count()];
+        for [a, b, c] in self.triangles() {
+            let (a, b, c) = (a as usize, b as usize, c as usize);
+            let edge1 = self.positions[b] - self.positions[a];
+            let edge2 = self.positions[c] - self.positions[a];
+            let delta1 = self.uvs[b] - self.uvs[a];
+            let delta2 = self.uvs[c] - self.uvs[a];
+            let determinant = delta1.x * delta2.y - delta2.x * delta1.y;
+            if determinant.abs() <= f32::EPSILON {
+                continue;
+            }
+            let inverse = 1.0 / determinant;
+            let tangent = (edge1 * delta2.y - edge2 * delta1.y) * inverse;
+            let bitangent = (edge2 * delta1.x - edge1 * delta2.x) * inverse;
+            for corner in [a, b, c] {
+                tangents[corner] += tangent;
+                bitangents[corner] += bitangent;
+            }
+        }
+
+        self.tangents = (0..self.vertex_count())
+            .map(|index| {
+                let normal = self.normals[index];
+                let tangent = tangents[index] - normal * normal.dot(&tangents[index]);
+                let tangent = tangent
+                    .try_normalize(f32::EPSILON)
+                    .unwrap_or_else(|| any_perpendicular(&normal));
+                let handedness = if normal.cross(&tangent).dot(&bitangents[index]) < 0.0 {
+                    -1.0
+                } else {
+                    1.0
+                };
+                tangent.push(handedness)
+            })
+            .collect();
+    }
+
+    pub fn vertices(&self) -> Vec<MeshVertex> {
+        (0..self.vertex_count())
+            .map(|index| MeshVertex {
+                position: self.positions[index].into(),
+                normal: self
+                    .normals
+                    .get(index)
+                    .copied()
+                    .unwrap_or_else(Vec3::zeros)
+                    .into(),
+                tangent: self
+                    .tangents
+                    .get(index)
+                    .copied()
+                    .unwrap_or_else(|| Vec4::new(1.0, 0.0, 0.0, 1.0))
+                    .into(),
+                uv: self
+                    .uvs
+                    .get(index)
+                    .copied()
+                    .unwrap_or_else(Vec2::zeros)
+                    .into(),
+                color: self
+                    .colors
+                    .get(index)
+                    .copied()
+                    .unwrap_or_else(|| Vec4::repeat(1.0))
+                    .into(),
+            })
+            .collect()
+    }
+
+    pub fn upload(&self) -> Result<GpuMesh> {
+        self.validate()?;
+        Ok(GpuMesh::new(self))
+    }
+
+    pub fn triangle() -> Self {
+        let mut mesh = Mesh::new("Triangle", PrimitiveTopology::Triangles);
+        mesh.positions = vec![
+            Vec3::new(1.0, -1.0, 0.0),
+            Vec3::new(-1.0, -1.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+        ];
+        mesh.normals = vec![Vec3::new(0.0, 0.0, -1.0); 3];
+        mesh.uvs = vec![
+            Vec2::new(1.0, 0.0),
+            Vec2::new(0.0, 0.0),
+            Vec2::new(0.5, 1.0),
+        ];
+        mesh.colors = vec![
+            Vec4::new(1.0, 0.0, 0.0, 1.0),
+            Vec4::new(0.0, 1.0, 0.0, 1.0),
+            Vec4::new(0.0, 0.0, 1.0, 1.0),
+        ];
+        mesh.indices = Some(Indices::U16(vec![0, 1, 2]));
+        mesh.finish()
+    }
+
+    pub fn cube(size: f32) -> Self {
+        let half = size * 0.5;
+        let faces = [
+            (Vec3::x(), Vec3::z(), Vec3::y()),
+            (-Vec3::x(), -Vec3::z(), Vec3::y()),
+            (Vec3::y(), Vec3::x(), Vec3::z()),
+            (-Vec3::y(), Vec3::x(), -Vec3::z()),
+            (Vec3::z(), -Vec3::x(), Vec3::y()),
+            (-Vec3::z(), Vec3::x(), Vec3::y()),
+        ];
+
+        let mut mesh = Mesh::new("Cube", PrimitiveTopology::Triangles);
+        let mut indices = Vec::with_capacity(36);
+        for (normal, right, up) in faces {
+            let base = mesh.positions.len() as u32;
+            for (u, v) in [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)] {
+                let offset = right * (u * 2.0 - 1.0) + up * (v * 2.0 - 1.0);
+                mesh.positions.push((normal + offset) * half);
+                mesh.normals.push(normal);
+                mesh.uvs.push(Vec2::new(u, v));
+            }
+            indices.extend_from_slice(&[base, base + 2, base + 1, base, base + 3, base + 2]);
+        }
+        mesh.indices = Some(Indices::compact(indices));
+        mesh.compute_tangents();
+        mesh.finish()
+    }
+
+    pub fn sphere(radius: f32, sectors: u32, stacks: u32) -> Self {
+        let sectors = sectors.max(3);
+        let stacks = stacks.max(2);
+        let mut mesh = Mesh::new("Sphere", PrimitiveTopology::Triangles);
+        for stack in 0..=stacks {
+            let v = stack as f32 / stacks as f32;
+            let phi = v * PI;
+            for sector in 0..=sectors {
+                let u = sector as f32 / sectors as f32;
+                let theta = u * TAU;
+                let normal = Vec3::new(phi.sin() * theta.cos(), phi.cos(), phi.sin() * theta.sin());
+                mesh.positions.push(normal * radius);
+                mesh.normals.push(normal);
+                mesh.uvs.push(Vec2::new(u, 1.0 - v));
+            }
+        }
+        // Stacks run from the north pole down, the opposite way to the torus rings.
+        mesh.indices = Some(Indices::compact(flip_winding(grid_indices(
+            sectors, stacks,
+        ))));
+        mesh.compute_tangents();
+        mesh.finish()
+    }
+
+    pub fn plane(width: f32, depth: f32, subdivisions: u32) -> Self {
+        let segments = subdivisions.max(1);
+        let mut mesh = Mesh::new("Plane", PrimitiveTopology::Triangles);
+        for row in 0..=segments {
+            let v = row as f32 / segments as f32;
+            for column in 0..=segments {
+                let u = column as f32 / segments as f32;
+                mesh.positions
+                    .push(Vec3::new((u - 0.5) * width, 0.0, (0.5 - v) * depth));
+                mesh.normals.push(Vec3::y());
+                mesh.uvs.push(Vec2::new(u, v));
+            }
+        }
+        mesh.indices = Some(Indices::compact(flip_winding(grid_indices(
+            segments, segments,
+        ))));
+        mesh.compute_tangents();
+        mesh.finish()
+    }
+
+    pub fn cylinder(radius: f32, height: f32, segments: u32) -> Self {
+        let segments = segments.max(3);
+        let half = height * 0.5;
+        let mut mesh = Mesh::new("Cylinder", PrimitiveTopology::Triangles);
+
+        for row in 0..=1 {
+            let v = row as f32;
+            for segment in 0..=segments {
+                let u = segment as f32 / segments as f32;
+                let theta = u * TAU;
+                let normal = Vec3::new(theta.cos(), 0.0, theta.sin());
+                mesh.positions
+                    .push(normal * radius + Vec3::new(0.0, v * height - half, 0.0));
+                mesh.normals.push(normal);
+                mesh.uvs.push(Vec2::new(u, v));
+            }
+        }
+        let mut indices = grid_indices(segments, 1);
+
+        for (y, normal) in [(half, Vec3::y()), (-half, -Vec3::y())] {
+            let center = mesh.positions.len() as u32;
+            mesh.positions.push(Vec3::new(0.0, y, 0.0));
+            mesh.normals.push(normal);
+            mesh.uvs.push(Vec2::new(0.5, 0.5));
+            for segment in 0..=segments {
+                let theta = segment as f32 / segments as f32 * TAU;
+                let (sin, cos) = theta.sin_cos();
+                mesh.positions
+                    .push(Vec3::new(cos * radius, y, sin * radius));
+                mesh.normals.push(normal);
+                mesh.uvs.push(Vec2::new(cos * 0.5 + 0.5, sin * 0.5 + 0.5));
+            }
+            for segment in 0..segments {
+                let current = center + 1 + segment;
+                if normal.y > 0.0 {
+                    indices.extend_from_slice(&[center, current + 1, current]);
+                } else {
+                    indices.extend_from_slice(&[center, current, current + 1]);
+                }
+            }
+        }
+
+        mesh.indices = Some(Indices::compact(indices));
+        mesh.compute_tangents();
+        mesh.finish()
+    }
+
+    pub fn torus(
+        major_radius: f32,
+        minor_radius: f32,
+        major_segments: u32,
+        minor_segments: u32,
+    ) -> Self {
+        let major_segments = major_segments.max(3);
+        let minor_segments = minor_segments.max(3);
+        let mut mesh = Mesh::new("Torus", PrimitiveTopology::Triangles);
+        for minor in 0..=minor_segments {
+            let v = minor as f32 / minor_segments as f32;
+            let phi = v * TAU;
+            for major in 0..=major_segments {
+                let u = major as f32 / major_segments as f32;
+                let theta = u * TAU;
+                let ring = Vec3::new(theta.cos(), 0.0, theta.sin());
+                let normal = ring * phi.cos() + Vec3::y() * phi.sin();
+                mesh.positions
+                    .push(ring * major_radius + normal * minor_radius);
+                mesh.normals.push(normal);
+                mesh.uvs.push(Vec2::new(u, v));
+            }
+        }
+        mesh.indices = Some(Indices::compact(grid_indices(
+            major_segments,
+            minor_segments,
+        )));
+        mesh.compute_tangents();
+        mesh.finish()
+    }
+
+    pub fn grid(size: f32, divisions: u32) -> Self {
+        let divisions = divisions.max(1);
+        let half = size * 0.5;
+        let step = size / divisions as f32;
+        let mut mesh = Mesh::new("Grid", PrimitiveTopology::Lines);
+        for line in 0..=divisions {
+            let offset = -half + line as f32 * step;
+            mesh.positions.push(Vec3::new(offset, 0.0, -half));
+            mesh.positions.push(Vec3::new(offset, 0.0, half));
+            mesh.positions.push(Vec3::new(-half, 0.0, offset));
+            mesh.positions.push(Vec3::new(half, 0.0, offset));
+        }
+        mesh.normals = vec![Vec3::y(); mesh.positions.len()];
+        mesh.colors = vec![Vec4::new(0.6, 0.6, 0.6, 1.0); mesh.positions.len()];
+        mesh.finish()
+    }
+
+    fn finish(mut self) -> Self {
+        self.compute_bounds();
+        self
+    }
+}
+
+fn grid_indices(columns: u32, rows: u32) -> Vec<u32> {
+    let stride = columns + 1;
+    let mut indices = Vec::with_capacity((columns * rows * 6) as usize);
+    for row in 0..rows {
+        for column in 0..columns {
+            let top_left = row * stride + column;
+            let bottom_left = top_left + stride;
+            indices.extend_from_slice(&[
+                top_left,
+                bottom_left,
+                top_left + 1,
+                top_left + 1,
+                bottom_left,
+                bottom_left + 1,
+            ]);
+        }
+    }
+    indices
+}
+
+fn flip_winding(mut indices: Vec<u32>) -> Vec<u32> {
+    for triangle in indices.chunks_exact_mut(3) {
+        triangle.swap(1, 2);
+    }
+    indices
+}
+
+fn any_perpendicular(normal: &Vec3) -> Vec3 {
+    let axis = if normal.x.abs() < 0.9 {
+        Vec3::x()
+    } else {
+        Vec3::y()
+    };
+    normal.cross(&axis).normalize()
+}
+
+pub fn triangulate_polygon(points: &[Vec3]) -> Vec<[usize; 3]> {
+    let fan = |remaining: &[usize]| {
+        (2..remaining.len())
+            .map(|index| [remaining[0], remaining[index - 1], remaining[index]])
+            .collect::<Vec<_>>()
+    };
+    let mut remaining = (0..points.len()).collect::<Vec<_>>();
+    if points.len() <= 3 {
+        return fan(&remaining);
+    }
+
+    let mut normal = Vec3::zeros();
+    for (index, a) in points.iter().enumerate() {
+        let b = points[(index + 1) % points.len()];
+        normal.x += (a.y - b.y) * (a.z + b.z);
+        normal.y += (a.z - b.z) * (a.x + b.x);
+        normal.z += (a.x - b.x) * (a.y + b.y);
+    }
+    if normal.norm() <= f32::EPSILON {
+        return fan(&remaining);
+    }
+
+    let side = |a: &Vec3, b: &Vec3, point: &Vec3| (b - a).cross(&(point - a)).dot(&normal);
+    let mut triangles = Vec::with_capacity(points.len() - 2);
+    while remaining.len() > 3 {
+        let count = remaining.len();
+        let ear = (0..count).find(|index| {
+            let a = remaining[(index + count - 1) % count];
+            let b = remaining[*index];
+            let c = remaining[(index + 1) % count];
+            let (pa, pb, pc) = (&points[a], &points[b], &points[c]);
+            side(pa, pb, pc) > 0.0
+                && !remaining.iter().any(|other| {
+                    ![a, b, c].contains(other)
+                        && side(pa, pb, &points[*other]) >= 0.0
+                        && side(pb, pc, &points[*other]) >= 0.0
+                        && side(pc, pa, &points[*other]) >= 0.0
+                })
+        });
+        let Some(ear) = ear else {
+            triangles.extend(fan(&remaining));
+            return triangles;
+        };
+        triangles.push([
+            remaining[(ear + count - 1) % count],
+            remaining[ear],
+            remaining[(ear + 1) % count],
+        ]);
+        remaining.remove(ear);
+    }
+    triangles.extend(fan(&remaining));
+    triangles
+}
+
+pub enum IndexBuffer {
+    U16(Buffer<u16>),
+    U32(Buffer<u32>),
+}
+
+pub struct GpuMesh {
+    pub vertex_array: VertexArray,
+    pub vertex_buffer: Buffer<MeshVertex>,
+    pub index_buffer: Option<IndexBuffer>,
+    pub topology: PrimitiveTopology,
+    pub element_count: usize,
+    pub submeshes: Vec<Submesh>,
+    pub bounds: BoundingBox,
+}
+
+impl GpuMesh {
+    pub fn new(mesh: &Mesh) -> Self {
+        let vertex_buffer =
+            Buffer::with_data(BufferTarget::Array, BufferUsage::Static, &mesh.vertices());
+        let vertex_array = VertexArray::with_layout(&vertex_buffer);
+
+        let index_buffer = mesh.indices.as_ref().map(|indices| match indices {
+            Indices::U16(indices) => IndexBuffer::U16(Buffer::with_data(
+                BufferTarget::ElementArray,
+                BufferUsage::Static,
+                indices,
+            )),
+            Indices::U32(indices) => IndexBuffer::U32(Buffer::with_data(
+                BufferTarget::ElementArray,
+                BufferUsage::Static,
+                indices,
+            )),
+        });
+        match &index_buffer {
+            Some(IndexBuffer::U16(buffer)) => vertex_array.set_index_buffer(buffer),
+            Some(IndexBuffer::U32(buffer)) => vertex_array.set_index_buffer(buffer),
+            None => {}
+        }
+        vertex_array.unbind();
+
+        Self {
+            vertex_array,
+            vertex_buffer,
+            index_buffer,
+            topology: mesh.topology,
+            element_count: mesh.element_count(),
+            submeshes: mesh.submeshes.clone(),
+            bounds: mesh.bounds,
+        }
+    }
+
+    pub fn draw(&self) {
+        self.draw_range(0, self.element_count);
+    }
+
+    pub fn draw_submesh(&self, index: usize) {
+        if let Some(submesh) = self.submeshes.get(index) {
+            self.draw_range(submesh.index_offset, submesh.index_count);
+        }
+    }
+
+    pub fn draw_range(&self, offset: usize, count: usize) {
+        self.vertex_array.bind();
+        let mode = self.topology.gl_mode();
+        unsafe {
+            match &self.index_buffer {
+                Some(IndexBuffer::U16(_)) => gl::DrawElements(
+                    mode,
+                    count as GLsizei,
+                    gl::UNSIGNED_SHORT,
+                    (offset * std::mem::size_of::<u16>()) as *const _,
+                ),
+                Some(IndexBuffer::U32(_)) => gl::DrawElements(
+                    mode,
+                    count as GLsizei,
+                    gl::UNSIGNED_INT,
+                    (offset * std::mem::size_of::<u32>()) as *const _,
+                ),
+                None => gl::DrawArrays(mode, offset as GLint, count as GLsizei),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Asserts that every triangle with some area faces the way its vertex normals point.
+    fn assert_wound_with_normals(mesh: &Mesh) {
+        for [a, b, c] in mesh.triangles() {
+            let [a, b, c] = [a as usize, b as usize, c as usize];
+            let face = (mesh.positions[b] - mesh.positions[a])
+                .cross(&(mesh.positions[c] - mesh.positions[a]));
+            if face.norm() < 1e-6 {
+                continue;
+            }
+            let normal = mesh.normals[a] + mesh.normals[b] + mesh.normals[c];
+            assert!(
+                face.dot(&normal) > 0.0,
+                "{} triangle {:?} is wound against its normals",
+                mesh.name,
+                [a, b, c]
+            );
+        }
+    }
+
+    #[test]
+    fn generators_wind_triangles_with_their_normals() {
+        for mesh in [
+            Mesh::triangle(),
+            Mesh::cube(2.0),
+            Mesh::sphere(1.0, 16, 8),
+            Mesh::plane(2.0, 3.0, 2),
+            Mesh::cylinder(1.0, 2.0, 12),
+            Mesh::torus(1.0, 0.25, 16, 8),
+        ] {
+            assert_wound_with_normals(&mesh);
+            mesh.validate().unwrap();
+        }
+    }
+
+    #[test]
+    fn generators_produce_expected_counts() {
+        let counts = |mesh: Mesh| (mesh.vertex_count(), mesh.element_count());
+        assert_eq!(counts(Mesh::triangle()), (3, 3));
+        assert_eq!(counts(Mesh::cube(1.0)), (24, 36));
+        assert_eq!(counts(Mesh::sphere(1.0, 16, 8)), (17 * 9, 16 * 8 * 6));
+        assert_eq!(counts(Mesh::plane(1.0, 1.0, 2)), (9, 2 * 2 * 6));
+        assert_eq!(
+            counts(Mesh::cylinder(1.0, 1.0, 12)),
+            (2 * 13 + 2 * 14, 12 * 6 + 2 * 12 * 3)
+        );
+        assert_eq!(counts(Mesh::torus(1.0, 0.25, 16, 8)), (17 * 9, 16 * 8 * 6));
+        assert_eq!(counts(Mesh::grid(1.0, 4)), (20, 20));
+        // Degenerate arguments are clamped to the smallest usable mesh.
+        assert_eq!(counts(Mesh::sphere(1.0, 0, 0)), (4 * 3, 3 * 2 * 6));
+    }
+
+    #[test]
+    fn flat_normals_keep_generated_facing() {
+        let mut plane = Mesh::plane(1.0, 1.0, 1);
+        plane.compute_flat_normals();
+        assert!(plane.normals.iter().all(|normal| normal.y > 0.99));
+    }
+}
@@ -0,0 +1,214 @@
+use anyhow::{Result, anyhow};
+use gl::types::*;
+use std::ffi::CStr;
+use std::fs;
+use std::path::PathBuf;
+
+const MAGIC: &[u8; 4] = b"GLPB";
+const HEADER_LENGTH: usize = 8;
+
+#[derive(Clone, Debug)]
+pub struct ProgramCache {
+    directory: PathBuf,
+    driver: String,
+}
+
+impl ProgramCache {
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        let driver = [gl::VENDOR, gl::RENDERER, gl::VERSION]
+            .iter()
+            .map(|name| gl_string(*name))
+            .collect::<Vec<_>>()
+            .join("\n");
+        Self {
+            directory: directory.into(),
+            driver,
+        }
+    }
+
+    pub fn is_supported() -> bool {
+        if !gl::GetProgramBinary::is_loaded() || !gl::ProgramBinary::is_loaded() {
+            return false;
+        }
+        let mut formats = 0;
+        unsafe {
+            gl::GetIntegerv(gl::NUM_PROGRAM_BINARY_FORMATS, &mut formats);
+        }
+        formats > 0
+    }
+
+    pub fn key<'a>(&self, parts: impl IntoIterator<Item = &'a [u8]>) -> u64 {
+        let mut hash = fnv1a(FNV_OFFSET_BASIS, self.driver.as_bytes());
+        for part in parts {
+            hash = fnv1a(hash, &[0]);
+            hash = fnv1a(hash, part);
+        }
+        hash
+    }
+
+    pub fn load(&self, key: u64) -> Option<(GLenum, Vec<u8>)> {
+        let bytes = fs::read(self.path(key)).ok()?;
+        if bytes.len() <= HEADER_LENGTH || &bytes[..4] != MAGIC {
+            return None;
+        }
+        let format = u32::from_le_bytes(bytes[4..HEADER_LENGTH].try_into().ok()?);
+        Some((format, bytes[HEADER_LENGTH..].to_vec()))
+    }
+
+    pub fn store(&self, key: u64, format: GLenum, binary: &[u8]) -> Result<()> {
+        fs::create_dir_all(&self.directory).map_err(|error| {
+            anyhow!(
+                "Failed to create program cache directory '{}': {}",
+                self.directory.display(),
+                error
+            )
+        })?;
+
+        let mut bytes = Vec::with_capacity(HEADER_LENGTH + binary.len());
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&format.to_le_bytes());
+        bytes.extend_from_slice(binary);
+
+        let path = self.path(key);
+        let temporary_path = path.with_extension("tmp");
+        fs::write(&temporary_path, &bytes)
+            .and_then(|_| fs::rename(&temporary_path, &path))
+            .map_err(|error| {
+                anyhow!(
+                    "Failed to write program binary '{}': {}",
+                    path.display(),
+                    error
+                )
+            })
+    }
+
+    pub fn remove(&self, key: u64) {
+        let _ = fs::remove_file(self.path(key));
+    }
+
+    pub fn clear(&self) -> Result<()> {
+        if self.directory.exists() {
+            fs::remove_dir_all(&self.directory).map_err(|error| {
+                anyhow!(
+                    "Failed to clear program cache '{}': {}",
+                    self.directory.display(),
+                    error
+                )
+            })?;
+        }
+        Ok(())
+    }
+
+    fn path(&self, key: u64) -> PathBuf {
+        self.directory.join(format!("{:016x}.bin", key))
+    }
+}
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+fn fnv1a(mut hash: u64, bytes: &[u8]) -> u64 {
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+    hash
+}
+
+fn gl_string(name: GLenum) -> String {
+    unsafe {
+        let pointer = gl::GetString(name);
+        if pointer.is_null() {
+            return String::new();
+        }
+        CStr::from_ptr(pointer as *const _)
+            .to_string_lossy()
+            .into_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temporary_cache(name: &str, driver: &str) -> ProgramCache {
+        ProgramCache {
+            directory: std::env::temp_dir().join(format!(
+                "program_cache_{}_{}",
+                name,
+                std::process::id()
+            )),
+            driver: driver.to_string(),
+        }
+    }
+
+    #[test]
+    fn key_depends_on_parts_and_driver() {
+        let cache = temporary_cache("key", "Mesa\nllvmpipe\n4.5");
+        let key = cache.key([b"vertex".as_slice(), b"fragment".as_slice()]);
+        assert_eq!(
+            key,
+            cache.key([b"vertex".as_slice(), b"fragment".as_slice()])
+        );
+        assert_ne!(
+            key,
+            cache.key([b"fragment".as_slice(), b"vertex".as_slice()])
+        );
+        // Parts are separated, so moving bytes across a boundary changes the key.
+        assert_ne!(
+            key,
+            cache.key([b"vertexf".as_slice(), b"ragment".as_slice()])
+        );
+        assert_ne!(key, cache.key([b"vertexfragment".as_slice()]));
+
+        let other = temporary_cache("key", "Mesa\nllvmpipe\n4.6");
+        assert_ne!(
+            key,
+            other.key([b"vertex".as_slice(), b"fragment".as_slice()])
+        );
+    }
+
+    #[test]
+    fn fnv1a_matches_reference_values() {
+        assert_eq!(fnv1a(FNV_OFFSET_BASIS, b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a(FNV_OFFSET_BASIS, b"a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(fnv1a(FNV_OFFSET_BASIS, b"foobar"), 0x8594_4171_f739_67e8);
+    }
+
+    #[test]
+    fn stores_and_loads_binaries() {
+        let cache = temporary_cache("round_trip", "driver");
+        cache.store(1, 0x8741, &[1, 2, 3, 4]).unwrap();
+        assert_eq!(cache.load(1), Some((0x8741, vec![1, 2, 3, 4])));
+        assert_eq!(cache.load(2), None);
+
+        cache.remove(1);
+        assert_eq!(cache.load(1), None);
+        cache.clear().unwrap();
+        assert!(!cache.directory.exists());
+    }
+
+    #[test]
+    fn rejects_bad_headers() {
+        let cache = temporary_cache("headers", "driver");
+        fs::create_dir_all(&cache.directory).unwrap();
+
+        let mut bytes = b"GLPB".to_vec();
+        bytes.extend_from_slice(&7_u32.to_le_bytes());
+        fs::write(cache.path(1), &bytes).unwrap();
+        assert_eq!(cache.load(1), None, "header without a binary");
+
+        bytes.push(9);
+        fs::write(cache.path(1), &bytes).unwrap();
+        assert_eq!(cache.load(1), Some((7, vec![9])));
+
+        bytes[..4].copy_from_slice(b"GLPX");
+        fs::write(cache.path(1), &bytes).unwrap();
+        assert_eq!(cache.load(1), None, "wrong magic");
+
+        fs::write(cache.path(1), b"GLP").unwrap();
+        assert_eq!(cache.load(1), None, "truncated header");
+
+        cache.clear().unwrap();
+    }
+}
@@ -0,0 +1,372 @@
+use crate::support::shader::{ProgramResource, ShaderKind, ShaderProgram};
+use anyhow::{Result, anyhow, bail};
+use gl::types::*;
+use std::ptr;
+
+const PRE_RASTER_STAGES: [GLbitfield; 3] = [
+    gl::GEOMETRY_SHADER_BIT,
+    gl::TESS_EVALUATION_SHADER_BIT,
+    gl::VERTEX_SHADER_BIT,
+];
+
+struct PipelineStage {
+    stages: GLbitfield,
+    program: GLuint,
+    program_stages: GLbitfield,
+    /// Outputs of the program's last stage, or `None` without program interface queries.
+    outputs: Option<Vec<ProgramResource>>,
+    /// Inputs of the program's first stage, or `None` without program interface queries.
+    inputs: Option<Vec<ProgramResource>>,
+}
+
+pub struct ProgramPipeline {
+    pub id: GLuint,
+    stages: Vec<PipelineStage>,
+}
+
+impl ProgramPipeline {
+    pub fn new() -> Self {
+        let mut id = 0;
+        unsafe {
+            gl::GenProgramPipelines(1, &mut id);
+        }
+        Self {
+            id,
+            stages: Vec::new(),
+        }
+    }
+
+    pub fn use_program(&mut self, program: &ShaderProgram) -> Result<&mut Self> {
+        self.use_stages(program.stages(), program)
+    }
+
+    pub fn use_stage(&mut self, kind: ShaderKind, program: &ShaderProgram) -> Result<&mut Self> {
+        self.use_stages(kind.stage_bit(), program)
+    }
+
+    pub fn use_stages(&mut self, stages: GLbitfield, program: &ShaderProgram) -> Result<&mut Self> {
+        if !program.is_separable() {
+            bail!("Program {} was not linked as separable", program.id);
+        }
+        // Pipeline validation fails when only some of a program's stages are active, and the
+        // program interface only describes its first stage inputs and last stage outputs.
+        if program.stages() != stages {
+            bail!(
+                "Program {} must be used for all of its stages ({:#x} requested of {:#x})",
+                program.id,
+                stages,
+                program.stages()
+            );
+        }
+
+        unsafe {
+            gl::UseProgramStages(self.id, stages, program.id);
+        }
+
+        for stage in &mut self.stages {
+            stage.stages &= !stages;
+        }
+        self.stages.retain(|stage| stage.stages != 0);
+        let queryable = gl::GetProgramResourceiv::is_loaded();
+        let inputs = queryable.then(|| program.resources(gl::PROGRAM_INPUT));
+        let outputs = queryable.then(|| program.resources(gl::PROGRAM_OUTPUT));
+        self.stages.push(PipelineStage {
+            stages,
+            program: program.id,
+            program_stages: program.stages(),
+            outputs,
+            inputs,
+        });
+
+        Ok(self)
+    }
+
+    pub fn clear_stages(&mut self, stages: GLbitfield) -> &mut Self {
+        unsafe {
+            gl::UseProgramStages(self.id, stages, 0);
+        }
+        for stage in &mut self.stages {
+            stage.stages &= !stages;
+        }
+        self.stages.retain(|stage| stage.stages != 0);
+        self
+    }
+
+    pub fn set_active_program(&self, program: &ShaderProgram) {
+        unsafe {
+            gl::ActiveShaderProgram(self.id, program.id);
+        }
+    }
+
+    pub fn bind(&self) {
+        unsafe {
+            gl::UseProgram(0);
+            gl::BindProgramPipeline(self.id);
+        }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        self.validate_interfaces()?;
+
+        let mut status = 0;
+        unsafe {
+            gl::ValidateProgramPipeline(self.id);
+            gl::GetProgramPipelineiv(self.id, gl::VALIDATE_STATUS, &mut status);
+        }
+        if status == 0 {
+            bail!("Program pipeline validation failed:\n{}", self.info_log());
+        }
+        Ok(())
+    }
+
+    fn validate_interfaces(&self) -> Result<()> {
+        if let Some(stage) = self
+            .stages
+            .iter()
+            .find(|stage| stage.stages != stage.program_stages)
+        {
+            bail!(
+                "Program {} is only active for some of its stages ({:#x} of {:#x})",
+                stage.program,
+                stage.stages,
+                stage.program_stages
+            );
+        }
+
+        let Some(fragment) = self.stage(gl::FRAGMENT_SHADER_BIT) else {
+            return Ok(());
+        };
+        let Some(producer) = PRE_RASTER_STAGES
+            .iter()
+            .find_map(|stage| self.stage(*stage))
+        else {
+            return Ok(());
+        };
+        if producer.program == fragment.program {
+            return Ok(());
+        }
+
+        let (Some(inputs), Some(outputs)) = (&fragment.inputs, &producer.outputs) else {
+            log::warn!(
+                "Skipped checking the interface between programs {} and {}, program \
+                 interface queries require OpenGL 4.3",
+                producer.program,
+                fragment.program
+            );
+            return Ok(());
+        };
+
+        let mut errors = Vec::new();
+        for input in inputs.iter().filter(|input| !input.is_builtin()) {
+            let output = outputs.iter().find(|output| {
+                if input.location >= 0 {
+                    output.location == input.location
+                } else {
+                    output.name == input.name
+                }
+            });
+            match output {
+                Some(output) if output.gl_type == input.gl_type => {}
+                Some(output) => errors.push(format!(
+                    "Fragment input '{}' is {} but output '{}' of program {} is {}",
+                    input.name,
+                    input.type_name(),
+                    output.name,
+                    producer.program,
+                    output.type_name()
+                )),
+                None => errors.push(format!(
+                    "Fragment input '{}' (location {}) is not written by program {}",
+                    input.name, input.location, producer.program
+                )),
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow!(
+                "Program pipeline interface mismatch:\n{}",
+                errors.join("\n")
+            ))
+        }
+    }
+
+    fn stage(&self, stage: GLbitfield) -> Option<&PipelineStage> {
+        self.stages
+            .iter()
+            .find(|pipeline_stage| pipeline_stage.stages & stage != 0)
+    }
+
+    fn info_log(&self) -> String {
+        let mut length = 0;
+        unsafe {
+            gl::GetProgramPipelineiv(self.id, gl::INFO_LOG_LENGTH, &mut length);
+        }
+        if length <= 1 {
+            return String::new();
+        }
+
+        let mut buffer = vec![0u8; length as usize];
+        unsafe {
+            gl::GetProgramPipelineInfoLog(
+                self.id,
+                length,
+                ptr::null_mut(),
+                buffer.as_mut_ptr() as *mut GLchar,
+            );
+        }
+        String::from_utf8_lossy(&buffer[..length as usize - 1]).into_owned()
+    }
+}
+
+impl Default for ProgramPipeline {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for ProgramPipeline {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteProgramPipelines(1, &self.id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::support::headless::headless_context;
+
+    const VERTEX: &str = "#version 450 core
+layout(location = 0) in vec3 position;
+layout(location = 0) out vec4 color;
+out gl_PerVertex { vec4 gl_Position; };
+void main() {
+    color = vec4(position, 1.0);
+    gl_Position = vec4(position, 1.0);
+}
+";
+
+    fn fragment(input: &str) -> String {
+        format!(
+            "#version 450 core
+{input}
+layout(location = 0) out vec4 frag_color;
+void main() {{
+    frag_color = vec4(1.0);
+}}
+"
+        )
+    }
+
+    fn program(stages: &[(ShaderKind, &str)], separable: bool) -> ShaderProgram {
+        let mut program = ShaderProgram::new();
+        for (kind, source) in stages {
+            program.attach_source(*kind, "test", source);
+        }
+        if separable {
+            program.separable();
+        }
+        program.link().unwrap();
+        program
+    }
+
+    fn pipeline_error(fragment_input: &str) -> String {
+        let vertex = program(&[(ShaderKind::Vertex, VERTEX)], true);
+        let fragment = program(&[(ShaderKind::Fragment, &fragment(fragment_input))], true);
+        let mut pipeline = ProgramPipeline::new();
+        pipeline
+            .use_program(&vertex)
+            .unwrap()
+            .use_program(&fragment)
+            .unwrap();
+        pipeline.validate().unwrap_err().to_string()
+    }
+
+    #[test]
+    #[ignore = "needs an OpenGL 4.5 context, such as Mesa's llvmpipe"]
+    fn validates_matching_programs() {
+        let _context = headless_context();
+        let vertex = program(&[(ShaderKind::Vertex, VERTEX)], true);
+        let fragment = program(
+            &[(
+                ShaderKind::Fragment,
+                &fragment("layout(location = 0) in vec4 color;"),
+            )],
+            true,
+        );
+        let mut pipeline = ProgramPipeline::new();
+        pipeline
+            .use_program(&vertex)
+            .unwrap()
+            .use_program(&fragment)
+            .unwrap();
+        pipeline.validate().unwrap();
+    }
+
+    #[test]
+    #[ignore = "needs an OpenGL 4.5 context, such as Mesa's llvmpipe"]
+    fn rejects_programs_that_are_not_separable() {
+        let _context = headless_context();
+        let vertex = program(&[(ShaderKind::Vertex, VERTEX)], false);
+        let error = ProgramPipeline::new()
+            .use_program(&vertex)
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(error.contains("was not linked as separable"), "{error}");
+    }
+
+    #[test]
+    #[ignore = "needs an OpenGL 4.5 context, such as Mesa's llvmpipe"]
+    fn rejects_partial_stage_use() {
+        let _context = headless_context();
+        let fragment = fragment("layout(location = 0) in vec4 color;");
+        let both = program(
+            &[
+                (ShaderKind::Vertex, VERTEX),
+                (ShaderKind::Fragment, &fragment),
+            ],
+            true,
+        );
+        let mut pipeline = ProgramPipeline::new();
+        let error = pipeline
+            .use_stage(ShaderKind::Vertex, &both)
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(
+            error.contains("must be used for all of its stages"),
+            "{error}"
+        );
+
+        // Clearing one stage afterwards leaves the program half active.
+        pipeline.use_program(&both).unwrap();
+        pipeline.validate().unwrap();
+        pipeline.clear_stages(gl::FRAGMENT_SHADER_BIT);
+        let error = pipeline.validate().unwrap_err().to_string();
+        assert!(error.contains("only active for some"), "{error}");
+    }
+
+    #[test]
+    #[ignore = "needs an OpenGL 4.5 context, such as Mesa's llvmpipe"]
+    fn reports_mismatched_types() {
+        let _context = headless_context();
+        let error = pipeline_error("layout(location = 0) in vec3 color;");
+        assert!(error.contains("interface mismatch"), "{error}");
+        assert!(error.contains("'color' is vec3"), "{error}");
+    }
+
+    #[test]
+    #[ignore = "needs an OpenGL 4.5 context, such as Mesa's llvmpipe"]
+    fn reports_missing_outputs() {
+        let _context = headless_context();
+        let error = pipeline_error("layout(location = 3) in vec4 normal;");
+        assert!(
+            error.contains("'normal' (location 3) is not written"),
+            "{error}"
+        );
+    }
+}
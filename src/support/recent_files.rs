@@ -0,0 +1,69 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const MAX_RECENT_FILES: usize = 10;
+
+#[derive(Debug, Default)]
+pub struct RecentFiles {
+    paths: Vec<PathBuf>,
+    storage: Option<PathBuf>,
+}
+
+impl RecentFiles {
+    pub fn load(storage: impl Into<PathBuf>) -> Self {
+        let storage = storage.into();
+        let paths = fs::read_to_string(&storage)
+            .map(|text| {
+                text.lines()
+                    .filter(|line| !line.trim().is_empty())
+                    .map(PathBuf::from)
+                    .take(MAX_RECENT_FILES)
+                    .collect()
+            })
+            .unwrap_or_default();
+        Self {
+            paths,
+            storage: Some(storage),
+        }
+    }
+
+    pub fn paths(&self) -> &[PathBuf] {
+        &self.paths
+    }
+
+    pub fn add(&mut self, path: &Path) {
+        let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        self.paths.retain(|existing| *existing != path);
+        self.paths.insert(0, path);
+        self.paths.truncate(MAX_RECENT_FILES);
+        self.save();
+    }
+
+    pub fn remove(&mut self, path: &Path) {
+        self.paths.retain(|existing| existing != path);
+        self.save();
+    }
+
+    pub fn clear(&mut self) {
+        self.paths.clear();
+        self.save();
+    }
+
+    fn save(&self) {
+        let Some(storage) = &self.storage else {
+            return;
+        };
+        let text = self
+            .paths
+            .iter()
+            .map(|path| format!("{}\n", path.display()))
+            .collect::<String>();
+        if let Err(error) = fs::write(storage, text) {
+            log::warn!(
+                "Failed to save recent files '{}': {}",
+                storage.display(),
+                error
+            );
+        }
+    }
+}
@@ -0,0 +1,215 @@
+use crate::support::import::{CameraData, ProjectionData};
+use crate::support::light::{Light, LightKind};
+use crate::support::material::{AlphaMode, Material, TextureRef};
+use crate::support::mesh::GpuMesh;
+use crate::support::transform::Transform;
+use nalgebra_glm::{Quat, Vec3, Vec4};
+use std::ops::RangeInclusive;
+
+pub trait Visitor {
+    fn label(&mut self, name: &str, value: &str);
+    fn bool(&mut self, name: &str, value: &mut bool);
+    fn f32(&mut self, name: &str, value: &mut f32, range: RangeInclusive<f32>);
+    fn angle(&mut self, name: &str, radians: &mut f32, range: RangeInclusive<f32>);
+    fn optional_f32(
+        &mut self,
+        name: &str,
+        value: &mut Option<f32>,
+        default: f32,
+        range: RangeInclusive<f32>,
+    );
+    fn string(&mut self, name: &str, value: &mut String);
+    fn vec3(&mut self, name: &str, value: &mut Vec3);
+    fn rotation(&mut self, name: &str, value: &mut Quat);
+    fn color3(&mut self, name: &str, value: &mut Vec3);
+    fn color4(&mut self, name: &str, value: &mut Vec4);
+    fn choice(&mut self, name: &str, selected: &mut usize, options: &[&str]);
+}
+
+pub trait Reflect {
+    fn type_name(&self) -> &'static str;
+    fn reflect(&mut self, visitor: &mut dyn Visitor);
+}
+
+impl Reflect for Transform {
+    fn type_name(&self) -> &'static str {
+        "Transform"
+    }
+
+    fn reflect(&mut self, visitor: &mut dyn Visitor) {
+        visitor.vec3("Position", &mut self.translation);
+        visitor.rotation("Rotation", &mut self.rotation);
+        visitor.vec3("Scale", &mut self.scale);
+    }
+}
+
+impl Reflect for Light {
+    fn type_name(&self) -> &'static str {
+        "Light"
+    }
+
+    fn reflect(&mut self, visitor: &mut dyn Visitor) {
+        let mut kind = match self.kind {
+            LightKind::Directional => 0,
+            LightKind::Point => 1,
+            LightKind::Spot { .. } => 2,
+        };
+        visitor.choice("Type", &mut kind, &["Directional", "Point", "Spot"]);
+        self.kind = match (kind, self.kind) {
+            (0, _) => LightKind::Directional,
+            (1, _) => LightKind::Point,
+            (_, spot @ LightKind::Spot { .. }) => spot,
+            _ => LightKind::Spot {
+                inner_cone_angle: 0.0,
+                outer_cone_angle: std::f32::consts::FRAC_PI_4,
+            },
+        };
+
+        visitor.color3("Color", &mut self.color);
+        visitor.f32("Intensity", &mut self.intensity, 0.0..=1000.0);
+        if let LightKind::Spot {
+            inner_cone_angle,
+            outer_cone_angle,
+        } = &mut self.kind
+        {
+            visitor.angle(
+                "Inner Cone",
+                inner_cone_angle,
+                0.0..=std::f32::consts::FRAC_PI_2,
+            );
+            visitor.angle(
+                "Outer Cone",
+                outer_cone_angle,
+                0.0..=std::f32::consts::FRAC_PI_2,
+            );
+            *inner_cone_angle = inner_cone_angle.min(*outer_cone_angle);
+        }
+        if self.kind != LightKind::Directional {
+            visitor.optional_f32("Range", &mut self.range, 10.0, 0.0..=10000.0);
+        }
+    }
+}
+
+impl Reflect for CameraData {
+    fn type_name(&self) -> &'static str {
+        "Camera"
+    }
+
+    fn reflect(&mut self, visitor: &mut dyn Visitor) {
+        let mut kind = match self.projection {
+            ProjectionData::Perspective { .. } => 0,
+            ProjectionData::Orthographic { .. } => 1,
+        };
+        visitor.choice("Projection", &mut kind, &["Perspective", "Orthographic"]);
+        self.projection = match (kind, self.projection) {
+            (0, ProjectionData::Orthographic { znear, zfar, .. }) => ProjectionData::Perspective {
+                yfov: 60_f32.to_radians(),
+                aspect_ratio: None,
+                znear: znear.max(0.01),
+                zfar: Some(zfar),
+            },
+            (1, ProjectionData::Perspective { znear, zfar, .. }) => ProjectionData::Orthographic {
+                xmag: 1.0,
+                ymag: 1.0,
+                znear,
+                zfar: zfar.unwrap_or(1000.0),
+            },
+            (_, projection) => projection,
+        };
+
+        match &mut self.projection {
+            ProjectionData::Perspective {
+                yfov, znear, zfar, ..
+            } => {
+                visitor.angle(
+                    "Field of View",
+                    yfov,
+                    1_f32.to_radians()..=179_f32.to_radians(),
+                );
+                visitor.f32("Near", znear, 0.001..=1000.0);
+                visitor.optional_f32("Far", zfar, 1000.0, 0.01..=1.0e6);
+            }
+            ProjectionData::Orthographic {
+                xmag,
+                ymag,
+                znear,
+                zfar,
+            } => {
+                visitor.f32("Width", xmag, 0.001..=1.0e4);
+                visitor.f32("Height", ymag, 0.001..=1.0e4);
+                visitor.f32("Near", znear, -1.0e4..=1.0e4);
+                visitor.f32("Far", zfar, -1.0e4..=1.0e6);
+            }
+        }
+    }
+}
+
+impl Reflect for Material {
+    fn type_name(&self) -> &'static str {
+        "Material"
+    }
+
+    fn reflect(&mut self, visitor: &mut dyn Visitor) {
+        visitor.string("Name", &mut self.name);
+        visitor.color4("Base Color", &mut self.base_color);
+        visitor.f32("Metallic", &mut self.metallic, 0.0..=1.0);
+        visitor.f32("Roughness", &mut self.roughness, 0.0..=1.0);
+        visitor.color3("Emissive", &mut self.emissive);
+
+        let mut alpha_mode = match self.alpha_mode {
+            AlphaMode::Opaque => 0,
+            AlphaMode::Mask(_) => 1,
+            AlphaMode::Blend => 2,
+        };
+        visitor.choice("Alpha Mode", &mut alpha_mode, &["Opaque", "Mask", "Blend"]);
+        self.alpha_mode = match (alpha_mode, self.alpha_mode) {
+            (0, _) => AlphaMode::Opaque,
+            (1, AlphaMode::Mask(cutoff)) => AlphaMode::Mask(cutoff),
+            (1, _) => AlphaMode::Mask(0.5),
+            _ => AlphaMode::Blend,
+        };
+        if let AlphaMode::Mask(cutoff) = &mut self.alpha_mode {
+            visitor.f32("Alpha Cutoff", cutoff, 0.0..=1.0);
+        }
+
+        visitor.bool("Double Sided", &mut self.double_sided);
+        visitor.bool("Unlit", &mut self.unlit);
+        visitor.f32("Point Size", &mut self.point_size, 1.0..=64.0);
+
+        for (name, TextureRef { texture, uv_set }) in self.textures() {
+            visitor.label(name, &format!("#{} (UV {})", texture, uv_set));
+        }
+        if self.normal_texture.is_some() {
+            visitor.f32("Normal Scale", &mut self.normal_scale, 0.0..=10.0);
+        }
+        if self.occlusion_texture.is_some() {
+            visitor.f32(
+                "Occlusion Strength",
+                &mut self.occlusion_strength,
+                0.0..=1.0,
+            );
+        }
+    }
+}
+
+impl Reflect for GpuMesh {
+    fn type_name(&self) -> &'static str {
+        "Mesh"
+    }
+
+    fn reflect(&mut self, visitor: &mut dyn Visitor) {
+        visitor.label("Topology", &format!("{:?}", self.topology));
+        visitor.label("Vertices", &self.vertex_buffer.len().to_string());
+        visitor.label("Elements", &self.element_count.to_string());
+        visitor.label("Submeshes", &self.submeshes.len().to_string());
+        let (min, max) = (self.bounds.min, self.bounds.max);
+        visitor.label(
+            "Bounds Min",
+            &format!("{:.3}, {:.3}, {:.3}", min.x, min.y, min.z),
+        );
+        visitor.label(
+            "Bounds Max",
+            &format!("{:.3}, {:.3}, {:.3}", max.x, max.y, max.z),
+        );
+    }
+}
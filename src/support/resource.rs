@@ -0,0 +1,816 @@
+use anyhow::{Result, bail};
+use gl::types::*;
+use std::cell::Cell;
+use std::ffi::CStr;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+static NEXT_CONTEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+thread_local! {
+    static CURRENT_CONTEXT: Cell<u64> = const { Cell::new(0) };
+}
+
+pub fn register_context() -> u64 {
+    let id = NEXT_CONTEXT_ID.fetch_add(1, Ordering::Relaxed);
+    set_current_context(id);
+    id
+}
+
+pub fn set_current_context(id: u64) {
+    CURRENT_CONTEXT.with(|current| current.set(id));
+}
+
+pub fn current_context() -> u64 {
+    CURRENT_CONTEXT.with(Cell::get)
+}
+
+pub fn gl_version() -> (GLint, GLint) {
+    let (mut major, mut minor) = (0, 0);
+    unsafe {
+        gl::GetIntegerv(gl::MAJOR_VERSION, &mut major);
+        gl::GetIntegerv(gl::MINOR_VERSION, &mut minor);
+    }
+    (major, minor)
+}
+
+pub fn has_extension(name: &str) -> bool {
+    let mut count = 0;
+    unsafe {
+        gl::GetIntegerv(gl::NUM_EXTENSIONS, &mut count);
+    }
+    (0..count as GLuint).any(|index| {
+        let extension = unsafe { gl::GetStringi(gl::EXTENSIONS, index) };
+        !extension.is_null()
+            && unsafe { CStr::from_ptr(extension.cast()) }.to_bytes() == name.as_bytes()
+    })
+}
+
+struct Owner {
+    context: u64,
+    _not_send: PhantomData<*const ()>,
+}
+
+impl Owner {
+    fn new() -> Self {
+        Self {
+            context: current_context(),
+            _not_send: PhantomData,
+        }
+    }
+
+    fn is_current(&self) -> bool {
+        self.context == current_context()
+    }
+
+    /// Using an object from another context would touch an unrelated object or none at
+    /// all, so callers skip the GL call when this returns false.
+    fn check(&self, kind: &str, id: GLuint) -> bool {
+        if self.is_current() {
+            return true;
+        }
+        log::error!(
+            "{} {} used outside of the OpenGL context that created it",
+            kind,
+            id
+        );
+        false
+    }
+
+    fn release(&self, kind: &str, id: GLuint, delete: impl FnOnce()) {
+        if id == 0 {
+            return;
+        }
+        if self.is_current() {
+            delete();
+        } else {
+            log::error!(
+                "Leaking {} {} dropped outside of the OpenGL context that created it",
+                kind,
+                id
+            );
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BufferTarget {
+    Array,
+    ElementArray,
+    Uniform,
+    ShaderStorage,
+    TransformFeedback,
+    PixelPack,
+    PixelUnpack,
+}
+
+impl BufferTarget {
+    pub fn gl_target(&self) -> GLenum {
+        match self {
+            BufferTarget::Array => gl::ARRAY_BUFFER,
+            BufferTarget::ElementArray => gl::ELEMENT_ARRAY_BUFFER,
+            BufferTarget::Uniform => gl::UNIFORM_BUFFER,
+            BufferTarget::ShaderStorage => gl::SHADER_STORAGE_BUFFER,
+            BufferTarget::TransformFeedback => gl::TRANSFORM_FEEDBACK_BUFFER,
+            BufferTarget::PixelPack => gl::PIXEL_PACK_BUFFER,
+            BufferTarget::PixelUnpack => gl::PIXEL_UNPACK_BUFFER,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BufferUsage {
+    #[default]
+    Static,
+    Dynamic,
+    Stream,
+    StaticRead,
+}
+
+impl BufferUsage {
+    pub fn gl_usage(&self) -> GLenum {
+        match self {
+            BufferUsage::Static => gl::STATIC_DRAW,
+            BufferUsage::Dynamic => gl::DYNAMIC_DRAW,
+            BufferUsage::Stream => gl::STREAM_DRAW,
+            BufferUsage::StaticRead => gl::STATIC_READ,
+        }
+    }
+}
+
+pub struct Buffer<T: bytemuck::Pod> {
+    id: GLuint,
+    target: BufferTarget,
+    usage: BufferUsage,
+    len: usize,
+    owner: Owner,
+    _element: PhantomData<T>,
+}
+
+impl<T: bytemuck::Pod> Buffer<T> {
+    pub fn new(target: BufferTarget, usage: BufferUsage) -> Self {
+        let mut id = 0;
+        unsafe {
+            gl::GenBuffers(1, &mut id);
+        }
+        Self {
+            id,
+            target,
+            usage,
+            len: 0,
+            owner: Owner::new(),
+            _element: PhantomData,
+        }
+    }
+
+    pub fn with_data(target: BufferTarget, usage: BufferUsage, data: &[T]) -> Self {
+        let mut buffer = Self::new(target, usage);
+        buffer.upload(data);
+        buffer
+    }
+
+    pub fn with_capacity(target: BufferTarget, usage: BufferUsage, len: usize) -> Self {
+        let mut buffer = Self::new(target, usage);
+        buffer.allocate(len);
+        buffer
+    }
+
+    pub fn id(&self) -> GLuint {
+        self.id
+    }
+
+    pub fn target(&self) -> BufferTarget {
+        self.target
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn size_in_bytes(&self) -> usize {
+        self.len * std::mem::size_of::<T>()
+    }
+
+    pub fn bind(&self) {
+        if !self.owner.check("Buffer", self.id) {
+            return;
+        }
+        unsafe {
+            gl::BindBuffer(self.target.gl_target(), self.id);
+        }
+    }
+
+    pub fn unbind(&self) {
+        unsafe {
+            gl::BindBuffer(self.target.gl_target(), 0);
+        }
+    }
+
+    pub fn bind_base(&self, target: BufferTarget, index: GLuint) {
+        if !self.owner.check("Buffer", self.id) {
+            return;
+        }
+        unsafe {
+            gl::BindBufferBase(target.gl_target(), index, self.id);
+        }
+    }
+
+    pub fn upload(&mut self, data: &[T]) {
+        if !self.owner.check("Buffer", self.id) {
+            return;
+        }
+        self.bind();
+        let bytes = bytemuck::cast_slice::<T, u8>(data);
+        unsafe {
+            gl::BufferData(
+                self.target.gl_target(),
+                bytes.len() as GLsizeiptr,
+                bytes.as_ptr() as *const _,
+                self.usage.gl_usage(),
+            );
+        }
+        self.len = data.len();
+    }
+
+    pub fn allocate(&mut self, len: usize) {
+        if !self.owner.check("Buffer", self.id) {
+            return;
+        }
+        self.bind();
+        unsafe {
+            gl::BufferData(
+                self.target.gl_target(),
+                (len * std::mem::size_of::<T>()) as GLsizeiptr,
+                std::ptr::null(),
+                self.usage.gl_usage(),
+            );
+        }
+        self.len = len;
+    }
+
+    pub fn update(&mut self, offset: usize, data: &[T]) -> Result<()> {
+        if !self.owner.is_current() {
+            bail!(
+                "Buffer {} used outside of the OpenGL context that created it",
+                self.id
+            );
+        }
+        if offset + data.len() > self.len {
+            bail!(
+                "Buffer update of {} elements at offset {} exceeds buffer length {}",
+                data.len(),
+                offset,
+                self.len
+            );
+        }
+        self.bind();
+        let bytes = bytemuck::cast_slice::<T, u8>(data);
+        unsafe {
+            gl::BufferSubData(
+                self.target.gl_target(),
+                (offset * std::mem::size_of::<T>()) as GLintptr,
+                bytes.len() as GLsizeiptr,
+                bytes.as_ptr() as *const _,
+            );
+        }
+        Ok(())
+    }
+
+    pub fn read(&self) -> Vec<T> {
+        if !self.owner.check("Buffer", self.id) {
+            return Vec::new();
+        }
+        let mut data = vec![T::zeroed(); self.len];
+        self.bind();
+        unsafe {
+            gl::GetBufferSubData(
+                self.target.gl_target(),
+                0,
+                self.size_in_bytes() as GLsizeiptr,
+                data.as_mut_ptr() as *mut _,
+            );
+        }
+        data
+    }
+}
+
+impl<T: bytemuck::Pod> Drop for Buffer<T> {
+    fn drop(&mut self) {
+        self.owner.release("buffer", self.id, || unsafe {
+            gl::DeleteBuffers(1, &self.id);
+        });
+    }
+}
+
+pub struct VertexArray {
+    id: GLuint,
+    owner: Owner,
+}
+
+impl VertexArray {
+    pub fn new() -> Self {
+        let mut id = 0;
+        unsafe {
+            gl::GenVertexArrays(1, &mut id);
+        }
+        Self {
+            id,
+            owner: Owner::new(),
+        }
+    }
+
+    pub fn id(&self) -> GLuint {
+        self.id
+    }
+
+    pub fn bind(&self) {
+        if !self.owner.check("VertexArray", self.id) {
+            return;
+        }
+        unsafe {
+            gl::BindVertexArray(self.id);
+        }
+    }
+
+    pub fn unbind(&self) {
+        unsafe {
+            gl::BindVertexArray(0);
+        }
+    }
+
+    /// Binds `buffer` as the source for the attributes set up next, whatever target it was
+    /// created for.
+    pub fn set_vertex_buffer<T: bytemuck::Pod>(&self, buffer: &Buffer<T>) {
+        self.bind_buffer(gl::ARRAY_BUFFER, buffer);
+    }
+
+    /// Records `buffer` as this vertex array's index buffer, whatever target it was created for.
+    pub fn set_index_buffer<T: bytemuck::Pod>(&self, buffer: &Buffer<T>) {
+        self.bind_buffer(gl::ELEMENT_ARRAY_BUFFER, buffer);
+    }
+
+    fn bind_buffer<T: bytemuck::Pod>(&self, target: GLenum, buffer: &Buffer<T>) {
+        if !self.owner.check("VertexArray", self.id) || !buffer.owner.check("Buffer", buffer.id) {
+            return;
+        }
+        self.bind();
+        unsafe {
+            gl::BindBuffer(target, buffer.id);
+        }
+    }
+
+    pub fn attribute(
+        &self,
+        location: GLuint,
+        components: GLint,
+        gl_type: GLenum,
+        normalized: bool,
+        stride: usize,
+        offset: usize,
+    ) {
+        if !self.owner.check("VertexArray", self.id) {
+            return;
+        }
+        self.bind();
+        unsafe {
+            match gl_type {
+                gl::FLOAT | gl::HALF_FLOAT => gl::VertexAttribPointer(
+                    location,
+                    components,
+                    gl_type,
+                    normalized as GLboolean,
+                    stride as GLsizei,
+                    offset as *const _,
+                ),
+                _ if normalized => gl::VertexAttribPointer(
+                    location,
+                    components,
+                    gl_type,
+                    gl::TRUE,
+                    stride as GLsizei,
+                    offset as *const _,
+                ),
+                _ => gl::VertexAttribIPointer(
+                    location,
+                    components,
+                    gl_type,
+                    stride as GLsizei,
+                    offset as *const _,
+                ),
+            }
+            gl::EnableVertexAttribArray(location);
+        }
+    }
+}
+
+impl Default for VertexArray {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for VertexArray {
+    fn drop(&mut self) {
+        self.owner.release("vertex array", self.id, || unsafe {
+            gl::DeleteVertexArrays(1, &self.id);
+        });
+    }
+}
+
+pub struct Texture {
+    id: GLuint,
+    target: GLenum,
+    owner: Owner,
+}
+
+impl Texture {
+    pub fn new(target: GLenum) -> Self {
+        let mut id = 0;
+        unsafe {
+            gl::GenTextures(1, &mut id);
+        }
+        Self {
+            id,
+            target,
+            owner: Owner::new(),
+        }
+    }
+
+    pub fn id(&self) -> GLuint {
+        self.id
+    }
+
+    pub fn target(&self) -> GLenum {
+        self.target
+    }
+
+    /// Gives up the texture without deleting it and returns its name, which the caller now
+    /// has to delete.
+    pub fn release(&mut self) -> GLuint {
+        std::mem::take(&mut self.id)
+    }
+
+    pub fn bind(&self) {
+        if !self.owner.check("Texture", self.id) {
+            return;
+        }
+        unsafe {
+            gl::BindTexture(self.target, self.id);
+        }
+    }
+
+    pub fn bind_unit(&self, unit: GLuint) {
+        if !self.owner.check("Texture", self.id) {
+            return;
+        }
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0 + unit);
+            gl::BindTexture(self.target, self.id);
+        }
+    }
+
+    pub fn parameter_i(&self, name: GLenum, value: GLint) {
+        if !self.owner.check("Texture", self.id) {
+            return;
+        }
+        self.bind();
+        unsafe {
+            gl::TexParameteri(self.target, name, value);
+        }
+    }
+
+    pub fn parameter_f(&self, name: GLenum, value: GLfloat) {
+        if !self.owner.check("Texture", self.id) {
+            return;
+        }
+        self.bind();
+        unsafe {
+            gl::TexParameterf(self.target, name, value);
+        }
+    }
+
+    pub fn generate_mipmaps(&self) {
+        if !self.owner.check("Texture", self.id) {
+            return;
+        }
+        self.bind();
+        unsafe {
+            gl::GenerateMipmap(self.target);
+        }
+    }
+}
+
+impl Drop for Texture {
+    fn drop(&mut self) {
+        self.owner.release("texture", self.id, || unsafe {
+            gl::DeleteTextures(1, &self.id);
+        });
+    }
+}
+
+pub struct Renderbuffer {
+    id: GLuint,
+    owner: Owner,
+}
+
+impl Renderbuffer {
+    pub fn new(internal_format: GLenum, width: u32, height: u32, samples: u32) -> Self {
+        let mut id = 0;
+        unsafe {
+            gl::GenRenderbuffers(1, &mut id);
+            gl::BindRenderbuffer(gl::RENDERBUFFER, id);
+            if samples > 1 {
+                gl::RenderbufferStorageMultisample(
+                    gl::RENDERBUFFER,
+                    samples as GLsizei,
+                    internal_format,
+                    width as GLsizei,
+                    height as GLsizei,
+                );
+            } else {
+                gl::RenderbufferStorage(
+                    gl::RENDERBUFFER,
+                    internal_format,
+                    width as GLsizei,
+                    height as GLsizei,
+                );
+            }
+            gl::BindRenderbuffer(gl::RENDERBUFFER, 0);
+        }
+        Self {
+            id,
+            owner: Owner::new(),
+        }
+    }
+
+    pub fn id(&self) -> GLuint {
+        self.id
+    }
+}
+
+impl Drop for Renderbuffer {
+    fn drop(&mut self) {
+        self.owner.release("renderbuffer", self.id, || unsafe {
+            gl::DeleteRenderbuffers(1, &self.id);
+        });
+    }
+}
+
+pub struct Framebuffer {
+    id: GLuint,
+    owner: Owner,
+}
+
+impl Framebuffer {
+    pub fn new() -> Self {
+        let mut id = 0;
+        unsafe {
+            gl::GenFramebuffers(1, &mut id);
+        }
+        Self {
+            id,
+            owner: Owner::new(),
+        }
+    }
+
+    pub fn id(&self) -> GLuint {
+        self.id
+    }
+
+    pub fn bind(&self) {
+        if !self.owner.check("Framebuffer", self.id) {
+            return;
+        }
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.id);
+        }
+    }
+
+    pub fn unbind(&self) {
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+        }
+    }
+
+    pub fn attach_texture(&self, attachment: GLenum, texture: &Texture, level: GLint) {
+        if !self.owner.check("Framebuffer", self.id) || !texture.owner.check("Texture", texture.id)
+        {
+            return;
+        }
+        self.bind();
+        unsafe {
+            gl::FramebufferTexture(gl::FRAMEBUFFER, attachment, texture.id(), level);
+        }
+    }
+
+    pub fn attach_texture_layer(
+        &self,
+        attachment: GLenum,
+        texture: &Texture,
+        level: GLint,
+        layer: GLint,
+    ) {
+        if !self.owner.check("Framebuffer", self.id) || !texture.owner.check("Texture", texture.id)
+        {
+            return;
+        }
+        self.bind();
+        unsafe {
+            gl::FramebufferTextureLayer(gl::FRAMEBUFFER, attachment, texture.id(), level, layer);
+        }
+    }
+
+    pub fn attach_renderbuffer(&self, attachment: GLenum, renderbuffer: &Renderbuffer) {
+        if !self.owner.check("Framebuffer", self.id)
+            || !renderbuffer.owner.check("Renderbuffer", renderbuffer.id)
+        {
+            return;
+        }
+        self.bind();
+        unsafe {
+            gl::FramebufferRenderbuffer(
+                gl::FRAMEBUFFER,
+                attachment,
+                gl::RENDERBUFFER,
+                renderbuffer.id(),
+            );
+        }
+    }
+
+    pub fn check_status(&self) -> Result<()> {
+        if !self.owner.is_current() {
+            bail!(
+                "Framebuffer {} used outside of the OpenGL context that created it",
+                self.id
+            );
+        }
+        self.bind();
+        let status = unsafe { gl::CheckFramebufferStatus(gl::FRAMEBUFFER) };
+        if status != gl::FRAMEBUFFER_COMPLETE {
+            bail!("Framebuffer {} is incomplete: {:#x}", self.id, status);
+        }
+        Ok(())
+    }
+}
+
+impl Default for Framebuffer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for Framebuffer {
+    fn drop(&mut self) {
+        self.owner.release("framebuffer", self.id, || unsafe {
+            gl::DeleteFramebuffers(1, &self.id);
+        });
+    }
+}
+
+pub struct Sampler {
+    id: GLuint,
+    owner: Owner,
+}
+
+impl Sampler {
+    pub fn new() -> Self {
+        let mut id = 0;
+        unsafe {
+            gl::GenSamplers(1, &mut id);
+        }
+        Self {
+            id,
+            owner: Owner::new(),
+        }
+    }
+
+    pub fn id(&self) -> GLuint {
+        self.id
+    }
+
+    pub fn bind(&self, unit: GLuint) {
+        if !self.owner.check("Sampler", self.id) {
+            return;
+        }
+        unsafe {
+            gl::BindSampler(unit, self.id);
+        }
+    }
+
+    pub fn parameter_i(&self, name: GLenum, value: GLint) {
+        if !self.owner.check("Sampler", self.id) {
+            return;
+        }
+        unsafe {
+            gl::SamplerParameteri(self.id, name, value);
+        }
+    }
+
+    pub fn parameter_f(&self, name: GLenum, value: GLfloat) {
+        if !self.owner.check("Sampler", self.id) {
+            return;
+        }
+        unsafe {
+            gl::SamplerParameterf(self.id, name, value);
+        }
+    }
+}
+
+impl Default for Sampler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for Sampler {
+    fn drop(&mut self) {
+        self.owner.release("sampler", self.id, || unsafe {
+            gl::DeleteSamplers(1, &self.id);
+        });
+    }
+}
+
+pub struct Query {
+    id: GLuint,
+    target: GLenum,
+    owner: Owner,
+}
+
+impl Query {
+    pub fn new(target: GLenum) -> Self {
+        let mut id = 0;
+        unsafe {
+            gl::GenQueries(1, &mut id);
+        }
+        Self {
+            id,
+            target,
+            owner: Owner::new(),
+        }
+    }
+
+    pub fn id(&self) -> GLuint {
+        self.id
+    }
+
+    pub fn begin(&self) {
+        if !self.owner.check("Query", self.id) {
+            return;
+        }
+        unsafe {
+            gl::BeginQuery(self.target, self.id);
+        }
+    }
+
+    pub fn end(&self) {
+        if !self.owner.check("Query", self.id) {
+            return;
+        }
+        unsafe {
+            gl::EndQuery(self.target);
+        }
+    }
+
+    pub fn timestamp(&self) {
+        if !self.owner.check("Query", self.id) {
+            return;
+        }
+        unsafe {
+            gl::QueryCounter(self.id, gl::TIMESTAMP);
+        }
+    }
+
+    pub fn is_available(&self) -> bool {
+        if !self.owner.check("Query", self.id) {
+            return false;
+        }
+        let mut available = 0;
+        unsafe {
+            gl::GetQueryObjectuiv(self.id, gl::QUERY_RESULT_AVAILABLE, &mut available);
+        }
+        available != 0
+    }
+
+    pub fn result(&self) -> u64 {
+        if !self.owner.check("Query", self.id) {
+            return 0;
+        }
+        let mut result = 0;
+        unsafe {
+            gl::GetQueryObjectui64v(self.id, gl::QUERY_RESULT, &mut result);
+        }
+        result
+    }
+}
+
+impl Drop for Query {
+    fn drop(&mut self) {
+        self.owner.release("query", self.id, || unsafe {
+            gl::DeleteQueries(1, &self.id);
+        });
+    }
+}
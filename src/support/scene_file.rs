@@ -0,0 +1,376 @@
+use crate::support::import::CameraData;
+use crate::support::light::Light;
+use crate::support::material::Material;
+use crate::support::mesh::Mesh;
+use crate::support::transform::Transform;
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const SCENE_FILE_VERSION: u32 = 1;
+pub const SCENE_FILE_EXTENSIONS: &[&str] = &["json"];
+
+type Migration = fn(&mut Value) -> Result<()>;
+
+/// `MIGRATIONS[n]` upgrades a version `n + 1` document to version `n + 2`. When the format
+/// changes, bump `SCENE_FILE_VERSION` and append the step that rewrites the previous layout.
+const MIGRATIONS: &[Migration] = &[];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Primitive {
+    Triangle,
+    Cube,
+    Sphere,
+    Plane,
+    Cylinder,
+    Torus,
+}
+
+impl Primitive {
+    pub fn mesh(&self) -> Mesh {
+        match self {
+            Primitive::Triangle => Mesh::triangle(),
+            Primitive::Cube => Mesh::cube(1.0),
+            Primitive::Sphere => Mesh::sphere(0.5, 32, 16),
+            Primitive::Plane => Mesh::plane(1.0, 1.0, 1),
+            Primitive::Cylinder => Mesh::cylinder(0.5, 1.0, 32),
+            Primitive::Torus => Mesh::torus(0.5, 0.2, 32, 16),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MeshSource {
+    Primitive(Primitive),
+    Import { import: usize, mesh: usize },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SceneSettings {
+    pub turntable: Option<usize>,
+    pub turntable_speed: f32,
+    pub wireframe: bool,
+    pub camera: Option<usize>,
+}
+
+impl Default for SceneSettings {
+    fn default() -> Self {
+        Self {
+            turntable: None,
+            turntable_speed: 30.0,
+            wireframe: false,
+            camera: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ImportRecord {
+    pub path: PathBuf,
+    pub root: Option<usize>,
+    #[serde(default)]
+    pub nodes: Vec<Option<usize>>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct NodeRecord {
+    pub name: String,
+    #[serde(default)]
+    pub parent: Option<usize>,
+    #[serde(default = "visible")]
+    pub visible: bool,
+    #[serde(default)]
+    pub transform: Transform,
+    #[serde(default)]
+    pub mesh: Option<usize>,
+    #[serde(default)]
+    pub camera: Option<CameraData>,
+    #[serde(default)]
+    pub light: Option<Light>,
+}
+
+fn visible() -> bool {
+    true
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SceneFile {
+    pub version: u32,
+    #[serde(default)]
+    pub settings: SceneSettings,
+    #[serde(default)]
+    pub imports: Vec<ImportRecord>,
+    #[serde(default)]
+    pub meshes: Vec<MeshSource>,
+    #[serde(default)]
+    pub materials: Vec<Material>,
+    #[serde(default)]
+    pub nodes: Vec<NodeRecord>,
+}
+
+impl SceneFile {
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read scene '{}'", path.display()))?;
+        Self::parse(&text).with_context(|| format!("Failed to load scene '{}'", path.display()))
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut value = serde_json::from_str::<Value>(text).context("Invalid JSON")?;
+        migrate(&mut value)?;
+        let file = serde_json::from_value::<SceneFile>(value)?;
+        file.validate()?;
+        Ok(file)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let text = serde_json::to_string_pretty(self)?;
+        fs::write(path, text).with_context(|| format!("Failed to write scene '{}'", path.display()))
+    }
+
+    /// Checks that every index in the file points at an existing entry, with parents
+    /// written before their children.
+    pub fn validate(&self) -> Result<()> {
+        let node_count = self.nodes.len();
+        for (index, node) in self.nodes.iter().enumerate() {
+            if let Some(parent) = node.parent
+                && parent >= index
+            {
+                bail!(
+                    "Node {} '{}' has parent {} which does not precede it",
+                    index,
+                    node.name,
+                    parent
+                );
+            }
+            if let Some(mesh) = node.mesh
+                && mesh >= self.meshes.len()
+            {
+                bail!(
+                    "Node {} '{}' references missing mesh {}",
+                    index,
+                    node.name,
+                    mesh
+                );
+            }
+        }
+        for (index, mesh) in self.meshes.iter().enumerate() {
+            if let MeshSource::Import { import, .. } = mesh
+                && *import >= self.imports.len()
+            {
+                bail!("Mesh {} references missing import {}", index, import);
+            }
+        }
+        for (index, import) in self.imports.iter().enumerate() {
+            if let Some(node) = import
+                .root
+                .iter()
+                .chain(import.nodes.iter().flatten())
+                .find(|node| **node >= node_count)
+            {
+                bail!(
+                    "Import {} '{}' references missing node {}",
+                    index,
+                    import.path.display(),
+                    node
+                );
+            }
+        }
+        for (setting, node) in [
+            ("turntable", self.settings.turntable),
+            ("camera", self.settings.camera),
+        ] {
+            if let Some(node) = node
+                && node >= node_count
+            {
+                bail!("The {} setting references missing node {}", setting, node);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn migrate(value: &mut Value) -> Result<()> {
+    let Some(version) = value.get("version").and_then(Value::as_u64) else {
+        bail!("Scene file has no version");
+    };
+    let version = version as u32;
+    if version == 0 || version > SCENE_FILE_VERSION {
+        bail!(
+            "Scene file version {} is not supported (expected 1 to {})",
+            version,
+            SCENE_FILE_VERSION
+        );
+    }
+    for (step, migration) in MIGRATIONS.iter().enumerate().skip(version as usize - 1) {
+        migration(value)
+            .with_context(|| format!("Failed to migrate scene from version {}", step + 1))?;
+        value["version"] = Value::from(step as u32 + 2);
+    }
+    Ok(())
+}
+
+/// Makes `path` relative to `directory` when it lives below it, so scenes can be moved
+/// together with their assets.
+pub fn relative_path(path: &Path, directory: &Path) -> PathBuf {
+    let absolute = |path: &Path| fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let path = absolute(path);
+    path.strip_prefix(absolute(directory))
+        .map(Path::to_path_buf)
+        .unwrap_or(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn scene() -> SceneFile {
+        SceneFile {
+            version: SCENE_FILE_VERSION,
+            settings: SceneSettings {
+                turntable: Some(1),
+                turntable_speed: 45.0,
+                wireframe: true,
+                camera: None,
+            },
+            imports: vec![ImportRecord {
+                path: PathBuf::from("models/duck.gltf"),
+                root: Some(1),
+                nodes: vec![Some(1), None],
+            }],
+            meshes: vec![
+                MeshSource::Primitive(Primitive::Cube),
+                MeshSource::Import { import: 0, mesh: 0 },
+            ],
+            materials: Vec::new(),
+            nodes: vec![
+                NodeRecord {
+                    name: "root".to_string(),
+                    parent: None,
+                    visible: true,
+                    transform: Transform::default(),
+                    mesh: Some(0),
+                    camera: None,
+                    light: None,
+                },
+                NodeRecord {
+                    name: "duck".to_string(),
+                    parent: Some(0),
+                    visible: false,
+                    transform: Transform::default(),
+                    mesh: Some(1),
+                    camera: None,
+                    light: None,
+                },
+            ],
+        }
+    }
+
+    fn error(file: &SceneFile) -> String {
+        file.validate().unwrap_err().to_string()
+    }
+
+    #[test]
+    fn saved_scenes_parse_back() {
+        let path =
+            std::env::temp_dir().join(format!("scene_file_test_{}.json", std::process::id()));
+        scene().save(&path).unwrap();
+        let text = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let file = SceneFile::parse(&text).unwrap();
+        assert_eq!(file.version, SCENE_FILE_VERSION);
+        assert_eq!(file.settings.turntable, Some(1));
+        assert_eq!(file.settings.turntable_speed, 45.0);
+        assert!(file.settings.wireframe);
+        assert_eq!(file.imports[0].path, Path::new("models/duck.gltf"));
+        assert_eq!(file.imports[0].nodes, [Some(1), None]);
+        assert_eq!(file.meshes, scene().meshes);
+        assert_eq!(file.nodes[1].name, "duck");
+        assert_eq!(file.nodes[1].parent, Some(0));
+        assert!(!file.nodes[1].visible);
+    }
+
+    #[test]
+    fn fills_in_defaults() {
+        let file = SceneFile::parse(r#"{ "version": 1, "nodes": [{ "name": "empty" }] }"#).unwrap();
+        assert_eq!(file.settings.turntable_speed, 30.0);
+        assert!(file.nodes[0].visible);
+        assert_eq!(file.nodes[0].parent, None);
+    }
+
+    #[test]
+    fn rejects_missing_and_unsupported_versions() {
+        let mut value = json!({ "nodes": [] });
+        assert_eq!(
+            migrate(&mut value).unwrap_err().to_string(),
+            "Scene file has no version"
+        );
+        for version in [json!(0), json!(SCENE_FILE_VERSION + 1), json!("1")] {
+            let mut value = json!({ "version": version });
+            assert!(migrate(&mut value).is_err(), "{}", version);
+        }
+        let mut value = json!({ "version": SCENE_FILE_VERSION });
+        migrate(&mut value).unwrap();
+        assert_eq!(value["version"], SCENE_FILE_VERSION);
+        assert!(SceneFile::parse("{ not json").is_err());
+    }
+
+    #[test]
+    fn rejects_out_of_range_indices() {
+        assert!(scene().validate().is_ok());
+
+        let mut file = scene();
+        file.nodes[0].parent = Some(1);
+        assert_eq!(
+            error(&file),
+            "Node 0 'root' has parent 1 which does not precede it"
+        );
+        file.nodes[0].parent = Some(0);
+        assert!(error(&file).contains("parent 0"));
+
+        let mut file = scene();
+        file.nodes[1].mesh = Some(2);
+        assert_eq!(error(&file), "Node 1 'duck' references missing mesh 2");
+
+        let mut file = scene();
+        file.meshes[1] = MeshSource::Import { import: 1, mesh: 0 };
+        assert_eq!(error(&file), "Mesh 1 references missing import 1");
+
+        let mut file = scene();
+        file.imports[0].root = Some(2);
+        assert_eq!(
+            error(&file),
+            "Import 0 'models/duck.gltf' references missing node 2"
+        );
+        let mut file = scene();
+        file.imports[0].nodes.push(Some(5));
+        assert!(error(&file).contains("missing node 5"));
+
+        let mut file = scene();
+        file.settings.turntable = Some(2);
+        assert_eq!(
+            error(&file),
+            "The turntable setting references missing node 2"
+        );
+        let mut file = scene();
+        file.settings.camera = Some(3);
+        assert_eq!(error(&file), "The camera setting references missing node 3");
+    }
+
+    #[test]
+    fn relative_paths_only_below_the_directory() {
+        let directory = std::env::temp_dir();
+        let inside = directory.join("models").join("duck.gltf");
+        assert_eq!(
+            relative_path(&inside, &directory),
+            Path::new("models/duck.gltf")
+        );
+        let outside = Path::new("/elsewhere/duck.gltf");
+        assert_eq!(relative_path(outside, &directory), outside);
+    }
+}
@@ -0,0 +1,639 @@
+use crate::support::import::CameraData;
+use crate::support::light::Light;
+use crate::support::reflect::Reflect;
+use crate::support::transform::Transform;
+use anyhow::{Result, bail};
+use nalgebra_glm::Mat4;
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NodeId {
+    index: u32,
+    generation: u32,
+}
+
+impl NodeId {
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+}
+
+impl fmt::Display for NodeId {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(formatter, "{}v{}", self.index, self.generation)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Node {
+    pub name: String,
+    pub visible: bool,
+    pub mesh: Option<usize>,
+    pub camera: Option<CameraData>,
+    pub light: Option<Light>,
+    transform: Transform,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+    world: Mat4,
+    world_visible: bool,
+    dirty: bool,
+}
+
+impl Node {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            visible: true,
+            mesh: None,
+            camera: None,
+            light: None,
+            transform: Transform::IDENTITY,
+            parent: None,
+            children: Vec::new(),
+            world: Mat4::identity(),
+            world_visible: true,
+            dirty: true,
+        }
+    }
+
+    pub fn with_transform(mut self, transform: Transform) -> Self {
+        self.transform = transform;
+        self
+    }
+
+    pub fn with_mesh(mut self, mesh: usize) -> Self {
+        self.mesh = Some(mesh);
+        self
+    }
+
+    pub fn with_camera(mut self, camera: CameraData) -> Self {
+        self.camera = Some(camera);
+        self
+    }
+
+    pub fn with_light(mut self, light: Light) -> Self {
+        self.light = Some(light);
+        self
+    }
+
+    pub fn transform(&self) -> &Transform {
+        &self.transform
+    }
+
+    pub fn transform_mut(&mut self) -> &mut Transform {
+        self.dirty = true;
+        &mut self.transform
+    }
+
+    pub fn set_transform(&mut self, transform: Transform) {
+        self.transform = transform;
+        self.dirty = true;
+    }
+
+    pub fn components_mut(&mut self) -> Vec<&mut dyn Reflect> {
+        let mut components = Vec::<&mut dyn Reflect>::new();
+        if let Some(camera) = &mut self.camera {
+            components.push(camera);
+        }
+        if let Some(light) = &mut self.light {
+            components.push(light);
+        }
+        components
+    }
+
+    pub fn parent(&self) -> Option<NodeId> {
+        self.parent
+    }
+
+    pub fn children(&self) -> &[NodeId] {
+        &self.children
+    }
+
+    pub fn world_matrix(&self) -> &Mat4 {
+        &self.world
+    }
+
+    pub fn is_visible_in_hierarchy(&self) -> bool {
+        self.world_visible
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+struct Slot {
+    generation: u32,
+    node: Option<Node>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct SceneGraph {
+    slots: Vec<Slot>,
+    free: Vec<u32>,
+    roots: Vec<NodeId>,
+    len: usize,
+}
+
+impl SceneGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    pub fn contains(&self, id: NodeId) -> bool {
+        self.get(id).is_some()
+    }
+
+    pub fn get(&self, id: NodeId) -> Option<&Node> {
+        self.slots
+            .get(id.index as usize)
+            .filter(|slot| slot.generation == id.generation)
+            .and_then(|slot| slot.node.as_ref())
+    }
+
+    pub fn get_mut(&mut self, id: NodeId) -> Option<&mut Node> {
+        self.slots
+            .get_mut(id.index as usize)
+            .filter(|slot| slot.generation == id.generation)
+            .and_then(|slot| slot.node.as_mut())
+    }
+
+    pub fn roots(&self) -> &[NodeId] {
+        &self.roots
+    }
+
+    pub fn add(&mut self, node: Node, parent: Option<NodeId>) -> Result<NodeId> {
+        if let Some(parent) = parent
+            && !self.contains(parent)
+        {
+            bail!("Parent node {} does not exist", parent);
+        }
+
+        let id = match self.free.pop() {
+            Some(index) => NodeId {
+                index,
+                generation: self.slots[index as usize].generation,
+            },
+            None => {
+                self.slots.push(Slot::default());
+                NodeId {
+                    index: self.slots.len() as u32 - 1,
+                    generation: 0,
+                }
+            }
+        };
+
+        let mut node = node;
+        node.parent = parent;
+        node.children.clear();
+        node.dirty = true;
+        self.slots[id.index as usize].node = Some(node);
+        self.len += 1;
+        self.siblings_mut(parent).push(id);
+        Ok(id)
+    }
+
+    pub fn remove(&mut self, id: NodeId) -> Option<Node> {
+        let parent = self.get(id)?.parent;
+        self.siblings_mut(parent).retain(|sibling| *sibling != id);
+
+        for descendant in self.descendants(id).into_iter().skip(1) {
+            self.take(descendant);
+        }
+        let mut node = self.take(id)?;
+        node.parent = None;
+        node.children.clear();
+        Some(node)
+    }
+
+    pub fn set_parent(
+        &mut self,
+        id: NodeId,
+        parent: Option<NodeId>,
+        keep_world_transform: bool,
+    ) -> Result<()> {
+        self.insert_at(id, parent, usize::MAX, keep_world_transform)
+    }
+
+    pub fn insert_at(
+        &mut self,
+        id: NodeId,
+        parent: Option<NodeId>,
+        position: usize,
+        keep_world_transform: bool,
+    ) -> Result<()> {
+        let Some(node) = self.get(id) else {
+            bail!("Node {} does not exist", id);
+        };
+        let old_parent = node.parent;
+        if let Some(parent) = parent {
+            if !self.contains(parent) {
+                bail!("Parent node {} does not exist", parent);
+            }
+            if self.ancestors(parent).contains(&id) || parent == id {
+                bail!("Cannot parent node {} to its own descendant {}", id, parent);
+            }
+        }
+
+        let world = self.compute_world_matrix(id);
+        self.siblings_mut(old_parent)
+            .retain(|sibling| *sibling != id);
+        let siblings = self.siblings_mut(parent);
+        siblings.insert(position.min(siblings.len()), id);
+
+        let parent_world =
+            parent.map_or_else(Mat4::identity, |parent| self.compute_world_matrix(parent));
+        if let Some(node) = self.get_mut(id) {
+            node.parent = parent;
+            node.dirty = true;
+            if keep_world_transform && let Some(inverse) = parent_world.try_inverse() {
+                node.transform = Transform::from_matrix(&(inverse * world));
+            }
+        }
+        Ok(())
+    }
+
+    /// Moves `nodes` under `parent` starting at `position`, in order. Every move is checked
+    /// before any is made, so a failure leaves the graph unchanged. None of `nodes` may be an
+    /// ancestor of another.
+    pub fn move_nodes(
+        &mut self,
+        nodes: &[NodeId],
+        parent: Option<NodeId>,
+        position: usize,
+        keep_world_transform: bool,
+    ) -> Result<()> {
+        if let Some(parent) = parent {
+            if !self.contains(parent) {
+                bail!("Parent node {} does not exist", parent);
+            }
+            let lineage = self.ancestors(parent);
+            if let Some(node) = nodes
+                .iter()
+                .find(|node| **node == parent || lineage.contains(node))
+            {
+                bail!(
+                    "Cannot parent node {} to its own descendant {}",
+                    node,
+                    parent
+                );
+            }
+        }
+        if let Some(node) = nodes.iter().find(|node| !self.contains(**node)) {
+            bail!("Node {} does not exist", node);
+        }
+
+        // Detach first so positions count only the siblings that stay.
+        for node in nodes {
+            self.set_parent(*node, None, keep_world_transform)?;
+        }
+        for (offset, node) in nodes.iter().enumerate() {
+            self.insert_at(
+                *node,
+                parent,
+                position.saturating_add(offset),
+                keep_world_transform,
+            )?;
+        }
+        Ok(())
+    }
+
+    pub fn ancestors(&self, id: NodeId) -> Vec<NodeId> {
+        let mut ancestors = Vec::new();
+        let mut current = self.get(id).and_then(Node::parent);
+        while let Some(parent) = current {
+            ancestors.push(parent);
+            current = self.get(parent).and_then(Node::parent);
+        }
+        ancestors
+    }
+
+    pub fn descendants(&self, id: NodeId) -> Vec<NodeId> {
+        let mut descendants = Vec::new();
+        let mut stack = vec![id];
+        while let Some(current) = stack.pop() {
+            let Some(node) = self.get(current) else {
+                continue;
+            };
+            descendants.push(current);
+            stack.extend(node.children.iter().rev());
+        }
+        descendants
+    }
+
+    pub fn depth_first(&self) -> Vec<NodeId> {
+        self.roots
+            .iter()
+            .flat_map(|root| self.descendants(*root))
+            .collect()
+    }
+
+    pub fn traverse(&self, mut visit: impl FnMut(NodeId, &Node, usize)) {
+        let mut stack = self
+            .roots
+            .iter()
+            .rev()
+            .map(|root| (*root, 0))
+            .collect::<Vec<_>>();
+        while let Some((id, depth)) = stack.pop() {
+            let Some(node) = self.get(id) else {
+                continue;
+            };
+            visit(id, node, depth);
+            stack.extend(node.children.iter().rev().map(|child| (*child, depth + 1)));
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (NodeId, &Node)> {
+        self.slots.iter().enumerate().filter_map(|(index, slot)| {
+            slot.node.as_ref().map(|node| {
+                (
+                    NodeId {
+                        index: index as u32,
+                        generation: slot.generation,
+                    },
+                    node,
+                )
+            })
+        })
+    }
+
+    pub fn find(&self, predicate: impl Fn(&Node) -> bool) -> Vec<NodeId> {
+        self.depth_first()
+            .into_iter()
+            .filter(|id| self.get(*id).is_some_and(&predicate))
+            .collect()
+    }
+
+    pub fn find_by_name(&self, name: &str) -> Option<NodeId> {
+        self.find(|node| node.name == name).into_iter().next()
+    }
+
+    pub fn meshes(&self) -> Vec<NodeId> {
+        self.find(|node| node.mesh.is_some())
+    }
+
+    pub fn cameras(&self) -> Vec<NodeId> {
+        self.find(|node| node.camera.is_some())
+    }
+
+    pub fn lights(&self) -> Vec<NodeId> {
+        self.find(|node| node.light.is_some())
+    }
+
+    pub fn update_world_transforms(&mut self) {
+        let mut stack = self
+            .roots
+            .iter()
+            .map(|root| (*root, Mat4::identity(), true, false))
+            .collect::<Vec<_>>();
+        while let Some((id, parent_world, parent_visible, parent_changed)) = stack.pop() {
+            let Some(node) = self.get_mut(id) else {
+                continue;
+            };
+            let changed = parent_changed || node.dirty;
+            if changed {
+                node.world = parent_world * node.transform.matrix();
+                node.dirty = false;
+            }
+            node.world_visible = parent_visible && node.visible;
+            let (world, visible) = (node.world, node.world_visible);
+            stack.extend(
+                node.children
+                    .iter()
+                    .map(|child| (*child, world, visible, changed)),
+            );
+        }
+    }
+
+    pub fn compute_world_matrix(&self, id: NodeId) -> Mat4 {
+        let mut world = Mat4::identity();
+        let mut current = Some(id);
+        while let Some(node) = current.and_then(|current| self.get(current)) {
+            world = node.transform.matrix() * world;
+            current = node.parent;
+        }
+        world
+    }
+
+    fn siblings_mut(&mut self, parent: Option<NodeId>) -> &mut Vec<NodeId> {
+        match parent.and_then(|parent| {
+            self.slots
+                .get_mut(parent.index as usize)
+                .and_then(|slot| slot.node.as_mut())
+        }) {
+            Some(parent) => &mut parent.children,
+            None => &mut self.roots,
+        }
+    }
+
+    fn take(&mut self, id: NodeId) -> Option<Node> {
+        let slot = self
+            .slots
+            .get_mut(id.index as usize)
+            .filter(|slot| slot.generation == id.generation)?;
+        let node = slot.node.take()?;
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free.push(id.index);
+        self.len -= 1;
+        Some(node)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra_glm::Vec3;
+
+    fn names(graph: &SceneGraph, ids: &[NodeId]) -> Vec<String> {
+        ids.iter()
+            .map(|id| graph.get(*id).unwrap().name.clone())
+            .collect()
+    }
+
+    #[test]
+    fn moving_onto_itself_or_a_descendant_changes_nothing() {
+        let mut graph = SceneGraph::new();
+        let a = graph.add(Node::new("a"), None).unwrap();
+        let b = graph.add(Node::new("b"), Some(a)).unwrap();
+        let c = graph.add(Node::new("c"), Some(b)).unwrap();
+        let d = graph.add(Node::new("d"), None).unwrap();
+
+        for target in [a, b, c] {
+            assert!(graph.move_nodes(&[d, a], Some(target), 0, true).is_err());
+            assert_eq!(names(&graph, graph.roots()), ["a", "d"]);
+            assert_eq!(graph.get(b).unwrap().parent(), Some(a));
+            assert_eq!(graph.get(c).unwrap().parent(), Some(b));
+        }
+    }
+
+    #[test]
+    fn moves_nodes_in_order_at_a_position() {
+        let mut graph = SceneGraph::new();
+        let parent = graph.add(Node::new("parent"), None).unwrap();
+        let x = graph.add(Node::new("x"), Some(parent)).unwrap();
+        graph.add(Node::new("y"), Some(parent)).unwrap();
+        let a = graph.add(Node::new("a"), None).unwrap();
+        let b = graph.add(Node::new("b"), None).unwrap();
+
+        graph.move_nodes(&[b, a], Some(parent), 1, true).unwrap();
+        assert_eq!(
+            names(&graph, graph.get(parent).unwrap().children()),
+            ["x", "b", "a", "y"]
+        );
+        assert_eq!(names(&graph, graph.roots()), ["parent"]);
+
+        // Positions count only the siblings that stay behind.
+        graph.move_nodes(&[x], Some(parent), 2, true).unwrap();
+        assert_eq!(
+            names(&graph, graph.get(parent).unwrap().children()),
+            ["b", "a", "x", "y"]
+        );
+    }
+
+    fn translation(world: &Mat4) -> Vec3 {
+        world.column(3).xyz()
+    }
+
+    #[test]
+    fn reparenting_keeps_or_replaces_the_world_transform() {
+        let mut graph = SceneGraph::new();
+        let parent = graph
+            .add(
+                Node::new("parent")
+                    .with_transform(Transform::from_translation(Vec3::new(10.0, 0.0, 0.0))),
+                None,
+            )
+            .unwrap();
+        let child = graph
+            .add(
+                Node::new("child")
+                    .with_transform(Transform::from_translation(Vec3::new(1.0, 2.0, 3.0))),
+                None,
+            )
+            .unwrap();
+
+        graph.set_parent(child, Some(parent), true).unwrap();
+        assert_eq!(graph.get(child).unwrap().parent(), Some(parent));
+        assert_eq!(graph.get(parent).unwrap().children(), [child]);
+        assert_eq!(graph.roots(), [parent]);
+        assert!(
+            (translation(&graph.compute_world_matrix(child)) - Vec3::new(1.0, 2.0, 3.0)).norm()
+                < 1e-5
+        );
+        assert!(
+            (graph.get(child).unwrap().transform().translation - Vec3::new(-9.0, 2.0, 3.0)).norm()
+                < 1e-5
+        );
+
+        graph.set_parent(child, None, false).unwrap();
+        assert_eq!(graph.get(child).unwrap().parent(), None);
+        assert!(graph.get(parent).unwrap().children().is_empty());
+        assert_eq!(graph.roots(), [parent, child]);
+        assert!(
+            (translation(&graph.compute_world_matrix(child)) - Vec3::new(-9.0, 2.0, 3.0)).norm()
+                < 1e-5
+        );
+
+        assert!(graph.set_parent(parent, Some(parent), true).is_err());
+        graph.set_parent(child, Some(parent), true).unwrap();
+        assert!(graph.set_parent(parent, Some(child), true).is_err());
+        assert_eq!(graph.roots(), [parent]);
+    }
+
+    #[test]
+    fn dirty_transforms_propagate_to_descendants() {
+        let mut graph = SceneGraph::new();
+        let root = graph.add(Node::new("root"), None).unwrap();
+        let middle = graph
+            .add(
+                Node::new("middle")
+                    .with_transform(Transform::from_translation(Vec3::new(0.0, 1.0, 0.0))),
+                Some(root),
+            )
+            .unwrap();
+        let leaf = graph
+            .add(
+                Node::new("leaf")
+                    .with_transform(Transform::from_translation(Vec3::new(0.0, 0.0, 1.0))),
+                Some(middle),
+            )
+            .unwrap();
+        let sibling = graph.add(Node::new("sibling"), Some(root)).unwrap();
+        graph.update_world_transforms();
+        assert_eq!(
+            translation(graph.get(leaf).unwrap().world_matrix()),
+            Vec3::new(0.0, 1.0, 1.0)
+        );
+
+        graph.get_mut(root).unwrap().transform_mut().translation = Vec3::new(5.0, 0.0, 0.0);
+        // World matrices are cached until the next update.
+        assert_eq!(
+            translation(graph.get(leaf).unwrap().world_matrix()),
+            Vec3::new(0.0, 1.0, 1.0)
+        );
+        graph.update_world_transforms();
+        assert_eq!(
+            translation(graph.get(leaf).unwrap().world_matrix()),
+            Vec3::new(5.0, 1.0, 1.0)
+        );
+        assert_eq!(
+            translation(graph.get(sibling).unwrap().world_matrix()),
+            Vec3::new(5.0, 0.0, 0.0)
+        );
+
+        // Reparenting marks the node dirty, even when its local transform stays.
+        graph.set_parent(leaf, None, false).unwrap();
+        graph.update_world_transforms();
+        assert_eq!(
+            translation(graph.get(leaf).unwrap().world_matrix()),
+            Vec3::new(0.0, 0.0, 1.0)
+        );
+
+        graph.get_mut(middle).unwrap().visible = false;
+        graph.set_parent(leaf, Some(middle), true).unwrap();
+        graph.update_world_transforms();
+        assert!(!graph.get(leaf).unwrap().is_visible_in_hierarchy());
+        assert!(graph.get(sibling).unwrap().is_visible_in_hierarchy());
+    }
+
+    #[test]
+    fn removed_ids_are_not_reused() {
+        let mut graph = SceneGraph::new();
+        let parent = graph.add(Node::new("parent"), None).unwrap();
+        let child = graph.add(Node::new("child"), Some(parent)).unwrap();
+        assert_eq!(graph.len(), 2);
+
+        let removed = graph.remove(parent).unwrap();
+        assert_eq!(removed.name, "parent");
+        assert!(removed.children().is_empty());
+        assert!(graph.is_empty());
+        assert!(!graph.contains(parent));
+        assert!(!graph.contains(child));
+        assert!(graph.remove(parent).is_none());
+
+        // The freed slots come back with a new generation, so stale ids stay dead.
+        let reused = graph.add(Node::new("reused"), None).unwrap();
+        assert!([parent.index(), child.index()].contains(&reused.index()));
+        assert_eq!(reused.generation(), 1);
+        assert!(graph.get(parent).is_none() && graph.get(child).is_none());
+        assert!(graph.get_mut(parent).is_none());
+        assert!(graph.add(Node::new("orphan"), Some(child)).is_err());
+        assert!(graph.set_parent(reused, Some(parent), true).is_err());
+        assert_eq!(names(&graph, graph.roots()), ["reused"]);
+        assert_eq!(graph.len(), 1);
+    }
+}
@@ -0,0 +1,441 @@
+use crate::support::scene_graph::{NodeId, SceneGraph};
+use std::collections::HashSet;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NodeTemplate {
+    Empty,
+    Cube,
+    Sphere,
+    Plane,
+    Cylinder,
+    Torus,
+    DirectionalLight,
+    PointLight,
+    SpotLight,
+    Camera,
+}
+
+impl NodeTemplate {
+    pub const ALL: [NodeTemplate; 10] = [
+        NodeTemplate::Empty,
+        NodeTemplate::Cube,
+        NodeTemplate::Sphere,
+        NodeTemplate::Plane,
+        NodeTemplate::Cylinder,
+        NodeTemplate::Torus,
+        NodeTemplate::DirectionalLight,
+        NodeTemplate::PointLight,
+        NodeTemplate::SpotLight,
+        NodeTemplate::Camera,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            NodeTemplate::Empty => "Empty",
+            NodeTemplate::Cube => "Cube",
+            NodeTemplate::Sphere => "Sphere",
+            NodeTemplate::Plane => "Plane",
+            NodeTemplate::Cylinder => "Cylinder",
+            NodeTemplate::Torus => "Torus",
+            NodeTemplate::DirectionalLight => "Directional Light",
+            NodeTemplate::PointLight => "Point Light",
+            NodeTemplate::SpotLight => "Spot Light",
+            NodeTemplate::Camera => "Camera",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SceneTreeAction {
+    Create {
+        template: NodeTemplate,
+        parent: Option<NodeId>,
+    },
+    Duplicate(Vec<NodeId>),
+    Delete(Vec<NodeId>),
+    Rename(NodeId, String),
+    SetVisible(NodeId, bool),
+    Reparent {
+        nodes: Vec<NodeId>,
+        parent: Option<NodeId>,
+        position: usize,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum DropPosition {
+    Before,
+    Inside,
+    After,
+}
+
+#[derive(Debug, Default)]
+pub struct SceneTreePanel {
+    selection: Vec<NodeId>,
+    anchor: Option<NodeId>,
+    renaming: Option<(NodeId, String)>,
+    filter: String,
+}
+
+impl SceneTreePanel {
+    pub fn selection(&self) -> &[NodeId] {
+        &self.selection
+    }
+
+    pub fn primary_selection(&self) -> Option<NodeId> {
+        self.selection.last().copied()
+    }
+
+    pub fn is_selected(&self, id: NodeId) -> bool {
+        self.selection.contains(&id)
+    }
+
+    pub fn select(&mut self, id: NodeId) {
+        self.selection = vec![id];
+        self.anchor = Some(id);
+    }
+
+    pub fn set_selection(&mut self, selection: Vec<NodeId>) {
+        self.anchor = selection.last().copied();
+        self.selection = selection;
+    }
+
+    pub fn clear_selection(&mut self) {
+        self.selection.clear();
+        self.anchor = None;
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui, graph: &SceneGraph) -> Vec<SceneTreeAction> {
+        self.selection.retain(|id| graph.contains(*id));
+        if self
+            .renaming
+            .as_ref()
+            .is_some_and(|(id, _)| !graph.contains(*id))
+        {
+            self.renaming = None;
+        }
+
+        let mut actions = Vec::new();
+        ui.horizontal(|ui| {
+            ui.label("Search:");
+            ui.text_edit_singleline(&mut self.filter);
+            if !self.filter.is_empty() && ui.small_button("x").clicked() {
+                self.filter.clear();
+            }
+        });
+        ui.separator();
+
+        let matches = self.matching_nodes(graph);
+        let order = graph
+            .depth_first()
+            .into_iter()
+            .filter(|id| matches.as_ref().is_none_or(|matches| matches.contains(id)))
+            .collect::<Vec<_>>();
+
+        let response = egui::ScrollArea::vertical()
+            .auto_shrink([false, false])
+            .show(ui, |ui| {
+                for root in graph.roots() {
+                    self.show_node(ui, graph, *root, &matches, &order, &mut actions);
+                }
+                ui.allocate_response(
+                    egui::vec2(ui.available_width(), ui.available_height().max(24.0)),
+                    egui::Sense::click(),
+                )
+            })
+            .inner;
+
+        if let Some(nodes) = response.dnd_release_payload::<Vec<NodeId>>() {
+            actions.push(SceneTreeAction::Reparent {
+                nodes: nodes.to_vec(),
+                parent: None,
+                position: usize::MAX,
+            });
+        }
+        if response.clicked() {
+            self.clear_selection();
+        }
+        response.context_menu(|ui| {
+            create_menu(ui, None, &mut actions);
+        });
+
+        if self.renaming.is_none() && ui.ui_contains_pointer() {
+            let (delete, duplicate, rename) = ui.input(|input| {
+                (
+                    input.key_pressed(egui::Key::Delete),
+                    input.modifiers.command && input.key_pressed(egui::Key::D),
+                    input.key_pressed(egui::Key::F2),
+                )
+            });
+            if delete && !self.selection.is_empty() {
+                actions.push(SceneTreeAction::Delete(self.selection.clone()));
+            }
+            if duplicate && !self.selection.is_empty() {
+                actions.push(SceneTreeAction::Duplicate(self.selection.clone()));
+            }
+            if rename
+                && let Some(id) = self.primary_selection()
+                && let Some(node) = graph.get(id)
+            {
+                self.renaming = Some((id, node.name.clone()));
+            }
+        }
+
+        actions
+    }
+
+    fn matching_nodes(&self, graph: &SceneGraph) -> Option<HashSet<NodeId>> {
+        let filter = self.filter.trim().to_lowercase();
+        if filter.is_empty() {
+            return None;
+        }
+        let mut visible = HashSet::new();
+        for id in graph.find(|node| node.name.to_lowercase().contains(&filter)) {
+            visible.insert(id);
+            visible.extend(graph.ancestors(id));
+        }
+        Some(visible)
+    }
+
+    fn show_node(
+        &mut self,
+        ui: &mut egui::Ui,
+        graph: &SceneGraph,
+        id: NodeId,
+        matches: &Option<HashSet<NodeId>>,
+        order: &[NodeId],
+        actions: &mut Vec<SceneTreeAction>,
+    ) {
+        if matches
+            .as_ref()
+            .is_some_and(|matches| !matches.contains(&id))
+        {
+            return;
+        }
+        let Some(node) = graph.get(id) else {
+            return;
+        };
+
+        if node.children().is_empty() {
+            ui.horizontal(|ui| {
+                ui.add_space(ui.spacing().indent);
+                self.show_row(ui, graph, id, order, actions);
+            });
+            return;
+        }
+
+        let mut state = egui::collapsing_header::CollapsingState::load_with_default_open(
+            ui.ctx(),
+            ui.make_persistent_id(("scene_tree_node", id)),
+            true,
+        );
+        if matches.is_some() {
+            state.set_open(true);
+        }
+        state
+            .show_header(ui, |ui| self.show_row(ui, graph, id, order, actions))
+            .body(|ui| {
+                for child in node.children() {
+                    self.show_node(ui, graph, *child, matches, order, actions);
+                }
+            });
+    }
+
+    fn show_row(
+        &mut self,
+        ui: &mut egui::Ui,
+        graph: &SceneGraph,
+        id: NodeId,
+        order: &[NodeId],
+        actions: &mut Vec<SceneTreeAction>,
+    ) {
+        let Some(node) = graph.get(id) else {
+            return;
+        };
+
+        let mut visible = node.visible;
+        if ui
+            .checkbox(&mut visible, "")
+            .on_hover_text("Visible")
+            .changed()
+        {
+            actions.push(SceneTreeAction::SetVisible(id, visible));
+        }
+
+        if let Some((renaming, name)) = &mut self.renaming
+            && *renaming == id
+        {
+            let response = ui.text_edit_singleline(name);
+            response.request_focus();
+            let (commit, cancel) = ui.input(|input| {
+                (
+                    input.key_pressed(egui::Key::Enter),
+                    input.key_pressed(egui::Key::Escape),
+                )
+            });
+            if cancel {
+                self.renaming = None;
+            } else if commit || response.lost_focus() {
+                let name = name.trim().to_string();
+                if !name.is_empty() && name != node.name {
+                    actions.push(SceneTreeAction::Rename(id, name));
+                }
+                self.renaming = None;
+            }
+            return;
+        }
+
+        let mut text = egui::RichText::new(&node.name);
+        if !node.is_visible_in_hierarchy() {
+            text = text.weak();
+        }
+        let response = ui.add(
+            egui::Button::selectable(self.is_selected(id), text)
+                .sense(egui::Sense::click_and_drag()),
+        );
+
+        if response.drag_started() {
+            if !self.is_selected(id) {
+                self.select(id);
+            }
+            response.dnd_set_drag_payload(self.selection.clone());
+        }
+        if response.dnd_hover_payload::<Vec<NodeId>>().is_some() {
+            let position = drop_position(ui, &response);
+            let rect = response.rect;
+            let stroke = ui.visuals().selection.stroke;
+            let painter = ui.painter();
+            match position {
+                DropPosition::Before => {
+                    painter.hline(rect.x_range(), rect.top(), stroke);
+                }
+                DropPosition::After => {
+                    painter.hline(rect.x_range(), rect.bottom(), stroke);
+                }
+                DropPosition::Inside => {
+                    painter.rect_stroke(rect, 2.0, stroke, egui::StrokeKind::Inside);
+                }
+            }
+            if let Some(dropped) = response.dnd_release_payload::<Vec<NodeId>>() {
+                actions.push(reparent_action(graph, id, dropped.to_vec(), position));
+            }
+        }
+
+        if response.double_clicked() {
+            self.renaming = Some((id, node.name.clone()));
+        } else if response.clicked() {
+            let modifiers = ui.input(|input| input.modifiers);
+            self.click(id, modifiers, order);
+        }
+
+        response.context_menu(|ui| {
+            if !self.is_selected(id) {
+                self.select(id);
+            }
+            ui.menu_button("Create Child", |ui| {
+                create_menu(ui, Some(id), actions);
+            });
+            if ui.button("Rename").clicked() {
+                self.renaming = Some((id, node.name.clone()));
+                ui.close();
+            }
+            if ui.button("Duplicate").clicked() {
+                actions.push(SceneTreeAction::Duplicate(self.selection.clone()));
+                ui.close();
+            }
+            if ui.button("Delete").clicked() {
+                actions.push(SceneTreeAction::Delete(self.selection.clone()));
+                ui.close();
+            }
+        });
+    }
+
+    fn click(&mut self, id: NodeId, modifiers: egui::Modifiers, order: &[NodeId]) {
+        if modifiers.shift
+            && let Some(anchor) = self.anchor
+            && let (Some(start), Some(end)) = (
+                order.iter().position(|node| *node == anchor),
+                order.iter().position(|node| *node == id),
+            )
+        {
+            let range = &order[start.min(end)..=start.max(end)];
+            if !modifiers.command {
+                self.selection.clear();
+            }
+            for node in range {
+                if !self.selection.contains(node) {
+                    self.selection.push(*node);
+                }
+            }
+            self.selection.retain(|node| *node != id);
+            self.selection.push(id);
+        } else if modifiers.command {
+            if self.is_selected(id) {
+                self.selection.retain(|node| *node != id);
+            } else {
+                self.selection.push(id);
+            }
+            self.anchor = Some(id);
+        } else {
+            self.select(id);
+        }
+    }
+}
+
+fn drop_position(ui: &egui::Ui, response: &egui::Response) -> DropPosition {
+    let Some(pointer) = ui.input(|input| input.pointer.interact_pos()) else {
+        return DropPosition::Inside;
+    };
+    let quarter = response.rect.height() / 4.0;
+    if pointer.y < response.rect.top() + quarter {
+        DropPosition::Before
+    } else if pointer.y > response.rect.bottom() - quarter {
+        DropPosition::After
+    } else {
+        DropPosition::Inside
+    }
+}
+
+fn reparent_action(
+    graph: &SceneGraph,
+    target: NodeId,
+    nodes: Vec<NodeId>,
+    position: DropPosition,
+) -> SceneTreeAction {
+    if position == DropPosition::Inside {
+        return SceneTreeAction::Reparent {
+            nodes,
+            parent: Some(target),
+            position: usize::MAX,
+        };
+    }
+
+    let parent = graph.get(target).and_then(|node| node.parent());
+    let siblings = match parent.and_then(|parent| graph.get(parent)) {
+        Some(parent) => parent.children(),
+        None => graph.roots(),
+    };
+    let index = siblings
+        .iter()
+        .filter(|sibling| !nodes.contains(sibling))
+        .position(|sibling| *sibling == target)
+        .unwrap_or(siblings.len());
+    SceneTreeAction::Reparent {
+        nodes,
+        parent,
+        position: if position == DropPosition::After {
+            index + 1
+        } else {
+            index
+        },
+    }
+}
+
+fn create_menu(ui: &mut egui::Ui, parent: Option<NodeId>, actions: &mut Vec<SceneTreeAction>) {
+    for template in NodeTemplate::ALL {
+        if ui.button(template.name()).clicked() {
+            actions.push(SceneTreeAction::Create { template, parent });
+            ui.close();
+        }
+    }
+}
@@ -1,8 +1,14 @@
+use crate::support::program_cache::ProgramCache;
+use crate::support::shader_error::{
+    Severity, ShaderCompileError, ShaderError, ShaderLinkError, parse_info_log,
+};
+use crate::support::transform_feedback::TransformFeedbackMode;
 use anyhow::{Result, anyhow};
 pub use gl::types::*;
 use std::ffi::CString;
 use std::{fs, ptr};
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum ShaderKind {
     Vertex,
     Fragment,
@@ -12,25 +18,91 @@ pub enum ShaderKind {
     Compute,
 }
 
-#[derive(Default)]
+impl ShaderKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ShaderKind::Vertex => "vertex",
+            ShaderKind::Fragment => "fragment",
+            ShaderKind::Geometry => "geometry",
+            ShaderKind::TessellationControl => "tessellation control",
+            ShaderKind::TessellationEvaluation => "tessellation evaluation",
+            ShaderKind::Compute => "compute",
+        }
+    }
+
+    pub fn stage_bit(&self) -> GLbitfield {
+        match self {
+            ShaderKind::Vertex => gl::VERTEX_SHADER_BIT,
+            ShaderKind::Fragment => gl::FRAGMENT_SHADER_BIT,
+            ShaderKind::Geometry => gl::GEOMETRY_SHADER_BIT,
+            ShaderKind::TessellationControl => gl::TESS_CONTROL_SHADER_BIT,
+            ShaderKind::TessellationEvaluation => gl::TESS_EVALUATION_SHADER_BIT,
+            ShaderKind::Compute => gl::COMPUTE_SHADER_BIT,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProgramResource {
+    pub name: String,
+    pub location: GLint,
+    pub gl_type: GLenum,
+    pub array_size: GLint,
+}
+
+impl ProgramResource {
+    pub fn is_builtin(&self) -> bool {
+        self.name.starts_with("gl_")
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        match self.gl_type {
+            gl::FLOAT => "float",
+            gl::FLOAT_VEC2 => "vec2",
+            gl::FLOAT_VEC3 => "vec3",
+            gl::FLOAT_VEC4 => "vec4",
+            gl::INT => "int",
+            gl::INT_VEC2 => "ivec2",
+            gl::INT_VEC3 => "ivec3",
+            gl::INT_VEC4 => "ivec4",
+            gl::UNSIGNED_INT => "uint",
+            gl::UNSIGNED_INT_VEC2 => "uvec2",
+            gl::UNSIGNED_INT_VEC3 => "uvec3",
+            gl::UNSIGNED_INT_VEC4 => "uvec4",
+            gl::FLOAT_MAT2 => "mat2",
+            gl::FLOAT_MAT3 => "mat3",
+            gl::FLOAT_MAT4 => "mat4",
+            _ => "unknown",
+        }
+    }
+}
+
 pub struct Shader {
     pub id: GLuint,
+    pub kind: ShaderKind,
+    pub warnings: Vec<ShaderError>,
 }
 
 impl Shader {
     pub fn new(shader_type: ShaderKind) -> Shader {
         Shader {
             id: unsafe { gl::CreateShader(Shader::map_type(&shader_type)) },
+            kind: shader_type,
+            warnings: Vec::new(),
         }
     }
 
     pub fn load_file(&mut self, path: &str) -> Result<()> {
         let source = fs::read_to_string(path)
             .map_err(|error| anyhow!("Failed to read shader file '{}': {}", path, error))?;
-        self.load(&source)
+        self.compile(&source, path)
     }
 
     pub fn load(&mut self, source: &str) -> Result<()> {
+        self.compile(source, "<source>")
+    }
+
+    pub fn compile(&mut self, source: &str, file: &str) -> Result<()> {
         let source_str = CString::new(source.as_bytes())
             .map_err(|error| anyhow!("Shader source contains null byte: {}", error))?;
 
@@ -39,35 +111,62 @@ impl Shader {
             gl::CompileShader(self.id);
         }
 
-        self.check_compile_status()
+        self.check_compile_status(source, file)
     }
 
-    fn check_compile_status(&self) -> Result<()> {
+    fn check_compile_status(&mut self, source: &str, file: &str) -> Result<()> {
         let mut success = 0;
         unsafe {
             gl::GetShaderiv(self.id, gl::COMPILE_STATUS, &mut success);
-            if success == 0 {
-                let mut length = 0;
-                gl::GetShaderiv(self.id, gl::INFO_LOG_LENGTH, &mut length);
-
-                if length > 0 {
-                    let mut buffer = vec![0u8; length as usize];
-                    gl::GetShaderInfoLog(
-                        self.id,
-                        length,
-                        ptr::null_mut(),
-                        buffer.as_mut_ptr() as *mut GLchar,
-                    );
-                    let error_message = String::from_utf8_lossy(&buffer[..length as usize - 1]);
-                    return Err(anyhow!("Shader compilation failed:\n{}", error_message));
-                }
+        }
+        let log = self.info_log();
 
-                return Err(anyhow!("Shader compilation failed with no error message"));
+        if success == 0 {
+            let mut errors = parse_info_log(&log, file, source, Severity::Error);
+            if errors.is_empty() {
+                errors.push(ShaderError {
+                    file: file.to_string(),
+                    line: None,
+                    column: None,
+                    message: "Shader compilation failed with no error message".to_string(),
+                    severity: Severity::Error,
+                    source_line: None,
+                });
             }
+            let error = ShaderCompileError {
+                stage: self.kind,
+                file: file.to_string(),
+                errors,
+            };
+            log::error!("{}", error);
+            return Err(error.into());
         }
+
+        self.warnings = successful_log_warnings(parse_info_log(&log, file, source, Severity::Info));
         Ok(())
     }
 
+    fn info_log(&self) -> String {
+        let mut length = 0;
+        unsafe {
+            gl::GetShaderiv(self.id, gl::INFO_LOG_LENGTH, &mut length);
+        }
+        if length <= 1 {
+            return String::new();
+        }
+
+        let mut buffer = vec![0u8; length as usize];
+        unsafe {
+            gl::GetShaderInfoLog(
+                self.id,
+                length,
+                ptr::null_mut(),
+                buffer.as_mut_ptr() as *mut GLchar,
+            );
+        }
+        String::from_utf8_lossy(&buffer[..length as usize - 1]).into_owned()
+    }
+
     fn map_type(shader_type: &ShaderKind) -> GLuint {
         match shader_type {
             ShaderKind::Vertex => gl::VERTEX_SHADER,
@@ -80,28 +179,62 @@ impl Shader {
     }
 }
 
+impl Drop for Shader {
+    fn drop(&mut self) {
+        unsafe { gl::DeleteShader(self.id) }
+    }
+}
+
+struct ShaderSource {
+    kind: ShaderKind,
+    path: String,
+    source: String,
+}
+
 #[derive(Default)]
 pub struct ShaderProgram {
     pub id: GLuint,
-    pub shader_ids: Vec<GLuint>,
+    sources: Vec<ShaderSource>,
+    defines: Vec<(String, String)>,
+    cache: Option<ProgramCache>,
+    warnings: Vec<ShaderError>,
+    separable: bool,
+    stages: GLbitfield,
+    feedback_varyings: Vec<CString>,
+    feedback_mode: TransformFeedbackMode,
 }
 
 impl ShaderProgram {
     pub fn new() -> Self {
         ShaderProgram {
             id: unsafe { gl::CreateProgram() },
-            shader_ids: Vec::new(),
+            sources: Vec::new(),
+            defines: Vec::new(),
+            cache: None,
+            warnings: Vec::new(),
+            separable: false,
+            stages: 0,
+            feedback_varyings: Vec::new(),
+            feedback_mode: TransformFeedbackMode::Interleaved,
         }
     }
 
     fn attach(&mut self, kind: ShaderKind, path: &str) -> Result<&mut Self> {
-        let mut shader = Shader::new(kind);
-        shader.load_file(path)?;
-        unsafe {
-            gl::AttachShader(self.id, shader.id);
-        }
-        self.shader_ids.push(shader.id);
-        Ok(self)
+        let source = fs::read_to_string(path)
+            .map_err(|error| anyhow!("Failed to read shader file '{}': {}", path, error))?;
+        Ok(self.attach_source(kind, path, &source))
+    }
+
+    /// Attaches a shader compiled into the binary, such as one embedded with `include_str!`.
+    /// `name` stands in for the file path in diagnostics.
+    pub fn attach_source(&mut self, kind: ShaderKind, name: &str, source: &str) -> &mut Self {
+        self.stages |= kind.stage_bit();
+        self.sources.push(ShaderSource {
+            kind,
+            path: name.to_string(),
+            source: source.to_string(),
+        });
+        self
     }
 
     pub fn vertex_shader(&mut self, path: &str) -> Result<&mut Self> {
@@ -128,51 +261,280 @@ impl ShaderProgram {
         self.attach(ShaderKind::Fragment, path)
     }
 
+    pub fn define(&mut self, name: &str, value: &str) -> &mut Self {
+        self.defines.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn separable(&mut self) -> &mut Self {
+        self.separable = true;
+        self
+    }
+
+    pub fn transform_feedback_varyings(
+        &mut self,
+        varyings: &[&str],
+        mode: TransformFeedbackMode,
+    ) -> Result<&mut Self> {
+        self.feedback_varyings = varyings
+            .iter()
+            .map(|varying| {
+                CString::new(*varying)
+                    .map_err(|error| anyhow!("Varying name contains null byte: {}", error))
+            })
+            .collect::<Result<_>>()?;
+        self.feedback_mode = mode;
+        Ok(self)
+    }
+
+    pub fn is_separable(&self) -> bool {
+        self.separable
+    }
+
+    pub fn stages(&self) -> GLbitfield {
+        self.stages
+    }
+
+    pub fn cache(&mut self, cache: &ProgramCache) -> &mut Self {
+        if ProgramCache::is_supported() {
+            self.cache = Some(cache.clone());
+        } else {
+            log::warn!("Program binaries are not supported by this driver, caching disabled");
+        }
+        self
+    }
+
     pub fn link(&mut self) -> Result<()> {
+        let key = self
+            .cache
+            .as_ref()
+            .map(|cache| cache.key(self.cache_key_parts()));
+
+        if let (Some(cache), Some(key)) = (self.cache.as_ref(), key)
+            && let Some((format, binary)) = cache.load(key)
+        {
+            self.apply_parameters();
+            unsafe {
+                gl::ProgramBinary(
+                    self.id,
+                    format,
+                    binary.as_ptr() as *const _,
+                    binary.len() as GLsizei,
+                );
+            }
+            if self.linked() {
+                log::debug!("Loaded program binary {:016x} from cache", key);
+                self.sources.clear();
+                return Ok(());
+            }
+            log::warn!(
+                "Driver rejected cached program binary {:016x}, recompiling",
+                key
+            );
+            cache.remove(key);
+            self.recreate();
+        }
+
+        self.warnings.clear();
+        let mut shaders = Vec::with_capacity(self.sources.len());
+        for source in &self.sources {
+            let mut shader = Shader::new(source.kind);
+            shader.compile(&inject_defines(&source.source, &self.defines), &source.path)?;
+            self.warnings.append(&mut shader.warnings);
+            shaders.push(shader);
+        }
+
+        unsafe {
+            for shader in &shaders {
+                gl::AttachShader(self.id, shader.id);
+            }
+        }
+
+        self.apply_parameters();
         unsafe {
             gl::LinkProgram(self.id);
         }
 
-        self.check_link_status()?;
+        let status = self.check_link_status();
 
         unsafe {
-            for id in &self.shader_ids {
-                gl::DeleteShader(*id);
+            for shader in &shaders {
+                gl::DetachShader(self.id, shader.id);
+            }
+        }
+        status?;
+        self.sources.clear();
+
+        if let (Some(cache), Some(key)) = (self.cache.as_ref(), key) {
+            match self.program_binary() {
+                Some((format, binary)) => {
+                    if let Err(error) = cache.store(key, format, &binary) {
+                        log::warn!("{}", error);
+                    }
+                }
+                None => log::warn!("Driver returned no program binary to cache"),
             }
         }
-        self.shader_ids.clear();
 
         Ok(())
     }
 
-    fn check_link_status(&self) -> Result<()> {
+    fn apply_parameters(&self) {
+        unsafe {
+            if self.separable {
+                gl::ProgramParameteri(self.id, gl::PROGRAM_SEPARABLE, gl::TRUE as GLint);
+            }
+            if !self.feedback_varyings.is_empty() {
+                let names = self
+                    .feedback_varyings
+                    .iter()
+                    .map(|varying| varying.as_ptr())
+                    .collect::<Vec<_>>();
+                gl::TransformFeedbackVaryings(
+                    self.id,
+                    names.len() as GLsizei,
+                    names.as_ptr(),
+                    self.feedback_mode.gl_mode(),
+                );
+            }
+            if self.cache.is_some() {
+                gl::ProgramParameteri(
+                    self.id,
+                    gl::PROGRAM_BINARY_RETRIEVABLE_HINT,
+                    gl::TRUE as GLint,
+                );
+            }
+        }
+    }
+
+    fn cache_key_parts(&self) -> Vec<&[u8]> {
+        let mut parts = Vec::new();
+        if self.separable {
+            parts.push(b"separable".as_slice());
+        }
+        if !self.feedback_varyings.is_empty() {
+            parts.push(match self.feedback_mode {
+                TransformFeedbackMode::Interleaved => b"interleaved".as_slice(),
+                TransformFeedbackMode::Separate => b"separate".as_slice(),
+            });
+            for varying in &self.feedback_varyings {
+                parts.push(varying.as_bytes());
+            }
+        }
+        for source in &self.sources {
+            parts.push(source.kind.name().as_bytes());
+            parts.push(source.source.as_bytes());
+        }
+        for (name, value) in &self.defines {
+            parts.push(name.as_bytes());
+            parts.push(value.as_bytes());
+        }
+        parts
+    }
+
+    fn program_binary(&self) -> Option<(GLenum, Vec<u8>)> {
+        let mut length = 0;
+        unsafe {
+            gl::GetProgramiv(self.id, gl::PROGRAM_BINARY_LENGTH, &mut length);
+        }
+        if length <= 0 {
+            return None;
+        }
+
+        let mut binary = vec![0u8; length as usize];
+        let mut written = 0;
+        let mut format = 0;
+        unsafe {
+            gl::GetProgramBinary(
+                self.id,
+                length,
+                &mut written,
+                &mut format,
+                binary.as_mut_ptr() as *mut _,
+            );
+        }
+        binary.truncate(written.max(0) as usize);
+        (!binary.is_empty()).then_some((format, binary))
+    }
+
+    fn recreate(&mut self) {
+        unsafe {
+            gl::DeleteProgram(self.id);
+            self.id = gl::CreateProgram();
+        }
+    }
+
+    pub fn warnings(&self) -> &[ShaderError] {
+        &self.warnings
+    }
+
+    fn linked(&self) -> bool {
         let mut success = 0;
         unsafe {
             gl::GetProgramiv(self.id, gl::LINK_STATUS, &mut success);
-            if success == 0 {
-                let mut length = 0;
-                gl::GetProgramiv(self.id, gl::INFO_LOG_LENGTH, &mut length);
+        }
+        success != 0
+    }
 
-                if length > 0 {
-                    let mut buffer = vec![0u8; length as usize];
-                    gl::GetProgramInfoLog(
-                        self.id,
-                        length,
-                        ptr::null_mut(),
-                        buffer.as_mut_ptr() as *mut GLchar,
-                    );
-                    let error_message = String::from_utf8_lossy(&buffer[..length as usize - 1]);
-                    return Err(anyhow!("Shader program linking failed:\n{}", error_message));
-                }
+    fn check_link_status(&mut self) -> Result<()> {
+        let log = self.info_log();
+        let file = self
+            .sources
+            .iter()
+            .map(|source| source.path.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
 
-                return Err(anyhow!(
-                    "Shader program linking failed with no error message"
-                ));
+        if !self.linked() {
+            let mut errors = parse_info_log(&log, &file, "", Severity::Error);
+            if errors.is_empty() {
+                errors.push(ShaderError {
+                    file,
+                    line: None,
+                    column: None,
+                    message: "Shader program linking failed with no error message".to_string(),
+                    severity: Severity::Error,
+                    source_line: None,
+                });
             }
+            let error = ShaderLinkError {
+                stages: self
+                    .sources
+                    .iter()
+                    .map(|source| (source.kind, source.path.clone()))
+                    .collect(),
+                errors,
+            };
+            log::error!("{}", error);
+            return Err(error.into());
         }
+
+        let warnings = successful_log_warnings(parse_info_log(&log, &file, "", Severity::Info));
+        self.warnings.extend(warnings);
         Ok(())
     }
 
+    fn info_log(&self) -> String {
+        let mut length = 0;
+        unsafe {
+            gl::GetProgramiv(self.id, gl::INFO_LOG_LENGTH, &mut length);
+        }
+        if length <= 1 {
+            return String::new();
+        }
+
+        let mut buffer = vec![0u8; length as usize];
+        unsafe {
+            gl::GetProgramInfoLog(
+                self.id,
+                length,
+                ptr::null_mut(),
+                buffer.as_mut_ptr() as *mut GLchar,
+            );
+        }
+        String::from_utf8_lossy(&buffer[..length as usize - 1]).into_owned()
+    }
+
     pub fn activate(&self) {
         unsafe {
             gl::UseProgram(self.id);
@@ -183,6 +545,61 @@ impl ShaderProgram {
         let name: CString = CString::new(name.as_bytes()).unwrap();
         unsafe { gl::GetUniformLocation(self.id, name.as_ptr()) }
     }
+
+    pub fn resources(&self, interface: GLenum) -> Vec<ProgramResource> {
+        if !gl::GetProgramResourceiv::is_loaded() {
+            log::warn!("Program interface queries require OpenGL 4.3");
+            return Vec::new();
+        }
+
+        let mut count = 0;
+        let mut maximum_name_length = 0;
+        unsafe {
+            gl::GetProgramInterfaceiv(self.id, interface, gl::ACTIVE_RESOURCES, &mut count);
+            gl::GetProgramInterfaceiv(
+                self.id,
+                interface,
+                gl::MAX_NAME_LENGTH,
+                &mut maximum_name_length,
+            );
+        }
+
+        let properties = [gl::LOCATION, gl::TYPE, gl::ARRAY_SIZE];
+        (0..count.max(0) as GLuint)
+            .map(|index| {
+                let mut values = [0; 3];
+                let mut name = vec![0u8; maximum_name_length.max(1) as usize];
+                let mut name_length = 0;
+                unsafe {
+                    gl::GetProgramResourceiv(
+                        self.id,
+                        interface,
+                        index,
+                        properties.len() as GLsizei,
+                        properties.as_ptr(),
+                        values.len() as GLsizei,
+                        ptr::null_mut(),
+                        values.as_mut_ptr(),
+                    );
+                    gl::GetProgramResourceName(
+                        self.id,
+                        interface,
+                        index,
+                        name.len() as GLsizei,
+                        &mut name_length,
+                        name.as_mut_ptr() as *mut GLchar,
+                    );
+                }
+                ProgramResource {
+                    name: String::from_utf8_lossy(&name[..name_length.max(0) as usize])
+                        .into_owned(),
+                    location: values[0],
+                    gl_type: values[1] as GLenum,
+                    array_size: values[2],
+                }
+            })
+            .collect()
+    }
 }
 
 impl Drop for ShaderProgram {
@@ -190,3 +607,47 @@ impl Drop for ShaderProgram {
         unsafe { gl::DeleteProgram(self.id) }
     }
 }
+
+/// Logs the messages of a successful compile or link and keeps the warnings. Lines without a
+/// severity are driver chatter such as "successfully compiled to run on hardware".
+fn successful_log_warnings(messages: Vec<ShaderError>) -> Vec<ShaderError> {
+    messages
+        .into_iter()
+        .filter(|message| {
+            if message.severity == Severity::Info {
+                log::debug!("{}", message);
+                false
+            } else {
+                log::warn!("{}", message);
+                true
+            }
+        })
+        .collect()
+}
+
+fn inject_defines(source: &str, defines: &[(String, String)]) -> String {
+    if defines.is_empty() {
+        return source.to_string();
+    }
+
+    let lines = source.lines().collect::<Vec<_>>();
+    let version_line = lines
+        .iter()
+        .position(|line| line.trim_start().starts_with("#version"));
+    let insert_at = version_line.map_or(0, |line| line + 1);
+
+    let mut output = String::with_capacity(source.len() + defines.len() * 32);
+    for line in &lines[..insert_at] {
+        output.push_str(line);
+        output.push('\n');
+    }
+    for (name, value) in defines {
+        output.push_str(&format!("#define {} {}\n", name, value));
+    }
+    output.push_str(&format!("#line {}\n", insert_at + 1));
+    for line in &lines[insert_at..] {
+        output.push_str(line);
+        output.push('\n');
+    }
+    output
+}
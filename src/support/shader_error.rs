@@ -0,0 +1,328 @@
+use crate::support::shader::ShaderKind;
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+    Info,
+}
+
+impl Severity {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Info => "info",
+        }
+    }
+
+    fn parse(text: &str) -> Option<Self> {
+        match text.trim().to_ascii_lowercase().as_str() {
+            "error" | "fatal error" => Some(Severity::Error),
+            "warning" => Some(Severity::Warning),
+            "info" | "note" | "remark" => Some(Severity::Info),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ShaderError {
+    pub file: String,
+    pub line: Option<u32>,
+    pub column: Option<u32>,
+    pub message: String,
+    pub severity: Severity,
+    pub source_line: Option<String>,
+}
+
+impl fmt::Display for ShaderError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(formatter, "{}", self.file)?;
+        if let Some(line) = self.line {
+            write!(formatter, ":{}", line)?;
+            if let Some(column) = self.column {
+                write!(formatter, ":{}", column)?;
+            }
+        }
+        write!(formatter, ": {}: {}", self.severity.name(), self.message)?;
+        if let (Some(line), Some(source_line)) = (self.line, self.source_line.as_ref()) {
+            write!(formatter, "\n{:>5} | {}", line, source_line)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct ShaderCompileError {
+    pub stage: ShaderKind,
+    pub file: String,
+    pub errors: Vec<ShaderError>,
+}
+
+impl fmt::Display for ShaderCompileError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            formatter,
+            "Failed to compile {} shader '{}'",
+            self.stage.name(),
+            self.file
+        )?;
+        for error in &self.errors {
+            write!(formatter, "\n{}", error)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ShaderCompileError {}
+
+#[derive(Debug)]
+pub struct ShaderLinkError {
+    pub stages: Vec<(ShaderKind, String)>,
+    pub errors: Vec<ShaderError>,
+}
+
+impl fmt::Display for ShaderLinkError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let stages = self
+            .stages
+            .iter()
+            .map(|(kind, file)| format!("{} '{}'", kind.name(), file))
+            .collect::<Vec<_>>()
+            .join(", ");
+        write!(formatter, "Failed to link shader program ({})", stages)?;
+        for error in &self.errors {
+            write!(formatter, "\n{}", error)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ShaderLinkError {}
+
+pub fn parse_info_log(
+    log: &str,
+    file: &str,
+    source: &str,
+    default_severity: Severity,
+) -> Vec<ShaderError> {
+    log.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !is_summary_line(line))
+        .map(|line| {
+            let mut error = parse_line(line).unwrap_or_else(|| ParsedLine {
+                line: None,
+                column: None,
+                severity: None,
+                message: line.to_string(),
+            });
+            if error.line == Some(0) {
+                error.line = None;
+            }
+            ShaderError {
+                file: file.to_string(),
+                line: error.line,
+                column: error.column,
+                message: error.message,
+                severity: error.severity.unwrap_or(default_severity),
+                source_line: error.line.and_then(|line| source_line(source, line)),
+            }
+        })
+        .collect()
+}
+
+pub fn source_line(source: &str, line: u32) -> Option<String> {
+    let mut found = None;
+    let mut current = 1;
+    for text in source.lines() {
+        if let Some(directive) = text.trim_start().strip_prefix("#line")
+            && let Some(Ok(next)) = directive.split_whitespace().next().map(str::parse::<u32>)
+        {
+            current = next;
+            continue;
+        }
+        if current == line {
+            found = Some(text.trim_end().to_string());
+        }
+        current += 1;
+    }
+    found
+}
+
+struct ParsedLine {
+    line: Option<u32>,
+    column: Option<u32>,
+    severity: Option<Severity>,
+    message: String,
+}
+
+fn parse_line(text: &str) -> Option<ParsedLine> {
+    parse_prefixed(text)
+        .or_else(|| parse_nvidia(text))
+        .or_else(|| parse_mesa(text))
+}
+
+// AMD, Intel, Apple and ANGLE: "ERROR: 0:12: 'foo' : undeclared identifier"
+fn parse_prefixed(text: &str) -> Option<ParsedLine> {
+    let (severity, rest) = text.split_once(':')?;
+    let severity = Severity::parse(severity)?;
+    let rest = rest.trim_start();
+    let (_source_string, rest) = rest.split_once(':')?;
+    let (line, message) = rest.split_once(':')?;
+    Some(ParsedLine {
+        line: Some(line.trim().parse().ok()?),
+        column: None,
+        severity: Some(severity),
+        message: message.trim().to_string(),
+    })
+}
+
+// NVIDIA: "0(12) : error C1008: undefined variable "foo""
+fn parse_nvidia(text: &str) -> Option<ParsedLine> {
+    let (location, rest) = text.split_once(')')?;
+    let (source_string, line) = location.split_once('(')?;
+    source_string.trim().parse::<u32>().ok()?;
+    let rest = rest.trim_start().strip_prefix(':')?.trim_start();
+    let (kind, message) = rest.split_once(':')?;
+    let severity = kind.split_whitespace().next().and_then(Severity::parse)?;
+    Some(ParsedLine {
+        line: Some(line.trim().parse().ok()?),
+        column: None,
+        severity: Some(severity),
+        message: message.trim().to_string(),
+    })
+}
+
+// Mesa: "0:12(5): error: 'foo' undeclared"
+fn parse_mesa(text: &str) -> Option<ParsedLine> {
+    let (source_string, rest) = text.split_once(':')?;
+    source_string.trim().parse::<u32>().ok()?;
+    let (line, rest) = rest.split_once('(')?;
+    let (column, rest) = rest.split_once(')')?;
+    let rest = rest.trim_start().strip_prefix(':')?;
+    let (severity, message) = rest.split_once(':')?;
+    Some(ParsedLine {
+        line: Some(line.trim().parse().ok()?),
+        column: column.trim().parse().ok(),
+        severity: Some(Severity::parse(severity)?),
+        message: message.trim().to_string(),
+    })
+}
+
+fn is_summary_line(text: &str) -> bool {
+    let lowercase = text.to_ascii_lowercase();
+    lowercase.contains("compilation errors") && lowercase.contains("no code generated")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parsed(text: &str) -> (Option<u32>, Option<u32>, Option<Severity>, String) {
+        let line = parse_line(text).expect("line should parse");
+        (line.line, line.column, line.severity, line.message)
+    }
+
+    #[test]
+    fn parses_nvidia_lines() {
+        assert_eq!(
+            parsed("0(12) : error C1008: undefined variable \"foo\""),
+            (
+                Some(12),
+                None,
+                Some(Severity::Error),
+                "undefined variable \"foo\"".to_string()
+            )
+        );
+        assert_eq!(
+            parsed("0(3) : warning C7050: \"x\" might be used before being initialized"),
+            (
+                Some(3),
+                None,
+                Some(Severity::Warning),
+                "\"x\" might be used before being initialized".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn parses_mesa_lines() {
+        assert_eq!(
+            parsed("0:12(5): error: `foo' undeclared"),
+            (
+                Some(12),
+                Some(5),
+                Some(Severity::Error),
+                "`foo' undeclared".to_string()
+            )
+        );
+        assert_eq!(
+            parsed("0:7(10): warning: unused variable"),
+            (
+                Some(7),
+                Some(10),
+                Some(Severity::Warning),
+                "unused variable".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn parses_prefixed_lines() {
+        assert_eq!(
+            parsed("ERROR: 0:12: 'foo' : undeclared identifier"),
+            (
+                Some(12),
+                None,
+                Some(Severity::Error),
+                "'foo' : undeclared identifier".to_string()
+            )
+        );
+        assert_eq!(
+            parsed("WARNING: 0:4: extension not supported"),
+            (
+                Some(4),
+                None,
+                Some(Severity::Warning),
+                "extension not supported".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn leaves_unrecognised_lines_unparsed() {
+        assert!(
+            parse_line("Vertex shader was successfully compiled to run on hardware.").is_none()
+        );
+        assert!(parse_line("error: something went wrong").is_none());
+    }
+
+    #[test]
+    fn info_log_uses_default_severity_and_source_lines() {
+        let source = "#version 450 core\nvoid main() {\n    foo = 1;\n}\n";
+        let log = "0:3(5): error: `foo' undeclared\nFragment shader was successfully compiled to run on hardware.\n\n";
+        let messages = parse_info_log(log, "test.fs.glsl", source, Severity::Info);
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].severity, Severity::Error);
+        assert_eq!(messages[0].source_line.as_deref(), Some("    foo = 1;"));
+        assert_eq!(messages[1].severity, Severity::Info);
+        assert_eq!(messages[1].line, None);
+    }
+
+    #[test]
+    fn drops_summary_lines_and_line_zero() {
+        let log = "0(0) : error C0000: syntax error\nCompilation errors: no code generated\n";
+        let messages = parse_info_log(log, "test.vs.glsl", "", Severity::Error);
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].line, None);
+    }
+
+    #[test]
+    fn source_line_follows_line_directives() {
+        let source = "#version 450 core\n#line 10\nfloat a;\nfloat b;\n";
+        assert_eq!(source_line(source, 11).as_deref(), Some("float b;"));
+        assert_eq!(source_line(source, 1).as_deref(), Some("#version 450 core"));
+    }
+}
@@ -0,0 +1,1053 @@
+use crate::support::shader::ShaderKind;
+use naga::front::glsl::{Frontend, Options};
+use naga::valid::{Capabilities, ValidationFlags, Validator};
+use naga::{Binding, Module, ShaderStage, TypeInner};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+    pub path: PathBuf,
+    pub line: u32,
+    pub column: u32,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            formatter,
+            "{}:{}:{}: {}",
+            self.path.display(),
+            self.line,
+            self.column,
+            self.message
+        )
+    }
+}
+
+pub fn shader_stage(path: &Path) -> Option<ShaderKind> {
+    let file_name = path.file_name()?.to_str()?;
+    let stem = file_name.strip_suffix(".glsl")?;
+    match stem.rsplit('.').next()? {
+        "vs" => Some(ShaderKind::Vertex),
+        "fs" => Some(ShaderKind::Fragment),
+        "gs" => Some(ShaderKind::Geometry),
+        "tcs" => Some(ShaderKind::TessellationControl),
+        "tes" => Some(ShaderKind::TessellationEvaluation),
+        "cs" => Some(ShaderKind::Compute),
+        _ => None,
+    }
+}
+
+/// The naga stage for a shader kind. Naga has no geometry or tessellation stages, so those
+/// shaders are only checked by the driver when they are compiled.
+pub fn naga_stage(kind: ShaderKind) -> Option<ShaderStage> {
+    match kind {
+        ShaderKind::Vertex => Some(ShaderStage::Vertex),
+        ShaderKind::Fragment => Some(ShaderStage::Fragment),
+        ShaderKind::Compute => Some(ShaderStage::Compute),
+        ShaderKind::Geometry
+        | ShaderKind::TessellationControl
+        | ShaderKind::TessellationEvaluation => None,
+    }
+}
+
+pub fn validate_shader(
+    path: &Path,
+    stage: ShaderStage,
+    source: &str,
+) -> Result<Module, Vec<Diagnostic>> {
+    let diagnostic = |line: u32, column: u32, message: String| Diagnostic {
+        path: path.to_path_buf(),
+        line,
+        column,
+        message,
+    };
+
+    let flattened = flatten_interface_blocks(&vulkan_builtins(&raise_version(source)));
+    let prepared = bind_loose_uniforms(&flattened.source).map_err(|errors| {
+        errors
+            .into_iter()
+            .map(|(line, message)| diagnostic(line, 1, message))
+            .collect::<Vec<_>>()
+    })?;
+    let module = Frontend::default()
+        .parse(&Options::from(stage), &prepared)
+        .map_err(|errors| {
+            errors
+                .errors
+                .iter()
+                .map(|error| {
+                    let location = error.meta.location(&prepared);
+                    diagnostic(
+                        location.line_number,
+                        location.line_position,
+                        error.kind.to_string(),
+                    )
+                })
+                .collect::<Vec<_>>()
+        })?;
+
+    Validator::new(ValidationFlags::all(), Capabilities::all())
+        .validate(&module)
+        .map_err(|error| {
+            let (line, column) = error.location(&prepared).map_or((0, 0), |location| {
+                (location.line_number, location.line_position)
+            });
+            let mut message = error.as_inner().to_string();
+            let mut cause: Option<&dyn std::error::Error> =
+                std::error::Error::source(error.as_inner());
+            while let Some(next) = cause {
+                message.push_str(&format!(": {}", next));
+                cause = next.source();
+            }
+            vec![diagnostic(line, column, message)]
+        })?;
+
+    Ok(module)
+}
+
+/// Naga only parses desktop GLSL from 440 on, so older core versions are checked as 450. The
+/// driver still compiles the shader with the version it declares.
+fn raise_version(source: &str) -> Cow<'_, str> {
+    let Some(line) = source
+        .lines()
+        .find(|line| line.trim_start().starts_with("#version"))
+    else {
+        return Cow::Borrowed(source);
+    };
+    let mut words = line.split_whitespace().skip(1);
+    let version = words.next().and_then(|version| version.parse::<u32>().ok());
+    match (version, words.next()) {
+        (Some(version), None | Some("core")) if (330..440).contains(&version) => {
+            Cow::Owned(source.replacen(line, "#version 450 core", 1))
+        }
+        _ => Cow::Borrowed(source),
+    }
+}
+
+/// Naga knows the vertex and instance index only by their Vulkan names.
+fn vulkan_builtins(source: &str) -> String {
+    let source = replace_identifier(source, "gl_VertexID", "gl_VertexIndex");
+    replace_identifier(&source, "gl_InstanceID", "gl_InstanceIndex")
+}
+
+/// Validates a shader file, returning `None` for stages naga cannot parse.
+pub fn validate_shader_file(path: &Path) -> Result<Option<Module>, Vec<Diagnostic>> {
+    let Some(kind) = shader_stage(path) else {
+        return Err(vec![Diagnostic {
+            path: path.to_path_buf(),
+            line: 0,
+            column: 0,
+            message: "Unknown shader stage, expected a '.vs.glsl', '.fs.glsl', '.gs.glsl', \
+                      '.tcs.glsl', '.tes.glsl' or '.cs.glsl' suffix"
+                .to_string(),
+        }]);
+    };
+    let Some(stage) = naga_stage(kind) else {
+        return Ok(None);
+    };
+
+    let source = fs::read_to_string(path).map_err(|error| {
+        vec![Diagnostic {
+            path: path.to_path_buf(),
+            line: 0,
+            column: 0,
+            message: format!("Failed to read shader file: {}", error),
+        }]
+    })?;
+
+    validate_shader(path, stage, &source).map(Some)
+}
+
+/// Matches fragment inputs to vertex outputs the way GL links them: by location when the
+/// input has an explicit one, and otherwise by name, with block members named `Block.member`.
+pub fn validate_interface(
+    vertex_path: &Path,
+    vertex: &Module,
+    vertex_source: &str,
+    fragment_path: &Path,
+    fragment: &Module,
+    fragment_source: &str,
+) -> Vec<Diagnostic> {
+    let outputs = stage_interface(vertex, vertex_source, ShaderStage::Vertex, false);
+    let inputs = stage_interface(fragment, fragment_source, ShaderStage::Fragment, true);
+
+    let mut diagnostics = Vec::new();
+    for input in &inputs {
+        let output = outputs.iter().find(|output| match input.explicit_location {
+            true => output.explicit_location && output.location == input.location,
+            false => output.name == input.name,
+        });
+        let message = match output {
+            Some(output) if output.ty == input.ty => continue,
+            Some(output) => format!(
+                "Fragment input '{}' is {} but vertex output '{}' in '{}' is {}",
+                input.name,
+                type_name(&input.ty),
+                output.name,
+                vertex_path.display(),
+                type_name(&output.ty)
+            ),
+            None if input.explicit_location => format!(
+                "Fragment input '{}' at location {} is not written by '{}'",
+                input.name,
+                input.location,
+                vertex_path.display()
+            ),
+            None => format!(
+                "Fragment input '{}' is not written by '{}'",
+                input.name,
+                vertex_path.display()
+            ),
+        };
+        let member = input.name.rsplit('.').next().unwrap_or(&input.name);
+        diagnostics.push(Diagnostic {
+            path: fragment_path.to_path_buf(),
+            line: declaration_line(fragment_source, member),
+            column: 1,
+            message,
+        });
+    }
+    diagnostics
+}
+
+pub fn validate_shader_directory(directory: &Path) -> Vec<Diagnostic> {
+    let mut files = Vec::new();
+    if let Err(error) = collect_shader_files(directory, &mut files) {
+        return vec![Diagnostic {
+            path: directory.to_path_buf(),
+            line: 0,
+            column: 0,
+            message: format!("Failed to read shader directory: {}", error),
+        }];
+    }
+    files.sort();
+
+    let mut diagnostics = Vec::new();
+    let mut modules = BTreeMap::new();
+    for path in files {
+        match validate_shader_file(&path) {
+            Ok(None) => {}
+            Ok(Some(module)) => {
+                let source = fs::read_to_string(&path).unwrap_or_default();
+                modules.insert(path, (module, source));
+            }
+            Err(errors) => diagnostics.extend(errors),
+        }
+    }
+
+    for (vertex_path, (vertex, vertex_source)) in &modules {
+        if shader_stage(vertex_path) != Some(ShaderKind::Vertex) {
+            continue;
+        }
+        let Some(fragment_path) = vertex_path
+            .to_str()
+            .and_then(|path| path.strip_suffix(".vs.glsl"))
+            .map(|stem| PathBuf::from(format!("{}.fs.glsl", stem)))
+        else {
+            continue;
+        };
+        if let Some((fragment, fragment_source)) = modules.get(&fragment_path) {
+            diagnostics.extend(validate_interface(
+                vertex_path,
+                vertex,
+                vertex_source,
+                &fragment_path,
+                fragment,
+                fragment_source,
+            ));
+        }
+    }
+
+    diagnostics
+}
+
+fn declaration_line(source: &str, name: &str) -> u32 {
+    source
+        .lines()
+        .position(|line| {
+            line.split(|character: char| !character.is_alphanumeric() && character != '_')
+                .any(|word| word == name)
+                && line.trim_end().ends_with(';')
+        })
+        .map_or(0, |index| index as u32 + 1)
+}
+
+fn type_name(inner: &TypeInner) -> String {
+    let prefix = |scalar: &naga::Scalar| match scalar.kind {
+        naga::ScalarKind::Sint => "i",
+        naga::ScalarKind::Uint => "u",
+        naga::ScalarKind::Bool => "b",
+        naga::ScalarKind::Float if scalar.width == 8 => "d",
+        _ => "",
+    };
+    match inner {
+        TypeInner::Scalar(scalar) => match scalar.kind {
+            naga::ScalarKind::Sint => "int".to_string(),
+            naga::ScalarKind::Uint => "uint".to_string(),
+            naga::ScalarKind::Bool => "bool".to_string(),
+            _ if scalar.width == 8 => "double".to_string(),
+            _ => "float".to_string(),
+        },
+        TypeInner::Vector { size, scalar } => format!("{}vec{}", prefix(scalar), *size as u8),
+        TypeInner::Matrix {
+            columns,
+            rows,
+            scalar,
+        } => format!("{}mat{}x{}", prefix(scalar), *columns as u8, *rows as u8),
+        other => format!("{:?}", other),
+    }
+}
+
+/// Naga only accepts uniforms with a binding, so loose ones are given bindings in a set of
+/// their own where they cannot clash with explicit `layout(binding = ...)` declarations. Every
+/// line of the source stays on its own line so diagnostics keep their positions. Declarations
+/// this cannot rewrite are returned as errors with their line numbers.
+fn bind_loose_uniforms(source: &str) -> Result<String, Vec<(u32, String)>> {
+    let mut binding = 0;
+    let mut replacements: Vec<(String, String)> = Vec::new();
+    let mut errors = Vec::new();
+    let mut output = String::with_capacity(source.len());
+    let lines = source.lines().collect::<Vec<_>>();
+    for (index, line) in lines.iter().enumerate() {
+        let next_line = lines[index + 1..]
+            .iter()
+            .map(|line| line.trim())
+            .find(|line| !line.is_empty());
+        let declaration = match parse_uniform_line(line, next_line) {
+            Ok(UniformLine::Loose(declaration)) => declaration,
+            Ok(UniformLine::Other) => {
+                let mut line = line.to_string();
+                for (name, replacement) in &replacements {
+                    line = replace_identifier(&line, name, replacement);
+                }
+                output.push_str(&line);
+                output.push('\n');
+                continue;
+            }
+            Err(message) => {
+                errors.push((index as u32 + 1, message));
+                output.push('\n');
+                continue;
+            }
+        };
+
+        let ty = declaration.ty;
+        let qualifiers = declaration
+            .qualifiers
+            .iter()
+            .map(|qualifier| format!("{} ", qualifier))
+            .collect::<String>();
+        for name in declaration.names {
+            match ty {
+                // Naga has no combined image samplers, so declare a separate texture and
+                // sampler and rebuild the combined sampler wherever it is used.
+                _ if is_combined_sampler(ty, name) => {
+                    let sampler = if ty.ends_with("Shadow") {
+                        "samplerShadow"
+                    } else {
+                        "sampler"
+                    };
+                    let texture = ty.replacen("sampler", "texture", 1);
+                    output.push_str(&format!(
+                        "layout(set = 1, binding = {}) uniform {} {}_texture; layout(set = 1, binding = {}) uniform {} {}_sampler; ",
+                        binding,
+                        texture.trim_end_matches("Shadow"),
+                        name,
+                        binding + 1,
+                        sampler,
+                        name
+                    ));
+                    replacements.push((
+                        name.to_string(),
+                        format!("{}({}_texture, {}_sampler)", ty, name, name),
+                    ));
+                    binding += 2;
+                }
+                _ if is_opaque_type(ty) => {
+                    output.push_str(&format!(
+                        "layout(set = 1, binding = {}) uniform {}{} {}; ",
+                        binding, qualifiers, ty, name
+                    ));
+                    binding += 1;
+                }
+                // Booleans are not host-shareable, so the block holds a uint that every use
+                // converts back.
+                "bool" if !name.contains('[') => {
+                    output.push_str(&format!(
+                        "layout(set = 1, binding = {}) uniform Loose_{} {{ uint {}; }}; ",
+                        binding, name, name
+                    ));
+                    replacements.push((name.to_string(), format!("({} != 0u)", name)));
+                    binding += 1;
+                }
+                _ => {
+                    let block_name = name.split('[').next().unwrap_or(name);
+                    output.push_str(&format!(
+                        "layout(set = 1, binding = {}) uniform Loose_{} {{ {} {}; }}; ",
+                        binding, block_name, ty, name
+                    ));
+                    binding += 1;
+                }
+            }
+        }
+        output.push('\n');
+    }
+    if errors.is_empty() {
+        Ok(output)
+    } else {
+        Err(errors)
+    }
+}
+
+enum UniformLine<'a> {
+    /// Anything the validator passes through unchanged, including uniforms with an explicit
+    /// binding and uniform blocks.
+    Other,
+    Loose(LooseUniform<'a>),
+}
+
+struct LooseUniform<'a> {
+    /// Memory qualifiers such as `readonly`; precision qualifiers are dropped.
+    qualifiers: Vec<&'a str>,
+    ty: &'a str,
+    names: Vec<&'a str>,
+}
+
+fn parse_uniform_line<'a>(
+    line: &'a str,
+    next_line: Option<&str>,
+) -> Result<UniformLine<'a>, String> {
+    let code = line.split("//").next().unwrap_or_default().trim();
+    let (layout, rest) = match code.strip_prefix("layout") {
+        Some(rest) => {
+            let Some(inner) = rest.trim_start().strip_prefix('(') else {
+                return Ok(UniformLine::Other);
+            };
+            let Some(end) = inner.find(')') else {
+                return Err("Layout qualifiers must close on the line they start".to_string());
+            };
+            (Some(&inner[..end]), inner[end + 1..].trim_start())
+        }
+        None => (None, code),
+    };
+    let Some(rest) = rest
+        .strip_prefix("uniform")
+        .filter(|rest| rest.starts_with(char::is_whitespace))
+    else {
+        return Ok(UniformLine::Other);
+    };
+    // Bindings are already explicit, and uniform locations mean nothing to naga.
+    if let Some(layout) = layout {
+        let only_locations = layout
+            .split(',')
+            .all(|qualifier| qualifier.split('=').next().unwrap_or_default().trim() == "location");
+        if !only_locations {
+            return Ok(UniformLine::Other);
+        }
+    }
+    if rest.contains('{') || next_line.is_some_and(|line| line.starts_with('{')) {
+        return Ok(UniformLine::Other);
+    }
+    let Some((declaration, trailing)) = rest.split_once(';') else {
+        return Err("Uniform declarations must end on the line they start".to_string());
+    };
+    if !trailing.trim().is_empty() {
+        return Err("Put each uniform declaration on a line of its own".to_string());
+    }
+    if declaration.contains('=') {
+        return Err("Uniform initializers are not supported by the validator".to_string());
+    }
+
+    let mut qualifiers = Vec::new();
+    let mut words = declaration.split_whitespace().peekable();
+    while let Some(word) = words.next_if(|word| is_qualifier(word)) {
+        if !matches!(word, "lowp" | "mediump" | "highp") {
+            qualifiers.push(word);
+        }
+    }
+    let Some(ty) = words.next() else {
+        return Err("Uniform declaration has no type".to_string());
+    };
+    let names_start = declaration.find(ty).unwrap_or_default() + ty.len();
+    let names = declaration[names_start..]
+        .split(',')
+        .map(str::trim)
+        .collect::<Vec<_>>();
+    if let Some(name) = names.iter().find(|name| !is_declarator(name)) {
+        return Err(format!(
+            "Could not parse '{}' in the declaration of a '{}' uniform",
+            name, ty
+        ));
+    }
+    Ok(UniformLine::Loose(LooseUniform {
+        qualifiers,
+        ty,
+        names,
+    }))
+}
+
+fn is_qualifier(word: &str) -> bool {
+    matches!(
+        word,
+        "lowp"
+            | "mediump"
+            | "highp"
+            | "readonly"
+            | "writeonly"
+            | "coherent"
+            | "volatile"
+            | "restrict"
+    )
+}
+
+/// An identifier, optionally followed by array sizes such as `lights[4]`.
+fn is_declarator(name: &str) -> bool {
+    let identifier = name.split('[').next().unwrap_or_default();
+    let is_identifier = !identifier.is_empty()
+        && !identifier.starts_with(|c: char| c.is_ascii_digit())
+        && identifier
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_');
+    let arrays = &name[identifier.len()..];
+    is_identifier
+        && arrays.split_terminator(']').all(|size| {
+            size.strip_prefix('[')
+                .is_some_and(|size| !size.contains(['[', ' ']))
+        })
+}
+
+fn is_opaque_type(ty: &str) -> bool {
+    let ty = ty.trim_start_matches(['i', 'u']);
+    ty.starts_with("sampler") || ty.starts_with("image") || ty.starts_with("texture")
+}
+
+fn is_combined_sampler(ty: &str, name: &str) -> bool {
+    ty.trim_start_matches(['i', 'u']).starts_with("sampler") && !name.contains('[')
+}
+
+fn replace_identifier(line: &str, name: &str, replacement: &str) -> String {
+    let is_identifier = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    let mut output = String::with_capacity(line.len());
+    let mut rest = line;
+    while let Some(index) = rest.find(name) {
+        let before = rest[..index].chars().next_back();
+        let after = rest[index + name.len()..].chars().next();
+        output.push_str(&rest[..index]);
+        if before.is_some_and(is_identifier) || after.is_some_and(is_identifier) {
+            output.push_str(name);
+        } else {
+            output.push_str(replacement);
+        }
+        rest = &rest[index + name.len()..];
+    }
+    output.push_str(rest);
+    output
+}
+
+fn collect_shader_files(directory: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in fs::read_dir(directory)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_shader_files(&path, files)?;
+        } else if path
+            .extension()
+            .is_some_and(|extension| extension == "glsl")
+        {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+struct Varying {
+    name: String,
+    location: u32,
+    explicit_location: bool,
+    ty: TypeInner,
+}
+
+fn stage_interface(
+    module: &Module,
+    source: &str,
+    stage: ShaderStage,
+    inputs: bool,
+) -> Vec<Varying> {
+    let mut interface = Vec::new();
+    let Some(entry_point) = module
+        .entry_points
+        .iter()
+        .find(|entry_point| entry_point.stage == stage)
+    else {
+        return interface;
+    };
+    let flattened = flatten_interface_blocks(&vulkan_builtins(&raise_version(source)));
+    let explicit = explicit_locations(&flattened.source);
+
+    let mut add =
+        |name: Option<&String>, binding: Option<&Binding>, ty: naga::Handle<naga::Type>| {
+            if let (Some(Binding::Location { location, .. }), Some(name)) = (binding, name) {
+                interface.push(Varying {
+                    name: flattened
+                        .members
+                        .get(name)
+                        .cloned()
+                        .unwrap_or_else(|| name.clone()),
+                    location: *location,
+                    explicit_location: explicit.contains(name)
+                        && !flattened.implicit_locations.contains(name),
+                    ty: module.types[ty].inner.clone(),
+                });
+            }
+        };
+
+    let mut visit =
+        |name: Option<&String>, binding: Option<&Binding>, ty: naga::Handle<naga::Type>| {
+            match &module.types[ty].inner {
+                TypeInner::Struct { members, .. } if binding.is_none() => {
+                    for member in members {
+                        add(member.name.as_ref(), member.binding.as_ref(), member.ty);
+                    }
+                }
+                _ => add(name, binding, ty),
+            }
+        };
+
+    if inputs {
+        for argument in &entry_point.function.arguments {
+            visit(
+                argument.name.as_ref(),
+                argument.binding.as_ref(),
+                argument.ty,
+            );
+        }
+    } else if let Some(result) = &entry_point.function.result {
+        visit(None, result.binding.as_ref(), result.ty);
+    }
+
+    interface
+}
+
+/// Names of `in` and `out` variables declared with `layout(location = ...)`.
+fn explicit_locations(source: &str) -> Vec<String> {
+    source
+        .split(';')
+        .map(|statement| {
+            statement
+                .lines()
+                .filter(|line| !line.trim_start().starts_with('#'))
+                .map(|line| line.split("//").next().unwrap_or_default())
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .filter(|statement| {
+            let statement = statement.trim();
+            statement.starts_with("layout")
+                && statement.contains("location")
+                && statement
+                    .split_whitespace()
+                    .any(|word| word == "in" || word == "out")
+        })
+        .filter_map(|statement| {
+            let name = statement.split_whitespace().last()?;
+            Some(name.split('[').next().unwrap_or(name).to_string())
+        })
+        .collect()
+}
+
+struct FlattenedSource {
+    source: String,
+    /// Flattened global name to `Block.member`.
+    members: HashMap<String, String>,
+    /// Flattened globals whose location was counted from the block rather than written.
+    implicit_locations: Vec<String>,
+}
+
+/// Naga turns `in` and `out` block members into globals named after the member alone, which
+/// collide with other variables of the same name, so the blocks are flattened here into
+/// globals prefixed with the block name. GL links block members by block and member name,
+/// which the prefix keeps. Members get consecutive locations as naga gives them, and each
+/// block is rewritten onto its first line with the lines it spanned kept empty.
+fn flatten_interface_blocks(source: &str) -> FlattenedSource {
+    let mut output = String::with_capacity(source.len());
+    let mut members = HashMap::new();
+    let mut implicit_locations = Vec::new();
+    let mut renames = Vec::new();
+    let mut rest = source;
+    while let Some((start, block)) = find_interface_block(rest) {
+        output.push_str(&rest[..start]);
+        let region = &rest[start..block.end];
+        let block_location = block.layout.and_then(layout_location);
+        let mut location = block_location.unwrap_or(0);
+        let mut declarations = Vec::new();
+        for member in &block.members {
+            let flattened = format!("{}_{}", block.name, member.name);
+            let member_location = member.layout.and_then(layout_location);
+            location = member_location.unwrap_or(location);
+            if block_location.is_none() && member_location.is_none() {
+                implicit_locations.push(flattened.clone());
+            }
+            declarations.push(format!(
+                "layout(location = {}) {}{} {} {}{};",
+                location, member.qualifiers, block.storage, member.ty, flattened, member.array
+            ));
+            location += 1;
+            let access = match block.instance {
+                Some(instance) => format!("{}.{}", instance, member.name),
+                None => member.name.to_string(),
+            };
+            members.insert(flattened.clone(), format!("{}.{}", block.name, member.name));
+            renames.push((access, flattened));
+        }
+        output.push_str(&declarations.join(" "));
+        output.extend(std::iter::repeat_n('\n', region.matches('\n').count()));
+        rest = &rest[block.end..];
+    }
+    output.push_str(rest);
+    for (access, flattened) in renames {
+        output = replace_identifier(&output, &access, &flattened);
+    }
+    FlattenedSource {
+        source: output,
+        members,
+        implicit_locations,
+    }
+}
+
+/// The `location` in a `layout(...)` qualifier.
+fn layout_location(layout: &str) -> Option<u32> {
+    let qualifiers = layout.split_once('(')?.1.trim_end_matches(')');
+    qualifiers.split(',').find_map(|qualifier| {
+        let (name, value) = qualifier.split_once('=')?;
+        (name.trim() == "location").then(|| value.trim().parse().ok())?
+    })
+}
+
+struct InterfaceBlock<'a> {
+    layout: Option<&'a str>,
+    storage: &'a str,
+    name: &'a str,
+    members: Vec<BlockMember<'a>>,
+    instance: Option<&'a str>,
+    /// Byte offset just past the block's closing semicolon.
+    end: usize,
+}
+
+struct BlockMember<'a> {
+    layout: Option<&'a str>,
+    qualifiers: String,
+    ty: &'a str,
+    name: &'a str,
+    array: &'a str,
+}
+
+/// Finds the first `in` or `out` block that starts a line, returning the offset of that line.
+/// Arrays of blocks are left alone, since naga handles them for the stages it parses.
+fn find_interface_block(source: &str) -> Option<(usize, InterfaceBlock<'_>)> {
+    let mut line_start = 0;
+    for line in source.split_inclusive('\n') {
+        let start = line_start;
+        line_start += line.len();
+        let code = line.trim_start();
+        let (layout, header) = match code.strip_prefix("layout") {
+            Some(rest) => {
+                let end = rest.find(')')?;
+                (
+                    Some(code[.."layout".len() + end + 1].trim()),
+                    rest[end + 1..].trim_start(),
+                )
+            }
+            None => (None, code),
+        };
+        let mut words = header.split(|c: char| c.is_whitespace() || c == '{');
+        let storage = words.next().filter(|word| *word == "in" || *word == "out");
+        let name = words.find(|word| !word.is_empty());
+        let (Some(storage), Some(name)) = (storage, name) else {
+            continue;
+        };
+        if !is_declarator(name) || name.contains('[') {
+            continue;
+        }
+        let body = &source[start..];
+        let (Some(open), semicolon) = (body.find('{'), body.find(';')) else {
+            continue;
+        };
+        if semicolon.is_some_and(|semicolon| semicolon < open) {
+            continue;
+        }
+        let close = open + body[open..].find('}')?;
+        let end = close + body[close..].find(';')?;
+        let instance = body[close + 1..end].trim();
+        if instance.contains('[') {
+            continue;
+        }
+        let members = body[open + 1..close]
+            .split(';')
+            .map(str::trim)
+            .filter(|member| !member.is_empty())
+            .map(parse_block_member)
+            .collect::<Option<Vec<_>>>()?;
+        return Some((
+            start,
+            InterfaceBlock {
+                layout,
+                storage,
+                name,
+                members,
+                instance: (!instance.is_empty()).then_some(instance),
+                end: start + end + 1,
+            },
+        ));
+    }
+    None
+}
+
+fn parse_block_member(member: &str) -> Option<BlockMember<'_>> {
+    let (layout, rest) = match member.strip_prefix("layout") {
+        Some(rest) => {
+            let end = rest.find(')')?;
+            (
+                Some(&member[.."layout".len() + end + 1]),
+                rest[end + 1..].trim_start(),
+            )
+        }
+        None => (None, member),
+    };
+    let words = rest.split_whitespace().collect::<Vec<_>>();
+    let (declarator, words) = words.split_last()?;
+    let (ty, qualifiers) = words.split_last()?;
+    let name = declarator.split('[').next()?;
+    Some(BlockMember {
+        layout,
+        qualifiers: qualifiers
+            .iter()
+            .map(|qualifier| format!("{} ", qualifier))
+            .collect(),
+        ty,
+        name,
+        array: &declarator[name.len()..],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn validate_fragment(source: &str) -> Result<Module, Vec<Diagnostic>> {
+        validate_shader(Path::new("test.fs.glsl"), ShaderStage::Fragment, source)
+    }
+
+    #[test]
+    fn shipped_shaders_are_valid() {
+        let diagnostics = validate_shader_directory(Path::new("shaders"));
+        assert!(
+            diagnostics.is_empty(),
+            "{}",
+            diagnostics
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join("\n")
+        );
+    }
+
+    #[test]
+    fn recognises_every_stage_suffix() {
+        let stage = |name: &str| shader_stage(Path::new(name));
+        assert_eq!(stage("mesh.vs.glsl"), Some(ShaderKind::Vertex));
+        assert_eq!(stage("mesh.fs.glsl"), Some(ShaderKind::Fragment));
+        assert_eq!(stage("fur.gs.glsl"), Some(ShaderKind::Geometry));
+        assert_eq!(
+            stage("terrain.tcs.glsl"),
+            Some(ShaderKind::TessellationControl)
+        );
+        assert_eq!(
+            stage("terrain.tes.glsl"),
+            Some(ShaderKind::TessellationEvaluation)
+        );
+        assert_eq!(stage("blur.cs.glsl"), Some(ShaderKind::Compute));
+        assert_eq!(stage("notes.glsl"), None);
+        assert_eq!(stage("mesh.vs"), None);
+        assert!(matches!(
+            validate_shader_file(Path::new("fur.gs.glsl")),
+            Ok(None)
+        ));
+    }
+
+    #[test]
+    fn accepts_qualified_and_grouped_uniforms() {
+        let source = "#version 450 core
+uniform highp mat4 transform;
+uniform vec4 first, second;
+uniform float weights[4];
+layout(location = 3) uniform bool enabled;
+uniform sampler2D albedo; // trailing comment
+out vec4 color;
+void main() {
+    color = transform * first + second * weights[2] + texture(albedo, vec2(0.5));
+    if (enabled) {
+        color.a = 1.0;
+    }
+}
+";
+        if let Err(diagnostics) = validate_fragment(source) {
+            panic!("{:?}", diagnostics);
+        }
+    }
+
+    #[test]
+    fn keeps_explicit_bindings_and_blocks() {
+        let source = "layout(binding = 2, std140) uniform Lights { vec4 light; };
+uniform Camera
+{
+    mat4 view;
+};
+layout(binding = 0) uniform sampler2D shadow_map;
+";
+        assert_eq!(bind_loose_uniforms(source).unwrap(), source);
+    }
+
+    #[test]
+    fn keeps_line_numbers() {
+        let source = "uniform mat4 a, b;\nuniform float c;\nvoid main() {}\n";
+        let prepared = bind_loose_uniforms(source).unwrap();
+        assert_eq!(prepared.lines().count(), source.lines().count());
+        assert_eq!(prepared.lines().nth(2), Some("void main() {}"));
+    }
+
+    #[test]
+    fn rejects_declarations_it_cannot_rewrite() {
+        let errors = |source: &str| {
+            bind_loose_uniforms(source)
+                .unwrap_err()
+                .into_iter()
+                .map(|(line, _)| line)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(errors("void f();\nuniform float scale = 2.0;\n"), vec![2]);
+        assert_eq!(errors("uniform mat4\n    transform;\n"), vec![1]);
+        assert_eq!(errors("uniform float a; uniform float b;\n"), vec![1]);
+        assert_eq!(errors("uniform vec4 a b;\n"), vec![1]);
+
+        let diagnostics = validate_fragment("uniform float scale = 2.0;\n").unwrap_err();
+        assert_eq!(diagnostics[0].line, 1);
+        assert!(diagnostics[0].message.contains("initializers"));
+    }
+
+    #[test]
+    fn checks_older_core_versions_as_450() {
+        assert_eq!(
+            raise_version("#version 430 core\nvoid main() {}\n"),
+            "#version 450 core\nvoid main() {}\n"
+        );
+        assert_eq!(raise_version("#version 330\n"), "#version 450 core\n");
+        for source in [
+            "#version 460 core\n",
+            "#version 310 es\n",
+            "void main() {}\n",
+        ] {
+            assert!(matches!(raise_version(source), Cow::Borrowed(_)));
+        }
+    }
+
+    #[test]
+    fn renames_vertex_and_instance_ids() {
+        assert_eq!(
+            vulkan_builtins("int i = gl_VertexID + gl_InstanceID + my_gl_VertexID;"),
+            "int i = gl_VertexIndex + gl_InstanceIndex + my_gl_VertexID;"
+        );
+    }
+
+    fn interface(vertex: &str, fragment: &str) -> Vec<Diagnostic> {
+        let vertex_path = Path::new("test.vs.glsl");
+        let fragment_path = Path::new("test.fs.glsl");
+        let vertex_module = validate_shader(vertex_path, ShaderStage::Vertex, vertex).unwrap();
+        let fragment_module = validate_fragment(fragment).unwrap();
+        validate_interface(
+            vertex_path,
+            &vertex_module,
+            vertex,
+            fragment_path,
+            &fragment_module,
+            fragment,
+        )
+    }
+
+    const BLOCK_VERTEX: &str = "#version 330 core
+out VS_OUT
+{
+    vec3 normal;
+    vec4 color;
+} vs_out;
+void main()
+{
+    vs_out.normal = vec3(0.0);
+    vs_out.color = vec4(1.0);
+    gl_Position = vec4(0.0);
+}
+";
+
+    #[test]
+    fn matches_block_members_by_name() {
+        let fragment = "#version 330 core
+in VS_OUT
+{
+    vec3 normal;
+    vec4 tint;
+} fs_in;
+out vec4 color;
+void main() { color = fs_in.tint + vec4(fs_in.normal, 0.0); }
+";
+        let diagnostics = interface(BLOCK_VERTEX, fragment);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].line, 5);
+        assert!(
+            diagnostics[0].message.contains("'VS_OUT.tint'"),
+            "{}",
+            diagnostics[0]
+        );
+    }
+
+    #[test]
+    fn block_members_do_not_collide_with_outputs() {
+        let fragment = "#version 330 core
+in VS_OUT
+{
+    vec3 normal;
+    vec4 color;
+} fs_in;
+out vec4 color;
+void main() { color = fs_in.color + vec4(fs_in.normal, 0.0); }
+";
+        assert!(interface(BLOCK_VERTEX, fragment).is_empty());
+
+        let fragment = fragment.replace("vec4 color;\n}", "vec3 color;\n}");
+        let fragment = fragment.replace("fs_in.color +", "vec4(fs_in.color, 1.0) +");
+        let diagnostics = interface(BLOCK_VERTEX, &fragment);
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0].message.contains("is vec3"));
+    }
+
+    #[test]
+    fn matches_explicit_locations() {
+        let vertex = "#version 330 core
+layout(location = 1) out vec2 uv;
+void main() { uv = vec2(0.0); gl_Position = vec4(0.0); }
+";
+        let fragment = "#version 330 core
+layout(location = 1) in vec2 texcoord;
+layout(location = 2) in vec3 missing;
+out vec4 color;
+void main() { color = vec4(texcoord, missing.x, 1.0); }
+";
+        let diagnostics = interface(vertex, fragment);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].line, 3);
+        assert!(diagnostics[0].message.contains("location 2"));
+    }
+}
@@ -0,0 +1,1107 @@
+use crate::support::compressed_image::CompressedImage;
+use crate::support::image_data::{ImageData, ImageFormat, SamplerSettings, SourceImage, Wrap};
+use crate::support::resource::{Sampler, Texture, gl_version, has_extension};
+use crate::support::shader::{ShaderKind, ShaderProgram};
+use anyhow::{Result, bail};
+use gl::types::*;
+use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
+use std::path::Path;
+
+// Core in OpenGL 4.6 and provided by EXT_texture_filter_anisotropic before that, but missing
+// from the 4.5 bindings.
+const TEXTURE_MAX_ANISOTROPY: GLenum = 0x84FE;
+const MAX_TEXTURE_MAX_ANISOTROPY: GLenum = 0x84FF;
+
+const EQUIRECT_TO_CUBE_SHADER: &str = "shaders/cubemap/equirect_to_cube.cs.glsl";
+const EQUIRECT_TO_CUBE_SOURCE: &str =
+    include_str!("../../shaders/cubemap/equirect_to_cube.cs.glsl");
+
+thread_local! {
+    static EGUI_TEXTURES: RefCell<Vec<GLuint>> = const { RefCell::new(Vec::new()) };
+    static RELEASED_EGUI_TEXTURES: RefCell<Vec<GLuint>> = const { RefCell::new(Vec::new()) };
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TextureOptions {
+    /// Treat 8-bit color channels as sRGB encoded so sampling returns linear values.
+    pub srgb: bool,
+    pub mipmaps: bool,
+    pub sampler: SamplerSettings,
+}
+
+impl Default for TextureOptions {
+    fn default() -> Self {
+        Self {
+            srgb: true,
+            mipmaps: true,
+            sampler: SamplerSettings::default(),
+        }
+    }
+}
+
+impl TextureOptions {
+    /// Options for textures holding data rather than colors, such as normal or roughness maps.
+    pub fn linear() -> Self {
+        Self {
+            srgb: false,
+            ..Self::default()
+        }
+    }
+}
+
+pub struct Texture2D {
+    texture: Texture,
+    sampler: Sampler,
+    name: String,
+    width: u32,
+    height: u32,
+    levels: u32,
+    internal_format: GLenum,
+    srgb: bool,
+    settings: SamplerSettings,
+    /// Set once `egui_id` hands the texture to the painter, which then deletes it.
+    in_egui: Cell<bool>,
+}
+
+impl Texture2D {
+    /// Loads an image file, or a KTX2 or DDS container whose data stays compressed.
+    pub fn load(path: impl AsRef<Path>, options: TextureOptions) -> Result<Self> {
+        Self::from_source(&SourceImage::load(path)?, options)
+    }
+
+    pub fn from_memory(name: &str, bytes: &[u8], options: TextureOptions) -> Result<Self> {
+        Self::from_source(&SourceImage::from_memory(name, bytes)?, options)
+    }
+
+    pub fn from_source(image: &SourceImage, options: TextureOptions) -> Result<Self> {
+        match image {
+            SourceImage::Decoded(image) => Self::from_image(image, options),
+            SourceImage::Compressed(image) => Self::from_compressed(image, options),
+        }
+    }
+
+    pub fn from_image(image: &ImageData, options: TextureOptions) -> Result<Self> {
+        check_size(&image.name, image.width, image.height, gl::MAX_TEXTURE_SIZE)?;
+
+        let upload = Upload::new(image, options.srgb);
+        let levels = if options.mipmaps {
+            mip_levels(image.width, image.height)
+        } else {
+            1
+        };
+
+        let texture = Texture::new(gl::TEXTURE_2D);
+        texture.bind();
+        unsafe {
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                upload.internal_format as GLint,
+                image.width as GLsizei,
+                image.height as GLsizei,
+                0,
+                upload.format,
+                upload.data_type,
+                upload.pixels.as_ptr().cast(),
+            );
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+        }
+        finish_upload(&texture, &upload, levels);
+
+        let mut texture = Self {
+            texture,
+            sampler: Sampler::new(),
+            name: image.name.clone(),
+            width: image.width,
+            height: image.height,
+            levels,
+            internal_format: upload.internal_format,
+            srgb: is_srgb_format(upload.internal_format),
+            settings: options.sampler,
+            in_egui: Cell::new(false),
+        };
+        texture.set_sampler(options.sampler);
+        Ok(texture)
+    }
+
+    /// Uploads the mip chain stored in the container; none are generated. The container's
+    /// color space wins over `options.srgb` when it records one.
+    pub fn from_compressed(image: &CompressedImage, options: TextureOptions) -> Result<Self> {
+        if image.is_cubemap() || image.array {
+            bail!("'{}' is a cubemap or array texture", image.name);
+        }
+        let srgb = image.srgb.unwrap_or(options.srgb);
+        let (texture, format) = image.upload(srgb)?;
+        let mut texture = Self {
+            texture,
+            sampler: Sampler::new(),
+            name: image.name.clone(),
+            width: image.width,
+            height: image.height,
+            levels: image.level_count(),
+            internal_format: format.gl_internal_format(srgb),
+            srgb: srgb && format.has_srgb(),
+            settings: options.sampler,
+            in_egui: Cell::new(false),
+        };
+        texture.set_sampler(options.sampler);
+        Ok(texture)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn levels(&self) -> u32 {
+        self.levels
+    }
+
+    pub fn internal_format(&self) -> GLenum {
+        self.internal_format
+    }
+
+    pub fn is_srgb(&self) -> bool {
+        self.srgb
+    }
+
+    pub fn texture(&self) -> &Texture {
+        &self.texture
+    }
+
+    pub fn sampler_settings(&self) -> &SamplerSettings {
+        &self.settings
+    }
+
+    /// Updates the sampler object used by `bind`. The texture's own parameters get the same
+    /// settings so that egui, which binds no sampler, shows the texture the same way.
+    pub fn set_sampler(&mut self, settings: SamplerSettings) {
+        self.settings = apply_sampler(&self.texture, &self.sampler, self.levels, settings);
+    }
+
+    pub fn bind(&self, unit: GLuint) {
+        self.texture.bind_unit(unit);
+        self.sampler.bind(unit);
+    }
+
+    /// An id for showing this texture in egui images. The texture must outlive the frames
+    /// that draw it. The painter deletes the textures it knows about when it is destroyed,
+    /// so from here on the texture is handed to it to free on drop rather than deleted.
+    pub fn egui_id(&self) -> egui::TextureId {
+        let id = self.texture.id();
+        self.in_egui.set(true);
+        EGUI_TEXTURES.with(|textures| textures.borrow_mut().push(id));
+        egui::TextureId::User(id as u64)
+    }
+}
+
+impl Drop for Texture2D {
+    fn drop(&mut self) {
+        if self.in_egui.get() {
+            let id = self.texture.release();
+            RELEASED_EGUI_TEXTURES.with(|textures| textures.borrow_mut().push(id));
+        }
+    }
+}
+
+/// A cubemap with faces in the order of the `TEXTURE_CUBE_MAP_POSITIVE_X` targets: +X, -X,
+/// +Y, -Y, +Z, -Z. `SamplerSettings::clamped` avoids filtering across face edges in contexts
+/// without seamless cubemap sampling.
+pub struct TextureCube {
+    texture: Texture,
+    sampler: Sampler,
+    name: String,
+    size: u32,
+    levels: u32,
+    internal_format: GLenum,
+    srgb: bool,
+    settings: SamplerSettings,
+}
+
+impl TextureCube {
+    pub fn load_faces<P: AsRef<Path>>(paths: &[P], options: TextureOptions) -> Result<Self> {
+        let faces = paths
+            .iter()
+            .map(ImageData::load)
+            .collect::<Result<Vec<_>>>()?;
+        Self::from_faces(&faces, options)
+    }
+
+    pub fn from_faces(faces: &[ImageData], options: TextureOptions) -> Result<Self> {
+        if faces.len() != 6 {
+            bail!("A cubemap needs 6 faces but {} were given", faces.len());
+        }
+        let stacked = stack_images(faces)?;
+        let size = faces[0].width;
+        if faces[0].height != size {
+            bail!(
+                "Cubemap faces must be square but '{}' is {}x{}",
+                faces[0].name,
+                size,
+                faces[0].height
+            );
+        }
+        check_size(&stacked.name, size, size, gl::MAX_CUBE_MAP_TEXTURE_SIZE)?;
+
+        let upload = Upload::new(&stacked, options.srgb);
+        let levels = if options.mipmaps {
+            mip_levels(size, size)
+        } else {
+            1
+        };
+        check_texture_storage(&stacked.name)?;
+        let texture = Texture::new(gl::TEXTURE_CUBE_MAP);
+        texture.bind();
+        unsafe {
+            gl::TexStorage2D(
+                gl::TEXTURE_CUBE_MAP,
+                levels as GLsizei,
+                upload.internal_format,
+                size as GLsizei,
+                size as GLsizei,
+            );
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+            let face_bytes = upload.pixels.len() / 6;
+            for (face, pixels) in upload.pixels.chunks_exact(face_bytes).enumerate() {
+                gl::TexSubImage2D(
+                    gl::TEXTURE_CUBE_MAP_POSITIVE_X + face as GLenum,
+                    0,
+                    0,
+                    0,
+                    size as GLsizei,
+                    size as GLsizei,
+                    upload.format,
+                    upload.data_type,
+                    pixels.as_ptr().cast(),
+                );
+            }
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+        }
+        finish_upload(&texture, &upload, levels);
+        Ok(Self::from_parts(
+            texture,
+            &stacked.name,
+            size,
+            levels,
+            upload.internal_format,
+            is_srgb_format(upload.internal_format),
+            options.sampler,
+        ))
+    }
+
+    pub fn load_equirectangular(
+        path: impl AsRef<Path>,
+        size: u32,
+        options: TextureOptions,
+    ) -> Result<Self> {
+        Self::from_equirectangular(&ImageData::load(path)?, size, options)
+    }
+
+    /// Projects an equirectangular panorama, such as an HDR environment map, onto a cubemap
+    /// with `size` pixel faces using a compute shader. The cubemap is RGBA16F whatever the
+    /// format of the panorama.
+    pub fn from_equirectangular(
+        image: &ImageData,
+        size: u32,
+        options: TextureOptions,
+    ) -> Result<Self> {
+        if gl_version() < (4, 3) && !has_extension("GL_ARB_compute_shader") {
+            bail!("Converting equirectangular images to cubemaps needs compute shaders");
+        }
+        let panorama = Texture2D::from_image(
+            image,
+            TextureOptions {
+                mipmaps: false,
+                sampler: SamplerSettings {
+                    wrap_t: Wrap::ClampToEdge,
+                    ..SamplerSettings::default()
+                },
+                ..options
+            },
+        )?;
+        let levels = if options.mipmaps {
+            mip_levels(size, size)
+        } else {
+            1
+        };
+        let cube = Self::empty(&image.name, size, gl::RGBA16F, levels, options.sampler)?;
+
+        let mut program = ShaderProgram::new();
+        program
+            .attach_source(
+                ShaderKind::Compute,
+                EQUIRECT_TO_CUBE_SHADER,
+                EQUIRECT_TO_CUBE_SOURCE,
+            )
+            .link()?;
+        program.activate();
+        panorama.bind(0);
+        let groups = size.div_ceil(8);
+        unsafe {
+            gl::Uniform1i(program.uniform_location("equirectangular"), 0);
+            for face in 0..6 {
+                gl::BindImageTexture(
+                    0,
+                    cube.texture.id(),
+                    0,
+                    gl::FALSE,
+                    face,
+                    gl::WRITE_ONLY,
+                    gl::RGBA16F,
+                );
+                gl::Uniform1i(program.uniform_location("face"), face);
+                gl::DispatchCompute(groups, groups, 1);
+            }
+            gl::MemoryBarrier(gl::TEXTURE_FETCH_BARRIER_BIT | gl::TEXTURE_UPDATE_BARRIER_BIT);
+            gl::BindImageTexture(0, 0, 0, gl::FALSE, 0, gl::WRITE_ONLY, gl::RGBA16F);
+            gl::BindSampler(0, 0);
+            gl::UseProgram(0);
+        }
+        if levels > 1 {
+            cube.texture.generate_mipmaps();
+        }
+        Ok(cube)
+    }
+
+    /// Uploads a cubemap from a KTX2 or DDS container with the mip chain it stores.
+    pub fn from_compressed(image: &CompressedImage, options: TextureOptions) -> Result<Self> {
+        if !image.is_cubemap() || image.array {
+            bail!("'{}' is not a single cubemap", image.name);
+        }
+        let srgb = image.srgb.unwrap_or(options.srgb);
+        let (texture, format) = image.upload(srgb)?;
+        Ok(Self::from_parts(
+            texture,
+            &image.name,
+            image.width,
+            image.level_count(),
+            format.gl_internal_format(srgb),
+            srgb && format.has_srgb(),
+            options.sampler,
+        ))
+    }
+
+    /// Allocates a cubemap to render into, such as a point light shadow map or a baked
+    /// environment.
+    pub fn empty(
+        name: &str,
+        size: u32,
+        internal_format: GLenum,
+        levels: u32,
+        sampler: SamplerSettings,
+    ) -> Result<Self> {
+        check_size(name, size, size, gl::MAX_CUBE_MAP_TEXTURE_SIZE)?;
+        let levels = levels.clamp(1, mip_levels(size, size));
+        check_texture_storage(name)?;
+        let texture = Texture::new(gl::TEXTURE_CUBE_MAP);
+        texture.bind();
+        unsafe {
+            gl::TexStorage2D(
+                gl::TEXTURE_CUBE_MAP,
+                levels as GLsizei,
+                internal_format,
+                size as GLsizei,
+                size as GLsizei,
+            );
+        }
+        Ok(Self::from_parts(
+            texture,
+            name,
+            size,
+            levels,
+            internal_format,
+            is_srgb_format(internal_format),
+            sampler,
+        ))
+    }
+
+    fn from_parts(
+        texture: Texture,
+        name: &str,
+        size: u32,
+        levels: u32,
+        internal_format: GLenum,
+        srgb: bool,
+        settings: SamplerSettings,
+    ) -> Self {
+        let mut cube = Self {
+            texture,
+            sampler: Sampler::new(),
+            name: name.to_string(),
+            size,
+            levels,
+            internal_format,
+            srgb,
+            settings,
+        };
+        cube.set_sampler(settings);
+        cube
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The width and height of each face.
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    pub fn levels(&self) -> u32 {
+        self.levels
+    }
+
+    pub fn internal_format(&self) -> GLenum {
+        self.internal_format
+    }
+
+    pub fn is_srgb(&self) -> bool {
+        self.srgb
+    }
+
+    pub fn texture(&self) -> &Texture {
+        &self.texture
+    }
+
+    pub fn sampler_settings(&self) -> &SamplerSettings {
+        &self.settings
+    }
+
+    pub fn set_sampler(&mut self, settings: SamplerSettings) {
+        self.settings = apply_sampler(&self.texture, &self.sampler, self.levels, settings);
+    }
+
+    pub fn bind(&self, unit: GLuint) {
+        self.texture.bind_unit(unit);
+        self.sampler.bind(unit);
+    }
+}
+
+/// Layers of equally sized images sampled with a layer index, such as terrain splat maps or
+/// the cascades of a shadow map.
+pub struct Texture2DArray {
+    texture: Texture,
+    sampler: Sampler,
+    name: String,
+    width: u32,
+    height: u32,
+    layers: u32,
+    levels: u32,
+    internal_format: GLenum,
+    srgb: bool,
+    settings: SamplerSettings,
+}
+
+impl Texture2DArray {
+    pub fn load_layers<P: AsRef<Path>>(paths: &[P], options: TextureOptions) -> Result<Self> {
+        let layers = paths
+            .iter()
+            .map(ImageData::load)
+            .collect::<Result<Vec<_>>>()?;
+        Self::from_layers(&layers, options)
+    }
+
+    pub fn from_layers(layers: &[ImageData], options: TextureOptions) -> Result<Self> {
+        Self::from_stacked(&stack_images(layers)?, layers.len() as u32, options)
+    }
+
+    /// Splits an image into `layers` equally tall slices, the first at the top.
+    pub fn from_stacked(image: &ImageData, layers: u32, options: TextureOptions) -> Result<Self> {
+        let (texture, internal_format, levels) =
+            upload_stacked(gl::TEXTURE_2D_ARRAY, image, layers, options)?;
+        Ok(Self::from_parts(
+            texture,
+            &image.name,
+            (image.width, image.height / layers, layers),
+            levels,
+            internal_format,
+            is_srgb_format(internal_format),
+            options.sampler,
+        ))
+    }
+
+    /// Uploads an array texture from a KTX2 or DDS container with the mip chain it stores.
+    pub fn from_compressed(image: &CompressedImage, options: TextureOptions) -> Result<Self> {
+        if !image.array || image.is_cubemap() {
+            bail!("'{}' is not an array of 2D textures", image.name);
+        }
+        let srgb = image.srgb.unwrap_or(options.srgb);
+        let (texture, format) = image.upload(srgb)?;
+        Ok(Self::from_parts(
+            texture,
+            &image.name,
+            (image.width, image.height, image.layers),
+            image.level_count(),
+            format.gl_internal_format(srgb),
+            srgb && format.has_srgb(),
+            options.sampler,
+        ))
+    }
+
+    /// Allocates layers to render into, for example with `Framebuffer::attach_texture_layer`.
+    pub fn empty(
+        name: &str,
+        (width, height, layers): (u32, u32, u32),
+        internal_format: GLenum,
+        levels: u32,
+        sampler: SamplerSettings,
+    ) -> Result<Self> {
+        check_size(name, width, height, gl::MAX_TEXTURE_SIZE)?;
+        check_depth(name, layers, gl::MAX_ARRAY_TEXTURE_LAYERS)?;
+        let levels = levels.clamp(1, mip_levels(width, height));
+        check_texture_storage(name)?;
+        let texture = Texture::new(gl::TEXTURE_2D_ARRAY);
+        texture.bind();
+        unsafe {
+            gl::TexStorage3D(
+                gl::TEXTURE_2D_ARRAY,
+                levels as GLsizei,
+                internal_format,
+                width as GLsizei,
+                height as GLsizei,
+                layers as GLsizei,
+            );
+        }
+        Ok(Self::from_parts(
+            texture,
+            name,
+            (width, height, layers),
+            levels,
+            internal_format,
+            is_srgb_format(internal_format),
+            sampler,
+        ))
+    }
+
+    fn from_parts(
+        texture: Texture,
+        name: &str,
+        (width, height, layers): (u32, u32, u32),
+        levels: u32,
+        internal_format: GLenum,
+        srgb: bool,
+        settings: SamplerSettings,
+    ) -> Self {
+        let mut array = Self {
+            texture,
+            sampler: Sampler::new(),
+            name: name.to_string(),
+            width,
+            height,
+            layers,
+            levels,
+            internal_format,
+            srgb,
+            settings,
+        };
+        array.set_sampler(settings);
+        array
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn layers(&self) -> u32 {
+        self.layers
+    }
+
+    pub fn levels(&self) -> u32 {
+        self.levels
+    }
+
+    pub fn internal_format(&self) -> GLenum {
+        self.internal_format
+    }
+
+    pub fn is_srgb(&self) -> bool {
+        self.srgb
+    }
+
+    pub fn texture(&self) -> &Texture {
+        &self.texture
+    }
+
+    pub fn sampler_settings(&self) -> &SamplerSettings {
+        &self.settings
+    }
+
+    pub fn set_sampler(&mut self, settings: SamplerSettings) {
+        self.settings = apply_sampler(&self.texture, &self.sampler, self.levels, settings);
+    }
+
+    pub fn bind(&self, unit: GLuint) {
+        self.texture.bind_unit(unit);
+        self.sampler.bind(unit);
+    }
+}
+
+/// A volume sampled with three coordinates and filtered between slices, for volume rendering
+/// and color lookup tables.
+pub struct Texture3D {
+    texture: Texture,
+    sampler: Sampler,
+    name: String,
+    width: u32,
+    height: u32,
+    depth: u32,
+    levels: u32,
+    internal_format: GLenum,
+    srgb: bool,
+    settings: SamplerSettings,
+}
+
+impl Texture3D {
+    pub fn load_slices<P: AsRef<Path>>(paths: &[P], options: TextureOptions) -> Result<Self> {
+        let slices = paths
+            .iter()
+            .map(ImageData::load)
+            .collect::<Result<Vec<_>>>()?;
+        Self::from_slices(&slices, options)
+    }
+
+    pub fn from_slices(slices: &[ImageData], options: TextureOptions) -> Result<Self> {
+        Self::from_stacked(&stack_images(slices)?, slices.len() as u32, options)
+    }
+
+    /// Splits an image into `depth` equally tall slices, the first at the top. Raw volume
+    /// data loaded into an `ImageData` `depth` times as tall as a slice uploads the same way.
+    pub fn from_stacked(image: &ImageData, depth: u32, options: TextureOptions) -> Result<Self> {
+        let (texture, internal_format, levels) =
+            upload_stacked(gl::TEXTURE_3D, image, depth, options)?;
+        Ok(Self::from_parts(
+            texture,
+            &image.name,
+            (image.width, image.height / depth, depth),
+            levels,
+            internal_format,
+            options.sampler,
+        ))
+    }
+
+    /// Allocates a volume to fill from shaders, for example with image stores.
+    pub fn empty(
+        name: &str,
+        (width, height, depth): (u32, u32, u32),
+        internal_format: GLenum,
+        levels: u32,
+        sampler: SamplerSettings,
+    ) -> Result<Self> {
+        check_size(name, width, height, gl::MAX_3D_TEXTURE_SIZE)?;
+        check_depth(name, depth, gl::MAX_3D_TEXTURE_SIZE)?;
+        let levels = levels.clamp(1, mip_levels(width.max(height), depth));
+        check_texture_storage(name)?;
+        let texture = Texture::new(gl::TEXTURE_3D);
+        texture.bind();
+        unsafe {
+            gl::TexStorage3D(
+                gl::TEXTURE_3D,
+                levels as GLsizei,
+                internal_format,
+                width as GLsizei,
+                height as GLsizei,
+                depth as GLsizei,
+            );
+        }
+        Ok(Self::from_parts(
+            texture,
+            name,
+            (width, height, depth),
+            levels,
+            internal_format,
+            sampler,
+        ))
+    }
+
+    fn from_parts(
+        texture: Texture,
+        name: &str,
+        (width, height, depth): (u32, u32, u32),
+        levels: u32,
+        internal_format: GLenum,
+        settings: SamplerSettings,
+    ) -> Self {
+        let mut volume = Self {
+            texture,
+            sampler: Sampler::new(),
+            name: name.to_string(),
+            width,
+            height,
+            depth,
+            levels,
+            internal_format,
+            srgb: is_srgb_format(internal_format),
+            settings,
+        };
+        volume.set_sampler(settings);
+        volume
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn depth(&self) -> u32 {
+        self.depth
+    }
+
+    pub fn levels(&self) -> u32 {
+        self.levels
+    }
+
+    pub fn internal_format(&self) -> GLenum {
+        self.internal_format
+    }
+
+    pub fn is_srgb(&self) -> bool {
+        self.srgb
+    }
+
+    pub fn texture(&self) -> &Texture {
+        &self.texture
+    }
+
+    pub fn sampler_settings(&self) -> &SamplerSettings {
+        &self.settings
+    }
+
+    pub fn set_sampler(&mut self, settings: SamplerSettings) {
+        self.settings = apply_sampler(&self.texture, &self.sampler, self.levels, settings);
+    }
+
+    pub fn bind(&self, unit: GLuint) {
+        self.texture.bind_unit(unit);
+        self.sampler.bind(unit);
+    }
+}
+
+/// Texture names handed out by `Texture2D::egui_id` since the last call. The painter maps
+/// `TextureId::User(name)` to the OpenGL texture with that name; its own user ids start at
+/// 2^32 and cannot collide.
+pub fn take_egui_textures() -> Vec<GLuint> {
+    EGUI_TEXTURES.with(|textures| std::mem::take(&mut *textures.borrow_mut()))
+}
+
+/// Names of dropped textures that were shown in egui. Freeing them in the painter deletes
+/// them and removes its entries, so a later texture reusing the name is not mistaken for them.
+pub fn take_released_egui_textures() -> Vec<GLuint> {
+    RELEASED_EGUI_TEXTURES.with(|textures| std::mem::take(&mut *textures.borrow_mut()))
+}
+
+/// Sets `settings` on the sampler object and the texture's own parameters. Returns the
+/// settings as applied, without mipmap filtering for textures with a single level.
+fn apply_sampler(
+    texture: &Texture,
+    sampler: &Sampler,
+    levels: u32,
+    mut settings: SamplerSettings,
+) -> SamplerSettings {
+    if levels == 1 {
+        settings.mipmap_filter = None;
+    }
+    let (compare_mode, compare_func) = match settings.compare {
+        Some(compare) => (gl::COMPARE_REF_TO_TEXTURE, compare.gl_value()),
+        None => (gl::NONE, gl::LEQUAL),
+    };
+    for (name, value) in [
+        (gl::TEXTURE_MAG_FILTER, settings.gl_mag_filter()),
+        (gl::TEXTURE_MIN_FILTER, settings.gl_min_filter()),
+        (gl::TEXTURE_WRAP_S, settings.wrap_s.gl_value()),
+        (gl::TEXTURE_WRAP_T, settings.wrap_t.gl_value()),
+        (gl::TEXTURE_WRAP_R, settings.wrap_r.gl_value()),
+        (gl::TEXTURE_COMPARE_MODE, compare_mode),
+        (gl::TEXTURE_COMPARE_FUNC, compare_func),
+    ] {
+        sampler.parameter_i(name, value as GLint);
+        texture.parameter_i(name, value as GLint);
+    }
+    let max_anisotropy = max_anisotropy();
+    if max_anisotropy > 1.0 {
+        let anisotropy = settings.anisotropy.clamp(1.0, max_anisotropy);
+        sampler.parameter_f(TEXTURE_MAX_ANISOTROPY, anisotropy);
+        texture.parameter_f(TEXTURE_MAX_ANISOTROPY, anisotropy);
+    }
+    settings
+}
+
+/// The largest anisotropy the driver supports, or 1 without anisotropic filtering.
+pub fn max_anisotropy() -> f32 {
+    if gl_version() < (4, 6)
+        && !has_extension("GL_EXT_texture_filter_anisotropic")
+        && !has_extension("GL_ARB_texture_filter_anisotropic")
+    {
+        return 1.0;
+    }
+    let mut value = 1.0;
+    unsafe {
+        gl::GetFloatv(MAX_TEXTURE_MAX_ANISOTROPY, &mut value);
+    }
+    value.max(1.0)
+}
+
+/// Whether `glTexStorage*` is available: core since OpenGL 4.2 and an extension before that.
+pub fn has_texture_storage() -> bool {
+    gl_version() >= (4, 2) || has_extension("GL_ARB_texture_storage")
+}
+
+fn check_texture_storage(name: &str) -> Result<()> {
+    if !has_texture_storage() {
+        bail!(
+            "'{}' needs immutable texture storage, which needs OpenGL 4.2 or ARB_texture_storage",
+            name
+        );
+    }
+    Ok(())
+}
+
+pub fn mip_levels(width: u32, height: u32) -> u32 {
+    32 - width.max(height).max(1).leading_zeros()
+}
+
+fn is_srgb_format(internal_format: GLenum) -> bool {
+    matches!(internal_format, gl::SRGB8 | gl::SRGB8_ALPHA8)
+}
+
+fn check_size(name: &str, width: u32, height: u32, limit: GLenum) -> Result<()> {
+    if width == 0 || height == 0 {
+        bail!("Image '{}' is empty", name);
+    }
+    let mut max_size = 0;
+    unsafe {
+        gl::GetIntegerv(limit, &mut max_size);
+    }
+    if width.max(height) > max_size as u32 {
+        bail!(
+            "Image '{}' is {}x{} but textures are limited to {}",
+            name,
+            width,
+            height,
+            max_size
+        );
+    }
+    Ok(())
+}
+
+fn check_depth(name: &str, depth: u32, limit: GLenum) -> Result<()> {
+    let mut max_depth = 0;
+    unsafe {
+        gl::GetIntegerv(limit, &mut max_depth);
+    }
+    if depth == 0 || depth > max_depth as u32 {
+        bail!(
+            "'{}' has {} slices but textures are limited to {}",
+            name,
+            depth,
+            max_depth
+        );
+    }
+    Ok(())
+}
+
+/// Joins equally sized images top to bottom, the layout `upload_stacked` expects.
+fn stack_images(images: &[ImageData]) -> Result<ImageData> {
+    let Some(first) = images.first() else {
+        bail!("No images to stack");
+    };
+    let mut pixels = Vec::with_capacity(first.pixels.len() * images.len());
+    for image in images {
+        if (image.width, image.height, image.format) != (first.width, first.height, first.format) {
+            bail!(
+                "'{}' is {}x{} {:?} but '{}' is {}x{} {:?}",
+                image.name,
+                image.width,
+                image.height,
+                image.format,
+                first.name,
+                first.width,
+                first.height,
+                first.format
+            );
+        }
+        pixels.extend_from_slice(&image.pixels);
+    }
+    ImageData::new(
+        &first.name,
+        first.width,
+        first.height * images.len() as u32,
+        first.format,
+        pixels,
+    )
+}
+
+/// Uploads an image holding `depth` slices stacked top to bottom into a 2D array or 3D
+/// texture. Returns the texture with its internal format and level count.
+fn upload_stacked(
+    target: GLenum,
+    image: &ImageData,
+    depth: u32,
+    options: TextureOptions,
+) -> Result<(Texture, GLenum, u32)> {
+    if depth == 0 || !image.height.is_multiple_of(depth) {
+        bail!(
+            "'{}' is {} pixels tall, which does not split into {} slices",
+            image.name,
+            image.height,
+            depth
+        );
+    }
+    let (width, height) = (image.width, image.height / depth);
+    let levels = if target == gl::TEXTURE_3D {
+        check_size(&image.name, width, height, gl::MAX_3D_TEXTURE_SIZE)?;
+        check_depth(&image.name, depth, gl::MAX_3D_TEXTURE_SIZE)?;
+        mip_levels(width.max(height), depth)
+    } else {
+        check_size(&image.name, width, height, gl::MAX_TEXTURE_SIZE)?;
+        check_depth(&image.name, depth, gl::MAX_ARRAY_TEXTURE_LAYERS)?;
+        mip_levels(width, height)
+    };
+    let levels = if options.mipmaps { levels } else { 1 };
+
+    check_texture_storage(&image.name)?;
+    let upload = Upload::new(image, options.srgb);
+    let texture = Texture::new(target);
+    texture.bind();
+    unsafe {
+        gl::TexStorage3D(
+            target,
+            levels as GLsizei,
+            upload.internal_format,
+            width as GLsizei,
+            height as GLsizei,
+            depth as GLsizei,
+        );
+        gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+        gl::TexSubImage3D(
+            target,
+            0,
+            0,
+            0,
+            0,
+            width as GLsizei,
+            height as GLsizei,
+            depth as GLsizei,
+            upload.format,
+            upload.data_type,
+            upload.pixels.as_ptr().cast(),
+        );
+        gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+    }
+    finish_upload(&texture, &upload, levels);
+    Ok((texture, upload.internal_format, levels))
+}
+
+/// Limits sampling to the allocated levels, applies the channel swizzle and fills the mip
+/// chain from level 0.
+fn finish_upload(texture: &Texture, upload: &Upload, levels: u32) {
+    texture.parameter_i(gl::TEXTURE_MAX_LEVEL, levels as GLint - 1);
+    if let Some(swizzle) = upload.swizzle {
+        unsafe {
+            gl::TexParameteriv(texture.target(), gl::TEXTURE_SWIZZLE_RGBA, swizzle.as_ptr());
+        }
+    }
+    if levels > 1 {
+        texture.generate_mipmaps();
+    }
+}
+
+struct Upload<'a> {
+    internal_format: GLenum,
+    format: GLenum,
+    data_type: GLenum,
+    pixels: Cow<'a, [u8]>,
+    swizzle: Option<[GLint; 4]>,
+}
+
+impl<'a> Upload<'a> {
+    fn new(image: &'a ImageData, srgb: bool) -> Self {
+        let (red, green, one) = (gl::RED as GLint, gl::GREEN as GLint, gl::ONE as GLint);
+        let upload = |internal_format, format, data_type, swizzle| Upload {
+            internal_format,
+            format,
+            data_type,
+            pixels: Cow::Borrowed(&image.pixels[..]),
+            swizzle,
+        };
+        match image.format {
+            // There is no single or dual channel sRGB format in core, so expand gray images.
+            ImageFormat::R8 | ImageFormat::Rg8 if srgb => Upload {
+                internal_format: gl::SRGB8_ALPHA8,
+                format: gl::RGBA,
+                data_type: gl::UNSIGNED_BYTE,
+                pixels: Cow::Owned(gray_to_rgba(image)),
+                swizzle: None,
+            },
+            ImageFormat::R8 => upload(
+                gl::R8,
+                gl::RED,
+                gl::UNSIGNED_BYTE,
+                Some([red, red, red, one]),
+            ),
+            ImageFormat::Rg8 => upload(
+                gl::RG8,
+                gl::RG,
+                gl::UNSIGNED_BYTE,
+                Some([red, red, red, green]),
+            ),
+            ImageFormat::Rgb8 if srgb => upload(gl::SRGB8, gl::RGB, gl::UNSIGNED_BYTE, None),
+            ImageFormat::Rgb8 => upload(gl::RGB8, gl::RGB, gl::UNSIGNED_BYTE, None),
+            ImageFormat::Rgba8 if srgb => {
+                upload(gl::SRGB8_ALPHA8, gl::RGBA, gl::UNSIGNED_BYTE, None)
+            }
+            ImageFormat::Rgba8 => upload(gl::RGBA8, gl::RGBA, gl::UNSIGNED_BYTE, None),
+            ImageFormat::R16 => upload(
+                gl::R16,
+                gl::RED,
+                gl::UNSIGNED_SHORT,
+                Some([red, red, red, one]),
+            ),
+            ImageFormat::Rg16 => upload(
+                gl::RG16,
+                gl::RG,
+                gl::UNSIGNED_SHORT,
+                Some([red, red, red, green]),
+            ),
+            ImageFormat::Rgb16 => upload(gl::RGB16, gl::RGB, gl::UNSIGNED_SHORT, None),
+            ImageFormat::Rgba16 => upload(gl::RGBA16, gl::RGBA, gl::UNSIGNED_SHORT, None),
+            ImageFormat::Rgb32F => upload(gl::RGB16F, gl::RGB, gl::FLOAT, None),
+            ImageFormat::Rgba32F => upload(gl::RGBA16F, gl::RGBA, gl::FLOAT, None),
+        }
+    }
+}
+
+fn gray_to_rgba(image: &ImageData) -> Vec<u8> {
+    let channels = image.format.channels();
+    image
+        .pixels
+        .chunks_exact(channels)
+        .flat_map(|pixel| {
+            let alpha = if channels == 2 { pixel[1] } else { 255 };
+            [pixel[0], pixel[0], pixel[0], alpha]
+        })
+        .collect()
+}
@@ -0,0 +1,84 @@
+use nalgebra_glm::{Mat4, Quat, Vec3};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Transform {
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Transform {
+    pub const IDENTITY: Self = Self {
+        translation: Vec3::new(0.0, 0.0, 0.0),
+        rotation: Quat::new(1.0, 0.0, 0.0, 0.0),
+        scale: Vec3::new(1.0, 1.0, 1.0),
+    };
+
+    pub fn from_translation(translation: Vec3) -> Self {
+        Self {
+            translation,
+            ..Self::IDENTITY
+        }
+    }
+
+    pub fn from_matrix(matrix: &Mat4) -> Self {
+        let translation = matrix.column(3).xyz();
+        let mut scale = Vec3::new(
+            matrix.column(0).xyz().norm(),
+            matrix.column(1).xyz().norm(),
+            matrix.column(2).xyz().norm(),
+        );
+        if matrix.fixed_view::<3, 3>(0, 0).determinant() < 0.0 {
+            scale.x = -scale.x;
+        }
+
+        let mut rotation_matrix = nalgebra_glm::Mat3::zeros();
+        for (column, axis_scale) in scale.iter().enumerate() {
+            let axis = matrix.column(column).xyz();
+            let axis = if axis_scale.abs() > f32::EPSILON {
+                axis / *axis_scale
+            } else {
+                axis
+            };
+            rotation_matrix.set_column(column, &axis);
+        }
+
+        Self {
+            translation,
+            rotation: nalgebra_glm::mat3_to_quat(&rotation_matrix),
+            scale,
+        }
+    }
+
+    pub fn matrix(&self) -> Mat4 {
+        nalgebra_glm::translation(&self.translation)
+            * nalgebra_glm::quat_to_mat4(&self.rotation)
+            * nalgebra_glm::scaling(&self.scale)
+    }
+
+    pub fn euler_degrees(&self) -> Vec3 {
+        let angles = nalgebra_glm::quat_euler_angles(&nalgebra_glm::quat_normalize(&self.rotation));
+        Vec3::new(
+            angles.z.to_degrees(),
+            angles.y.to_degrees(),
+            angles.x.to_degrees(),
+        )
+    }
+
+    pub fn set_euler_degrees(&mut self, degrees: &Vec3) {
+        let axis = |angle: f32, x: f32, y: f32, z: f32| {
+            nalgebra_glm::quat_angle_axis(angle.to_radians(), &Vec3::new(x, y, z))
+        };
+        self.rotation = axis(degrees.z, 0.0, 0.0, 1.0)
+            * axis(degrees.y, 0.0, 1.0, 0.0)
+            * axis(degrees.x, 1.0, 0.0, 0.0);
+    }
+}
@@ -0,0 +1,270 @@
+use crate::support::resource::{Buffer, BufferTarget, Query};
+use anyhow::{Result, bail};
+use gl::types::*;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TransformFeedbackMode {
+    #[default]
+    Interleaved,
+    Separate,
+}
+
+impl TransformFeedbackMode {
+    pub fn gl_mode(&self) -> GLenum {
+        match self {
+            TransformFeedbackMode::Interleaved => gl::INTERLEAVED_ATTRIBS,
+            TransformFeedbackMode::Separate => gl::SEPARATE_ATTRIBS,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FeedbackPrimitive {
+    Points,
+    Lines,
+    Triangles,
+}
+
+impl FeedbackPrimitive {
+    pub fn gl_mode(&self) -> GLenum {
+        match self {
+            FeedbackPrimitive::Points => gl::POINTS,
+            FeedbackPrimitive::Lines => gl::LINES,
+            FeedbackPrimitive::Triangles => gl::TRIANGLES,
+        }
+    }
+}
+
+pub struct TransformFeedback {
+    pub id: GLuint,
+    query: Query,
+    active: bool,
+}
+
+impl TransformFeedback {
+    pub fn new() -> Self {
+        let mut id = 0;
+        unsafe {
+            gl::GenTransformFeedbacks(1, &mut id);
+        }
+        Self {
+            id,
+            query: Query::new(gl::TRANSFORM_FEEDBACK_PRIMITIVES_WRITTEN),
+            active: false,
+        }
+    }
+
+    pub fn bind(&self) {
+        unsafe {
+            gl::BindTransformFeedback(gl::TRANSFORM_FEEDBACK, self.id);
+        }
+    }
+
+    pub fn unbind(&self) {
+        unsafe {
+            gl::BindTransformFeedback(gl::TRANSFORM_FEEDBACK, 0);
+        }
+    }
+
+    pub fn bind_buffer<T: bytemuck::Pod>(&self, index: GLuint, buffer: &Buffer<T>) {
+        self.bind();
+        buffer.bind_base(BufferTarget::TransformFeedback, index);
+    }
+
+    pub fn bind_buffer_range<T: bytemuck::Pod>(
+        &self,
+        index: GLuint,
+        buffer: &Buffer<T>,
+        offset: usize,
+        len: usize,
+    ) {
+        let element_size = std::mem::size_of::<T>();
+        self.bind();
+        unsafe {
+            gl::BindBufferRange(
+                gl::TRANSFORM_FEEDBACK_BUFFER,
+                index,
+                buffer.id(),
+                (offset * element_size) as GLintptr,
+                (len * element_size) as GLsizeiptr,
+            );
+        }
+    }
+
+    pub fn begin(&mut self, primitive: FeedbackPrimitive) -> Result<()> {
+        if self.active {
+            bail!("Transform feedback {} is already capturing", self.id);
+        }
+        self.bind();
+        self.query.begin();
+        unsafe {
+            gl::BeginTransformFeedback(primitive.gl_mode());
+        }
+        self.active = true;
+        Ok(())
+    }
+
+    pub fn pause(&self) {
+        unsafe {
+            gl::PauseTransformFeedback();
+        }
+    }
+
+    pub fn resume(&self) {
+        unsafe {
+            gl::ResumeTransformFeedback();
+        }
+    }
+
+    pub fn end(&mut self) {
+        if !self.active {
+            return;
+        }
+        unsafe {
+            gl::EndTransformFeedback();
+        }
+        self.query.end();
+        self.unbind();
+        self.active = false;
+    }
+
+    pub fn capture(
+        &mut self,
+        primitive: FeedbackPrimitive,
+        discard_rasterizer: bool,
+        draw: impl FnOnce(),
+    ) -> Result<u32> {
+        self.begin(primitive)?;
+        unsafe {
+            if discard_rasterizer {
+                gl::Enable(gl::RASTERIZER_DISCARD);
+            }
+        }
+        draw();
+        self.end();
+        unsafe {
+            if discard_rasterizer {
+                gl::Disable(gl::RASTERIZER_DISCARD);
+            }
+        }
+        Ok(self.primitives_written())
+    }
+
+    pub fn primitives_written(&self) -> u32 {
+        self.query.result() as u32
+    }
+}
+
+impl Default for TransformFeedback {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for TransformFeedback {
+    fn drop(&mut self) {
+        self.end();
+        unsafe {
+            gl::DeleteTransformFeedbacks(1, &self.id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::support::headless::headless_context;
+    use crate::support::resource::{BufferUsage, Framebuffer, Renderbuffer, VertexArray};
+    use crate::support::shader::ShaderProgram;
+
+    #[test]
+    #[ignore = "needs an OpenGL 4.5 context, such as Mesa's llvmpipe"]
+    fn captures_transformed_triangle_vertices() {
+        let _context = headless_context();
+        // A surfaceless context has no default framebuffer to draw to, even with the
+        // rasterizer discarding everything.
+        let framebuffer = Framebuffer::new();
+        let color = Renderbuffer::new(gl::RGBA8, 1, 1, 1);
+        framebuffer.attach_renderbuffer(gl::COLOR_ATTACHMENT0, &color);
+        framebuffer.check_status().unwrap();
+
+        let mut program = ShaderProgram::new();
+        program
+            .vertex_shader("shaders/triangle/triangle.vs.glsl")
+            .unwrap()
+            .transform_feedback_varyings(
+                &["gl_Position", "VS_OUT.color"],
+                TransformFeedbackMode::Interleaved,
+            )
+            .unwrap();
+        program.link().unwrap();
+        program.activate();
+        let scale = nalgebra_glm::scaling(&nalgebra_glm::vec3(2.0, 3.0, 1.0));
+        unsafe {
+            gl::UniformMatrix4fv(
+                program.uniform_location("mvp"),
+                1,
+                gl::FALSE,
+                scale.as_ptr(),
+            );
+        }
+
+        let vertices: [[f32; 8]; 3] = [
+            [-0.5, -0.5, 0.0, 1.0, 1.0, 0.0, 0.0, 1.0],
+            [0.5, -0.5, 0.0, 1.0, 0.0, 1.0, 0.0, 1.0],
+            [0.0, 0.5, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0],
+        ];
+        let vertex_buffer = Buffer::with_data(BufferTarget::Array, BufferUsage::Static, &vertices);
+        let vertex_array = VertexArray::new();
+        vertex_array.set_vertex_buffer(&vertex_buffer);
+        let stride = std::mem::size_of::<[f32; 8]>();
+        vertex_array.attribute(0, 4, gl::FLOAT, false, stride, 0);
+        vertex_array.attribute(1, 4, gl::FLOAT, false, stride, 16);
+
+        let output = Buffer::<[f32; 8]>::with_capacity(
+            BufferTarget::TransformFeedback,
+            BufferUsage::StaticRead,
+            3,
+        );
+        let mut feedback = TransformFeedback::new();
+        feedback.bind_buffer(0, &output);
+        let primitives = feedback
+            .capture(FeedbackPrimitive::Triangles, true, || unsafe {
+                gl::DrawArrays(gl::TRIANGLES, 0, 3);
+            })
+            .unwrap();
+
+        assert_eq!(primitives, 1);
+        let expected =
+            vertices.map(|[x, y, z, w, r, g, b, a]| [x * 2.0, y * 3.0, z, w, r, g, b, a]);
+        assert_eq!(output.read(), expected);
+        assert_eq!(unsafe { gl::GetError() }, gl::NO_ERROR);
+    }
+
+    #[test]
+    #[ignore = "needs an OpenGL 4.5 context, such as Mesa's llvmpipe"]
+    fn refuses_to_begin_twice() {
+        let _context = headless_context();
+
+        let mut program = ShaderProgram::new();
+        program
+            .vertex_shader("shaders/triangle/triangle.vs.glsl")
+            .unwrap()
+            .transform_feedback_varyings(&["gl_Position"], TransformFeedbackMode::Interleaved)
+            .unwrap();
+        program.link().unwrap();
+        program.activate();
+        let output = Buffer::<[f32; 4]>::with_capacity(
+            BufferTarget::TransformFeedback,
+            BufferUsage::StaticRead,
+            3,
+        );
+        let mut feedback = TransformFeedback::new();
+        feedback.bind_buffer(0, &output);
+
+        feedback.begin(FeedbackPrimitive::Points).unwrap();
+        assert!(feedback.begin(FeedbackPrimitive::Points).is_err());
+        feedback.end();
+        assert_eq!(unsafe { gl::GetError() }, gl::NO_ERROR);
+    }
+}
@@ -0,0 +1,267 @@
+const DEFAULT_LIMIT: usize = 100;
+
+pub trait UndoCommand {
+    type Target;
+
+    fn label(&self) -> String;
+    fn undo(&self, target: &mut Self::Target);
+    fn redo(&self, target: &mut Self::Target);
+
+    /// Folds `next` into `self` so a continuous edit becomes a single undo step.
+    fn merge(&mut self, _next: &Self) -> bool {
+        false
+    }
+}
+
+#[derive(Debug)]
+pub struct UndoStack<C> {
+    done: Vec<C>,
+    undone: Vec<C>,
+    limit: usize,
+    merging: bool,
+}
+
+impl<C> Default for UndoStack<C> {
+    fn default() -> Self {
+        Self {
+            done: Vec::new(),
+            undone: Vec::new(),
+            limit: DEFAULT_LIMIT,
+            merging: false,
+        }
+    }
+}
+
+impl<C> UndoStack<C> {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            limit: limit.max(1),
+            ..Self::default()
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.done.clear();
+        self.undone.clear();
+        self.merging = false;
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.done.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.undone.is_empty()
+    }
+
+    /// Number of commands currently applied; `jump_to` accepts values from 0 to `len()`.
+    pub fn position(&self) -> usize {
+        self.done.len()
+    }
+
+    pub fn len(&self) -> usize {
+        self.done.len() + self.undone.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Ends the current merge window; the next pushed command starts a new undo step.
+    pub fn end_merge(&mut self) {
+        self.merging = false;
+    }
+}
+
+impl<C: UndoCommand> UndoStack<C> {
+    /// Records a command whose effect has already been applied.
+    pub fn push(&mut self, command: C) {
+        self.push_command(command, false);
+    }
+
+    /// Like `push`, but merges with the previous command while the merge window is open.
+    pub fn push_merging(&mut self, command: C) {
+        self.push_command(command, true);
+    }
+
+    fn push_command(&mut self, command: C, merge: bool) {
+        self.undone.clear();
+        if merge
+            && self.merging
+            && let Some(last) = self.done.last_mut()
+            && last.merge(&command)
+        {
+            return;
+        }
+        self.done.push(command);
+        if self.done.len() > self.limit {
+            self.done.remove(0);
+        }
+        self.merging = merge;
+    }
+
+    pub fn undo(&mut self, target: &mut C::Target) -> bool {
+        let Some(command) = self.done.pop() else {
+            return false;
+        };
+        command.undo(target);
+        self.undone.push(command);
+        self.merging = false;
+        true
+    }
+
+    pub fn redo(&mut self, target: &mut C::Target) -> bool {
+        let Some(command) = self.undone.pop() else {
+            return false;
+        };
+        command.redo(target);
+        self.done.push(command);
+        self.merging = false;
+        true
+    }
+
+    pub fn jump_to(&mut self, position: usize, target: &mut C::Target) {
+        while self.done.len() > position && self.undo(target) {}
+        while self.done.len() < position && self.redo(target) {}
+    }
+
+    pub fn undo_label(&self) -> Option<String> {
+        self.done.last().map(UndoCommand::label)
+    }
+
+    pub fn redo_label(&self) -> Option<String> {
+        self.undone.last().map(UndoCommand::label)
+    }
+
+    /// Labels of all commands, oldest first, followed by the undone ones in redo order.
+    pub fn labels(&self) -> Vec<String> {
+        self.done
+            .iter()
+            .chain(self.undone.iter().rev())
+            .map(UndoCommand::label)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    struct Set {
+        before: i32,
+        after: i32,
+    }
+
+    impl UndoCommand for Set {
+        type Target = i32;
+
+        fn label(&self) -> String {
+            format!("Set {}", self.after)
+        }
+
+        fn undo(&self, target: &mut i32) {
+            *target = self.before;
+        }
+
+        fn redo(&self, target: &mut i32) {
+            *target = self.after;
+        }
+
+        fn merge(&mut self, next: &Self) -> bool {
+            self.after = next.after;
+            true
+        }
+    }
+
+    fn set(stack: &mut UndoStack<Set>, value: &mut i32, after: i32, merge: bool) {
+        let command = Set {
+            before: *value,
+            after,
+        };
+        *value = after;
+        if merge {
+            stack.push_merging(command);
+        } else {
+            stack.push(command);
+        }
+    }
+
+    #[test]
+    fn merges_only_inside_the_merge_window() {
+        let mut stack = UndoStack::default();
+        let mut value = 0;
+        set(&mut stack, &mut value, 1, true);
+        set(&mut stack, &mut value, 2, true);
+        set(&mut stack, &mut value, 3, true);
+        assert_eq!(stack.labels(), ["Set 3"]);
+
+        stack.end_merge();
+        set(&mut stack, &mut value, 4, true);
+        // A plain push never merges and closes the window for the next one.
+        set(&mut stack, &mut value, 5, false);
+        set(&mut stack, &mut value, 6, true);
+        assert_eq!(stack.labels(), ["Set 3", "Set 4", "Set 5", "Set 6"]);
+
+        assert!(stack.undo(&mut value));
+        assert_eq!(value, 5);
+        set(&mut stack, &mut value, 7, true);
+        assert_eq!(stack.labels(), ["Set 3", "Set 4", "Set 5", "Set 7"]);
+
+        stack.jump_to(0, &mut value);
+        assert_eq!(value, 0);
+    }
+
+    #[test]
+    fn drops_the_oldest_commands_past_the_limit() {
+        let mut stack = UndoStack::new(3);
+        let mut value = 0;
+        for after in 1..=5 {
+            set(&mut stack, &mut value, after, false);
+        }
+        assert_eq!(stack.labels(), ["Set 3", "Set 4", "Set 5"]);
+        stack.jump_to(0, &mut value);
+        assert_eq!(value, 2);
+        assert!(!stack.can_undo());
+        assert_eq!(UndoStack::<Set>::new(0).limit, 1);
+    }
+
+    #[test]
+    fn pushing_after_undo_discards_the_redo_history() {
+        let mut stack = UndoStack::default();
+        let mut value = 0;
+        set(&mut stack, &mut value, 1, false);
+        set(&mut stack, &mut value, 2, false);
+        stack.undo(&mut value);
+        assert_eq!(stack.redo_label().as_deref(), Some("Set 2"));
+
+        set(&mut stack, &mut value, 3, false);
+        assert!(!stack.can_redo());
+        assert!(!stack.redo(&mut value));
+        assert_eq!(stack.labels(), ["Set 1", "Set 3"]);
+        assert_eq!(value, 3);
+    }
+
+    #[test]
+    fn jump_to_moves_in_both_directions_and_clamps() {
+        let mut stack = UndoStack::default();
+        let mut value = 0;
+        for after in 1..=4 {
+            set(&mut stack, &mut value, after, false);
+        }
+
+        stack.jump_to(1, &mut value);
+        assert_eq!((value, stack.position(), stack.len()), (1, 1, 4));
+        assert_eq!(stack.labels(), ["Set 1", "Set 2", "Set 3", "Set 4"]);
+
+        stack.jump_to(3, &mut value);
+        assert_eq!((value, stack.position()), (3, 3));
+
+        stack.jump_to(10, &mut value);
+        assert_eq!((value, stack.position()), (4, 4));
+
+        stack.jump_to(0, &mut value);
+        assert_eq!((value, stack.position()), (0, 0));
+        assert_eq!(stack.undo_label(), None);
+    }
+}
@@ -0,0 +1,236 @@
+use crate::support::resource::{Buffer, VertexArray};
+use crate::support::shader::ShaderProgram;
+use anyhow::{Result, bail};
+use gl::types::*;
+
+pub use app_derive::VertexLayout;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VertexAttribute {
+    pub name: &'static str,
+    pub location: GLuint,
+    pub components: GLint,
+    pub gl_type: GLenum,
+    pub normalized: bool,
+    pub offset: usize,
+}
+
+impl VertexAttribute {
+    fn is_integer(&self) -> bool {
+        !self.normalized && !matches!(self.gl_type, gl::FLOAT | gl::HALF_FLOAT | gl::DOUBLE)
+    }
+}
+
+pub trait VertexLayout: bytemuck::Pod {
+    fn attributes() -> &'static [VertexAttribute];
+
+    fn stride() -> usize {
+        std::mem::size_of::<Self>()
+    }
+}
+
+pub trait VertexFormat {
+    const COMPONENTS: GLint;
+    const GL_TYPE: GLenum;
+}
+
+macro_rules! vertex_format {
+    ($($scalar:ty => $gl_type:expr),* $(,)?) => {
+        $(
+            impl VertexFormat for $scalar {
+                const COMPONENTS: GLint = 1;
+                const GL_TYPE: GLenum = $gl_type;
+            }
+
+            // A vertex attribute holds at most four components, so larger arrays fail to
+            // compile wherever a layout uses them.
+            impl<const N: usize> VertexFormat for [$scalar; N] {
+                const COMPONENTS: GLint = {
+                    assert!(N >= 1 && N <= 4, "vertex attributes have 1 to 4 components");
+                    N as GLint
+                };
+                const GL_TYPE: GLenum = $gl_type;
+            }
+        )*
+    };
+}
+
+vertex_format! {
+    f32 => gl::FLOAT,
+    i32 => gl::INT,
+    u32 => gl::UNSIGNED_INT,
+    i16 => gl::SHORT,
+    u16 => gl::UNSIGNED_SHORT,
+    i8 => gl::BYTE,
+    u8 => gl::UNSIGNED_BYTE,
+}
+
+impl VertexFormat for nalgebra_glm::Vec2 {
+    const COMPONENTS: GLint = 2;
+    const GL_TYPE: GLenum = gl::FLOAT;
+}
+
+impl VertexFormat for nalgebra_glm::Vec3 {
+    const COMPONENTS: GLint = 3;
+    const GL_TYPE: GLenum = gl::FLOAT;
+}
+
+impl VertexFormat for nalgebra_glm::Vec4 {
+    const COMPONENTS: GLint = 4;
+    const GL_TYPE: GLenum = gl::FLOAT;
+}
+
+impl VertexArray {
+    pub fn with_layout<V: VertexLayout>(vertex_buffer: &Buffer<V>) -> Self {
+        let vertex_array = Self::new();
+        vertex_array.configure_layout(vertex_buffer);
+        vertex_array
+    }
+
+    pub fn configure_layout<V: VertexLayout>(&self, vertex_buffer: &Buffer<V>) {
+        self.set_vertex_buffer(vertex_buffer);
+        for attribute in V::attributes() {
+            self.attribute(
+                attribute.location,
+                attribute.components,
+                attribute.gl_type,
+                attribute.normalized,
+                V::stride(),
+                attribute.offset,
+            );
+        }
+    }
+}
+
+pub fn validate_layout<V: VertexLayout>(program: &ShaderProgram) -> Result<()> {
+    if !gl::GetProgramResourceiv::is_loaded() {
+        log::warn!(
+            "Skipped checking the vertex layout of program {}, program interface queries \
+             require OpenGL 4.3",
+            program.id
+        );
+        return Ok(());
+    }
+    let attributes = V::attributes();
+    let mut errors = Vec::new();
+
+    for input in program
+        .resources(gl::PROGRAM_INPUT)
+        .iter()
+        .filter(|input| !input.is_builtin())
+    {
+        let Some(attribute) = attributes
+            .iter()
+            .find(|attribute| attribute.location as GLint == input.location)
+        else {
+            errors.push(format!(
+                "Shader input '{}' at location {} has no matching vertex attribute",
+                input.name, input.location
+            ));
+            continue;
+        };
+
+        // Missing or extra components are filled in or dropped by GL, so only the base
+        // type has to agree.
+        if integer_input(input.gl_type) != attribute.is_integer() {
+            errors.push(format!(
+                "Vertex attribute '{}' at location {} is {} but shader input '{}' is {}",
+                attribute.name,
+                attribute.location,
+                if attribute.is_integer() {
+                    "an integer"
+                } else {
+                    "a float"
+                },
+                input.name,
+                input.type_name()
+            ));
+        }
+    }
+
+    if !errors.is_empty() {
+        bail!(
+            "Vertex layout does not match program {}:\n{}",
+            program.id,
+            errors.join("\n")
+        );
+    }
+    Ok(())
+}
+
+fn integer_input(gl_type: GLenum) -> bool {
+    matches!(
+        gl_type,
+        gl::INT
+            | gl::UNSIGNED_INT
+            | gl::INT_VEC2
+            | gl::UNSIGNED_INT_VEC2
+            | gl::INT_VEC3
+            | gl::UNSIGNED_INT_VEC3
+            | gl::INT_VEC4
+            | gl::UNSIGNED_INT_VEC4
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[repr(C)]
+    #[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable, VertexLayout)]
+    struct PackedVertex {
+        #[location = 0]
+        position: [f32; 3],
+        #[location = 3]
+        #[normalized]
+        color: [u8; 4],
+        #[location = 1]
+        joints: [u16; 2],
+        #[location = 2]
+        weight: f32,
+    }
+
+    #[test]
+    fn derive_records_offsets_and_formats() {
+        let attributes = PackedVertex::attributes();
+        let summary = attributes
+            .iter()
+            .map(|attribute| {
+                (
+                    attribute.name,
+                    attribute.location,
+                    attribute.components,
+                    attribute.gl_type,
+                    attribute.normalized,
+                    attribute.offset,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            [
+                ("position", 0, 3, gl::FLOAT, false, 0),
+                ("color", 3, 4, gl::UNSIGNED_BYTE, true, 12),
+                ("joints", 1, 2, gl::UNSIGNED_SHORT, false, 16),
+                ("weight", 2, 1, gl::FLOAT, false, 20),
+            ]
+        );
+        assert_eq!(PackedVertex::stride(), 24);
+        assert!(!attributes[1].is_integer());
+        assert!(attributes[2].is_integer());
+    }
+
+    #[test]
+    fn mesh_vertex_layout_is_tightly_packed() {
+        use crate::support::mesh::MeshVertex;
+
+        let attributes = MeshVertex::attributes();
+        let offsets = attributes
+            .iter()
+            .map(|attribute| (attribute.location, attribute.offset))
+            .collect::<Vec<_>>();
+        assert_eq!(offsets, [(0, 0), (1, 12), (2, 24), (3, 40), (4, 48)]);
+        assert_eq!(MeshVertex::stride(), 64);
+        assert_eq!(<nalgebra_glm::Vec3 as VertexFormat>::COMPONENTS, 3);
+    }
+}